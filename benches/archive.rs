@@ -0,0 +1,100 @@
+use std::fs::File;
+use std::io::{Cursor, Write};
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use obsidian_lib::{Compression, ObbyArchive, ObbyReadOptions, ObbyWriter};
+use tempfile::NamedTempFile;
+
+const LARGE_ENTRY_SIZE: usize = 100 * 1024 * 1024;
+
+/// Deterministic, moderately compressible bytes (roughly like managed DLLs)
+fn sample_data(len: usize, seed: u32) -> Vec<u8> {
+    let mut state = seed.wrapping_mul(2_654_435_761).max(1);
+    (0..len)
+        .map(|i| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            if i % 4 == 0 { state as u8 } else { (i / 64) as u8 }
+        })
+        .collect()
+}
+
+/// An archive shaped like a typical plugin: a manifest plus a few hundred small files
+fn typical_archive() -> Vec<u8> {
+    let mut writer = ObbyWriter::new(Vec::new(), "BenchPlugin", "1.0.0");
+    writer.add_entry("plugin.json", br#"{"id": "bench", "version": "1.0.0"}"#).unwrap();
+    for i in 0..300 {
+        writer.add_entry(&format!("lib/Dependency{}.dll", i), &sample_data(16 * 1024, i)).unwrap();
+    }
+    writer.finish().unwrap()
+}
+
+fn large_archive() -> Vec<u8> {
+    let mut writer = ObbyWriter::new(Vec::new(), "BenchPlugin", "1.0.0");
+    writer.set_compression(Compression::fast());
+    writer.add_entry("assets/large.bin", &sample_data(LARGE_ENTRY_SIZE, 7)).unwrap();
+    writer.finish().unwrap()
+}
+
+fn to_temp_file(bytes: &[u8]) -> NamedTempFile {
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(bytes).unwrap();
+    file
+}
+
+fn bench_header_parse(c: &mut Criterion) {
+    let bytes = typical_archive();
+    let file = to_temp_file(&bytes);
+    let mut group = c.benchmark_group("header_parse");
+
+    group.bench_function("cursor", |b| {
+        b.iter(|| ObbyArchive::new(Cursor::new(black_box(&bytes[..]))).unwrap())
+    });
+    for buffer_size in [0, 8 * 1024, 64 * 1024] {
+        let mut options = ObbyReadOptions::default();
+        options.set_buffer_size(buffer_size);
+        group.bench_with_input(BenchmarkId::new("file", buffer_size), &options, |b, options| {
+            b.iter(|| ObbyArchive::with_options(File::open(file.path()).unwrap(), options.clone()).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_extract_small(c: &mut Criterion) {
+    let bytes = typical_archive();
+    let mut archive = ObbyArchive::new(Cursor::new(bytes)).unwrap();
+
+    c.bench_function("extract_small_entry", |b| {
+        b.iter(|| archive.extract_entry(black_box("plugin.json")).unwrap())
+    });
+}
+
+fn bench_extract_large(c: &mut Criterion) {
+    let bytes = large_archive();
+    let mut archive = ObbyArchive::new(Cursor::new(bytes)).unwrap();
+    let mut group = c.benchmark_group("extract_large_entry");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(LARGE_ENTRY_SIZE as u64));
+
+    group.bench_function("100MB", |b| {
+        b.iter(|| archive.extract_entry(black_box("assets/large.bin")).unwrap())
+    });
+    group.finish();
+}
+
+fn bench_extract_all(c: &mut Criterion) {
+    let bytes = typical_archive();
+    let mut archive = ObbyArchive::new(Cursor::new(bytes)).unwrap();
+
+    c.bench_function("extract_all", |b| {
+        b.iter(|| {
+            for name in archive.list_entries() {
+                black_box(archive.extract_entry(&name).unwrap());
+            }
+        })
+    });
+}
+
+criterion_group!(benches, bench_header_parse, bench_extract_small, bench_extract_large, bench_extract_all);
+criterion_main!(benches);
@@ -0,0 +1,30 @@
+//! Generates man pages for `obby` when the `cli` feature is enabled
+//!
+//! Pages are written to `$OBBY_MAN_DIR` if set, for packagers, and to `$OUT_DIR/man`
+//! otherwise: one for `obby` itself and one per subcommand, such as `obby-extract.1`.
+
+#[cfg(feature = "cli")]
+#[allow(dead_code)]
+#[path = "src/cli.rs"]
+mod cli;
+
+fn main() -> std::io::Result<()> {
+    println!("cargo:rerun-if-changed=src/cli.rs");
+    println!("cargo:rerun-if-env-changed=OBBY_MAN_DIR");
+    #[cfg(feature = "cli")]
+    generate_man_pages()?;
+    Ok(())
+}
+
+#[cfg(feature = "cli")]
+fn generate_man_pages() -> std::io::Result<()> {
+    use clap::CommandFactory;
+    use std::path::PathBuf;
+
+    let dir = match std::env::var_os("OBBY_MAN_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("OUT_DIR").expect("OUT_DIR is set by cargo")).join("man"),
+    };
+    std::fs::create_dir_all(&dir)?;
+    clap_mangen::generate_to(cli::Cli::command(), &dir)
+}
@@ -0,0 +1,41 @@
+// Parses a plugin while it downloads, posting the manifest as soon as it has arrived.
+//
+// Works as a module web worker (`new Worker("stream-worker.js", { type: "module" })`,
+// then `worker.postMessage(url)`) and in Deno (`deno run --allow-read --allow-net
+// examples/stream-worker.js <url>`). Build the package first with `npm run build`.
+import init, { WasmObbyParser } from "../pkg/obsidian_lib.js";
+
+async function parse(url, report) {
+    await init();
+    const response = await fetch(url);
+    if (!response.ok) {
+        throw new Error(`Download failed: ${response.status}`);
+    }
+
+    const parser = new WasmObbyParser();
+    let manifestSent = false;
+    for await (const chunk of response.body) {
+        parser.push(chunk);
+        if (!manifestSent) {
+            const manifest = parser.pluginManifest();
+            if (manifest) {
+                report({ type: "manifest", manifest, metadata: parser.metadata() });
+                manifestSent = true;
+            }
+        }
+        report({ type: "progress", bytes: parser.bytesReceived });
+    }
+
+    const archive = parser.finish();
+    archive.verifyHash();
+    report({ type: "done", entries: archive.entriesWithInfo() });
+}
+
+if (typeof Deno !== "undefined" && Deno.args.length > 0) {
+    await parse(Deno.args[0], (message) => console.log(message));
+} else {
+    self.onmessage = (event) => {
+        parse(event.data, (message) => self.postMessage(message))
+            .catch((error) => self.postMessage({ type: "error", message: String(error) }));
+    };
+}
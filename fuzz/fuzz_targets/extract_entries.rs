@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use obsidian_lib::ObbyArchive;
+use std::io::Cursor;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(mut archive) = ObbyArchive::new(Cursor::new(data)) {
+        for name in archive.list_entries() {
+            let _ = archive.extract_entry(&name);
+        }
+    }
+});
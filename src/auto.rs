@@ -0,0 +1,146 @@
+//! Opening archives that were compressed as a whole, such as `.obby.gz` downloads
+
+use std::fs::File;
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::{ObbyArchive, ObbyReadOptions, MAGIC};
+
+/// Largest unwrapped archive accepted, a little above the format's 2 GiB data limit
+const MAX_UNWRAPPED_LEN: u64 = i32::MAX as u64 + 1024 * 1024;
+
+/// The source behind an archive opened with [`open_auto`]
+#[derive(Debug)]
+pub enum AutoSource {
+    /// A plain archive, read from the file directly
+    File(File),
+    /// A wrapped archive, decompressed into memory
+    Unwrapped(Cursor<Vec<u8>>),
+}
+
+impl Read for AutoSource {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            AutoSource::File(file) => file.read(buf),
+            AutoSource::Unwrapped(cursor) => cursor.read(buf),
+        }
+    }
+}
+
+impl Seek for AutoSource {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            AutoSource::File(file) => file.seek(pos),
+            AutoSource::Unwrapped(cursor) => cursor.seek(pos),
+        }
+    }
+}
+
+/// Opens an `.obby` file that may be wrapped in gzip or zstd compression
+///
+/// Some mirrors serve archives compressed as a whole (`.obby.gz`). The wrapping is
+/// detected from the file's first bytes, not its name, using the codecs in the default
+/// [`ObbyReadOptions`]: gzip always, zstd with the `zstd` feature. A wrapped archive is
+/// decompressed into memory; a plain one is read from the file as with [`crate::open`].
+///
+/// # Arguments
+///
+/// * `path` - Path to the file.
+///
+/// # Returns
+///
+/// The `ObbyArchive`, or an `io::Error` if the file is neither an archive nor a
+/// wrapped archive, or is wrapped with an unsupported codec.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// let mut archive = obsidian_lib::open_auto("downloads/plugin.obby.gz")?;
+/// println!("{:?}", archive.list_entries());
+/// # Ok(())
+/// # }
+/// ```
+pub fn open_auto<P: AsRef<Path>>(path: P) -> io::Result<ObbyArchive<AutoSource>> {
+    open_auto_with_options(path, ObbyReadOptions::default())
+}
+
+/// Like [`open_auto`], with custom read options
+///
+/// The codecs registered in `options` are also used to detect the wrapping.
+///
+/// # Arguments
+///
+/// * `path` - Path to the file.
+/// * `options` - The `ObbyReadOptions` to use for the archive.
+pub fn open_auto_with_options<P: AsRef<Path>>(path: P, options: ObbyReadOptions) -> io::Result<ObbyArchive<AutoSource>> {
+    let mut file = File::open(path)?;
+    let mut prefix = Vec::with_capacity(16);
+    (&mut file).take(16).read_to_end(&mut prefix)?;
+    file.seek(SeekFrom::Start(0))?;
+
+    let source = match options.codecs().sniff(&prefix) {
+        Some(codec) if !prefix.starts_with(MAGIC) => {
+            let mut data = Vec::new();
+            codec
+                .decoder(Box::new(file))?
+                .take(MAX_UNWRAPPED_LEN + 1)
+                .read_to_end(&mut data)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("Failed to unwrap {} archive: {}", codec.name(), e)))?;
+            if data.len() as u64 > MAX_UNWRAPPED_LEN {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "Unwrapped archive is too large"));
+            }
+            AutoSource::Unwrapped(Cursor::new(data))
+        }
+        _ if prefix.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Archive is wrapped in zstd, which needs the zstd feature",
+            ));
+        }
+        _ => AutoSource::File(file),
+    };
+    ObbyArchive::with_options(source, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    #[test]
+    fn test_open_auto() {
+        let buffer = ObbyTestBuilder::new().entry("plugin.json", br#"{"id": "x"}"#).build();
+        let dir = tempfile::tempdir().unwrap();
+
+        let plain = dir.path().join("plain.obby");
+        std::fs::write(&plain, &buffer).unwrap();
+        let archive = open_auto(&plain).unwrap();
+        assert!(matches!(archive.reader, AutoSource::File(_)));
+
+        let wrapped = dir.path().join("wrapped.obby.gz");
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&buffer).unwrap();
+        std::fs::write(&wrapped, encoder.finish().unwrap()).unwrap();
+        let mut archive = open_auto(&wrapped).unwrap();
+        assert!(matches!(archive.reader, AutoSource::Unwrapped(_)));
+        assert_eq!(archive.extract_entry("plugin.json").unwrap(), br#"{"id": "x"}"#);
+        archive.verify_hash().unwrap();
+
+        let garbage = dir.path().join("garbage.obby");
+        std::fs::write(&garbage, b"not an archive").unwrap();
+        assert_eq!(open_auto(&garbage).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_open_auto_zstd() {
+        let buffer = ObbyTestBuilder::new().entry("plugin.json", b"{}").build();
+        let dir = tempfile::tempdir().unwrap();
+        let wrapped = dir.path().join("wrapped.obby.zst");
+        std::fs::write(&wrapped, zstd::encode_all(&buffer[..], 0).unwrap()).unwrap();
+        assert_eq!(open_auto(&wrapped).unwrap().list_entries(), vec!["plugin.json"]);
+    }
+}
@@ -0,0 +1,168 @@
+//! `obby batch`: runs operations over many archives from a job file
+//!
+//! A job file is TOML:
+//!
+//! ```toml
+//! output_dir = "out"   # where results go, one subdirectory per archive
+//! threads = 8          # optional, defaults to the number of CPUs
+//! keys = "trusted/"    # optional, public key or directory of keys for `verify`
+//!
+//! [[job]]
+//! archives = ["uploads/a.obby", "uploads/b.obby"]
+//! operations = ["verify", "manifest", "zip"]
+//! output_dir = "out/special"   # optional, overrides the top-level setting
+//! ```
+//!
+//! Operations are `manifest` (write the manifest to `<output>/<name>/`), `verify`
+//! (check the hash, and the signature when `keys` is set), `extract` (extract every
+//! entry to `<output>/<name>/`) and `zip` (convert to `<output>/<name>.zip`).
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Instant;
+
+use obsidian_lib::{ObbyArchive, SanitizePolicy};
+use rsa::RsaPublicKey;
+use serde::Deserialize;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::exit::Failure;
+use crate::parallel::in_parallel;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JobFile {
+    output_dir: PathBuf,
+    threads: Option<usize>,
+    keys: Option<PathBuf>,
+    #[serde(rename = "job")]
+    jobs: Vec<Job>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Job {
+    archives: Vec<PathBuf>,
+    operations: Vec<Operation>,
+    output_dir: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "kebab-case")]
+enum Operation {
+    Manifest,
+    Verify,
+    Extract,
+    Zip,
+}
+
+/// One archive to process, flattened out of the jobs
+struct Task<'a> {
+    archive: &'a Path,
+    operations: &'a [Operation],
+    output_dir: &'a Path,
+}
+
+/// Runs the job file at `path` and prints a summary
+///
+/// Exits with status 1 if any archive failed.
+pub fn batch(path: &Path) -> io::Result<()> {
+    let text = fs::read_to_string(path)?;
+    let job_file: JobFile =
+        toml::from_str(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    let keys = match &job_file.keys {
+        Some(keys) => crate::load_keys(keys)?.0,
+        None => Vec::new(),
+    };
+
+    let tasks: Vec<Task> = job_file
+        .jobs
+        .iter()
+        .flat_map(|job| {
+            let output_dir = job.output_dir.as_deref().unwrap_or(&job_file.output_dir);
+            job.archives.iter().map(move |archive| Task {
+                archive,
+                operations: &job.operations,
+                output_dir,
+            })
+        })
+        .collect();
+    let threads = job_file.threads.unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()));
+
+    let started = Instant::now();
+    let results = in_parallel(&tasks, threads, |task| run_task(task, &keys));
+
+    let failed = results.iter().filter(|result| result.is_err()).count();
+    for (task, result) in tasks.iter().zip(&results) {
+        match result {
+            Ok(()) => println!("ok      {}", task.archive.display()),
+            Err(e) => println!("FAILED  {}: {}", task.archive.display(), e),
+        }
+    }
+    println!(
+        "\n{} archives processed in {:.1}s: {} succeeded, {} failed",
+        results.len(),
+        started.elapsed().as_secs_f64(),
+        results.len() - failed,
+        failed
+    );
+
+    if failed > 0 {
+        std::process::exit(Failure::Other.code().into());
+    }
+    Ok(())
+}
+
+/// Runs every operation of `task`, stopping at the first failure
+fn run_task(task: &Task, keys: &[RsaPublicKey]) -> io::Result<()> {
+    let mut archive = obsidian_lib::open(task.archive)?;
+    let stem = task
+        .archive
+        .file_stem()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Archive path has no file name"))?;
+    let out = task.output_dir.join(stem);
+
+    for operation in task.operations {
+        let result = match operation {
+            Operation::Manifest => write_manifest(&mut archive, &out),
+            Operation::Verify if keys.is_empty() => archive.verify_hash(),
+            Operation::Verify => obsidian_lib::signing::verify_signature(&mut archive, keys).map(|_| ()),
+            Operation::Extract => archive.extract_all(&out).map(|_| ()),
+            Operation::Zip => write_zip(&mut archive, &out.with_extension("zip")),
+        };
+        result.map_err(|e| io::Error::new(e.kind(), format!("{:?}: {}", operation, e)))?;
+    }
+    Ok(())
+}
+
+fn write_manifest(archive: &mut ObbyArchive<File>, out: &Path) -> io::Result<()> {
+    let manifest = archive.find_manifest()?.to_string();
+    let data = archive.extract_entry(&manifest)?;
+    fs::create_dir_all(out)?;
+    fs::write(out.join(&manifest), data)
+}
+
+/// Writes every entry into a new zip file, using the sanitized entry paths as names
+fn write_zip(archive: &mut ObbyArchive<File>, path: &Path) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut zip = ZipWriter::new(File::create(path)?);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    for name in archive.list_entries() {
+        let zip_name = SanitizePolicy::Strict
+            .sanitize(&name)?
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let data = archive.extract_entry(&name)?;
+        zip.start_file(zip_name, options).map_err(io::Error::other)?;
+        zip.write_all(&data)?;
+    }
+    zip.finish().map_err(io::Error::other)?;
+    Ok(())
+}
@@ -0,0 +1,248 @@
+//! `obby browse`: an interactive terminal browser for archives
+//!
+//! Built with the `tui` feature.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+
+use obsidian_lib::{EntryTree, ObbyArchive, SanitizePolicy};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+
+/// Most bytes of a text entry shown in the preview pane
+const PREVIEW_LEN: usize = 64 * 1024;
+
+/// One visible line of the entry tree
+struct Row {
+    depth: usize,
+    label: String,
+    /// Directory path (for directories) or entry name (for files)
+    path: String,
+    is_dir: bool,
+}
+
+struct Browser {
+    archive: ObbyArchive<File>,
+    /// Decompressed size of each entry
+    sizes: HashMap<String, u64>,
+    tree: EntryTree,
+    expanded: HashSet<String>,
+    rows: Vec<Row>,
+    list: ListState,
+    preview_title: String,
+    preview: String,
+    status: String,
+}
+
+/// Runs the browser on `path` until the user quits
+pub fn browse(path: &Path) -> io::Result<()> {
+    let archive = obsidian_lib::open(path)?;
+    let header = obsidian_lib::parse_header(io::BufReader::new(File::open(path)?))?;
+    let mut browser = Browser {
+        sizes: header.entries.into_iter().map(|entry| (entry.name, entry.length)).collect(),
+        tree: archive.tree(),
+        archive,
+        expanded: HashSet::new(),
+        rows: Vec::new(),
+        list: ListState::default(),
+        preview_title: String::new(),
+        preview: String::new(),
+        status: "↑/↓ move  →/enter open  ← collapse  m manifest  x extract  q quit".to_string(),
+    };
+    browser.refresh_rows();
+    browser.list.select(Some(0));
+    browser.show_manifest();
+
+    let mut terminal = ratatui::init();
+    let result = browser.run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+impl Browser {
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+                KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+                KeyCode::Right | KeyCode::Enter | KeyCode::Char('l') => self.open_selected(),
+                KeyCode::Left | KeyCode::Char('h') => self.collapse_selected(),
+                KeyCode::Char('m') => self.show_manifest(),
+                KeyCode::Char('x') => self.extract_selected(),
+                _ => {}
+            }
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, status] = Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let [tree, preview] = Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)]).areas(main);
+
+        let items: Vec<ListItem> = self
+            .rows
+            .iter()
+            .map(|row| {
+                let marker = match (row.is_dir, self.expanded.contains(&row.path)) {
+                    (true, true) => "▾ ",
+                    (true, false) => "▸ ",
+                    (false, _) => "  ",
+                };
+                ListItem::new(format!("{}{}{}", "  ".repeat(row.depth), marker, row.label))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title("Entries"))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, tree, &mut self.list);
+
+        let text = Paragraph::new(self.preview.as_str())
+            .block(Block::default().borders(Borders::ALL).title(self.preview_title.as_str()))
+            .wrap(Wrap { trim: false });
+        frame.render_widget(text, preview);
+        frame.render_widget(Line::from(self.status.as_str()), status);
+    }
+
+    /// Rebuilds the visible rows from the tree and the set of expanded directories
+    fn refresh_rows(&mut self) {
+        fn walk(node: &EntryTree, parent: &str, depth: usize, expanded: &HashSet<String>, rows: &mut Vec<Row>) {
+            for child in node.children() {
+                let path = match child {
+                    EntryTree::File { path, .. } => path.clone(),
+                    EntryTree::Dir { name, .. } if parent.is_empty() => name.clone(),
+                    EntryTree::Dir { name, .. } => format!("{}/{}", parent, name),
+                };
+                rows.push(Row {
+                    depth,
+                    label: child.name().to_string(),
+                    path: path.clone(),
+                    is_dir: child.is_dir(),
+                });
+                if child.is_dir() && expanded.contains(&path) {
+                    walk(child, &path, depth + 1, expanded, rows);
+                }
+            }
+        }
+
+        self.rows.clear();
+        walk(&self.tree, "", 0, &self.expanded, &mut self.rows);
+    }
+
+    fn selected(&self) -> Option<&Row> {
+        self.list.selected().and_then(|index| self.rows.get(index))
+    }
+
+    fn move_selection(&mut self, step: isize) {
+        if self.rows.is_empty() {
+            return;
+        }
+        let current = self.list.selected().unwrap_or(0) as isize;
+        let next = (current + step).clamp(0, self.rows.len() as isize - 1);
+        self.list.select(Some(next as usize));
+    }
+
+    fn open_selected(&mut self) {
+        let Some(row) = self.selected() else {
+            return;
+        };
+        let (path, is_dir) = (row.path.clone(), row.is_dir);
+        if is_dir {
+            self.expanded.insert(path);
+            self.refresh_rows();
+        } else {
+            self.preview_entry(&path);
+        }
+    }
+
+    /// Collapses the selected directory, or the one containing the selected entry
+    fn collapse_selected(&mut self) {
+        let Some(row) = self.selected() else {
+            return;
+        };
+        let dir = if row.is_dir && self.expanded.contains(&row.path) {
+            row.path.clone()
+        } else {
+            match row.path.rsplit_once('/') {
+                Some((parent, _)) => parent.to_string(),
+                None => return,
+            }
+        };
+        self.expanded.remove(&dir);
+        self.refresh_rows();
+        let index = self.rows.iter().position(|row| row.path == dir).unwrap_or(0);
+        self.list.select(Some(index));
+    }
+
+    fn show_manifest(&mut self) {
+        match self.archive.find_manifest().map(str::to_string) {
+            Ok(manifest) => self.preview_entry(&manifest),
+            Err(e) => {
+                self.preview_title = "Manifest".to_string();
+                self.preview = e.to_string();
+            }
+        }
+    }
+
+    fn preview_entry(&mut self, name: &str) {
+        self.preview_title = name.to_string();
+        self.preview = match self.render_preview(name) {
+            Ok(text) => text,
+            Err(e) => format!("Failed to read entry: {}", e),
+        };
+    }
+
+    fn render_preview(&mut self, name: &str) -> io::Result<String> {
+        let mime = self.archive.entry_mime(name)?;
+        let size = self.sizes.get(name).copied().unwrap_or(0);
+        let is_text = mime.starts_with("text/") || mime.ends_with("json") || mime.ends_with("xml") || mime.ends_with("yaml");
+        if !is_text {
+            return Ok(format!("{}\n{} bytes\n\n(binary content not shown)", mime, size));
+        }
+
+        let data = self.archive.read_entry_range(name, 0, PREVIEW_LEN)?;
+        let mut text = String::from_utf8_lossy(&data).into_owned();
+        if size > PREVIEW_LEN as u64 {
+            text.push_str(&format!("\n\n… {} more bytes", size - PREVIEW_LEN as u64));
+        }
+        Ok(text)
+    }
+
+    /// Extracts the selected entry into the current directory, keeping its path
+    fn extract_selected(&mut self) {
+        let Some(row) = self.selected() else {
+            return;
+        };
+        if row.is_dir {
+            self.status = "Select a file to extract".to_string();
+            return;
+        }
+        let name = row.path.clone();
+        self.status = match self.extract(&name) {
+            Ok(path) => format!("Extracted {}", path),
+            Err(e) => format!("Failed to extract {}: {}", name, e),
+        };
+    }
+
+    fn extract(&mut self, name: &str) -> io::Result<String> {
+        let path = SanitizePolicy::Strict.sanitize(name)?;
+        let data = self.archive.extract_entry(name)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, data)?;
+        Ok(path.display().to_string())
+    }
+}
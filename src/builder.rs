@@ -0,0 +1,352 @@
+//! Chainable construction of archive readers and writers
+//!
+//! [`ObbyArchiveBuilder`] and [`ObbyWriterBuilder`] collect every option in one place,
+//! so new options can be added without changing any constructor's signature.
+
+use std::fs::File;
+use std::io::{self, Cursor, Read, Seek, Write};
+use std::path::Path;
+
+use flate2::Compression;
+
+#[cfg(feature = "signing")]
+use rsa::RsaPrivateKey;
+
+use crate::codec::Codec;
+use crate::writer::NameMapper;
+use crate::{ObbyArchive, ObbyReadOptions, ObbyWriter, OutputFile, SanitizePolicy};
+
+/// Builder for opening an [`ObbyArchive`], returned by [`ObbyArchive::builder`]
+///
+/// Each method sets the [`ObbyReadOptions`] setting of the same name.
+///
+/// # Example
+///
+/// ```no_run
+/// use obsidian_lib::ObbyArchive;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let archive = ObbyArchive::builder()
+///     .max_entry_size(64 * 1024 * 1024)
+///     .strict(true)
+///     .normalize_names(true)
+///     .open_path("plugin.obby")?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ObbyArchiveBuilder {
+    options: ObbyReadOptions,
+}
+
+impl ObbyArchiveBuilder {
+    /// Creates a builder with the default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the options collected so far
+    pub fn options(&self) -> &ObbyReadOptions {
+        &self.options
+    }
+
+    /// Registers an additional decompression codec; see [`ObbyReadOptions::register_codec`]
+    pub fn codec<C: Codec + 'static>(mut self, codec: C) -> Self {
+        self.options.register_codec(codec);
+        self
+    }
+
+    /// See [`ObbyReadOptions::set_buffer_size`]
+    pub fn buffer_size(mut self, buffer_size: usize) -> Self {
+        self.options.set_buffer_size(buffer_size);
+        self
+    }
+
+    /// See [`ObbyReadOptions::set_sanitize_policy`]
+    pub fn sanitize_policy(mut self, policy: SanitizePolicy) -> Self {
+        self.options.set_sanitize_policy(policy);
+        self
+    }
+
+    /// See [`ObbyReadOptions::set_entry_filter`]
+    pub fn entry_filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        self.options.set_entry_filter(filter);
+        self
+    }
+
+    /// See [`ObbyReadOptions::set_entry_prefix`]
+    pub fn entry_prefix(mut self, prefix: &str) -> Self {
+        self.options.set_entry_prefix(prefix);
+        self
+    }
+
+    /// Refuses to decompress entries that declare more than `max_entry_size` bytes
+    ///
+    /// See [`ObbyReadOptions::set_max_entry_size`].
+    pub fn max_entry_size(mut self, max_entry_size: u64) -> Self {
+        self.options.set_max_entry_size(Some(max_entry_size));
+        self
+    }
+
+    /// See [`ObbyReadOptions::set_strict`]
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.options.set_strict(strict);
+        self
+    }
+
+    /// See [`ObbyReadOptions::set_reject_trailing_data`]
+    pub fn reject_trailing_data(mut self, reject_trailing_data: bool) -> Self {
+        self.options.set_reject_trailing_data(reject_trailing_data);
+        self
+    }
+
+    /// See [`ObbyReadOptions::set_normalize_names`]
+    pub fn normalize_names(mut self, normalize_names: bool) -> Self {
+        self.options.set_normalize_names(normalize_names);
+        self
+    }
+
+    /// Opens an archive from any source that implements `Read` and `Seek`
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The source, positioned at the start of the archive.
+    ///
+    /// # Returns
+    ///
+    /// The opened `ObbyArchive`, or an `io::Error` if the archive can't be read.
+    pub fn open<R: Read + Seek>(self, reader: R) -> io::Result<ObbyArchive<R>> {
+        ObbyArchive::with_options(reader, self.options)
+    }
+
+    /// Opens the archive at `path`
+    pub fn open_path<P: AsRef<Path>>(self, path: P) -> io::Result<ObbyArchive<File>> {
+        self.open(File::open(path)?)
+    }
+
+    /// Opens an archive from an owned in-memory buffer
+    pub fn from_bytes(self, bytes: Vec<u8>) -> io::Result<ObbyArchive<Cursor<Vec<u8>>>> {
+        self.open(Cursor::new(bytes))
+    }
+}
+
+impl From<ObbyReadOptions> for ObbyArchiveBuilder {
+    fn from(options: ObbyReadOptions) -> Self {
+        ObbyArchiveBuilder { options }
+    }
+}
+
+impl ObbyArchive<File> {
+    /// Starts configuring how an archive is opened
+    ///
+    /// The returned [`ObbyArchiveBuilder`] can open any `Read + Seek` source, not just files.
+    pub fn builder() -> ObbyArchiveBuilder {
+        ObbyArchiveBuilder::new()
+    }
+}
+
+/// Builder for an [`ObbyWriter`], returned by [`ObbyWriter::builder`]
+///
+/// Each method sets the `ObbyWriter` setting of the same name.
+///
+/// # Example
+///
+/// ```no_run
+/// use obsidian_lib::{Compression, ObbyWriter};
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut writer = ObbyWriter::builder("MyPlugin", "1.2.3")
+///     .compression(Compression::best())
+///     .reproducible(true)
+///     .create("plugin.obby")?;
+/// writer.add_dir("./bin/Release")?;
+/// writer.finish()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ObbyWriterBuilder {
+    plugin_assembly: String,
+    plugin_version: String,
+    api_version: Option<String>,
+    compression: Compression,
+    name_mapper: Option<NameMapper>,
+    reproducible: bool,
+    write_extras: bool,
+    #[cfg(feature = "json")]
+    embed_checksums: bool,
+    atomic: bool,
+    #[cfg(feature = "signing")]
+    signing_key: Option<RsaPrivateKey>,
+}
+
+impl ObbyWriterBuilder {
+    /// Creates a builder for an archive of the given plugin
+    ///
+    /// # Arguments
+    ///
+    /// * `plugin_assembly` - The plugin's assembly name (e.g. `MyPlugin`).
+    /// * `plugin_version` - The plugin's version (e.g. `1.2.3`).
+    pub fn new(plugin_assembly: &str, plugin_version: &str) -> Self {
+        ObbyWriterBuilder {
+            plugin_assembly: plugin_assembly.to_string(),
+            plugin_version: plugin_version.to_string(),
+            api_version: None,
+            compression: Compression::default(),
+            name_mapper: None,
+            reproducible: false,
+            write_extras: false,
+            #[cfg(feature = "json")]
+            embed_checksums: false,
+            atomic: true,
+            #[cfg(feature = "signing")]
+            signing_key: None,
+        }
+    }
+
+    /// See [`ObbyWriter::set_api_version`]
+    pub fn api_version(mut self, api_version: &str) -> Self {
+        self.api_version = Some(api_version.to_string());
+        self
+    }
+
+    /// See [`ObbyWriter::set_compression`]
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// See [`ObbyWriter::set_name_mapper`]
+    pub fn name_mapper<F: Fn(&str) -> String + Send + Sync + 'static>(mut self, mapper: F) -> Self {
+        self.name_mapper = Some(Box::new(mapper));
+        self
+    }
+
+    /// See [`ObbyWriter::set_reproducible`]
+    pub fn reproducible(mut self, reproducible: bool) -> Self {
+        self.reproducible = reproducible;
+        self
+    }
+
+    /// See [`ObbyWriter::set_write_extras`]
+    pub fn write_extras(mut self, write_extras: bool) -> Self {
+        self.write_extras = write_extras;
+        self
+    }
+
+    /// See [`ObbyWriter::set_embed_checksums`]
+    #[cfg(feature = "json")]
+    pub fn embed_checksums(mut self, embed_checksums: bool) -> Self {
+        self.embed_checksums = embed_checksums;
+        self
+    }
+
+    /// Chooses whether [`ObbyWriterBuilder::create`] writes through a temporary file
+    ///
+    /// See [`ObbyWriter::set_atomic`]. Has no effect on [`ObbyWriterBuilder::build`].
+    pub fn atomic(mut self, atomic: bool) -> Self {
+        self.atomic = atomic;
+        self
+    }
+
+    /// Signs the archive with the given RSA-3072 key; see [`ObbyWriter::set_signing_key`]
+    ///
+    /// The key size is checked when the writer is built.
+    #[cfg(feature = "signing")]
+    pub fn signing_key(mut self, key: RsaPrivateKey) -> Self {
+        self.signing_key = Some(key);
+        self
+    }
+
+    /// Creates a writer that will write the archive to `sink`
+    ///
+    /// # Returns
+    ///
+    /// The configured `ObbyWriter`, or an `io::Error` if the signing key is unusable.
+    pub fn build<W: Write>(self, sink: W) -> io::Result<ObbyWriter<W>> {
+        let mut writer = ObbyWriter::new(sink, &self.plugin_assembly, &self.plugin_version);
+        if let Some(api_version) = &self.api_version {
+            writer.set_api_version(api_version);
+        }
+        writer.set_compression(self.compression);
+        if let Some(mapper) = self.name_mapper {
+            writer.set_name_mapper(mapper);
+        }
+        writer.set_reproducible(self.reproducible);
+        writer.set_write_extras(self.write_extras);
+        #[cfg(feature = "json")]
+        writer.set_embed_checksums(self.embed_checksums);
+        #[cfg(feature = "signing")]
+        if let Some(key) = self.signing_key {
+            writer.set_signing_key(key)?;
+        }
+        Ok(writer)
+    }
+
+    /// Creates a writer that will write the archive to the file at `path`
+    ///
+    /// Like [`ObbyWriter::create`], the file is written atomically unless
+    /// [`ObbyWriterBuilder::atomic`] turned that off.
+    pub fn create<P: AsRef<Path>>(self, path: P) -> io::Result<ObbyWriter<OutputFile>> {
+        let mut output = OutputFile::new(path);
+        output.set_atomic(self.atomic);
+        self.build(output)
+    }
+}
+
+impl ObbyWriter<OutputFile> {
+    /// Starts configuring a writer for the given plugin
+    ///
+    /// The returned [`ObbyWriterBuilder`] can write to any sink, not just files.
+    pub fn builder(plugin_assembly: &str, plugin_version: &str) -> ObbyWriterBuilder {
+        ObbyWriterBuilder::new(plugin_assembly, plugin_version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+
+    #[test]
+    fn test_archive_builder() {
+        let buffer = ObbyTestBuilder::new()
+            .entry(".\\lib\\main.dll", b"dll")
+            .entry("plugin.json", b"{}")
+            .entry("notes.txt", &[b'x'; 64])
+            .build();
+
+        let mut archive = ObbyArchive::builder()
+            .normalize_names(true)
+            .max_entry_size(16)
+            .from_bytes(buffer.clone())
+            .unwrap();
+        assert_eq!(archive.list_entries(), vec!["lib/main.dll", "plugin.json", "notes.txt"]);
+        assert_eq!(archive.extract_entry("lib/main.dll").unwrap(), b"dll");
+        assert!(archive.extract_entry("notes.txt").is_err());
+
+        let archive = ObbyArchive::builder().entry_prefix("plugin").open(Cursor::new(&buffer[..])).unwrap();
+        assert_eq!(archive.list_entries(), vec!["plugin.json"]);
+    }
+
+    #[test]
+    fn test_writer_builder() {
+        let writer = ObbyWriter::builder("Plugin", "1.0.0")
+            .api_version("2.0.0")
+            .name_mapper(crate::normalize_entry_name)
+            .reproducible(true);
+        let mut writer = writer.build(Vec::new()).unwrap();
+        writer.add_entry("b.txt", b"b").unwrap();
+        writer.add_entry(".\\a.txt", b"a").unwrap();
+        let archive = ObbyArchive::from_bytes(writer.finish().unwrap()).unwrap();
+        assert_eq!(archive.metadata().api_version, "2.0.0");
+        assert_eq!(archive.list_entries(), vec!["a.txt", "b.txt"]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("plugin.obby");
+        ObbyWriter::builder("Plugin", "1.0.0").atomic(false).create(&path).unwrap().finish().unwrap();
+        assert!(crate::open(&path).unwrap().list_entries().is_empty());
+    }
+}
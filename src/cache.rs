@@ -0,0 +1,165 @@
+//! Memoizing wrapper for repeated extraction
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Read, Seek};
+use std::sync::Arc;
+
+use crate::ObbyArchive;
+
+/// An `ObbyArchive` that keeps recently extracted entries in memory
+///
+/// Decompressed entries are cached up to a total size in bytes; when adding an entry
+/// would exceed it, the least recently used entries are evicted. Entries larger than the
+/// whole budget are never cached. Cached data is shared as `Arc<[u8]>`, so handing it
+/// out doesn't copy.
+///
+/// # Example
+///
+/// ```no_run
+/// use obsidian_lib::CachedObbyArchive;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut cached = CachedObbyArchive::new(obsidian_lib::open("plugin.obby")?, 16 * 1024 * 1024);
+/// let first = cached.extract_entry("icon.png")?; // decompressed
+/// let again = cached.extract_entry("icon.png")?; // served from memory
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct CachedObbyArchive<R: Read + Seek> {
+    archive: ObbyArchive<R>,
+    max_bytes: usize,
+    used_bytes: usize,
+    /// Cached data and the tick of its last use
+    entries: HashMap<String, (Arc<[u8]>, u64)>,
+    /// Entry names by the tick of their last use, oldest first
+    recency: BTreeMap<u64, String>,
+    tick: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl<R: Read + Seek> CachedObbyArchive<R> {
+    /// Wraps an archive with a cache of at most `max_bytes` of decompressed data
+    ///
+    /// # Arguments
+    ///
+    /// * `archive` - The archive to read entries from.
+    /// * `max_bytes` - The cache budget. `0` disables caching.
+    pub fn new(archive: ObbyArchive<R>, max_bytes: usize) -> Self {
+        CachedObbyArchive {
+            archive,
+            max_bytes,
+            used_bytes: 0,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            tick: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Returns the wrapped archive
+    pub fn archive(&self) -> &ObbyArchive<R> {
+        &self.archive
+    }
+
+    /// Consumes the cache, returning the wrapped archive
+    pub fn into_inner(self) -> ObbyArchive<R> {
+        self.archive
+    }
+
+    /// Extracts an entry, serving it from the cache when possible
+    ///
+    /// # Arguments
+    ///
+    /// * `entry_name` - The name of the entry to extract.
+    ///
+    /// # Returns
+    ///
+    /// The entry's decompressed data. Errors are not cached.
+    pub fn extract_entry(&mut self, entry_name: &str) -> io::Result<Arc<[u8]>> {
+        self.tick += 1;
+        if let Some((data, last_used)) = self.entries.get_mut(entry_name) {
+            self.recency.remove(last_used);
+            *last_used = self.tick;
+            self.recency.insert(self.tick, entry_name.to_string());
+            self.hits += 1;
+            return Ok(Arc::clone(data));
+        }
+
+        self.misses += 1;
+        let data: Arc<[u8]> = self.archive.extract_entry(entry_name)?.into();
+        if data.len() <= self.max_bytes {
+            while self.used_bytes + data.len() > self.max_bytes {
+                self.evict_oldest();
+            }
+            self.used_bytes += data.len();
+            self.entries.insert(entry_name.to_string(), (Arc::clone(&data), self.tick));
+            self.recency.insert(self.tick, entry_name.to_string());
+        }
+        Ok(data)
+    }
+
+    /// Returns the total size of the cached entries in bytes
+    pub fn cached_bytes(&self) -> usize {
+        self.used_bytes
+    }
+
+    /// Returns how many extractions were served from the cache and how many were not
+    pub fn hit_stats(&self) -> (u64, u64) {
+        (self.hits, self.misses)
+    }
+
+    /// Drops all cached entries
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+        self.used_bytes = 0;
+    }
+
+    fn evict_oldest(&mut self) {
+        if let Some((_, name)) = self.recency.pop_first() {
+            if let Some((data, _)) = self.entries.remove(&name) {
+                self.used_bytes -= data.len();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_lru_eviction() {
+        let buffer = ObbyTestBuilder::new()
+            .entry("a", &[1u8; 40])
+            .entry("b", &[2u8; 40])
+            .entry("c", &[3u8; 40])
+            .entry("huge", &[4u8; 200])
+            .build();
+        let mut cached = CachedObbyArchive::new(ObbyArchive::new(Cursor::new(buffer)).unwrap(), 100);
+
+        cached.extract_entry("a").unwrap();
+        cached.extract_entry("b").unwrap();
+        cached.extract_entry("a").unwrap();
+        assert_eq!(cached.hit_stats(), (1, 2));
+
+        // Adding c evicts b, the least recently used
+        cached.extract_entry("c").unwrap();
+        assert_eq!(cached.cached_bytes(), 80);
+        assert_eq!(&*cached.extract_entry("a").unwrap(), &[1u8; 40]);
+        assert_eq!(cached.hit_stats(), (2, 3));
+        cached.extract_entry("b").unwrap();
+        assert_eq!(cached.hit_stats(), (2, 4));
+
+        // Entries over the budget are returned but not cached
+        assert_eq!(cached.extract_entry("huge").unwrap().len(), 200);
+        assert_eq!(cached.cached_bytes(), 80);
+
+        assert!(cached.extract_entry("missing").is_err());
+    }
+}
@@ -0,0 +1,187 @@
+//! Extracting entries into a content-addressed store shared between archives
+
+use std::fs;
+use std::io::{self, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Map, Value};
+
+use crate::output::{create_temp, OutputFile};
+use crate::{to_hex, HashAlgo, ObbyArchive};
+
+/// Directory below the store root holding the payloads
+const OBJECTS_DIR: &str = "sha256";
+
+/// Directory below the store root holding one manifest per archive
+const MANIFESTS_DIR: &str = "manifests";
+
+/// An entry written by [`ObbyArchive::extract_into_store`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct StoredEntry {
+    /// Name of the entry
+    pub name: String,
+    /// SHA-256 of the decompressed entry, as lowercase hex
+    pub sha256: String,
+    /// Decompressed size in bytes
+    pub size: u64,
+}
+
+/// What [`ObbyArchive::extract_into_store`] wrote
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct StoreManifest {
+    /// Path of the manifest file mapping entry names to hashes
+    pub path: PathBuf,
+    /// Every entry, in archive order
+    pub entries: Vec<StoredEntry>,
+    /// Number of payloads that weren't in the store yet
+    pub objects_written: usize,
+}
+
+impl StoreManifest {
+    /// Returns the path of an entry's payload in the store
+    pub fn object_path<P: AsRef<Path>>(&self, store_dir: P, name: &str) -> Option<PathBuf> {
+        let entry = self.entries.iter().find(|entry| entry.name == name)?;
+        Some(store_object_path(store_dir, &entry.sha256))
+    }
+}
+
+/// Returns where a payload with the given SHA-256 is kept in a store
+///
+/// Payloads are spread over subdirectories by the first two hex digits, as in
+/// `sha256/ab/cdef…`, so no directory grows too large.
+///
+/// # Arguments
+///
+/// * `store_dir` - The root of the store.
+/// * `sha256` - The payload's SHA-256 as lowercase hex.
+pub fn store_object_path<P: AsRef<Path>>(store_dir: P, sha256: &str) -> PathBuf {
+    let (prefix, rest) = sha256.split_at(2.min(sha256.len()));
+    store_dir.as_ref().join(OBJECTS_DIR).join(prefix).join(rest)
+}
+
+impl<R: Read + Seek> ObbyArchive<R> {
+    /// Extracts every entry into a content-addressed store
+    ///
+    /// Each entry's payload is stored once under its SHA-256 (see [`store_object_path`]),
+    /// so installing many plugins, or many versions of one, keeps a single copy of
+    /// every shared file. Payloads already in the store are not written again. A JSON
+    /// manifest mapping entry names to hashes and sizes is written to
+    /// `manifests/<hash>.json`, where `<hash>` is the archive's own SHA-384 from its
+    /// header, so each archive version gets its own manifest.
+    ///
+    /// Payloads and the manifest are written to temporary files and renamed into place,
+    /// so several processes can fill the same store at once and an interrupted
+    /// extraction never leaves a partial payload behind. The manifest is written last,
+    /// once all payloads are in place.
+    ///
+    /// # Arguments
+    ///
+    /// * `store_dir` - The root of the store. It is created if it doesn't exist.
+    ///
+    /// # Returns
+    ///
+    /// The [`StoreManifest`], or an `io::Error` if an entry can't be read or a file
+    /// can't be written.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut archive = obsidian_lib::open("plugin.obby")?;
+    /// let stored = archive.extract_into_store("/var/cache/launcher/store")?;
+    /// println!("{} new payloads, manifest at {}", stored.objects_written, stored.path.display());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn extract_into_store<P: AsRef<Path>>(&mut self, store_dir: P) -> io::Result<StoreManifest> {
+        let store_dir = store_dir.as_ref();
+        let mut entries = Vec::with_capacity(self.order.len());
+        let mut objects_written = 0;
+        for name in self.order.clone() {
+            let (data, digest) = self.extract_entry_hashed(&name, HashAlgo::Sha256)?;
+            let sha256 = digest.to_hex();
+            let object = store_object_path(store_dir, &sha256);
+            if !object.is_file() {
+                write_atomically(&object, &data)?;
+                objects_written += 1;
+            }
+            entries.push(StoredEntry { name, sha256, size: data.len() as u64 });
+        }
+
+        let files: Map<String, Value> = entries
+            .iter()
+            .map(|entry| (entry.name.clone(), json!({ "sha256": entry.sha256, "size": entry.size })))
+            .collect();
+        let manifest = json!({
+            "assembly": self.metadata.plugin_assembly,
+            "version": self.metadata.plugin_version,
+            "entries": files,
+        });
+        let path = store_dir.join(MANIFESTS_DIR).join(format!("{}.json", to_hex(&self.metadata.hash)));
+        fs::create_dir_all(store_dir.join(MANIFESTS_DIR))?;
+        let mut out = OutputFile::new(&path);
+        serde_json::to_writer_pretty(&mut out, &manifest)?;
+        out.flush()?;
+
+        Ok(StoreManifest { path, entries, objects_written })
+    }
+}
+
+/// Writes `data` to a temporary file next to `path` and renames it into place
+pub(crate) fn write_atomically(path: &Path, data: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let (mut file, temp) = create_temp(path)?;
+    let result = file
+        .write_all(data)
+        .and_then(|()| file.sync_all())
+        .and_then(|()| fs::rename(&temp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+
+    #[test]
+    fn test_extract_into_store() {
+        let store = tempfile::tempdir().unwrap();
+        let first = ObbyTestBuilder::new()
+            .plugin("Plugin", "1.0.0")
+            .entry("plugin.json", br#"{"version": "1.0.0"}"#)
+            .entry("lib/Shared.dll", b"shared library")
+            .stored_entry("copy.dll", b"shared library")
+            .build();
+        let second = ObbyTestBuilder::new()
+            .plugin("Plugin", "1.1.0")
+            .entry("plugin.json", br#"{"version": "1.1.0"}"#)
+            .entry("lib/Shared.dll", b"shared library")
+            .build();
+
+        let stored = ObbyArchive::from_bytes(first).unwrap().extract_into_store(store.path()).unwrap();
+        assert_eq!(stored.objects_written, 2);
+        assert_eq!(stored.entries[1].sha256, stored.entries[2].sha256);
+        let object = stored.object_path(store.path(), "copy.dll").unwrap();
+        assert_eq!(fs::read(&object).unwrap(), b"shared library");
+        assert_eq!(object.parent().unwrap().file_name().unwrap().len(), 2);
+
+        let manifest: Value = serde_json::from_slice(&fs::read(&stored.path).unwrap()).unwrap();
+        assert_eq!(manifest["version"], "1.0.0");
+        assert_eq!(manifest["entries"]["lib/Shared.dll"]["sha256"], stored.entries[1].sha256.as_str());
+        assert_eq!(manifest["entries"]["lib/Shared.dll"]["size"], 14);
+
+        // The second version only adds its own plugin.json
+        let stored_again = ObbyArchive::from_bytes(second).unwrap().extract_into_store(store.path()).unwrap();
+        assert_eq!(stored_again.objects_written, 1);
+        assert_ne!(stored_again.path, stored.path);
+        assert_eq!(fs::read_dir(store.path().join(MANIFESTS_DIR)).unwrap().count(), 2);
+        assert_eq!(stored_again.object_path(store.path(), "missing"), None);
+    }
+}
@@ -0,0 +1,353 @@
+//! Inventory of the plugins installed in a directory
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::scan;
+
+#[cfg(feature = "notify")]
+pub use crate::watch::CatalogWatcher;
+
+/// The plugins found in a directory tree, built by [`Catalog::build`]
+///
+/// Every `.obby` file under the root is opened and its manifest read. Archives that
+/// can't be read are listed in `failures` rather than failing the whole catalog, and
+/// plugin IDs claimed by more than one archive are listed in `conflicts`.
+///
+/// With the `serde` feature enabled the catalog implements `Serialize`, and
+/// [`Catalog::write_json`] writes it as JSON.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// let catalog = obsidian_lib::catalog::Catalog::build("plugins")?;
+/// for plugin in &catalog.plugins {
+///     println!("{} {}", plugin.id, plugin.version);
+/// }
+/// for conflict in &catalog.conflicts {
+///     eprintln!("{} is installed {} times", conflict.id, conflict.paths.len());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Catalog {
+    /// The directory that was scanned
+    pub root: PathBuf,
+    /// Readable archives, ordered by path
+    pub plugins: Vec<CatalogEntry>,
+    /// Archives and directories that couldn't be read, ordered by path
+    pub failures: Vec<CatalogFailure>,
+    /// Plugin IDs used by more than one archive, ordered by ID
+    pub conflicts: Vec<CatalogConflict>,
+}
+
+/// One installed plugin in a [`Catalog`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CatalogEntry {
+    /// Path of the archive
+    pub path: PathBuf,
+    /// The manifest's `id`, or the assembly name if the manifest has none
+    pub id: String,
+    /// The manifest's `name`, if present
+    pub name: Option<String>,
+    /// The manifest's `version`, or the version in the header if the manifest has none
+    pub version: String,
+    /// The Obsidian API version the plugin was built against
+    pub api_version: String,
+    /// The plugin's assembly name
+    pub plugin_assembly: String,
+    /// Whether the archive carries a signature
+    pub signed: bool,
+    /// The parsed manifest
+    pub manifest: serde_json::Value,
+}
+
+/// An archive or directory a [`Catalog`] couldn't read
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CatalogFailure {
+    /// The path that failed
+    pub path: PathBuf,
+    /// Why it failed
+    pub error: String,
+}
+
+/// A plugin ID claimed by several archives in a [`Catalog`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CatalogConflict {
+    /// The plugin ID
+    pub id: String,
+    /// Paths of the archives using it, ordered by path
+    pub paths: Vec<PathBuf>,
+    /// The distinct versions among those archives, sorted
+    pub versions: Vec<String>,
+}
+
+impl CatalogConflict {
+    /// Whether the archives disagree on the version, rather than being copies of one release
+    pub fn is_version_conflict(&self) -> bool {
+        self.versions.len() > 1
+    }
+}
+
+/// How an archive changed, reported by [`Catalog::refresh`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum CatalogChange {
+    /// An archive appeared
+    Added(PathBuf),
+    /// An archive disappeared
+    Removed(PathBuf),
+    /// An archive was already in the catalog and has been read again
+    Changed(PathBuf),
+}
+
+impl Catalog {
+    /// Scans `root` recursively and builds a catalog of the plugins in it
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The plugins directory.
+    ///
+    /// # Returns
+    ///
+    /// The `Catalog`, or an `io::Error` if `root` itself can't be read.
+    pub fn build<P: AsRef<Path>>(root: P) -> io::Result<Catalog> {
+        let root = root.as_ref();
+        std::fs::read_dir(root)?;
+
+        let mut catalog = Catalog {
+            root: root.to_path_buf(),
+            plugins: Vec::new(),
+            failures: Vec::new(),
+            conflicts: Vec::new(),
+        };
+        for (path, result) in scan::walk(root) {
+            catalog.add(path, result.map(|_| ()));
+        }
+        catalog.plugins.sort_by(|a, b| a.path.cmp(&b.path));
+        catalog.failures.sort_by(|a, b| a.path.cmp(&b.path));
+        catalog.find_conflicts();
+        Ok(catalog)
+    }
+
+    /// Brings the catalog up to date with a path that changed on disk
+    ///
+    /// `path` may be an archive or a directory. Archives at or under it that no longer
+    /// exist are dropped, and those that exist are read again, so a directory that was
+    /// moved into or out of the tree is handled in one call. `conflicts` is recomputed.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path that changed, inside `root`.
+    ///
+    /// # Returns
+    ///
+    /// What changed, ordered by path; empty if `path` holds no archives before or after.
+    pub fn refresh(&mut self, path: &Path) -> Vec<CatalogChange> {
+        let mut before: Vec<PathBuf> = self
+            .plugins
+            .iter()
+            .map(|plugin| &plugin.path)
+            .chain(self.failures.iter().map(|failure| &failure.path))
+            .filter(|known| known.starts_with(path))
+            .cloned()
+            .collect();
+        before.sort();
+        self.plugins.retain(|plugin| !plugin.path.starts_with(path));
+        self.failures.retain(|failure| !failure.path.starts_with(path));
+
+        let mut changes = Vec::new();
+        let mut after = Vec::new();
+        if path.is_dir() {
+            for (archive, result) in scan::walk(path) {
+                after.push(archive.clone());
+                self.add(archive, result.map(|_| ()));
+            }
+        } else if path.is_file() && scan::is_archive_path(path) {
+            after.push(path.to_path_buf());
+            self.add(path.to_path_buf(), Ok(()));
+        }
+        for archive in &before {
+            if !after.contains(archive) {
+                changes.push(CatalogChange::Removed(archive.clone()));
+            }
+        }
+        for archive in after {
+            if before.contains(&archive) {
+                changes.push(CatalogChange::Changed(archive));
+            } else {
+                changes.push(CatalogChange::Added(archive));
+            }
+        }
+        changes.sort_by(|a, b| a.path().cmp(b.path()));
+
+        self.plugins.sort_by(|a, b| a.path.cmp(&b.path));
+        self.failures.sort_by(|a, b| a.path.cmp(&b.path));
+        self.find_conflicts();
+        changes
+    }
+
+    /// Returns the first plugin with the given ID, by path order
+    pub fn get(&self, id: &str) -> Option<&CatalogEntry> {
+        self.plugins.iter().find(|plugin| plugin.id == id)
+    }
+
+    /// Writes the catalog as pretty-printed JSON
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - Where to write the JSON.
+    #[cfg(feature = "serde")]
+    pub fn write_json<W: io::Write>(&self, out: W) -> io::Result<()> {
+        serde_json::to_writer_pretty(out, self).map_err(io::Error::from)
+    }
+
+    /// Reads the archive at `path` into `plugins`, or records `scanned` or the read error in `failures`
+    fn add(&mut self, path: PathBuf, scanned: io::Result<()>) {
+        match scanned.and_then(|_| CatalogEntry::load(&path)) {
+            Ok(entry) => self.plugins.push(entry),
+            Err(e) => self.failures.push(CatalogFailure { path, error: e.to_string() }),
+        }
+    }
+
+    /// Recomputes `conflicts` from `plugins`
+    fn find_conflicts(&mut self) {
+        let mut by_id: BTreeMap<&str, Vec<&CatalogEntry>> = BTreeMap::new();
+        for plugin in &self.plugins {
+            by_id.entry(&plugin.id).or_default().push(plugin);
+        }
+        self.conflicts = by_id
+            .into_iter()
+            .filter(|(_, plugins)| plugins.len() > 1)
+            .map(|(id, plugins)| {
+                let mut versions: Vec<String> = plugins.iter().map(|plugin| plugin.version.clone()).collect();
+                versions.sort();
+                versions.dedup();
+                CatalogConflict {
+                    id: id.to_string(),
+                    paths: plugins.iter().map(|plugin| plugin.path.clone()).collect(),
+                    versions,
+                }
+            })
+            .collect();
+    }
+}
+
+impl CatalogChange {
+    /// The archive's path
+    pub fn path(&self) -> &Path {
+        match self {
+            CatalogChange::Added(path) | CatalogChange::Removed(path) | CatalogChange::Changed(path) => path,
+        }
+    }
+}
+
+impl CatalogEntry {
+    /// Opens the archive at `path` and reads its manifest
+    fn load(path: &Path) -> io::Result<CatalogEntry> {
+        let mut archive = crate::open(path)?;
+        let manifest_name = archive.find_manifest()?.to_string();
+        let manifest: serde_json::Value = serde_json::from_slice(&archive.extract_entry(&manifest_name)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid {}: {}", manifest_name, e)))?;
+        let field = |name| manifest.get(name).and_then(serde_json::Value::as_str).map(str::to_string);
+
+        let metadata = archive.metadata();
+        Ok(CatalogEntry {
+            path: path.to_path_buf(),
+            id: field("id").unwrap_or_else(|| metadata.plugin_assembly.clone()),
+            name: field("name"),
+            version: field("version").unwrap_or_else(|| metadata.plugin_version.clone()),
+            api_version: metadata.api_version.clone(),
+            plugin_assembly: metadata.plugin_assembly.clone(),
+            signed: metadata.signature.is_some(),
+            manifest,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+    use std::fs;
+
+    fn plugin(manifest: &str) -> Vec<u8> {
+        ObbyTestBuilder::new()
+            .plugin("Assembly", "0.1.0")
+            .entry("plugin.json", manifest.as_bytes())
+            .build()
+    }
+
+    #[test]
+    fn test_build_catalog() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("old")).unwrap();
+        fs::write(dir.path().join("a.obby"), plugin(r#"{"id": "alpha", "name": "Alpha", "version": "2.0.0"}"#)).unwrap();
+        fs::write(dir.path().join("b.obby"), plugin(r#"{"id": "beta", "version": "1.0.0"}"#)).unwrap();
+        fs::write(dir.path().join("c.obby"), plugin(r#"{"name": "No ID"}"#)).unwrap();
+        fs::write(dir.path().join("old/a.obby"), plugin(r#"{"id": "alpha", "version": "1.0.0"}"#)).unwrap();
+        fs::write(dir.path().join("old/b.obby"), plugin(r#"{"id": "beta", "version": "1.0.0"}"#)).unwrap();
+        fs::write(dir.path().join("bad.obby"), plugin("not json")).unwrap();
+
+        let catalog = Catalog::build(dir.path()).unwrap();
+        assert_eq!(catalog.plugins.len(), 5);
+        let alpha = catalog.get("alpha").unwrap();
+        assert_eq!(alpha.name.as_deref(), Some("Alpha"));
+        assert_eq!(alpha.version, "2.0.0");
+        let fallback = catalog.get("Assembly").unwrap();
+        assert_eq!(fallback.version, "0.1.0");
+
+        assert_eq!(catalog.failures.len(), 1);
+        assert!(catalog.failures[0].path.ends_with("bad.obby"));
+
+        assert_eq!(catalog.conflicts.len(), 2);
+        assert_eq!(catalog.conflicts[0].id, "alpha");
+        assert_eq!(catalog.conflicts[0].versions, vec!["1.0.0", "2.0.0"]);
+        assert!(catalog.conflicts[0].is_version_conflict());
+        assert!(!catalog.conflicts[1].is_version_conflict());
+
+        assert!(Catalog::build(dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_refresh() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.obby"), plugin(r#"{"id": "alpha", "version": "1.0.0"}"#)).unwrap();
+        let mut catalog = Catalog::build(dir.path()).unwrap();
+
+        fs::write(dir.path().join("a.obby"), plugin(r#"{"id": "alpha", "version": "1.1.0"}"#)).unwrap();
+        let a = dir.path().join("a.obby");
+        assert_eq!(catalog.refresh(&a), vec![CatalogChange::Changed(a.clone())]);
+        assert_eq!(catalog.get("alpha").unwrap().version, "1.1.0");
+
+        fs::create_dir(dir.path().join("more")).unwrap();
+        fs::write(dir.path().join("more/b.obby"), plugin(r#"{"id": "alpha", "version": "1.0.0"}"#)).unwrap();
+        let more = dir.path().join("more");
+        assert_eq!(catalog.refresh(&more), vec![CatalogChange::Added(more.join("b.obby"))]);
+        assert_eq!(catalog.conflicts.len(), 1);
+
+        fs::remove_dir_all(&more).unwrap();
+        assert_eq!(catalog.refresh(&more), vec![CatalogChange::Removed(more.join("b.obby"))]);
+        assert!(catalog.conflicts.is_empty());
+        assert!(catalog.refresh(&dir.path().join("notes.txt")).is_empty());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_write_json() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.obby"), plugin(r#"{"id": "alpha", "version": "1.0.0"}"#)).unwrap();
+        let mut out = Vec::new();
+        Catalog::build(dir.path()).unwrap().write_json(&mut out).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(json["plugins"][0]["id"], "alpha");
+        assert_eq!(json["plugins"][0]["manifest"]["version"], "1.0.0");
+    }
+}
@@ -0,0 +1,136 @@
+//! Per-entry checksums embedded in the archive as `checksums.json`
+//!
+//! The header's hash covers the whole data section, so it only says that something
+//! changed. The checksums entry, written by [`crate::ObbyWriter::set_embed_checksums`],
+//! lists the SHA-256 of every other entry's contents:
+//!
+//! ```json
+//! {
+//!   "sha256": {
+//!     "plugin.json": "5d1be7e9...",
+//!     "MyPlugin.dll": "0c8f2a41..."
+//!   }
+//! }
+//! ```
+
+use std::io::{self, Read, Seek};
+
+use serde_json::{Map, Value};
+
+use crate::{to_hex, HashAlgo, ObbyArchive, ObbyError};
+
+/// Name of the entry holding the checksums
+pub const CHECKSUMS_NAME: &str = "checksums.json";
+
+/// Serializes the checksums of `entries`, given as names with their SHA-256 digests
+pub(crate) fn checksums_json<'a>(entries: impl Iterator<Item = (&'a str, &'a [u8])>) -> Vec<u8> {
+    let sha256: Map<String, Value> = entries.map(|(name, digest)| (name.to_string(), Value::String(to_hex(digest)))).collect();
+    let mut json = serde_json::to_vec_pretty(&serde_json::json!({ "sha256": sha256 })).expect("JSON values always serialize");
+    json.push(b'\n');
+    json
+}
+
+impl<R: Read + Seek> ObbyArchive<R> {
+    /// Checks every entry against the checksums stored in the archive's `checksums.json`
+    ///
+    /// This gives integrity at the entry level even for unsigned archives, and names
+    /// the entries that changed. Note that the checksums are only as trustworthy as the
+    /// archive itself: anyone who can modify entries can rewrite `checksums.json` too,
+    /// unless the archive is signed.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if every entry is listed with a matching checksum and every listed entry
+    /// exists, an `io::Error` of kind `NotFound` if there is no [`CHECKSUMS_NAME`] entry,
+    /// or of kind `InvalidData` carrying [`ObbyError::ChecksumMismatch`] otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use obsidian_lib::ObbyError;
+    ///
+    /// let mut archive = obsidian_lib::open("plugin.obby").unwrap();
+    /// if let Err(e) = archive.verify_embedded_checksums() {
+    ///     if let Some(ObbyError::ChecksumMismatch { entries }) = ObbyError::from_io(&e) {
+    ///         eprintln!("modified: {:?}", entries);
+    ///     }
+    /// }
+    /// ```
+    pub fn verify_embedded_checksums(&mut self) -> io::Result<()> {
+        let json = self.extract_entry(CHECKSUMS_NAME)?;
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid {}: {}", CHECKSUMS_NAME, message));
+        let value: Value = serde_json::from_slice(&json).map_err(|e| invalid(&e.to_string()))?;
+        let Some(Value::Object(mut expected)) = value.get("sha256").cloned() else {
+            return Err(invalid("no \"sha256\" object"));
+        };
+
+        let mut mismatched = Vec::new();
+        for name in self.list_entries() {
+            if name == CHECKSUMS_NAME {
+                continue;
+            }
+            let matches = match expected.shift_remove(&name) {
+                Some(Value::String(hex)) => {
+                    let (_, digest) = self.extract_entry_hashed(&name, HashAlgo::Sha256)?;
+                    hex.eq_ignore_ascii_case(&digest.to_hex())
+                }
+                _ => false,
+            };
+            if !matches {
+                mismatched.push(name);
+            }
+        }
+        // Listed entries that don't exist
+        mismatched.extend(expected.into_iter().map(|(name, _)| name));
+
+        if mismatched.is_empty() {
+            Ok(())
+        } else {
+            Err(ObbyError::ChecksumMismatch { entries: mismatched }.into_io())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+    use crate::ObbyWriter;
+
+    #[test]
+    fn test_embedded_checksums() {
+        let mut writer = ObbyWriter::new(Vec::new(), "Plugin", "1.0.0");
+        writer.set_embed_checksums(true);
+        writer.add_entry("plugin.json", b"{}").unwrap();
+        writer.add_entry_from_reader("main.js", &b"console.log(1)"[..]).unwrap();
+        let mut archive = ObbyArchive::from_bytes(writer.finish().unwrap()).unwrap();
+        assert_eq!(archive.list_entries(), vec!["plugin.json", "main.js", CHECKSUMS_NAME]);
+        archive.verify_embedded_checksums().unwrap();
+
+        // Copying entries keeps their checksums
+        let mut writer = ObbyWriter::new(Vec::new(), "Plugin", "1.0.0");
+        writer.set_embed_checksums(true);
+        writer.set_reproducible(true);
+        writer.copy_entry_from(&mut archive, "main.js").unwrap();
+        let mut copy = ObbyArchive::from_bytes(writer.finish().unwrap()).unwrap();
+        assert_eq!(copy.list_entries(), vec![CHECKSUMS_NAME, "main.js"]);
+        copy.verify_embedded_checksums().unwrap();
+
+        // A modified entry, an unlisted one and a missing one
+        let checksums = archive.extract_entry(CHECKSUMS_NAME).unwrap();
+        let tampered = ObbyTestBuilder::new()
+            .entry("main.js", b"console.log(2)")
+            .entry("extra.js", b"")
+            .entry(CHECKSUMS_NAME, &checksums)
+            .build();
+        let err = ObbyArchive::from_bytes(tampered).unwrap().verify_embedded_checksums().unwrap_err();
+        let Some(ObbyError::ChecksumMismatch { entries }) = ObbyError::from_io(&err) else {
+            panic!("unexpected error {}", err);
+        };
+        assert_eq!(entries, &["main.js", "extra.js", "plugin.json"]);
+
+        let plain = ObbyTestBuilder::new().entry("main.js", b"").build();
+        let err = ObbyArchive::from_bytes(plain).unwrap().verify_embedded_checksums().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+}
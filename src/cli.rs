@@ -0,0 +1,251 @@
+//! Command-line interface definition of `obby`
+//!
+//! Kept apart from the command implementations so the build script can include it to
+//! generate man pages.
+
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+
+/// Inspect and extract Obsidian `.obby` plugin archives
+///
+/// Exit statuses: 1 other failure, 2 invalid archive, 3 file or entry not found,
+/// 4 verification failed, 5 unsafe entry name, 64 invalid usage.
+#[derive(Parser)]
+#[command(name = "obby", version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Path to an `.obby` file to run the reader examples against
+    pub file: Option<PathBuf>,
+
+    /// How to print errors to stderr
+    #[arg(long, global = true, value_enum, default_value_t = ErrorFormat::Text)]
+    pub error_format: ErrorFormat,
+}
+
+/// Handling of existing files by `obby extract`, chosen with `--overwrite`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OnExisting {
+    /// Fail before writing anything
+    Error,
+    /// Keep the existing file
+    Skip,
+    /// Replace the existing file
+    Overwrite,
+    /// Write next to the existing file as `name (1).ext`
+    Rename,
+}
+
+/// Skeleton generated by `obby new`, chosen with `--template`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Template {
+    /// `plugin.json` and a README
+    Basic,
+    /// Only `plugin.json`
+    Minimal,
+}
+
+/// Format of error messages, chosen with `--error-format`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ErrorFormat {
+    /// A human-readable line
+    Text,
+    /// A single-line JSON object with the exit status, failure class and message
+    Json,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Write the raw (decompressed) bytes of an entry to stdout
+    Cat {
+        /// Path to the `.obby` file
+        file: PathBuf,
+        /// Name of the entry to print
+        entry: String,
+    },
+    /// List an archive's entries with their sizes
+    List {
+        /// Path to the `.obby` file
+        file: PathBuf,
+        /// Print a JSON report including metadata and SHA-256 hashes
+        #[arg(long, conflicts_with = "csv")]
+        json: bool,
+        /// Print the entries as CSV including SHA-256 hashes
+        #[arg(long)]
+        csv: bool,
+    },
+    /// Show which entries were added, removed or modified between two archives
+    Diff {
+        /// Path to the old `.obby` file
+        old: PathBuf,
+        /// Path to the new `.obby` file
+        new: PathBuf,
+        /// Print the differences as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Report entry payloads shared between archives and the space deduplication would save
+    Dedupe {
+        /// Paths to the `.obby` files
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Write a repository index listing every archive in a directory
+    ///
+    /// Saving it as `index.json` in that directory lets any static file server host the
+    /// directory as a plugin repository.
+    Index {
+        /// Directory holding the `.obby` files, searched recursively
+        dir: PathBuf,
+        /// Where to write the index; standard output if omitted
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Mirror a plugin repository into a local directory
+    ///
+    /// Downloads the archives that are new or changed since the last sync, verifies
+    /// them against the index, and writes an `index.json` for the mirror.
+    #[cfg(feature = "registry")]
+    Sync {
+        /// URL of the repository's index
+        #[arg(long = "from", value_name = "INDEX_URL")]
+        from: String,
+        /// Directory of the mirror
+        #[arg(long = "to", value_name = "DIR")]
+        to: PathBuf,
+        /// Only accept archives signed by this PEM-encoded RSA public key, or by any `.pem` file in this directory
+        #[arg(long)]
+        key: Option<PathBuf>,
+        /// Extra request header as `Name: value`, such as an API token; may be repeated
+        #[arg(long = "header", value_name = "HEADER")]
+        headers: Vec<String>,
+        /// Proxy to connect through, or `system` to use the one in `HTTPS_PROXY` and the like
+        #[arg(long)]
+        proxy: Option<String>,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print an annotated breakdown of every header field, table row and data offset
+    ///
+    /// Useful for debugging archives from other packers: the breakdown continues past
+    /// problems that would stop other commands, up to where the file can't be read.
+    Explain {
+        /// Path to the `.obby` file
+        file: PathBuf,
+    },
+    /// Run operations over many archives as described by a TOML job file
+    Batch {
+        /// Path to the job file
+        jobs: PathBuf,
+    },
+    /// Browse an archive interactively in the terminal
+    #[cfg(feature = "tui")]
+    Browse {
+        /// Path to the `.obby` file
+        file: PathBuf,
+    },
+    /// Mount an archive as a read-only filesystem until it is unmounted
+    #[cfg(all(feature = "fuse", unix))]
+    Mount {
+        /// Path to the `.obby` file
+        file: PathBuf,
+        /// Existing directory to mount it on
+        mountpoint: PathBuf,
+    },
+    /// Extract every entry of an archive into a directory
+    Extract {
+        /// Path to the `.obby` file
+        file: PathBuf,
+        /// Directory to extract into
+        #[arg(long, short, default_value = ".")]
+        out: PathBuf,
+        /// Allow entry names with components starting with a dot
+        #[arg(long)]
+        allow_dotfiles: bool,
+        /// What to do with files that already exist
+        #[arg(long, value_enum, default_value_t = OnExisting::Overwrite)]
+        overwrite: OnExisting,
+        /// Entry name or path to leave out; may be repeated
+        #[arg(long, value_name = "NAME")]
+        exclude: Vec<String>,
+    },
+    /// Check an archive's hash and signature against trusted public keys
+    ///
+    /// Exits with status 4 if the archive is unsigned, tampered with, or signed by a
+    /// key that isn't trusted.
+    VerifySig {
+        /// Path to the `.obby` file
+        file: PathBuf,
+        /// PEM-encoded RSA public key, or a directory of `.pem` files to trust
+        #[arg(long)]
+        key: PathBuf,
+    },
+    /// Package a directory into a new `.obby` archive
+    Create {
+        /// Path of the archive to create
+        output: PathBuf,
+        /// Directory whose files become the archive entries
+        #[arg(long)]
+        dir: PathBuf,
+        /// Plugin assembly name
+        #[arg(long)]
+        assembly: String,
+        /// Plugin version
+        #[arg(long)]
+        version: String,
+        /// Obsidian API version to record in the header
+        #[arg(long, default_value = "1.0.0")]
+        api_version: String,
+        /// Deflate level from 0 (store uncompressed) to 9 (smallest output)
+        #[arg(long, default_value_t = 6, value_parser = clap::value_parser!(u32).range(0..=9))]
+        level: u32,
+        /// PEM-encoded RSA-3072 private key to sign the archive with
+        #[arg(long)]
+        key: Option<PathBuf>,
+    },
+    /// Generate the skeleton of a new plugin, ready to be packaged with `obby create`
+    New {
+        /// The plugin's ID, such as `my-plugin`
+        id: String,
+        /// Directory to create the plugin in; defaults to the ID
+        #[arg(long)]
+        dir: Option<PathBuf>,
+        /// Which files to generate
+        #[arg(long, value_enum, default_value_t = Template::Basic)]
+        template: Template,
+        /// Display name; defaults to the ID in title case
+        #[arg(long)]
+        name: Option<String>,
+        /// Author to list in `plugin.json`; may be repeated
+        #[arg(long)]
+        author: Vec<String>,
+    },
+    /// Print a shell completion script to stdout
+    ///
+    /// For example `obby completions bash > /usr/share/bash-completion/completions/obby`.
+    Completions {
+        /// Shell to generate the script for
+        shell: Shell,
+    },
+    /// Rewrite an archive with a different compression level
+    Repack {
+        /// Archive to read
+        input: PathBuf,
+        /// Path of the rewritten archive
+        output: PathBuf,
+        /// Deflate level from 0 (store uncompressed) to 9 (smallest output); without it,
+        /// entries are copied as stored
+        #[arg(long, value_parser = clap::value_parser!(u32).range(0..=9))]
+        level: Option<u32>,
+        /// PEM-encoded RSA-3072 private key to re-sign the archive with
+        #[arg(long)]
+        key: Option<PathBuf>,
+    },
+}
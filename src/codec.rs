@@ -0,0 +1,180 @@
+//! Pluggable decompression codecs
+//!
+//! Every `.obby` file produced by Obsidian today uses raw deflate for compressed entries
+//! (those whose `compressed_length` differs from `length`). The format carries no codec
+//! field, so the codec for an entry is chosen from a hint: the first bytes of its payload.
+//! Container formats such as gzip and zstd start with magic bytes that can never begin a
+//! valid raw deflate stream, which makes the sniffing unambiguous.
+
+use std::fmt;
+use std::io::{self, Read};
+use std::sync::Arc;
+
+/// A decompression codec that can be registered on [`crate::ObbyReadOptions`]
+pub trait Codec: Send + Sync {
+    /// A short, human-readable name such as `deflate`
+    fn name(&self) -> &str;
+
+    /// Returns `true` if a payload starting with `prefix` is encoded with this codec
+    ///
+    /// `prefix` holds at most the first 16 bytes of the compressed entry.
+    fn sniff(&self, prefix: &[u8]) -> bool;
+
+    /// Wraps `input` in a decoder that yields the decompressed bytes
+    fn decoder<'a>(&self, input: Box<dyn Read + 'a>) -> io::Result<Box<dyn Read + 'a>>;
+}
+
+/// Raw deflate (RFC 1951), the codec used by Obsidian's packer
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DeflateCodec;
+
+impl Codec for DeflateCodec {
+    fn name(&self) -> &str {
+        "deflate"
+    }
+
+    fn sniff(&self, _prefix: &[u8]) -> bool {
+        // Raw deflate has no magic bytes; it is the registry's fallback instead
+        false
+    }
+
+    fn decoder<'a>(&self, input: Box<dyn Read + 'a>) -> io::Result<Box<dyn Read + 'a>> {
+        Ok(Box::new(flate2::read::DeflateDecoder::new(input)))
+    }
+}
+
+/// Gzip (RFC 1952) streams, recognised by their `1f 8b` magic
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GzipCodec;
+
+impl Codec for GzipCodec {
+    fn name(&self) -> &str {
+        "gzip"
+    }
+
+    fn sniff(&self, prefix: &[u8]) -> bool {
+        prefix.starts_with(&[0x1f, 0x8b])
+    }
+
+    fn decoder<'a>(&self, input: Box<dyn Read + 'a>) -> io::Result<Box<dyn Read + 'a>> {
+        Ok(Box::new(flate2::read::GzDecoder::new(input)))
+    }
+}
+
+/// Zstandard frames, recognised by their `28 b5 2f fd` magic
+#[cfg(feature = "zstd")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ZstdCodec;
+
+#[cfg(feature = "zstd")]
+impl Codec for ZstdCodec {
+    fn name(&self) -> &str {
+        "zstd"
+    }
+
+    fn sniff(&self, prefix: &[u8]) -> bool {
+        prefix.starts_with(&[0x28, 0xb5, 0x2f, 0xfd])
+    }
+
+    fn decoder<'a>(&self, input: Box<dyn Read + 'a>) -> io::Result<Box<dyn Read + 'a>> {
+        Ok(Box::new(zstd::stream::read::Decoder::new(input)?))
+    }
+}
+
+/// The set of codecs available when extracting compressed entries
+///
+/// Codecs are tried in registration order; the first one whose [`Codec::sniff`] accepts
+/// the payload is used. Payloads no codec claims go to the fallback, which is deflate
+/// unless changed with [`CodecRegistry::set_fallback`].
+#[derive(Clone)]
+pub struct CodecRegistry {
+    codecs: Vec<Arc<dyn Codec>>,
+    fallback: Arc<dyn Codec>,
+}
+
+impl CodecRegistry {
+    /// Creates a registry with no sniffed codecs and deflate as the fallback
+    pub fn empty() -> Self {
+        CodecRegistry {
+            codecs: Vec::new(),
+            fallback: Arc::new(DeflateCodec),
+        }
+    }
+
+    /// Registers a codec, giving it lower priority than those already registered
+    pub fn register<C: Codec + 'static>(&mut self, codec: C) {
+        self.codecs.push(Arc::new(codec));
+    }
+
+    /// Sets the codec used for payloads that no registered codec recognises
+    pub fn set_fallback<C: Codec + 'static>(&mut self, codec: C) {
+        self.fallback = Arc::new(codec);
+    }
+
+    /// Picks the codec for a compressed payload from its leading bytes
+    pub fn select(&self, prefix: &[u8]) -> &dyn Codec {
+        self.sniff(prefix).unwrap_or(self.fallback.as_ref())
+    }
+
+    /// Returns the registered codec that recognises `prefix`, ignoring the fallback
+    pub fn sniff(&self, prefix: &[u8]) -> Option<&dyn Codec> {
+        self.codecs.iter().find(|codec| codec.sniff(prefix)).map(|codec| codec.as_ref())
+    }
+}
+
+impl Default for CodecRegistry {
+    /// Deflate as the fallback, plus gzip and (with the `zstd` feature) zstd
+    fn default() -> Self {
+        let mut registry = CodecRegistry::empty();
+        registry.register(GzipCodec);
+        #[cfg(feature = "zstd")]
+        registry.register(ZstdCodec);
+        registry
+    }
+}
+
+impl fmt::Debug for CodecRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CodecRegistry")
+            .field("codecs", &self.codecs.iter().map(|c| c.name()).collect::<Vec<_>>())
+            .field("fallback", &self.fallback.name())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::{DeflateEncoder, GzEncoder};
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn decode(registry: &CodecRegistry, payload: &[u8]) -> (String, Vec<u8>) {
+        let codec = registry.select(payload);
+        let mut out = Vec::new();
+        codec.decoder(Box::new(payload)).unwrap().read_to_end(&mut out).unwrap();
+        (codec.name().to_string(), out)
+    }
+
+    #[test]
+    fn test_select_by_magic() {
+        let data = b"hello hello hello hello".to_vec();
+        let registry = CodecRegistry::default();
+
+        let mut deflate = DeflateEncoder::new(Vec::new(), Compression::default());
+        deflate.write_all(&data).unwrap();
+        assert_eq!(decode(&registry, &deflate.finish().unwrap()), ("deflate".to_string(), data.clone()));
+
+        let mut gzip = GzEncoder::new(Vec::new(), Compression::default());
+        gzip.write_all(&data).unwrap();
+        assert_eq!(decode(&registry, &gzip.finish().unwrap()), ("gzip".to_string(), data.clone()));
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_select_zstd() {
+        let data = b"hello hello hello hello".to_vec();
+        let payload = zstd::encode_all(&data[..], 0).unwrap();
+        assert_eq!(decode(&CodecRegistry::default(), &payload), ("zstd".to_string(), data));
+    }
+}
@@ -0,0 +1,143 @@
+//! Comparing the Obsidian API version a plugin was built against with the one a server runs
+
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+use crate::ObbyMetadata;
+
+/// A parsed `major.minor.patch` API version
+///
+/// Missing components count as `0`, so `1.2` equals `1.2.0`. A leading `v` and any
+/// pre-release or build suffix (`-beta.1`, `+abc`) are accepted and ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ApiVersion {
+    /// Incremented for breaking changes
+    pub major: u64,
+    /// Incremented for backwards-compatible additions
+    pub minor: u64,
+    /// Incremented for fixes
+    pub patch: u64,
+}
+
+/// How well a plugin's API version fits a server's, returned by [`check_api_compat`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Compat {
+    /// The versions are the same
+    Exact,
+    /// The major versions match and the server's minor version is at least the plugin's,
+    /// so everything the plugin uses is available
+    MinorOk,
+    /// The major versions differ, the plugin needs a newer minor version than the server
+    /// has, or a version couldn't be parsed
+    Incompatible,
+}
+
+impl Compat {
+    /// Whether the plugin can be loaded
+    pub fn is_compatible(self) -> bool {
+        self != Compat::Incompatible
+    }
+}
+
+impl FromStr for ApiVersion {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> io::Result<Self> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("invalid API version {:?}", s));
+        let trimmed = s.trim();
+        let trimmed = trimmed.strip_prefix(['v', 'V']).unwrap_or(trimmed);
+        let core = trimmed.split(['-', '+']).next().unwrap_or_default();
+        let mut parts = [0u64; 3];
+        for (i, part) in core.split('.').enumerate() {
+            if i == parts.len() || part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(invalid());
+            }
+            parts[i] = part.parse().map_err(|_| invalid())?;
+        }
+        Ok(ApiVersion { major: parts[0], minor: parts[1], patch: parts[2] })
+    }
+}
+
+impl fmt::Display for ApiVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl ObbyMetadata {
+    /// Parses the Obsidian API version the plugin was built against
+    ///
+    /// # Returns
+    ///
+    /// The [`ApiVersion`], or an `io::Error` of kind `InvalidData` if the header's
+    /// `api_version` isn't a version number.
+    pub fn api_version_parsed(&self) -> io::Result<ApiVersion> {
+        self.api_version.parse()
+    }
+}
+
+/// Checks whether a plugin built against `plugin_api` can run on a server providing `server_api`
+///
+/// Versions are compared as [`ApiVersion`]s, so `1.2` and `1.2.0` are an exact match.
+/// Patch versions are ignored beyond that: they don't add API. If either version can't
+/// be parsed, the strings must be identical to be compatible.
+///
+/// # Arguments
+///
+/// * `plugin_api` - The API version from the archive header, see [`ObbyMetadata::api_version`].
+/// * `server_api` - The API version the server or launcher provides.
+///
+/// # Example
+///
+/// ```
+/// use obsidian_lib::{check_api_compat, Compat};
+///
+/// assert_eq!(check_api_compat("1.2.0", "1.2"), Compat::Exact);
+/// assert_eq!(check_api_compat("1.2.0", "1.4.1"), Compat::MinorOk);
+/// assert_eq!(check_api_compat("1.5.0", "1.4.1"), Compat::Incompatible);
+/// assert_eq!(check_api_compat("1.2.0", "2.0.0"), Compat::Incompatible);
+/// ```
+pub fn check_api_compat(plugin_api: &str, server_api: &str) -> Compat {
+    match (plugin_api.parse::<ApiVersion>(), server_api.parse::<ApiVersion>()) {
+        (Ok(plugin), Ok(server)) if plugin == server => Compat::Exact,
+        (Ok(plugin), Ok(server)) if plugin.major == server.major && plugin.minor <= server.minor => Compat::MinorOk,
+        (Ok(_), Ok(_)) => Compat::Incompatible,
+        _ if plugin_api.trim() == server_api.trim() => Compat::Exact,
+        _ => Compat::Incompatible,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_api_version() {
+        let parse = |s: &str| s.parse::<ApiVersion>().ok();
+        assert_eq!(parse("1.2.3"), Some(ApiVersion { major: 1, minor: 2, patch: 3 }));
+        assert_eq!(parse(" v2 "), Some(ApiVersion { major: 2, minor: 0, patch: 0 }));
+        assert_eq!(parse("1.4.0-beta.2+build"), Some(ApiVersion { major: 1, minor: 4, patch: 0 }));
+        for invalid in ["", "1..2", "1.2.3.4", "one", "1.-2", "v"] {
+            assert_eq!(parse(invalid), None, "{:?}", invalid);
+        }
+        assert_eq!(ApiVersion { major: 1, minor: 0, patch: 7 }.to_string(), "1.0.7");
+    }
+
+    #[test]
+    fn test_check_api_compat() {
+        assert_eq!(check_api_compat("1.2.3", "1.2.0"), Compat::MinorOk);
+        assert_eq!(check_api_compat("1.2.3", "1.1.9"), Compat::Incompatible);
+        assert_eq!(check_api_compat("0.9", "1.0"), Compat::Incompatible);
+        assert_eq!(check_api_compat("nightly", "nightly"), Compat::Exact);
+        assert_eq!(check_api_compat("nightly", "1.0.0"), Compat::Incompatible);
+        assert!(!Compat::Incompatible.is_compatible());
+
+        let buffer = crate::testing::ObbyTestBuilder::new().build();
+        let archive = crate::ObbyArchive::from_bytes(buffer).unwrap();
+        let parsed = archive.metadata().api_version_parsed().unwrap();
+        assert_eq!(parsed, ApiVersion { major: 1, minor: 0, patch: 0 });
+    }
+}
@@ -0,0 +1,249 @@
+//! Cross-checking the plugin identity recorded in the header, manifest and assembly
+
+use std::fmt;
+use std::io::{self, Read, Seek};
+
+use crate::{tree, ObbyArchive};
+
+/// The key .NET compilers give the assembly version in a DLL's version resource
+const ASSEMBLY_VERSION_KEY: &str = "Assembly Version";
+
+/// Which piece of identity a [`Mismatch`] is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub enum ConsistencyField {
+    /// The manifest's `id` against the header's assembly name
+    Id,
+    /// The manifest's `version` against the header's plugin version
+    Version,
+    /// The plugin DLL's assembly version against the header's plugin version
+    AssemblyVersion,
+}
+
+impl fmt::Display for ConsistencyField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ConsistencyField::Id => "id",
+            ConsistencyField::Version => "version",
+            ConsistencyField::AssemblyVersion => "assembly version",
+        })
+    }
+}
+
+/// A disagreement between the header and an entry; part of a [`ConsistencyReport`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Mismatch {
+    /// What disagrees
+    pub field: ConsistencyField,
+    /// The value recorded in the header
+    pub header: String,
+    /// The value found in `entry`
+    pub found: String,
+    /// The entry the value was found in, such as `plugin.json` or `MyPlugin.dll`
+    pub entry: String,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {:?} in {} doesn't match {:?} in the header", self.field, self.found, self.entry, self.header)
+    }
+}
+
+/// The outcome of [`ObbyArchive::check_consistency`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ConsistencyReport {
+    /// The plugin DLL that was inspected, if it was asked for and found
+    pub assembly_entry: Option<String>,
+    /// The assembly version read from the DLL's version resource, if it has one
+    pub assembly_version: Option<String>,
+    /// Every disagreement found
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl ConsistencyReport {
+    /// Whether nothing disagreed
+    pub fn is_consistent(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+impl<R: Read + Seek> ObbyArchive<R> {
+    /// Cross-checks the assembly name and version in the header against the manifest,
+    /// and optionally against the plugin DLL
+    ///
+    /// Loaders typically find a plugin by one of these and trust the others, so a
+    /// mismatch left behind by a half-finished rename or version bump breaks loading in
+    /// confusing ways. The manifest's `id` is compared with the assembly name ignoring
+    /// case and the separators `-`, `_` and `.`, so `my-plugin` matches `MyPlugin`.
+    /// Versions are compared by their numeric components, so `1.2` matches `1.2.0.0`;
+    /// assembly versions can't carry a pre-release suffix, so it is ignored for them.
+    /// Manifest fields that aren't set are skipped.
+    ///
+    /// The plugin DLL is the entry named after the assembly with a `.dll` extension,
+    /// preferring the one closest to the root. Its version is read from the
+    /// `Assembly Version` string of its version resource, which the .NET SDK writes by
+    /// default; DLLs without one are reported with no `assembly_version`.
+    ///
+    /// # Arguments
+    ///
+    /// * `inspect_assembly` - Whether to also read the plugin DLL's assembly version.
+    ///
+    /// # Returns
+    ///
+    /// The [`ConsistencyReport`], or an `io::Error` if there is no valid manifest or an
+    /// entry can't be read.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut archive = obsidian_lib::open("plugin.obby")?;
+    /// for mismatch in archive.check_consistency(true)?.mismatches {
+    ///     eprintln!("warning: {}", mismatch);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn check_consistency(&mut self, inspect_assembly: bool) -> io::Result<ConsistencyReport> {
+        let manifest_entry = self.find_manifest()?.to_string();
+        let manifest = self.plugin_manifest()?;
+        let assembly = self.metadata.plugin_assembly.clone();
+        let version = self.metadata.plugin_version.clone();
+        let mut report = ConsistencyReport::default();
+        let mismatch = |field, header: &str, found: &str, entry: &str| Mismatch {
+            field,
+            header: header.to_string(),
+            found: found.to_string(),
+            entry: entry.to_string(),
+        };
+
+        if let Some(id) = &manifest.id {
+            if identifier_key(id) != identifier_key(&assembly) {
+                report.mismatches.push(mismatch(ConsistencyField::Id, &assembly, id, &manifest_entry));
+            }
+        }
+        if let Some(manifest_version) = &manifest.version {
+            if !same_version(manifest_version, &version, false) {
+                report.mismatches.push(mismatch(ConsistencyField::Version, &version, manifest_version, &manifest_entry));
+            }
+        }
+
+        if inspect_assembly {
+            let file_name = format!("{}.dll", assembly);
+            let dll = self
+                .order
+                .iter()
+                .filter(|name| tree::components(name).last().is_some_and(|last| last.eq_ignore_ascii_case(&file_name)))
+                .min_by_key(|name| tree::components(name).count())
+                .cloned();
+            if let Some(dll) = dll {
+                report.assembly_version = assembly_version(&self.extract_entry(&dll)?);
+                if let Some(found) = &report.assembly_version {
+                    if !same_version(found, &version, true) {
+                        report.mismatches.push(mismatch(ConsistencyField::AssemblyVersion, &version, found, &dll));
+                    }
+                }
+                report.assembly_entry = Some(dll);
+            }
+        }
+        Ok(report)
+    }
+}
+
+/// Normalizes an identifier for comparison, dropping case and separators
+fn identifier_key(id: &str) -> String {
+    id.chars()
+        .filter(|c| !matches!(c, '-' | '_' | '.'))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Compares versions by their numeric components, ignoring trailing zeros, a leading
+/// `v` and build metadata; pre-release suffixes must match unless `ignore_pre` is set
+fn same_version(a: &str, b: &str, ignore_pre: bool) -> bool {
+    fn split(version: &str, ignore_pre: bool) -> (Vec<&str>, &str) {
+        let version = version.trim();
+        let version = version.strip_prefix(['v', 'V']).unwrap_or(version);
+        let version = version.split('+').next().unwrap_or_default();
+        let (core, pre) = version.split_once('-').unwrap_or((version, ""));
+        let mut parts: Vec<&str> = core.split('.').map(|part| part.trim_start_matches('0')).collect();
+        while parts.last() == Some(&"") {
+            parts.pop();
+        }
+        (parts, if ignore_pre { "" } else { pre })
+    }
+    split(a, ignore_pre) == split(b, ignore_pre)
+}
+
+/// Reads the `Assembly Version` string from a DLL's version resource
+fn assembly_version(dll: &[u8]) -> Option<String> {
+    let key: Vec<u8> = ASSEMBLY_VERSION_KEY.encode_utf16().chain([0]).flat_map(u16::to_le_bytes).collect();
+    let start = dll.windows(key.len()).position(|window| window == key.as_slice())? + key.len();
+    // The value is NUL-terminated UTF-16, aligned to 32 bits after the key
+    let units: Vec<u16> = dll[start..]
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .skip_while(|&unit| unit == 0)
+        .take_while(|&unit| unit != 0)
+        .take(64)
+        .collect();
+    let version = String::from_utf16(&units).ok()?;
+    (!version.is_empty()).then_some(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+
+    /// A stand-in for a DLL whose version resource holds `version`
+    fn dll(version: &str) -> Vec<u8> {
+        let utf16 = |text: &str| text.encode_utf16().chain([0]).flat_map(u16::to_le_bytes).collect::<Vec<u8>>();
+        [&b"MZ\0\0"[..], &utf16("Assembly Version"), &[0, 0], &utf16(version)].concat()
+    }
+
+    #[test]
+    fn test_check_consistency() {
+        let buffer = ObbyTestBuilder::new()
+            .plugin("MyPlugin", "1.2.0")
+            .entry("plugin.json", br#"{"id": "my-plugin", "version": "v1.2"}"#)
+            .entry("MyPlugin.dll", &dll("1.2.0.0"))
+            .build();
+        let report = ObbyArchive::from_bytes(buffer).unwrap().check_consistency(true).unwrap();
+        assert!(report.is_consistent(), "{:?}", report.mismatches);
+        assert_eq!(report.assembly_entry.as_deref(), Some("MyPlugin.dll"));
+        assert_eq!(report.assembly_version.as_deref(), Some("1.2.0.0"));
+
+        let buffer = ObbyTestBuilder::new()
+            .plugin("MyPlugin", "1.3.0-beta")
+            .entry("plugin.json", br#"{"id": "other", "version": "1.3.0"}"#)
+            .entry("lib/myplugin.dll", &dll("1.2.0.0"))
+            .build();
+        let mut archive = ObbyArchive::from_bytes(buffer).unwrap();
+        let report = archive.check_consistency(true).unwrap();
+        let fields: Vec<_> = report.mismatches.iter().map(|mismatch| mismatch.field).collect();
+        assert_eq!(fields, vec![ConsistencyField::Id, ConsistencyField::Version, ConsistencyField::AssemblyVersion]);
+        assert_eq!(
+            report.mismatches[0].to_string(),
+            r#"id "other" in plugin.json doesn't match "MyPlugin" in the header"#
+        );
+        assert_eq!(report.mismatches[2].entry, "lib/myplugin.dll");
+
+        let report = archive.check_consistency(false).unwrap();
+        assert_eq!(report.mismatches.len(), 2);
+        assert_eq!(report.assembly_entry, None);
+    }
+
+    #[test]
+    fn test_same_version() {
+        assert!(same_version("1.0", "1.0.0.0", false));
+        assert!(same_version("1.0.0+build.5", "v1.0.0", false));
+        assert!(!same_version("1.0.0-rc.1", "1.0.0", false));
+        assert!(same_version("1.0.0-rc.1", "1.0.0.0", true));
+        assert!(!same_version("1.10", "1.1", false));
+        assert!(same_version("0.1", "0.1.0", false));
+    }
+}
@@ -0,0 +1,220 @@
+//! Finding identical entry payloads across many archives
+
+use std::collections::HashMap;
+use std::io::{self, Read, Seek};
+use std::path::PathBuf;
+
+use crate::{HashAlgo, ObbyArchive};
+
+/// Where a payload was found; part of a [`DuplicateGroup`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Occurrence {
+    /// The label the archive was added under
+    pub archive: String,
+    /// Name of the entry
+    pub entry: String,
+}
+
+/// A payload stored more than once
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DuplicateGroup {
+    /// SHA-256 of the decompressed payload, as lowercase hex
+    pub sha256: String,
+    /// Decompressed size of the payload in bytes
+    pub size: u64,
+    /// Every entry holding the payload, in the order they were added
+    pub occurrences: Vec<Occurrence>,
+}
+
+impl DuplicateGroup {
+    /// Bytes saved by storing the payload once instead of once per occurrence
+    pub fn savings(&self) -> u64 {
+        self.size * (self.occurrences.len() as u64 - 1)
+    }
+}
+
+/// Duplicate payloads across a set of archives; returned by [`DedupeAnalyzer::report`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DedupeReport {
+    /// Number of archives analyzed
+    pub archives: usize,
+    /// Number of entries across all archives
+    pub entries: usize,
+    /// Number of distinct payloads
+    pub unique_payloads: usize,
+    /// Sum of the decompressed sizes of all entries
+    pub total_size: u64,
+    /// Sum of the decompressed sizes of the distinct payloads, i.e. what
+    /// content-addressed storage would hold
+    pub unique_size: u64,
+    /// Payloads stored more than once, those saving the most first
+    pub duplicates: Vec<DuplicateGroup>,
+}
+
+impl DedupeReport {
+    /// Bytes saved by storing every distinct payload once
+    pub fn savings(&self) -> u64 {
+        self.total_size - self.unique_size
+    }
+
+    /// [`DedupeReport::savings`] as a fraction of the total size, from `0.0` to `1.0`
+    pub fn savings_ratio(&self) -> f64 {
+        if self.total_size == 0 {
+            0.0
+        } else {
+            self.savings() as f64 / self.total_size as f64
+        }
+    }
+}
+
+/// Hashes the entries of archives one at a time and tracks identical payloads
+///
+/// Only a digest and the locations of each payload are kept, so any number of archives
+/// can be analyzed; each entry is decompressed once to hash it.
+///
+/// # Example
+///
+/// ```no_run
+/// use obsidian_lib::DedupeAnalyzer;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut analyzer = DedupeAnalyzer::new();
+/// for result in obsidian_lib::scan_dir("registry") {
+///     let (path, _) = result?;
+///     analyzer.add_archive(&path.display().to_string(), &mut obsidian_lib::open(&path)?)?;
+/// }
+/// let report = analyzer.report();
+/// println!("{} of {} bytes are duplicates ({:.1}%)", report.savings(), report.total_size, report.savings_ratio() * 100.0);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct DedupeAnalyzer {
+    /// Payloads by digest, in the order first seen
+    payloads: HashMap<Vec<u8>, (usize, DuplicateGroup)>,
+    archives: usize,
+    entries: usize,
+    total_size: u64,
+}
+
+impl DedupeAnalyzer {
+    /// Creates an analyzer that hasn't seen any archives
+    pub fn new() -> Self {
+        DedupeAnalyzer::default()
+    }
+
+    /// Hashes every entry of an archive
+    ///
+    /// # Arguments
+    ///
+    /// * `label` - How the archive is named in [`Occurrence::archive`], such as its path.
+    /// * `archive` - The archive to add.
+    ///
+    /// # Returns
+    ///
+    /// Nothing, or an `io::Error` if an entry can't be read. Entries hashed before the
+    /// error stay counted.
+    pub fn add_archive<R: Read + Seek>(&mut self, label: &str, archive: &mut ObbyArchive<R>) -> io::Result<()> {
+        self.archives += 1;
+        for name in archive.order.clone() {
+            let (data, digest) = archive.extract_entry_hashed(&name, HashAlgo::Sha256)?;
+            let size = data.len() as u64;
+            self.entries += 1;
+            self.total_size += size;
+            let first_seen = self.payloads.len();
+            let (_, group) = self.payloads.entry(digest.bytes).or_insert_with_key(|digest| {
+                let group = DuplicateGroup { sha256: crate::to_hex(digest), size, occurrences: Vec::new() };
+                (first_seen, group)
+            });
+            group.occurrences.push(Occurrence { archive: label.to_string(), entry: name });
+        }
+        Ok(())
+    }
+
+    /// Summarizes the archives added so far
+    pub fn report(&self) -> DedupeReport {
+        let mut duplicates: Vec<&(usize, DuplicateGroup)> =
+            self.payloads.values().filter(|(_, group)| group.occurrences.len() > 1).collect();
+        // Ties keep the order the payloads were first seen in
+        duplicates.sort_by(|(a_seen, a), (b_seen, b)| b.savings().cmp(&a.savings()).then(a_seen.cmp(b_seen)));
+        DedupeReport {
+            archives: self.archives,
+            entries: self.entries,
+            unique_payloads: self.payloads.len(),
+            total_size: self.total_size,
+            unique_size: self.payloads.values().map(|(_, group)| group.size).sum(),
+            duplicates: duplicates.into_iter().map(|(_, group)| group.clone()).collect(),
+        }
+    }
+}
+
+/// Opens each archive and reports identical payloads across all of them
+///
+/// Archives are labelled with their paths. Use [`DedupeAnalyzer`] directly to skip
+/// archives that fail to open or to label them differently.
+///
+/// # Arguments
+///
+/// * `paths` - The archives to analyze.
+///
+/// # Returns
+///
+/// The [`DedupeReport`], or the first `io::Error` from opening or reading an archive.
+pub fn dedupe<I: IntoIterator<Item = PathBuf>>(paths: I) -> io::Result<DedupeReport> {
+    let mut analyzer = DedupeAnalyzer::new();
+    for path in paths {
+        let label = path.display().to_string();
+        analyzer
+            .add_archive(&label, &mut crate::open(&path)?)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", label, e)))?;
+    }
+    Ok(analyzer.report())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+
+    #[test]
+    fn test_dedupe() {
+        let shared = vec![7u8; 1000];
+        let first = ObbyTestBuilder::new()
+            .entry("plugin.json", br#"{"id": "a"}"#)
+            .entry("lib/Newtonsoft.Json.dll", &shared)
+            .stored_entry("icon.png", b"icon")
+            .build();
+        let second = ObbyTestBuilder::new()
+            .entry("plugin.json", br#"{"id": "b"}"#)
+            .stored_entry("Newtonsoft.Json.dll", &shared)
+            .entry("assets/icon.png", b"icon")
+            .entry("assets/copy.png", b"icon")
+            .build();
+
+        let mut analyzer = DedupeAnalyzer::new();
+        analyzer.add_archive("a.obby", &mut ObbyArchive::from_bytes(first).unwrap()).unwrap();
+        analyzer.add_archive("b.obby", &mut ObbyArchive::from_bytes(second).unwrap()).unwrap();
+        let report = analyzer.report();
+
+        assert_eq!((report.archives, report.entries, report.unique_payloads), (2, 7, 4));
+        assert_eq!(report.total_size, 11 + 11 + 2000 + 12);
+        assert_eq!(report.savings(), 1000 + 8);
+        assert_eq!(report.duplicates.len(), 2);
+        let shared_group = &report.duplicates[0];
+        assert_eq!(shared_group.size, 1000);
+        assert_eq!(
+            shared_group.occurrences,
+            vec![
+                Occurrence { archive: "a.obby".into(), entry: "lib/Newtonsoft.Json.dll".into() },
+                Occurrence { archive: "b.obby".into(), entry: "Newtonsoft.Json.dll".into() },
+            ]
+        );
+        assert_eq!(report.duplicates[1].occurrences.len(), 3);
+        assert_eq!(report.duplicates[1].savings(), 8);
+        assert!(report.savings_ratio() > 0.49 && report.savings_ratio() < 0.5);
+        assert_eq!(DedupeAnalyzer::new().report().savings_ratio(), 0.0);
+    }
+}
@@ -0,0 +1,440 @@
+//! Incremental updates between two versions of an archive
+//!
+//! [`create_delta`] compares an old and a new archive and records only what changed:
+//! entries whose stored bytes are unchanged (or moved) are copied from the old archive,
+//! changed entries are shipped in full or, with the `bsdiff` feature, as a binary patch
+//! against the old entry of the same name. [`apply_delta`] rebuilds the new archive
+//! byte for byte from the old one, so its hash and signature stay valid.
+//!
+//! Patches are computed over entry data as stored, i.e. compressed. Small edits to
+//! compressed entries tend to change most of the deflate stream, so binary patches pay
+//! off mostly for stored entries and for compressed ones that are largely unchanged.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use obsidian_lib::delta::{apply_delta, create_delta, Delta};
+//! use std::fs::File;
+//!
+//! # fn main() -> std::io::Result<()> {
+//! // On the server
+//! let mut old = obsidian_lib::open("plugin-1.0.obby")?;
+//! let mut new = obsidian_lib::open("plugin-1.1.obby")?;
+//! create_delta(&mut old, &mut new)?.write_to(File::create("1.0-to-1.1.obdl")?)?;
+//!
+//! // In the launcher
+//! let delta = Delta::read_from(File::open("1.0-to-1.1.obdl")?)?;
+//! let mut installed = obsidian_lib::open("plugin-1.0.obby")?;
+//! apply_delta(&mut installed, &delta, File::create("plugin-1.1.obby")?)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use sha2::{Digest, Sha384};
+
+use crate::format::wire::{BinaryReader, BinaryWriter};
+use crate::{read_header, read_raw, ObbyArchive, HASH_LEN};
+
+/// Magic bytes at the start of every serialized delta
+const DELTA_MAGIC: &[u8; 4] = b"OBDL";
+
+/// Version of the serialized delta format
+const DELTA_VERSION: u8 = 1;
+
+const OP_COPY: u8 = 0;
+const OP_DATA: u8 = 1;
+const OP_PATCH: u8 = 2;
+
+/// How the stored bytes of one entry of the new archive are produced
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeltaOp {
+    /// Reuse the stored bytes of an entry of the old archive unchanged
+    Copy {
+        /// Name of the entry in the old archive
+        from: String,
+    },
+    /// Use the given stored bytes
+    Data {
+        /// The entry's data as stored in the new archive
+        bytes: Vec<u8>,
+    },
+    /// Apply a deflate-compressed bsdiff patch to the stored bytes of an entry of the old archive
+    Patch {
+        /// Name of the entry in the old archive
+        from: String,
+        /// The bsdiff patch, deflate-compressed
+        patch: Vec<u8>,
+    },
+}
+
+/// A patch that turns one archive into another
+///
+/// Created with [`create_delta`], applied with [`apply_delta`], and serialized with
+/// [`Delta::write_to`] and [`Delta::read_from`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Delta {
+    /// Hash of the data section of the archive the delta applies to
+    base_hash: Vec<u8>,
+    /// SHA-384 of the complete archive produced by applying the delta
+    target_digest: Vec<u8>,
+    /// Header and entry table of the new archive, verbatim
+    prefix: Vec<u8>,
+    /// One operation per row of the new entry table
+    ops: Vec<(String, DeltaOp)>,
+    /// Bytes after the last entry of the new archive
+    trailer: Vec<u8>,
+}
+
+impl Delta {
+    /// Returns the operations that produce each entry of the new archive, in table order
+    pub fn ops(&self) -> &[(String, DeltaOp)] {
+        &self.ops
+    }
+
+    /// Returns the names of entries that aren't copied unchanged from the old archive
+    pub fn changed_entries(&self) -> Vec<&str> {
+        self.ops
+            .iter()
+            .filter(|(_, op)| !matches!(op, DeltaOp::Copy { .. }))
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /// Serializes the delta
+    ///
+    /// # Arguments
+    ///
+    /// * `sink` - Where to write the delta.
+    pub fn write_to<W: Write>(&self, mut sink: W) -> io::Result<()> {
+        let mut out = BinaryWriter::new(Vec::new());
+        out.write_bytes(DELTA_MAGIC)?;
+        out.write_u8(DELTA_VERSION)?;
+        out.write_bytes(&self.base_hash)?;
+        out.write_bytes(&self.target_digest)?;
+        write_blob(&mut out, &self.prefix)?;
+        out.write_i32(length_field(self.ops.len())?)?;
+        for (name, op) in &self.ops {
+            out.write_string(name)?;
+            match op {
+                DeltaOp::Copy { from } => {
+                    out.write_u8(OP_COPY)?;
+                    out.write_string(from)?;
+                }
+                DeltaOp::Data { bytes } => {
+                    out.write_u8(OP_DATA)?;
+                    write_blob(&mut out, bytes)?;
+                }
+                DeltaOp::Patch { from, patch } => {
+                    out.write_u8(OP_PATCH)?;
+                    out.write_string(from)?;
+                    write_blob(&mut out, patch)?;
+                }
+            }
+        }
+        write_blob(&mut out, &self.trailer)?;
+        sink.write_all(&out.into_inner())
+    }
+
+    /// Reads a delta written by [`Delta::write_to`]
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Where to read the delta from.
+    pub fn read_from<R: Read>(source: R) -> io::Result<Delta> {
+        let mut reader = BinaryReader::new(source);
+        if reader.read_bytes(DELTA_MAGIC.len())? != DELTA_MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid delta header"));
+        }
+        let version = reader.read_u8()?;
+        if version != DELTA_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unsupported delta version {}", version),
+            ));
+        }
+        let base_hash = reader.read_bytes(HASH_LEN)?;
+        let target_digest = reader.read_bytes(HASH_LEN)?;
+        let prefix = read_blob(&mut reader)?;
+
+        let count = reader.read_length("operation count")?;
+        let mut ops = Vec::new();
+        for _ in 0..count {
+            let name = reader.read_string()?;
+            let op = match reader.read_u8()? {
+                OP_COPY => DeltaOp::Copy {
+                    from: reader.read_string()?,
+                },
+                OP_DATA => DeltaOp::Data {
+                    bytes: read_blob(&mut reader)?,
+                },
+                OP_PATCH => DeltaOp::Patch {
+                    from: reader.read_string()?,
+                    patch: read_blob(&mut reader)?,
+                },
+                tag => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Unknown delta operation {}", tag),
+                    ))
+                }
+            };
+            ops.push((name, op));
+        }
+        let trailer = read_blob(&mut reader)?;
+
+        Ok(Delta {
+            base_hash,
+            target_digest,
+            prefix,
+            ops,
+            trailer,
+        })
+    }
+}
+
+/// Computes the delta that turns `old` into `new`
+///
+/// # Arguments
+///
+/// * `old` - The archive the delta will be applied to.
+/// * `new` - The archive the delta produces.
+///
+/// # Returns
+///
+/// The `Delta`. It only references `old` by its hash, so it can be serialized and
+/// applied elsewhere.
+pub fn create_delta<R1, R2>(old: &mut ObbyArchive<R1>, new: &mut ObbyArchive<R2>) -> io::Result<Delta>
+where
+    R1: Read + Seek,
+    R2: Read + Seek,
+{
+    let mut old_entries = HashMap::new();
+    for name in old.list_entries() {
+        let raw = read_raw(&mut old.reader, old.data_start_pos, &old.entries[&name])?;
+        old_entries.insert(name, raw);
+    }
+    let mut by_content: HashMap<&[u8], &str> = HashMap::new();
+    for (name, raw) in &old_entries {
+        by_content.entry(raw.as_slice()).or_insert(name.as_str());
+    }
+
+    // The whole new archive is needed verbatim, including duplicate table rows that
+    // `ObbyArchive` hides, so the table is parsed again from the raw prefix
+    let mut prefix = vec![0u8; (new.data_start_pos - new.start_pos) as usize];
+    new.reader.seek(SeekFrom::Start(new.start_pos))?;
+    new.reader.read_exact(&mut prefix)?;
+    let table = read_header(&prefix[..])?.table;
+
+    let mut target = Sha384::new();
+    target.update(&prefix);
+    let mut ops = Vec::with_capacity(table.len());
+    let mut data_end = new.data_start_pos;
+    for (name, info) in &table {
+        let raw = read_raw(&mut new.reader, new.data_start_pos, info)?;
+        target.update(&raw);
+        data_end = data_end.max(new.data_start_pos + info.offset + raw.len() as u64);
+
+        let op = if old_entries.get(name) == Some(&raw) {
+            DeltaOp::Copy { from: name.clone() }
+        } else if let Some(from) = by_content.get(raw.as_slice()) {
+            DeltaOp::Copy { from: from.to_string() }
+        } else {
+            diff_entry(old_entries.get_key_value(name), raw)?
+        };
+        ops.push((name.clone(), op));
+    }
+
+    let mut trailer = Vec::new();
+    new.reader.seek(SeekFrom::Start(data_end))?;
+    new.reader.read_to_end(&mut trailer)?;
+    target.update(&trailer);
+
+    Ok(Delta {
+        base_hash: old.metadata().hash.clone(),
+        target_digest: target.finalize().to_vec(),
+        prefix,
+        ops,
+        trailer,
+    })
+}
+
+/// Applies `delta` to `old`, writing the new archive to `sink`
+///
+/// The result is checked against the digest recorded in the delta before anything is
+/// written, so a failed update never leaves a half-written archive in `sink`.
+///
+/// # Arguments
+///
+/// * `old` - The archive the delta was created against.
+/// * `delta` - The delta to apply.
+/// * `sink` - Where to write the new archive.
+///
+/// # Returns
+///
+/// The sink, or an error of kind `InvalidInput` if `old` is not the delta's base archive.
+pub fn apply_delta<R, W>(old: &mut ObbyArchive<R>, delta: &Delta, mut sink: W) -> io::Result<W>
+where
+    R: Read + Seek,
+    W: Write,
+{
+    if old.metadata().hash != delta.base_hash {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Delta was created for a different archive",
+        ));
+    }
+
+    let mut out = delta.prefix.clone();
+    for (_, op) in &delta.ops {
+        match op {
+            DeltaOp::Copy { from } => {
+                let entry = crate::lookup_entry(&old.entries, from)?;
+                out.extend_from_slice(&read_raw(&mut old.reader, old.data_start_pos, entry)?);
+            }
+            DeltaOp::Data { bytes } => out.extend_from_slice(bytes),
+            DeltaOp::Patch { from, patch } => {
+                let entry = crate::lookup_entry(&old.entries, from)?;
+                let base = read_raw(&mut old.reader, old.data_start_pos, entry)?;
+                out.extend_from_slice(&apply_patch(&base, patch)?);
+            }
+        }
+    }
+    out.extend_from_slice(&delta.trailer);
+
+    if Sha384::digest(&out).as_slice() != delta.target_digest.as_slice() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Archive produced by the delta doesn't match the expected digest",
+        ));
+    }
+    sink.write_all(&out)?;
+    Ok(sink)
+}
+
+/// Encodes a changed entry, as a patch against the old entry of the same name if that's smaller
+#[cfg(feature = "bsdiff")]
+fn diff_entry(old: Option<(&String, &Vec<u8>)>, raw: Vec<u8>) -> io::Result<DeltaOp> {
+    if let Some((from, base)) = old {
+        // bsdiff output is mostly zeros and is meant to be compressed
+        let mut patch = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::best());
+        bsdiff::diff(base, &raw, &mut patch)?;
+        let patch = patch.finish()?;
+        if patch.len() < raw.len() {
+            return Ok(DeltaOp::Patch {
+                from: from.clone(),
+                patch,
+            });
+        }
+    }
+    Ok(DeltaOp::Data { bytes: raw })
+}
+
+#[cfg(not(feature = "bsdiff"))]
+fn diff_entry(_old: Option<(&String, &Vec<u8>)>, raw: Vec<u8>) -> io::Result<DeltaOp> {
+    Ok(DeltaOp::Data { bytes: raw })
+}
+
+#[cfg(feature = "bsdiff")]
+fn apply_patch(base: &[u8], patch: &[u8]) -> io::Result<Vec<u8>> {
+    let mut patched = Vec::new();
+    bsdiff::patch(base, &mut flate2::read::DeflateDecoder::new(patch), &mut patched)?;
+    Ok(patched)
+}
+
+#[cfg(not(feature = "bsdiff"))]
+fn apply_patch(_base: &[u8], _patch: &[u8]) -> io::Result<Vec<u8>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Delta contains binary patches; enable the `bsdiff` feature to apply it",
+    ))
+}
+
+fn length_field(len: usize) -> io::Result<i32> {
+    i32::try_from(len).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Delta section is too large"))
+}
+
+fn write_blob<W: Write>(out: &mut BinaryWriter<W>, bytes: &[u8]) -> io::Result<()> {
+    out.write_i32(length_field(bytes.len())?)?;
+    out.write_bytes(bytes)
+}
+
+fn read_blob<R: Read>(reader: &mut BinaryReader<R>) -> io::Result<Vec<u8>> {
+    let len = reader.read_length("delta section length")?;
+    reader.read_bytes(len as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+    use std::io::Cursor;
+
+    fn archive(bytes: &[u8]) -> ObbyArchive<Cursor<Vec<u8>>> {
+        ObbyArchive::new(Cursor::new(bytes.to_vec())).unwrap()
+    }
+
+    fn versions() -> (Vec<u8>, Vec<u8>) {
+        let dll: Vec<u8> = (0..8192u32).map(|i| (i * 7 % 251) as u8).collect();
+        let mut dll2 = dll.clone();
+        dll2[100] ^= 0xFF;
+
+        let old = ObbyTestBuilder::new()
+            .plugin("Plugin", "1.0.0")
+            .entry("plugin.json", br#"{"version": "1.0.0"}"#)
+            .stored_entry("Plugin.dll", &dll)
+            .entry("icon.png", &[9u8; 512])
+            .build();
+        let new = ObbyTestBuilder::new()
+            .plugin("Plugin", "1.1.0")
+            .entry("plugin.json", br#"{"version": "1.1.0"}"#)
+            .stored_entry("Plugin.dll", &dll2)
+            .entry("assets/icon.png", &[9u8; 512])
+            .build();
+        (old, new)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let (old, new) = versions();
+        let delta = create_delta(&mut archive(&old), &mut archive(&new)).unwrap();
+
+        // The moved icon is copied rather than shipped again
+        assert_eq!(
+            delta.ops()[2].1,
+            DeltaOp::Copy {
+                from: "icon.png".to_string()
+            }
+        );
+        assert_eq!(delta.changed_entries(), vec!["plugin.json", "Plugin.dll"]);
+
+        let mut serialized = Vec::new();
+        delta.write_to(&mut serialized).unwrap();
+        let delta = Delta::read_from(&serialized[..]).unwrap();
+
+        let rebuilt = apply_delta(&mut archive(&old), &delta, Vec::new()).unwrap();
+        assert_eq!(rebuilt, new);
+    }
+
+    #[cfg(feature = "bsdiff")]
+    #[test]
+    fn test_changed_entries_are_patched() {
+        let (old, new) = versions();
+        let delta = create_delta(&mut archive(&old), &mut archive(&new)).unwrap();
+        assert!(matches!(delta.ops()[1].1, DeltaOp::Patch { .. }));
+
+        let mut serialized = Vec::new();
+        delta.write_to(&mut serialized).unwrap();
+        assert!(serialized.len() < new.len() / 4);
+    }
+
+    #[test]
+    fn test_wrong_base() {
+        let (old, new) = versions();
+        let delta = create_delta(&mut archive(&old), &mut archive(&new)).unwrap();
+        let err = apply_delta(&mut archive(&new), &delta, Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
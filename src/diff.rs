@@ -0,0 +1,168 @@
+//! Entry-level comparison of two archives
+
+use std::io::{self, Read, Seek};
+
+use crate::ObbyArchive;
+
+/// Differences between two archives, returned by [`diff_archives`]
+///
+/// Entries are compared by their decompressed contents, so an entry that was only
+/// recompressed is not reported as modified. Each list is sorted by name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ArchiveDiff {
+    /// Plugin version recorded in the old archive's header
+    pub old_version: String,
+    /// Plugin version recorded in the new archive's header
+    pub new_version: String,
+    /// Entries only present in the new archive
+    pub added: Vec<EntryDiff>,
+    /// Entries only present in the old archive
+    pub removed: Vec<EntryDiff>,
+    /// Entries present in both whose contents differ
+    pub modified: Vec<EntryDiff>,
+}
+
+/// One changed entry of an [`ArchiveDiff`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct EntryDiff {
+    /// The entry's name
+    pub name: String,
+    /// Decompressed size in the old archive, if the entry exists there
+    pub old_size: Option<u64>,
+    /// Decompressed size in the new archive, if the entry exists there
+    pub new_size: Option<u64>,
+}
+
+impl ArchiveDiff {
+    /// Whether the archives hold the same entries with the same contents
+    ///
+    /// The plugin versions are not considered.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+
+    /// Whether the plugin version differs between the archives
+    pub fn version_changed(&self) -> bool {
+        self.old_version != self.new_version
+    }
+}
+
+impl EntryDiff {
+    /// The change in decompressed size, counting a missing entry as zero bytes
+    pub fn size_delta(&self) -> i64 {
+        self.new_size.unwrap_or(0) as i64 - self.old_size.unwrap_or(0) as i64
+    }
+}
+
+/// Compares the entries of two archives
+///
+/// Entries whose sizes match are decompressed from both archives and compared byte
+/// for byte; entries whose sizes differ are reported as modified without reading them.
+///
+/// # Arguments
+///
+/// * `old` - The archive to compare against.
+/// * `new` - The archive whose changes are reported.
+///
+/// # Returns
+///
+/// An [`ArchiveDiff`] listing added, removed and modified entries.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// let mut old = obsidian_lib::open("plugin-1.0.obby")?;
+/// let mut new = obsidian_lib::open("plugin-1.1.obby")?;
+/// let diff = obsidian_lib::diff_archives(&mut old, &mut new)?;
+/// for entry in &diff.modified {
+///     println!("{} ({:+} bytes)", entry.name, entry.size_delta());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn diff_archives<R1: Read + Seek, R2: Read + Seek>(
+    old: &mut ObbyArchive<R1>,
+    new: &mut ObbyArchive<R2>,
+) -> io::Result<ArchiveDiff> {
+    let mut diff = ArchiveDiff {
+        old_version: old.metadata.plugin_version.clone(),
+        new_version: new.metadata.plugin_version.clone(),
+        ..ArchiveDiff::default()
+    };
+
+    for name in &old.order.clone() {
+        let old_size = Some(old.entries[name].length as u64);
+        match new.entries.get(name) {
+            None => diff.removed.push(EntryDiff { name: name.clone(), old_size, new_size: None }),
+            Some(info) => {
+                let new_size = Some(info.length as u64);
+                if old_size != new_size || old.extract_entry(name)? != new.extract_entry(name)? {
+                    diff.modified.push(EntryDiff { name: name.clone(), old_size, new_size });
+                }
+            }
+        }
+    }
+    for name in &new.order {
+        if !old.entries.contains_key(name) {
+            let new_size = Some(new.entries[name].length as u64);
+            diff.added.push(EntryDiff { name: name.clone(), old_size: None, new_size });
+        }
+    }
+
+    for list in [&mut diff.added, &mut diff.removed, &mut diff.modified] {
+        list.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+    Ok(diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+
+    #[test]
+    fn test_diff_archives() {
+        let old = ObbyTestBuilder::new()
+            .plugin("TestPlugin", "1.0.0")
+            .entry("plugin.json", br#"{"version": "1.0.0"}"#)
+            .entry("main.js", b"console.log(1)")
+            .entry("same.css", b"body {}")
+            .entry("old.txt", b"gone")
+            .build();
+        let new = ObbyTestBuilder::new()
+            .plugin("TestPlugin", "1.1.0")
+            .stored_entry("same.css", b"body {}")
+            .entry("plugin.json", br#"{"version": "1.1.0"}"#)
+            .entry("main.js", b"console.log(12)")
+            .entry("new.txt", b"hello")
+            .build();
+
+        let diff = diff_archives(
+            &mut ObbyArchive::from_bytes(old).unwrap(),
+            &mut ObbyArchive::from_bytes(new).unwrap(),
+        )
+        .unwrap();
+        assert!(diff.version_changed());
+        let names = |list: &[EntryDiff]| list.iter().map(|e| e.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&diff.added), vec!["new.txt"]);
+        assert_eq!(names(&diff.removed), vec!["old.txt"]);
+        assert_eq!(names(&diff.modified), vec!["main.js", "plugin.json"]);
+        assert_eq!(diff.modified[0].size_delta(), 1);
+        assert_eq!(diff.removed[0].size_delta(), -4);
+    }
+
+    #[test]
+    fn test_identical_archives() {
+        let buffer = ObbyTestBuilder::new().entry("plugin.json", b"{}").build();
+        let diff = diff_archives(
+            &mut ObbyArchive::from_slice(&buffer).unwrap(),
+            &mut ObbyArchive::from_slice(&buffer).unwrap(),
+        )
+        .unwrap();
+        assert!(diff.is_empty());
+        assert!(!diff.version_changed());
+    }
+}
@@ -0,0 +1,260 @@
+//! Changing entries of an existing archive
+
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, Read, Seek, Write};
+use std::path::Path;
+
+#[cfg(feature = "signing")]
+use rsa::RsaPrivateKey;
+
+use crate::manifest::PluginManifest;
+use crate::{ObbyArchive, ObbyWriter};
+
+/// Applies edits to an archive and writes the result as a new archive
+///
+/// Edits are collected in memory; nothing is written until [`ObbyEditor::write`] or
+/// [`ObbyEditor::save`]. Entries that weren't edited are copied without recompression,
+/// and the hash is recomputed. The result is unsigned unless a key is set with
+/// `set_signing_key`.
+///
+/// # Example
+///
+/// ```no_run
+/// use obsidian_lib::ObbyEditor;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut editor = ObbyEditor::open("plugin.obby")?;
+/// editor.manifest_mut()?.set_version("1.2.4");
+/// editor.save("plugin.obby")?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ObbyEditor<R: Read + Seek> {
+    archive: ObbyArchive<R>,
+    plugin_version: String,
+    /// New contents of replaced and added entries
+    changed: HashMap<String, Vec<u8>>,
+    /// Added entries, in the order they were added
+    added: Vec<String>,
+    removed: HashSet<String>,
+    /// The manifest and the entry it is written to, once it was asked for
+    manifest: Option<(String, PluginManifest)>,
+    #[cfg(feature = "signing")]
+    signing_key: Option<RsaPrivateKey>,
+}
+
+impl ObbyEditor<File> {
+    /// Opens the archive at `path` for editing
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(ObbyEditor::new(crate::open(path)?))
+    }
+}
+
+impl<R: Read + Seek> ObbyEditor<R> {
+    /// Starts editing `archive`
+    pub fn new(archive: ObbyArchive<R>) -> Self {
+        let plugin_version = archive.metadata().plugin_version.clone();
+        ObbyEditor {
+            archive,
+            plugin_version,
+            changed: HashMap::new(),
+            added: Vec::new(),
+            removed: HashSet::new(),
+            manifest: None,
+            #[cfg(feature = "signing")]
+            signing_key: None,
+        }
+    }
+
+    /// Returns the archive being edited, as it was opened
+    pub fn archive(&self) -> &ObbyArchive<R> {
+        &self.archive
+    }
+
+    /// Sets the plugin version recorded in the header
+    ///
+    /// [`ObbyEditor::set_manifest`] does this too when the manifest has a version.
+    pub fn set_plugin_version(&mut self, plugin_version: &str) {
+        self.plugin_version = plugin_version.to_string();
+    }
+
+    /// Replaces an entry's contents, or adds the entry if it doesn't exist
+    pub fn set_entry(&mut self, name: &str, data: Vec<u8>) -> io::Result<()> {
+        crate::validate_entry_name(name)?;
+        self.removed.remove(name);
+        if !self.archive.entries.contains_key(name) && !self.changed.contains_key(name) {
+            self.added.push(name.to_string());
+        }
+        self.changed.insert(name.to_string(), data);
+        if self.manifest.as_ref().is_some_and(|(manifest_name, _)| manifest_name == name) {
+            self.manifest = None;
+        }
+        Ok(())
+    }
+
+    /// Removes an entry, returning whether it existed
+    pub fn remove_entry(&mut self, name: &str) -> bool {
+        let existed = self.names().iter().any(|existing| existing == name);
+        self.changed.remove(name);
+        self.added.retain(|added| added != name);
+        if self.archive.entries.contains_key(name) {
+            self.removed.insert(name.to_string());
+        }
+        if self.manifest.as_ref().is_some_and(|(manifest_name, _)| manifest_name == name) {
+            self.manifest = None;
+        }
+        existed
+    }
+
+    /// Returns the plugin manifest for modification
+    ///
+    /// The manifest is read on first use; changes made through the returned reference
+    /// are written like [`ObbyEditor::set_manifest`].
+    ///
+    /// # Returns
+    ///
+    /// The manifest, or an `io::Error` if the archive has no valid manifest.
+    pub fn manifest_mut(&mut self) -> io::Result<&mut PluginManifest> {
+        if self.manifest.is_none() {
+            let name = self.manifest_name()?;
+            let manifest = match self.changed.remove(&name) {
+                Some(json) => PluginManifest::from_json(&json)?,
+                None => self.archive.plugin_manifest()?,
+            };
+            self.manifest = Some((name, manifest));
+        }
+        Ok(&mut self.manifest.as_mut().expect("read above").1)
+    }
+
+    /// Replaces the plugin manifest
+    ///
+    /// The manifest is serialized to the archive's manifest entry, or to `plugin.json`
+    /// if it has none. If the manifest has a version, the header's plugin version is
+    /// updated to match.
+    pub fn set_manifest(&mut self, manifest: PluginManifest) {
+        let name = self.manifest_name().unwrap_or_else(|_| crate::MANIFEST_NAMES[0].to_string());
+        if !self.archive.entries.contains_key(&name) && !self.added.contains(&name) {
+            self.added.push(name.clone());
+        }
+        self.removed.remove(&name);
+        self.changed.remove(&name);
+        self.manifest = Some((name, manifest));
+    }
+
+    /// Signs the edited archive with the given RSA-3072 key
+    #[cfg(feature = "signing")]
+    pub fn set_signing_key(&mut self, key: RsaPrivateKey) -> io::Result<()> {
+        crate::signing::check_key_size(&key)?;
+        self.signing_key = Some(key);
+        Ok(())
+    }
+
+    /// Writes the edited archive to `out`
+    ///
+    /// # Returns
+    ///
+    /// `out`, once the archive was written to it.
+    pub fn write<W: Write>(&mut self, out: W) -> io::Result<W> {
+        let metadata = self.archive.metadata().clone();
+        let mut plugin_version = self.plugin_version.clone();
+        let manifest = match &self.manifest {
+            Some((name, manifest)) => {
+                if let Some(version) = &manifest.version {
+                    plugin_version = version.clone();
+                }
+                Some((name.as_str(), manifest.to_json().into_bytes()))
+            }
+            None => None,
+        };
+
+        let mut writer = ObbyWriter::new(out, &metadata.plugin_assembly, &plugin_version);
+        writer.set_api_version(&metadata.api_version);
+        // Keep the extras block of archives that already have one
+        writer.set_write_extras(!self.archive.extras.is_empty());
+        #[cfg(feature = "signing")]
+        if let Some(key) = &self.signing_key {
+            writer.set_signing_key(key.clone())?;
+        }
+        for name in &self.names() {
+            match (&manifest, self.changed.get(name)) {
+                (Some((manifest_name, json)), _) if manifest_name == name => writer.add_entry(name, json)?,
+                (_, Some(data)) => writer.add_entry(name, data)?,
+                _ => writer.copy_entry_from(&mut self.archive, name)?,
+            }
+        }
+        writer.finish()
+    }
+
+    /// Writes the edited archive to the file at `path`, replacing it atomically
+    ///
+    /// `path` may be the file the archive was opened from.
+    pub fn save<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.write(crate::OutputFile::new(path))?;
+        Ok(())
+    }
+
+    /// Returns the names of the entries the edited archive will have, in order
+    fn names(&self) -> Vec<String> {
+        let kept = self.archive.entry_names().filter(|name| !self.removed.contains(*name));
+        kept.chain(self.added.iter().map(String::as_str)).map(str::to_string).collect()
+    }
+
+    /// Returns the name of the manifest entry, taking edits into account
+    fn manifest_name(&self) -> io::Result<String> {
+        match &self.manifest {
+            Some((name, _)) => Ok(name.clone()),
+            None => crate::find_manifest_in(&self.names()).map(str::to_string),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+
+    #[test]
+    fn test_edit_archive() {
+        let buffer = ObbyTestBuilder::new()
+            .plugin("Plugin", "1.0.0")
+            .entry("plugin.json", br#"{"id": "plugin", "version": "1.0.0", "minApi": 3}"#)
+            .entry("main.js", b"old")
+            .entry("data.json", b"{}")
+            .build();
+        let mut editor = ObbyEditor::new(ObbyArchive::from_bytes(buffer).unwrap());
+        editor.manifest_mut().unwrap().set_version("1.0.1");
+        editor.set_entry("main.js", b"new".to_vec()).unwrap();
+        editor.set_entry("lib/extra.js", b"extra".to_vec()).unwrap();
+        assert!(editor.remove_entry("data.json"));
+        assert!(!editor.remove_entry("missing"));
+
+        let mut edited = ObbyArchive::from_bytes(editor.write(Vec::new()).unwrap()).unwrap();
+        edited.verify_hash().unwrap();
+        assert_eq!(edited.metadata().plugin_version, "1.0.1");
+        assert_eq!(edited.list_entries(), vec!["plugin.json", "main.js", "lib/extra.js"]);
+        assert_eq!(edited.extract_entry("main.js").unwrap(), b"new");
+        let manifest = edited.plugin_manifest().unwrap();
+        assert_eq!(manifest.version.as_deref(), Some("1.0.1"));
+        assert_eq!(manifest.extra["minApi"], 3);
+    }
+
+    #[test]
+    fn test_save_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("plugin.obby");
+        std::fs::write(&path, ObbyTestBuilder::new().entry("main.js", b"js").build()).unwrap();
+
+        let mut editor = ObbyEditor::open(&path).unwrap();
+        assert!(editor.manifest_mut().is_err());
+        let mut manifest = PluginManifest::default();
+        manifest.set_version("2.0.0");
+        editor.set_manifest(manifest);
+        editor.save(&path).unwrap();
+
+        let mut saved = crate::open(&path).unwrap();
+        assert_eq!(saved.list_entries(), vec!["main.js", "plugin.json"]);
+        assert_eq!(saved.plugin_manifest().unwrap().version.as_deref(), Some("2.0.0"));
+        assert_eq!(saved.metadata().plugin_version, "2.0.0");
+    }
+}
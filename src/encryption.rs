@@ -0,0 +1,314 @@
+//! Encrypted entries, an extension to the `.obby` format
+//!
+//! Selected entries can be encrypted with AES-256-GCM, for plugins that bundle
+//! license-restricted assets. An encrypted entry is stored uncompressed as a random
+//! 12-byte nonce followed by the ciphertext and tag, with the entry name as associated
+//! data so entries can't be swapped. Readers that don't know the extension still parse
+//! the archive and see these entries as opaque bytes.
+//!
+//! An archive using the extension is flagged by a [`ENCRYPTION_NAME`] entry:
+//!
+//! ```json
+//! {
+//!   "cipher": "aes-256-gcm",
+//!   "kdf": { "algorithm": "pbkdf2-hmac-sha256", "iterations": 600000, "salt": "9f3c..." },
+//!   "entries": ["assets/licensed.bin"]
+//! }
+//! ```
+//!
+//! `kdf` is `null` when the archive was encrypted with a raw key instead of a passphrase.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::io::{self, Read, Seek};
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use serde_json::{json, Value};
+use sha2::Sha256;
+
+use crate::{to_hex, ObbyArchive, ObbyError};
+
+/// Name of the entry that flags an archive as having encrypted entries
+///
+/// Like [`crate::CHECKSUMS_NAME`], it has no leading dot, so extraction with the default
+/// [`crate::SanitizePolicy`] doesn't reject it.
+pub const ENCRYPTION_NAME: &str = "encryption.json";
+
+const CIPHER: &str = "aes-256-gcm";
+const KDF: &str = "pbkdf2-hmac-sha256";
+/// OWASP's recommendation for PBKDF2-HMAC-SHA256; tests only check the plumbing
+const PBKDF2_ITERATIONS: u32 = if cfg!(test) { 1_000 } else { 600_000 };
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// What entries are encrypted with
+#[derive(Clone)]
+pub enum EncryptionSecret {
+    /// A passphrase, stretched into a key with PBKDF2 and a random salt
+    Passphrase(String),
+    /// A raw 256-bit key, used as-is
+    Key([u8; 32]),
+}
+
+impl fmt::Debug for EncryptionSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncryptionSecret::Passphrase(_) => f.write_str("Passphrase(..)"),
+            EncryptionSecret::Key(_) => f.write_str("Key(..)"),
+        }
+    }
+}
+
+/// Salt and work factor a passphrase was stretched with
+struct Kdf {
+    salt: Vec<u8>,
+    iterations: u32,
+}
+
+fn derive_key(passphrase: &str, kdf: &Kdf) -> Aes256Gcm {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), &kdf.salt, kdf.iterations, &mut key);
+    Aes256Gcm::new(&key.into())
+}
+
+/// Encrypts entries for an [`crate::ObbyWriter`] and remembers which ones it encrypted
+pub(crate) struct Encryptor {
+    cipher: Aes256Gcm,
+    kdf: Option<Kdf>,
+    entries: Vec<String>,
+}
+
+impl Encryptor {
+    pub(crate) fn new(secret: &EncryptionSecret) -> Self {
+        match secret {
+            EncryptionSecret::Passphrase(passphrase) => {
+                let mut salt = vec![0u8; SALT_LEN];
+                OsRng.fill_bytes(&mut salt);
+                let kdf = Kdf { salt, iterations: PBKDF2_ITERATIONS };
+                Encryptor { cipher: derive_key(passphrase, &kdf), kdf: Some(kdf), entries: Vec::new() }
+            }
+            EncryptionSecret::Key(key) => Encryptor { cipher: Aes256Gcm::new(key.into()), kdf: None, entries: Vec::new() },
+        }
+    }
+
+    /// Returns the stored form of entry `name` with contents `data`
+    pub(crate) fn encrypt(&mut self, name: &str, data: &[u8]) -> io::Result<Vec<u8>> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, Payload { msg: data, aad: name.as_bytes() })
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, format!("Entry '{}' is too large to encrypt", name)))?;
+        self.entries.push(name.to_string());
+        Ok([nonce.as_slice(), &ciphertext].concat())
+    }
+
+    /// Returns whether any entry was encrypted
+    pub(crate) fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Serializes the [`ENCRYPTION_NAME`] entry
+    pub(crate) fn metadata_json(&self) -> Vec<u8> {
+        let kdf = self.kdf.as_ref().map(|kdf| {
+            json!({ "algorithm": KDF, "iterations": kdf.iterations, "salt": to_hex(&kdf.salt) })
+        });
+        let mut json = serde_json::to_vec_pretty(&json!({ "cipher": CIPHER, "kdf": kdf, "entries": self.entries }))
+            .expect("JSON values always serialize");
+        json.push(b'\n');
+        json
+    }
+}
+
+/// Decrypts the encrypted entries of an archive; returned by [`ObbyArchive::decryptor`]
+pub struct Decryptor {
+    cipher: Aes256Gcm,
+    entries: HashSet<String>,
+}
+
+impl fmt::Debug for Decryptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Decryptor").field("entries", &self.entries).finish_non_exhaustive()
+    }
+}
+
+impl Decryptor {
+    /// Returns whether `name` is one of the archive's encrypted entries
+    pub fn is_encrypted(&self, name: &str) -> bool {
+        self.entries.contains(name)
+    }
+
+    /// Extracts an entry, decrypting it if it is encrypted
+    ///
+    /// # Arguments
+    ///
+    /// * `archive` - The archive this decryptor was created for.
+    /// * `name` - The entry to extract.
+    ///
+    /// # Returns
+    ///
+    /// The entry's contents, or an `io::Error` of kind `InvalidData` carrying
+    /// [`ObbyError::Decryption`] if the key is wrong or the entry was modified.
+    pub fn extract_entry<R: Read + Seek>(&self, archive: &mut ObbyArchive<R>, name: &str) -> io::Result<Vec<u8>> {
+        let stored = archive.extract_entry(name)?;
+        if !self.is_encrypted(name) {
+            return Ok(stored);
+        }
+        let failed = || ObbyError::Decryption { entry: name.to_string() }.into_io();
+        if stored.len() < NONCE_LEN {
+            return Err(failed());
+        }
+        let (nonce, ciphertext) = stored.split_at(NONCE_LEN);
+        self.cipher
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: name.as_bytes() })
+            .map_err(|_| failed())
+    }
+}
+
+impl<R: Read + Seek> ObbyArchive<R> {
+    /// Returns whether the archive has encrypted entries, i.e. an [`ENCRYPTION_NAME`] entry
+    pub fn has_encrypted_entries(&self) -> bool {
+        self.entries.contains_key(ENCRYPTION_NAME)
+    }
+
+    /// Prepares to decrypt the archive's encrypted entries
+    ///
+    /// For a passphrase, this derives the key, which is deliberately slow, so create
+    /// one `Decryptor` and use it for every entry.
+    ///
+    /// # Arguments
+    ///
+    /// * `secret` - The passphrase or key the entries were encrypted with.
+    ///
+    /// # Returns
+    ///
+    /// A `Decryptor`, or an `io::Error` of kind `NotFound` if the archive has no
+    /// encrypted entries, or of kind `InvalidData` if the [`ENCRYPTION_NAME`] entry is
+    /// malformed or names an unsupported cipher. A wrong secret is only detected when
+    /// decrypting an entry.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use obsidian_lib::EncryptionSecret;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut archive = obsidian_lib::open("plugin.obby")?;
+    /// let decryptor = archive.decryptor(&EncryptionSecret::Passphrase("hunter2".to_string()))?;
+    /// let model = decryptor.extract_entry(&mut archive, "assets/model.bin")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn decryptor(&mut self, secret: &EncryptionSecret) -> io::Result<Decryptor> {
+        let json = self.extract_entry(ENCRYPTION_NAME)?;
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid {}: {}", ENCRYPTION_NAME, message));
+        let value: Value = serde_json::from_slice(&json).map_err(|e| invalid(&e.to_string()))?;
+        if value["cipher"] != CIPHER {
+            return Err(invalid(&format!("unsupported cipher {}", value["cipher"])));
+        }
+        let entries = match &value["entries"] {
+            Value::Array(names) => names.iter().map(|name| name.as_str().map(str::to_string)).collect::<Option<_>>(),
+            _ => None,
+        }
+        .ok_or_else(|| invalid("\"entries\" isn't a list of names"))?;
+
+        let cipher = match (secret, &value["kdf"]) {
+            (EncryptionSecret::Key(key), _) => Aes256Gcm::new(key.into()),
+            (EncryptionSecret::Passphrase(_), Value::Null) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Archive was encrypted with a key, not a passphrase",
+                ))
+            }
+            (EncryptionSecret::Passphrase(passphrase), kdf) => {
+                if kdf["algorithm"] != KDF {
+                    return Err(invalid(&format!("unsupported key derivation {}", kdf["algorithm"])));
+                }
+                let iterations = kdf["iterations"].as_u64().and_then(|n| u32::try_from(n).ok()).filter(|&n| n > 0);
+                let salt = kdf["salt"].as_str().and_then(from_hex);
+                let (Some(iterations), Some(salt)) = (iterations, salt) else {
+                    return Err(invalid("bad key derivation parameters"));
+                };
+                derive_key(passphrase, &Kdf { salt, iterations })
+            }
+        };
+        Ok(Decryptor { cipher, entries })
+    }
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| hex.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ObbyWriter;
+
+    fn encrypted(secret: &EncryptionSecret) -> Vec<u8> {
+        let mut writer = ObbyWriter::new(Vec::new(), "Plugin", "1.0.0");
+        writer.set_encryption(secret).unwrap();
+        writer.add_entry("plugin.json", b"{}").unwrap();
+        writer.add_encrypted_entry("assets/licensed.bin", b"secret asset").unwrap();
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn test_key_round_trip() {
+        let key = EncryptionSecret::Key([7; 32]);
+        let mut archive = ObbyArchive::from_bytes(encrypted(&key)).unwrap();
+        assert!(archive.has_encrypted_entries());
+        assert_eq!(archive.list_entries(), vec!["plugin.json", "assets/licensed.bin", ENCRYPTION_NAME]);
+        // Other readers see the ciphertext
+        let stored = archive.extract_entry("assets/licensed.bin").unwrap();
+        assert!(!stored.windows(6).any(|window| window == b"secret"));
+
+        // The flag entry doesn't get in the way of extracting everything
+        let dir = tempfile::tempdir().unwrap();
+        archive.extract_all(dir.path()).unwrap();
+        assert!(dir.path().join(ENCRYPTION_NAME).exists());
+
+        let decryptor = archive.decryptor(&key).unwrap();
+        assert!(decryptor.is_encrypted("assets/licensed.bin"));
+        assert_eq!(decryptor.extract_entry(&mut archive, "assets/licensed.bin").unwrap(), b"secret asset");
+        assert_eq!(decryptor.extract_entry(&mut archive, "plugin.json").unwrap(), b"{}");
+
+        let wrong = archive.decryptor(&EncryptionSecret::Key([8; 32])).unwrap();
+        let err = wrong.extract_entry(&mut archive, "assets/licensed.bin").unwrap_err();
+        assert!(matches!(ObbyError::from_io(&err), Some(ObbyError::Decryption { .. })));
+        assert_eq!(archive.decryptor(&EncryptionSecret::Passphrase("key".to_string())).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_passphrase_round_trip() {
+        let passphrase = EncryptionSecret::Passphrase("correct horse".to_string());
+        let mut archive = ObbyArchive::from_bytes(encrypted(&passphrase)).unwrap();
+        let decryptor = archive.decryptor(&passphrase).unwrap();
+        assert_eq!(decryptor.extract_entry(&mut archive, "assets/licensed.bin").unwrap(), b"secret asset");
+
+        let plain = ObbyArchive::from_bytes(crate::testing::ObbyTestBuilder::new().entry("a", b"").build());
+        assert_eq!(plain.unwrap().decryptor(&passphrase).unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_one_secret_per_writer() {
+        let mut writer = ObbyWriter::new(Vec::new(), "Plugin", "1.0.0");
+        writer.set_encryption(&EncryptionSecret::Key([1; 32])).unwrap();
+        // Replacing the secret is fine until it has been used
+        writer.set_encryption(&EncryptionSecret::Key([2; 32])).unwrap();
+        writer.add_encrypted_entry("a.bin", b"a").unwrap();
+        let err = writer.set_encryption(&EncryptionSecret::Key([3; 32])).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        let mut archive = ObbyArchive::from_bytes(writer.finish().unwrap()).unwrap();
+        let decryptor = archive.decryptor(&EncryptionSecret::Key([2; 32])).unwrap();
+        assert_eq!(decryptor.extract_entry(&mut archive, "a.bin").unwrap(), b"a");
+    }
+}
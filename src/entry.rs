@@ -0,0 +1,172 @@
+//! Borrowed handles to single entries
+
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::Range;
+
+use crate::{decoding_reader, EntryInfo, ObbyArchive, ObbyError, ObbyReadOptions};
+
+/// A handle to one entry of an archive, returned by [`ObbyArchive::get`]
+///
+/// The entry's sizes are available right away. Reading from the handle streams the
+/// entry's decompressed contents; nothing is read from the archive until the first
+/// call to `read`. The handle borrows the archive mutably, so only one entry can be
+/// read at a time.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::io::Read;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut archive = obsidian_lib::open("plugin.obby")?;
+/// if let Some(mut entry) = archive.get("main.js") {
+///     println!("{} is {} bytes", entry.name(), entry.size());
+///     let mut contents = String::new();
+///     entry.read_to_string(&mut contents)?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct EntryGuard<'a, R: Read + Seek> {
+    name: &'a str,
+    info: &'a EntryInfo,
+    data_start_pos: u64,
+    options: &'a ObbyReadOptions,
+    /// The archive's source, until the entry is opened
+    source: Option<&'a mut R>,
+    stream: Option<Box<dyn Read + 'a>>,
+}
+
+impl<R: Read + Seek> ObbyArchive<R> {
+    /// Returns a handle to an entry, for reading its sizes or streaming its contents
+    ///
+    /// # Arguments
+    ///
+    /// * `entry_name` - The name of the entry.
+    ///
+    /// # Returns
+    ///
+    /// The [`EntryGuard`], or `None` if there is no entry with that name.
+    pub fn get<'a>(&'a mut self, entry_name: &str) -> Option<EntryGuard<'a, R>> {
+        let (name, info) = self.entries.get_key_value(entry_name)?;
+        Some(EntryGuard {
+            name,
+            info,
+            data_start_pos: self.data_start_pos,
+            options: &self.options,
+            source: Some(&mut self.reader),
+            stream: None,
+        })
+    }
+}
+
+impl<'a, R: Read + Seek> EntryGuard<'a, R> {
+    /// Returns the entry's name
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// Returns the entry's decompressed size, as declared in the entry table
+    pub fn size(&self) -> u64 {
+        self.info.length as u64
+    }
+
+    /// Returns the size of the entry's stored data
+    pub fn compressed_size(&self) -> u64 {
+        self.info.compressed_length as u64
+    }
+
+    /// Returns whether the entry is stored compressed
+    pub fn is_compressed(&self) -> bool {
+        self.info.is_compressed()
+    }
+
+    /// Returns where the entry's stored bytes lie in the source; see [`ObbyArchive::entry_byte_range`]
+    pub fn byte_range(&self) -> Range<u64> {
+        let start = self.data_start_pos + self.info.offset;
+        start..start + self.compressed_size()
+    }
+
+    /// Seeks to the entry's data and sets up decompression on first use
+    ///
+    /// Only failures of the decompressor are wrapped in [`ObbyError::Decompression`], so
+    /// seek errors and size limits keep their own errors.
+    fn stream(&mut self) -> io::Result<&mut Box<dyn Read + 'a>> {
+        if self.stream.is_none() {
+            let source = self
+                .source
+                .take()
+                .ok_or_else(|| io::Error::other(format!("Entry '{}' failed to open earlier", self.name)))?;
+            source.seek(SeekFrom::Start(self.data_start_pos + self.info.offset))?;
+            let raw = source.take(self.compressed_size());
+            self.stream = Some(if self.is_compressed() {
+                decoding_reader(self.options, raw, self.info.length)
+                    .map_err(|source| ObbyError::decompression(self.name, source))?
+            } else {
+                Box::new(raw)
+            });
+        }
+        Ok(self.stream.as_mut().expect("opened above"))
+    }
+}
+
+impl<R: Read + Seek> Read for EntryGuard<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let (name, compressed) = (self.name, self.is_compressed());
+        match self.stream()?.read(buf) {
+            Err(source) if compressed && source.kind() != io::ErrorKind::Interrupted => Err(ObbyError::decompression(name, source)),
+            result => result,
+        }
+    }
+}
+
+impl<R: Read + Seek> fmt::Debug for EntryGuard<'_, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EntryGuard")
+            .field("name", &self.name)
+            .field("size", &self.size())
+            .field("compressed_size", &self.compressed_size())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+
+    #[test]
+    fn test_entry_guard() {
+        let text = "lorem ipsum ".repeat(100);
+        let buffer = ObbyTestBuilder::new()
+            .stored_entry("plugin.json", b"{}")
+            .entry("notes.txt", text.as_bytes())
+            .build();
+        let mut archive = ObbyArchive::from_bytes(buffer.clone()).unwrap();
+        assert!(archive.get("missing").is_none());
+
+        let mut entry = archive.get("notes.txt").unwrap();
+        assert_eq!(entry.name(), "notes.txt");
+        assert_eq!(entry.size(), text.len() as u64);
+        assert!(entry.is_compressed() && entry.compressed_size() < entry.size());
+        let range = entry.byte_range();
+        let mut contents = String::new();
+        entry.read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, text);
+        drop(entry);
+        assert_eq!(range, archive.entry_byte_range("notes.txt").unwrap());
+
+        let mut data = Vec::new();
+        archive.get("plugin.json").unwrap().read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"{}");
+
+        // A size limit is reported as such, not as corrupt data
+        let mut options = ObbyReadOptions::default();
+        options.set_max_entry_size(Some(16));
+        let mut archive = ObbyArchive::with_options(std::io::Cursor::new(buffer), options).unwrap();
+        let err = archive.get("notes.txt").unwrap().read_to_end(&mut data).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(ObbyError::from_io(&err).is_none(), "{:?}", err);
+    }
+}
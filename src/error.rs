@@ -0,0 +1,182 @@
+//! Typed error details for archive operations
+//!
+//! All fallible functions in this crate return `io::Result`. Where a failure needs more
+//! structure than an `io::ErrorKind` and a message, the `io::Error` carries an
+//! [`ObbyError`] as its inner error, which can be recovered with [`ObbyError::from_io`].
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Structured details attached to some `io::Error`s returned by this crate
+///
+/// # Example
+///
+/// ```no_run
+/// use obsidian_lib::{ObbyError, open};
+///
+/// let mut archive = open("plugin.obby").unwrap();
+/// if let Err(e) = archive.extract_entry("plugin.json") {
+///     match ObbyError::from_io(&e) {
+///         Some(ObbyError::Decompression { entry, .. }) => eprintln!("{} is corrupt", entry),
+///         _ => eprintln!("{}", e),
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ObbyError {
+    /// The header or entry table is malformed or truncated
+    Parse {
+        /// The field being read, such as `entry[12].compressed_length`
+        field: String,
+        /// Position of the field in the source
+        offset: u64,
+        /// The underlying error
+        source: io::Error,
+    },
+    /// A compressed entry could not be decoded
+    Decompression {
+        /// Name of the entry
+        entry: String,
+        /// The error reported by the codec
+        source: io::Error,
+    },
+    /// An entry name would escape the extraction directory or is otherwise rejected
+    /// by the [`crate::SanitizePolicy`] in use
+    UnsafePath {
+        /// Name of the entry
+        entry: String,
+    },
+    /// Extraction would have written through a symlink in the destination directory
+    SymlinkInPath {
+        /// The symlink
+        path: PathBuf,
+    },
+    /// The data section doesn't match the SHA-384 hash stored in the header
+    HashMismatch {
+        /// The hash stored in the header
+        expected: Vec<u8>,
+        /// The hash of the data actually present
+        actual: Vec<u8>,
+    },
+    /// The archive is not signed
+    MissingSignature,
+    /// The archive's signature was not made by any of the trusted keys
+    InvalidSignature,
+    /// Entries don't match the checksums in the archive's [`crate::CHECKSUMS_NAME`]
+    ChecksumMismatch {
+        /// Entries that were modified, aren't listed, or are listed but don't exist
+        entries: Vec<String>,
+    },
+    /// An encrypted entry could not be decrypted, because the key is wrong or the
+    /// entry was modified
+    Decryption {
+        /// Name of the entry
+        entry: String,
+    },
+    /// None of the known manifest names (see [`crate::MANIFEST_NAMES`]) exist in the archive
+    ManifestNotFound {
+        /// Names of the entries at the root of the archive, to help diagnose unusual layouts
+        root_entries: Vec<String>,
+    },
+}
+
+impl ObbyError {
+    /// Returns the `ObbyError` carried by `error`, if any
+    pub fn from_io(error: &io::Error) -> Option<&ObbyError> {
+        error.get_ref().and_then(|inner| inner.downcast_ref::<ObbyError>())
+    }
+
+    /// Wraps a failure to decompress `entry` in [`ObbyError::Decompression`]
+    ///
+    /// Errors for entries over [`crate::ObbyReadOptions::max_entry_size`] are returned
+    /// unchanged, so a size limit is reported the same way on every path rather than as
+    /// corrupt data.
+    pub(crate) fn decompression(entry: &str, source: io::Error) -> io::Error {
+        if source.get_ref().is_some_and(|inner| inner.is::<EntryTooLarge>()) {
+            return source;
+        }
+        ObbyError::Decompression { entry: entry.to_string(), source }.into_io()
+    }
+
+    /// Wraps this error in an `io::Error` of the appropriate kind
+    pub(crate) fn into_io(self) -> io::Error {
+        let kind = match &self {
+            ObbyError::Parse { source, .. } => source.kind(),
+            ObbyError::Decompression { .. }
+            | ObbyError::UnsafePath { .. }
+            | ObbyError::SymlinkInPath { .. }
+            | ObbyError::HashMismatch { .. }
+            | ObbyError::ChecksumMismatch { .. }
+            | ObbyError::Decryption { .. }
+            | ObbyError::MissingSignature
+            | ObbyError::InvalidSignature => io::ErrorKind::InvalidData,
+            ObbyError::ManifestNotFound { .. } => io::ErrorKind::NotFound,
+        };
+        io::Error::new(kind, self)
+    }
+}
+
+impl fmt::Display for ObbyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObbyError::Parse { field, offset, source } => {
+                write!(f, "Failed to read {} at offset {:#x}: {}", field, offset, source)
+            }
+            ObbyError::Decompression { entry, source } => {
+                write!(f, "Failed to decompress entry '{}': {}", entry, source)
+            }
+            ObbyError::UnsafePath { entry } => {
+                write!(f, "Refusing to extract entry with unsafe name {:?}", entry)
+            }
+            ObbyError::SymlinkInPath { path } => {
+                write!(f, "Refusing to extract through symlink {}", path.display())
+            }
+            ObbyError::HashMismatch { .. } => {
+                write!(f, "Archive data doesn't match the hash in its header")
+            }
+            ObbyError::ChecksumMismatch { entries } => {
+                write!(f, "Entries don't match the embedded checksums: {}", entries.join(", "))
+            }
+            ObbyError::Decryption { entry } => {
+                write!(f, "Failed to decrypt entry '{}': wrong key or modified entry", entry)
+            }
+            ObbyError::MissingSignature => write!(f, "Archive is not signed"),
+            ObbyError::InvalidSignature => {
+                write!(f, "Archive signature doesn't match any trusted key")
+            }
+            ObbyError::ManifestNotFound { root_entries } => write!(
+                f,
+                "No plugin manifest found (tried {}); root entries: [{}]",
+                crate::MANIFEST_NAMES.join(", "),
+                root_entries.join(", ")
+            ),
+        }
+    }
+}
+
+impl Error for ObbyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ObbyError::Parse { source, .. } | ObbyError::Decompression { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Inner error of an entry that declares more than [`crate::ObbyReadOptions::max_entry_size`] bytes
+#[derive(Debug)]
+pub(crate) struct EntryTooLarge {
+    pub(crate) length: i32,
+    pub(crate) max: u64,
+}
+
+impl fmt::Display for EntryTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Declared size of {} bytes exceeds the limit of {} bytes", self.length, self.max)
+    }
+}
+
+impl Error for EntryTooLarge {}
@@ -0,0 +1,90 @@
+//! Exit statuses and error reporting for `obby`
+//!
+//! Each class of failure exits with its own status, so wrappers such as panel scripts
+//! and CI jobs can react without parsing messages. The statuses are stable:
+//!
+//! | Status | Failure                                                          |
+//! |--------|------------------------------------------------------------------|
+//! | 0      | success                                                          |
+//! | 1      | any other failure, such as an I/O error or failed batch jobs     |
+//! | 2      | the archive is malformed, truncated or has a corrupt entry       |
+//! | 3      | a file, entry or plugin manifest doesn't exist                   |
+//! | 4      | the hash doesn't match, or the signature is missing or untrusted |
+//! | 5      | an entry would be written outside the extraction directory       |
+//! | 64     | invalid command-line usage                                       |
+
+use std::io;
+use std::process::ExitCode;
+
+use obsidian_lib::ObbyError;
+
+use crate::cli::ErrorFormat;
+
+/// A class of failure, with its exit status
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    Other = 1,
+    InvalidFormat = 2,
+    NotFound = 3,
+    VerificationFailed = 4,
+    UnsafePath = 5,
+    Usage = 64,
+}
+
+impl Failure {
+    /// Classifies an error returned by a command
+    pub fn of(error: &io::Error) -> Failure {
+        match ObbyError::from_io(error) {
+            Some(
+                ObbyError::HashMismatch { .. }
+                | ObbyError::ChecksumMismatch { .. }
+                | ObbyError::MissingSignature
+                | ObbyError::InvalidSignature,
+            ) => Failure::VerificationFailed,
+            Some(ObbyError::UnsafePath { .. } | ObbyError::SymlinkInPath { .. }) => Failure::UnsafePath,
+            Some(ObbyError::Parse { .. } | ObbyError::Decompression { .. }) => Failure::InvalidFormat,
+            _ => match error.kind() {
+                io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof => Failure::InvalidFormat,
+                io::ErrorKind::NotFound => Failure::NotFound,
+                _ => Failure::Other,
+            },
+        }
+    }
+
+    /// Name of the class in JSON error reports
+    fn name(self) -> &'static str {
+        match self {
+            Failure::Other => "other",
+            Failure::InvalidFormat => "invalid_format",
+            Failure::NotFound => "not_found",
+            Failure::VerificationFailed => "verification_failed",
+            Failure::UnsafePath => "unsafe_path",
+            Failure::Usage => "usage",
+        }
+    }
+
+    /// The exit status
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+}
+
+/// Prints `message` to stderr in the requested format and returns the exit status for `failure`
+///
+/// JSON reports are a single line: `{"error":{"code":4,"class":"verification_failed","message":"..."}}`.
+pub fn report(failure: Failure, message: &str, format: ErrorFormat) -> ExitCode {
+    match format {
+        ErrorFormat::Text => eprintln!("Error: {}", message),
+        ErrorFormat::Json => eprintln!(
+            "{}",
+            serde_json::json!({
+                "error": {
+                    "code": failure.code(),
+                    "class": failure.name(),
+                    "message": message,
+                }
+            })
+        ),
+    }
+    ExitCode::from(failure.code())
+}
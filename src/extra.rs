@@ -0,0 +1,283 @@
+//! Per-entry extra fields, such as modification times and permissions
+//!
+//! The `.obby` format itself has no per-entry fields beyond an entry's name and lengths
+//! (see [`crate::format`]). This crate can keep extras in a block of its own after the
+//! last entry's data. [`crate::ObbyWriter`] only writes the block when enabled with
+//! [`crate::ObbyWriter::set_write_extras`]; archives without it are plain `.obby` files.
+//!
+//! The block is laid out as follows, using the encodings in [`crate::format::wire`]:
+//!
+//! | Field        | Encoding                                                   |
+//! |--------------|------------------------------------------------------------|
+//! | magic        | the 4 bytes `OBBX`                                         |
+//! | record count | `i32`                                                      |
+//! | records      | per entry: name (string), field count (`i32`), fields      |
+//! | field        | key (string), value (7-bit encoded length, then the bytes) |
+//!
+//! Only entries with at least one field get a record. The block is part of the data
+//! section: it is counted in the header's data length and covered by the SHA-384 hash
+//! and signature, so loaders that check the hash read it along with the entries.
+//! Nothing in the entry table points into it, so it doesn't change the entries they see.
+
+use std::collections::BTreeMap;
+use std::io::{self, Read};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::format::wire::{BinaryReader, BinaryWriter};
+use crate::ParseWarning;
+
+/// Magic bytes that start the extras block
+pub(crate) const EXTRA_MAGIC: &[u8; 4] = b"OBBX";
+
+/// Key of the modification time: seconds since the Unix epoch, as a little-endian `i64`
+pub const MODIFIED_KEY: &str = "mtime";
+
+/// Key of the Unix permission bits, as a little-endian `u32`
+pub const MODE_KEY: &str = "mode";
+
+/// Extra fields stored for an entry, returned by [`crate::ObbyArchive::entry_extra`]
+///
+/// Fields are raw bytes keyed by name. [`EntryExtra::modified`] and [`EntryExtra::mode`]
+/// decode the fields this crate knows about; fields added by newer writers are kept as
+/// they are, and reported in [`crate::ObbyArchive::warnings`] so that strict readers
+/// can reject them.
+///
+/// # Example
+///
+/// ```no_run
+/// use obsidian_lib::{EntryExtra, ObbyWriter};
+/// use std::time::SystemTime;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut writer = ObbyWriter::create("plugin.obby", "MyPlugin", "1.2.3");
+/// writer.set_write_extras(true);
+/// writer.add_entry("main.js", b"console.log(1)")?;
+/// let mut extra = EntryExtra::new();
+/// extra.set_modified(SystemTime::now());
+/// extra.set_mode(0o644);
+/// writer.set_entry_extra("main.js", extra)?;
+/// writer.finish()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct EntryExtra {
+    fields: BTreeMap<String, Vec<u8>>,
+}
+
+impl EntryExtra {
+    /// Creates an empty set of fields
+    pub fn new() -> Self {
+        EntryExtra::default()
+    }
+
+    /// Returns the raw value of the field named `key`
+    pub fn get(&self, key: &str) -> Option<&[u8]> {
+        self.fields.get(key).map(Vec::as_slice)
+    }
+
+    /// Sets the raw value of the field named `key`, replacing any previous value
+    pub fn insert(&mut self, key: &str, value: Vec<u8>) {
+        self.fields.insert(key.to_string(), value);
+    }
+
+    /// Returns the fields, sorted by key
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[u8])> {
+        self.fields.iter().map(|(key, value)| (key.as_str(), value.as_slice()))
+    }
+
+    /// Returns the number of fields
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    /// Returns whether there are no fields
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Returns the entry's modification time, if recorded
+    pub fn modified(&self) -> Option<SystemTime> {
+        let secs = i64::from_le_bytes(self.get(MODIFIED_KEY)?.try_into().ok()?);
+        match u64::try_from(secs) {
+            Ok(secs) => UNIX_EPOCH.checked_add(Duration::from_secs(secs)),
+            Err(_) => UNIX_EPOCH.checked_sub(Duration::from_secs(secs.unsigned_abs())),
+        }
+    }
+
+    /// Records the entry's modification time, truncated to whole seconds
+    pub fn set_modified(&mut self, time: SystemTime) {
+        let secs = match time.duration_since(UNIX_EPOCH) {
+            Ok(elapsed) => i64::try_from(elapsed.as_secs()).unwrap_or(i64::MAX),
+            Err(e) => i64::try_from(e.duration().as_secs()).map_or(i64::MIN, |secs| -secs),
+        };
+        self.insert(MODIFIED_KEY, secs.to_le_bytes().to_vec());
+    }
+
+    /// Returns the entry's Unix permission bits, if recorded
+    pub fn mode(&self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.get(MODE_KEY)?.try_into().ok()?))
+    }
+
+    /// Records the entry's Unix permission bits
+    pub fn set_mode(&mut self, mode: u32) {
+        self.insert(MODE_KEY, mode.to_le_bytes().to_vec());
+    }
+}
+
+/// Size in bytes of the value of each known field
+fn known_len(key: &str) -> Option<usize> {
+    match key {
+        MODIFIED_KEY => Some(8),
+        MODE_KEY => Some(4),
+        _ => None,
+    }
+}
+
+/// Parses an extras block, returning `None` if `reader` doesn't start with one
+///
+/// Fields this crate doesn't know are kept and reported as warnings; known fields of
+/// the wrong size make the block invalid.
+pub(crate) fn read_extras<R: Read>(
+    reader: &mut BinaryReader<R>,
+    warnings: &mut Vec<ParseWarning>,
+) -> io::Result<Option<Vec<(String, EntryExtra)>>> {
+    if reader.read_bytes(EXTRA_MAGIC.len())? != EXTRA_MAGIC {
+        return Ok(None);
+    }
+    let count = reader.read_length("extras record count")?;
+    let mut records = Vec::new();
+    for _ in 0..count {
+        let name = reader.read_string()?;
+        let mut extra = EntryExtra::new();
+        for _ in 0..reader.read_length("extra field count")? {
+            let key = reader.read_string()?;
+            let value = reader.read_string_bytes()?;
+            match known_len(&key) {
+                Some(len) if len != value.len() => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Extra field '{}' of entry '{}' holds {} bytes", key, name, value.len()),
+                    ));
+                }
+                Some(_) => {}
+                None => warnings.push(ParseWarning::UnknownExtraField { entry: name.clone(), key: key.clone() }),
+            }
+            extra.fields.insert(key, value);
+        }
+        records.push((name, extra));
+    }
+    Ok(Some(records))
+}
+
+/// Serializes the extras block for `records`, or nothing if no entry has extras
+pub(crate) fn write_extras<'a>(records: impl Iterator<Item = (&'a str, &'a EntryExtra)>) -> io::Result<Vec<u8>> {
+    let records: Vec<_> = records.filter(|(_, extra)| !extra.is_empty()).collect();
+    if records.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut writer = BinaryWriter::new(EXTRA_MAGIC.to_vec());
+    writer.write_i32(records.len() as i32)?;
+    for (name, extra) in records {
+        writer.write_string(name)?;
+        writer.write_i32(extra.len() as i32)?;
+        for (key, value) in extra.iter() {
+            let length = i32::try_from(value.len())
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Extra field is too long to encode"))?;
+            writer.write_string(key)?;
+            writer.write_7bit_encoded_int(length)?;
+            writer.write_bytes(value)?;
+        }
+    }
+    Ok(writer.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+    use crate::{ObbyArchive, ObbyReadOptions, ObbyWriter};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_entry_extra_round_trip() {
+        let modified = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut writer = ObbyWriter::new(Vec::new(), "Plugin", "1.0.0");
+        writer.set_write_extras(true);
+        writer.add_entry("plugin.json", b"{}").unwrap();
+        writer.add_entry("main.js", &[b'a'; 300]).unwrap();
+        let mut extra = EntryExtra::new();
+        extra.set_modified(modified);
+        extra.set_mode(0o755);
+        writer.set_entry_extra("main.js", extra.clone()).unwrap();
+        assert!(writer.set_entry_extra("missing.js", EntryExtra::new()).is_err());
+        let buffer = writer.finish().unwrap();
+
+        let mut archive = ObbyArchive::from_slice(&buffer).unwrap();
+        assert!(archive.warnings().is_empty());
+        assert_eq!(archive.trailing_data_len(), 0);
+        assert_eq!(archive.entry_extra("main.js"), Some(&extra));
+        assert_eq!(archive.entry_extra("main.js").unwrap().modified(), Some(modified));
+        assert_eq!(archive.entry_extra("main.js").unwrap().mode(), Some(0o755));
+        assert_eq!(archive.entry_extra("plugin.json"), None);
+        assert_eq!(archive.extract_entry("main.js").unwrap(), vec![b'a'; 300]);
+        archive.verify_hash().unwrap();
+
+        let before_epoch = UNIX_EPOCH - Duration::from_secs(60);
+        extra.set_modified(before_epoch);
+        assert_eq!(extra.modified(), Some(before_epoch));
+    }
+
+    #[test]
+    fn test_extras_are_opt_in() {
+        let mut extra = EntryExtra::new();
+        extra.set_mode(0o644);
+        let write = |write_extras: bool| {
+            let mut writer = ObbyWriter::new(Vec::new(), "Plugin", "1.0.0");
+            writer.set_write_extras(write_extras);
+            writer.add_entry("main.js", b"console.log(1)").unwrap();
+            writer.set_entry_extra("main.js", extra.clone()).unwrap();
+            writer.finish().unwrap()
+        };
+
+        let plain = write(false);
+        let archive = ObbyArchive::from_slice(&plain).unwrap();
+        assert_eq!(archive.entry_extra("main.js"), None);
+        assert!(!plain.windows(EXTRA_MAGIC.len()).any(|window| window == EXTRA_MAGIC));
+
+        let with_extras = write(true);
+        assert!(with_extras.len() > plain.len());
+        let archive = ObbyArchive::from_slice(&with_extras).unwrap();
+        assert_eq!(archive.entry_extra("main.js"), Some(&extra));
+    }
+
+    #[test]
+    fn test_unknown_extra_fields() {
+        let mut extra = EntryExtra::new();
+        extra.insert("xattr.user.comment", b"hi".to_vec());
+        let block = write_extras([("a.txt", &extra)].into_iter()).unwrap();
+        let mut buffer = ObbyTestBuilder::new().stored_entry("a.txt", b"a").build();
+        buffer.extend_from_slice(&block);
+
+        let archive = ObbyArchive::from_slice(&buffer).unwrap();
+        assert_eq!(archive.entry_extra("a.txt").unwrap().get("xattr.user.comment"), Some(&b"hi"[..]));
+        assert_eq!(
+            archive.warnings(),
+            &[ParseWarning::UnknownExtraField { entry: "a.txt".to_string(), key: "xattr.user.comment".to_string() }]
+        );
+        assert_eq!(archive.trailing_data_len(), 0);
+
+        let mut strict = ObbyReadOptions::default();
+        strict.set_strict(true);
+        let err = ObbyArchive::with_options(Cursor::new(&buffer), strict).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // A damaged block is left as trailing data
+        buffer.truncate(buffer.len() - 1);
+        let archive = ObbyArchive::from_slice(&buffer).unwrap();
+        assert_eq!(archive.entry_extra("a.txt"), None);
+        assert_eq!(archive.trailing_data_len(), block.len() as u64 - 1);
+        assert!(matches!(archive.warnings()[0], ParseWarning::InvalidExtraFields { .. }));
+    }
+}
@@ -0,0 +1,306 @@
+//! Options for extracting archives to disk
+
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::io::{self, Read, Seek};
+use std::path::{Path, PathBuf};
+
+use crate::plan::{entry_collisions, Collision, PlannedFile};
+use crate::{ObbyArchive, ObbyError};
+
+/// What [`ObbyArchive::extract_all_with_options`] does when a file already exists
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverwritePolicy {
+    /// Fails with `AlreadyExists` before writing anything, also if several entries
+    /// would write the same file (see [`Collision::Duplicate`] and [`Collision::CaseOnly`])
+    Error,
+    /// Leaves a file that existed before the extraction alone and doesn't extract the
+    /// entry; entries writing the same file replace each other as with
+    /// [`ObbyArchive::extract_all`]
+    Skip,
+    /// Replaces the existing file
+    #[default]
+    Overwrite,
+    /// Writes the entry next to the existing file, as `name (1).ext`, `name (2).ext`, ...
+    RenameWithSuffix,
+}
+
+/// Options for [`ObbyArchive::extract_all_with_options`]
+///
+/// # Example
+///
+/// ```no_run
+/// use obsidian_lib::{ExtractOptions, OverwritePolicy};
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut options = ExtractOptions::default();
+/// options.set_overwrite_policy(OverwritePolicy::Skip);
+/// options.exclude("data.json");
+/// let mut archive = obsidian_lib::open("plugin.obby")?;
+/// archive.extract_all_with_options("plugins/my-plugin", &options)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ExtractOptions {
+    overwrite_policy: OverwritePolicy,
+    excluded: HashSet<String>,
+}
+
+impl ExtractOptions {
+    /// Returns what happens to files that already exist
+    pub fn overwrite_policy(&self) -> OverwritePolicy {
+        self.overwrite_policy
+    }
+
+    /// Sets what happens to files that already exist
+    ///
+    /// Defaults to [`OverwritePolicy::Overwrite`], matching [`ObbyArchive::extract_all`].
+    pub fn set_overwrite_policy(&mut self, policy: OverwritePolicy) {
+        self.overwrite_policy = policy;
+    }
+
+    /// Leaves an entry out of the extraction
+    ///
+    /// `name` is matched against entry names and against the paths they map to,
+    /// relative to the destination and with `/` separators, so both `lib\main.dll` and
+    /// `lib/main.dll` exclude an entry stored as `lib\main.dll`.
+    pub fn exclude(&mut self, name: impl Into<String>) {
+        self.excluded.insert(name.into());
+    }
+
+    /// Returns whether the entry `name`, mapped to the relative path `path`, is excluded
+    fn is_excluded(&self, name: &str, path: &Path) -> bool {
+        if self.excluded.is_empty() {
+            return false;
+        }
+        let parts: Vec<_> = path.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+        self.excluded.contains(name) || self.excluded.contains(&parts.join("/"))
+    }
+}
+
+impl<R: Read + Seek> ObbyArchive<R> {
+    /// Extracts every entry into a directory, with control over existing files
+    ///
+    /// Works like [`ObbyArchive::extract_all`], except that excluded entries are left
+    /// out and existing files are handled by the [`OverwritePolicy`]. All names, and with
+    /// [`OverwritePolicy::Error`] all destinations and clashes between entries, are
+    /// checked before anything is written.
+    ///
+    /// # Arguments
+    ///
+    /// * `dest` - The directory to extract into. It is created if it doesn't exist.
+    /// * `options` - The `ExtractOptions` to use.
+    ///
+    /// # Returns
+    ///
+    /// The paths of the written files, in archive order, or an `io::Error` of kind
+    /// `AlreadyExists` if a file exists and the policy is [`OverwritePolicy::Error`].
+    pub fn extract_all_with_options<P: AsRef<Path>>(&mut self, dest: P, options: &ExtractOptions) -> io::Result<Vec<PathBuf>> {
+        let dest = dest.as_ref();
+        let policy = self.options.sanitize_policy();
+        let mut targets = Vec::with_capacity(self.order.len());
+        let mut relative_paths = Vec::with_capacity(self.order.len());
+        for name in &self.order {
+            let relative = policy.sanitize(name)?;
+            if !options.is_excluded(name, &relative) {
+                let size = self.entries[name].length as u64;
+                targets.push(PlannedFile { entry: name.clone(), path: dest.join(&relative), size });
+                relative_paths.push(relative);
+            }
+        }
+        if options.overwrite_policy == OverwritePolicy::Error {
+            if let Some(file) = targets.iter().find(|file| exists(&file.path)) {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} already exists", file.path.display()),
+                ));
+            }
+            for collision in entry_collisions(dest, &targets, &relative_paths) {
+                let paths = match collision {
+                    Collision::Duplicate { path, .. } => vec![path],
+                    Collision::CaseOnly { paths } => paths,
+                    _ => continue,
+                };
+                let paths: Vec<_> = paths.iter().map(|path| path.display().to_string()).collect();
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("Several entries would be written to {}", paths.join(" / ")),
+                ));
+            }
+        }
+        // Only files from before the extraction count as existing for Skip, so that
+        // entries sharing a path behave as with extract_all
+        let existed: Vec<bool> = match options.overwrite_policy {
+            OverwritePolicy::Skip => targets.iter().map(|file| exists(&file.path)).collect(),
+            _ => Vec::new(),
+        };
+
+        let mut written = Vec::with_capacity(targets.len());
+        for (index, PlannedFile { entry: name, mut path, .. }) in targets.into_iter().enumerate() {
+            match options.overwrite_policy {
+                OverwritePolicy::Skip if existed[index] => continue,
+                OverwritePolicy::RenameWithSuffix if exists(&path) => path = free_name(&path),
+                _ => {}
+            }
+            let data = self.extract_entry(&name)?;
+            prepare_target(dest, &path)?;
+            std::fs::write(&path, data)?;
+            written.push(path);
+        }
+        Ok(written)
+    }
+}
+
+/// Creates the directories from `dest` down to `path`, refusing to pass through symlinks
+///
+/// A symlink at `path` itself is removed, so that writing replaces the link instead of
+/// the file it points to.
+fn prepare_target(dest: &Path, path: &Path) -> io::Result<()> {
+    std::fs::create_dir_all(dest)?;
+    let relative = path.strip_prefix(dest).expect("targets are joined onto dest");
+    let mut current = dest.to_path_buf();
+    let mut parts = relative.components().peekable();
+    while let Some(part) = parts.next() {
+        current.push(part);
+        let is_target = parts.peek().is_none();
+        match current.symlink_metadata() {
+            Ok(metadata) if metadata.file_type().is_symlink() => {
+                if !is_target {
+                    return Err(ObbyError::SymlinkInPath { path: current }.into_io());
+                }
+                std::fs::remove_file(&current)?;
+            }
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound && !is_target => std::fs::create_dir(&current)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+fn exists(path: &Path) -> bool {
+    path.symlink_metadata().is_ok()
+}
+
+/// Returns the first of `name (1).ext`, `name (2).ext`, ... that doesn't exist next to `path`
+fn free_name(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default();
+    let extension = path.extension();
+    (1u64..)
+        .map(|n| {
+            let mut name = OsString::from(stem);
+            name.push(format!(" ({})", n));
+            if let Some(extension) = extension {
+                name.push(".");
+                name.push(extension);
+            }
+            path.with_file_name(name)
+        })
+        .find(|candidate| !exists(candidate))
+        .expect("some suffix is free")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+
+    fn archive() -> ObbyArchive<io::Cursor<Vec<u8>>> {
+        let buffer = ObbyTestBuilder::new()
+            .entry("plugin.json", b"new")
+            .entry("main.js", b"js")
+            .entry("lib\\data.json", b"data")
+            .build();
+        ObbyArchive::from_bytes(buffer).unwrap()
+    }
+
+    #[test]
+    fn test_overwrite_policies() {
+        let dir = tempfile::tempdir().unwrap();
+        let read = |name: &str| std::fs::read_to_string(dir.path().join(name)).unwrap();
+        std::fs::write(dir.path().join("plugin.json"), "old").unwrap();
+
+        let mut options = ExtractOptions::default();
+        options.set_overwrite_policy(OverwritePolicy::Error);
+        let err = archive().extract_all_with_options(dir.path(), &options).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert!(!dir.path().join("main.js").exists());
+
+        options.set_overwrite_policy(OverwritePolicy::Skip);
+        let written = archive().extract_all_with_options(dir.path(), &options).unwrap();
+        assert_eq!(written.len(), 2);
+        assert_eq!(read("plugin.json"), "old");
+
+        options.set_overwrite_policy(OverwritePolicy::RenameWithSuffix);
+        archive().extract_all_with_options(dir.path(), &options).unwrap();
+        archive().extract_all_with_options(dir.path(), &options).unwrap();
+        assert_eq!(read("plugin.json"), "old");
+        assert_eq!(read("plugin (1).json"), "new");
+        assert_eq!(read("plugin (2).json"), "new");
+        assert_eq!(read("main (1).js"), "js");
+
+        options.set_overwrite_policy(OverwritePolicy::Overwrite);
+        archive().extract_all_with_options(dir.path(), &options).unwrap();
+        assert_eq!(read("plugin.json"), "new");
+    }
+
+    #[test]
+    fn test_entries_sharing_a_path() {
+        let buffer = ObbyTestBuilder::new()
+            .entry("lib/a.txt", b"first")
+            .entry("lib\\a.txt", b"second")
+            .build();
+        let dir = tempfile::tempdir().unwrap();
+        let mut options = ExtractOptions::default();
+        options.set_overwrite_policy(OverwritePolicy::Error);
+        let err = ObbyArchive::from_bytes(buffer.clone()).unwrap().extract_all_with_options(dir.path(), &options).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert!(!dir.path().join("lib").exists());
+
+        // Skip keeps the last entry, like extract_all
+        options.set_overwrite_policy(OverwritePolicy::Skip);
+        ObbyArchive::from_bytes(buffer.clone()).unwrap().extract_all_with_options(dir.path(), &options).unwrap();
+        assert_eq!(std::fs::read_to_string(dir.path().join("lib/a.txt")).unwrap(), "second");
+
+        let case_only = ObbyTestBuilder::new().entry("README.md", b"a").entry("readme.md", b"b").build();
+        options.set_overwrite_policy(OverwritePolicy::Error);
+        let err = ObbyArchive::from_bytes(case_only).unwrap().extract_all_with_options(dir.path(), &options).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_are_not_followed() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempfile::tempdir().unwrap();
+        let (dest, outside) = (dir.path().join("dest"), dir.path().join("outside"));
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::create_dir_all(&dest).unwrap();
+        std::fs::write(outside.join("plugin.json"), "victim").unwrap();
+
+        // A symlinked file is replaced, not written through
+        symlink(outside.join("plugin.json"), dest.join("plugin.json")).unwrap();
+        // A symlinked directory stops the extraction
+        symlink(&outside, dest.join("lib")).unwrap();
+        let err = archive().extract_all(&dest).unwrap_err();
+        assert!(matches!(ObbyError::from_io(&err), Some(ObbyError::SymlinkInPath { path }) if *path == dest.join("lib")));
+
+        assert_eq!(std::fs::read_to_string(outside.join("plugin.json")).unwrap(), "victim");
+        assert!(!outside.join("data.json").exists());
+        assert!(!dest.join("plugin.json").symlink_metadata().unwrap().file_type().is_symlink());
+        assert_eq!(std::fs::read_to_string(dest.join("plugin.json")).unwrap(), "new");
+    }
+
+    #[test]
+    fn test_exclusions() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut options = ExtractOptions::default();
+        options.exclude("plugin.json");
+        options.exclude("lib/data.json");
+        let written = archive().extract_all_with_options(dir.path(), &options).unwrap();
+        assert_eq!(written, vec![dir.path().join("main.js")]);
+    }
+}
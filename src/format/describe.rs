@@ -0,0 +1,267 @@
+//! Field-by-field breakdown of an archive's bytes
+
+use std::fmt;
+use std::io::{self, Read};
+
+use super::wire::BinaryReader;
+use crate::{to_hex, HASH_LEN, MAGIC, SIGNATURE_LEN};
+
+/// Number of leading bytes kept in [`FieldLayout::bytes`]
+pub const PREVIEW_LEN: usize = 16;
+
+/// One field of an archive, as found by [`describe`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FieldLayout {
+    /// Position of the field from the start of the archive
+    pub offset: u64,
+    /// Length of the field in bytes, including any length prefix
+    pub len: u64,
+    /// The field's name, such as `api_version` or `entry[3].compressed_length`
+    pub name: String,
+    /// What the field holds, decoded, such as `"1.0.0"` or `1234 bytes, compressed`
+    pub value: String,
+    /// The first [`PREVIEW_LEN`] bytes of the field as stored
+    pub bytes: Vec<u8>,
+}
+
+/// The fields of an archive in file order; returned by [`describe`]
+#[derive(Debug)]
+pub struct Layout {
+    /// Every field that could be read
+    pub fields: Vec<FieldLayout>,
+    /// Why reading stopped early, if it did
+    pub error: Option<io::Error>,
+}
+
+impl fmt::Display for Layout {
+    /// Formats the layout as an annotated hex dump, one field per line
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:>8}  {:>8}  {:<28}  {:<50}  value", "offset", "length", "field", "bytes")?;
+        for field in &self.fields {
+            let mut bytes = field.bytes.iter().map(|byte| format!("{:02x}", byte)).collect::<Vec<_>>().join(" ");
+            if field.len > field.bytes.len() as u64 {
+                bytes.push_str(" ..");
+            }
+            writeln!(f, "{:08x}  {:>8}  {:<28}  {:<50}  {}", field.offset, field.len, field.name, bytes, field.value)?;
+        }
+        if let Some(error) = &self.error {
+            writeln!(f, "stopped: {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+/// Breaks an archive down into its fields, for debugging archives from other packers
+///
+/// Unlike opening an archive, this doesn't stop at the first problem the parser would
+/// reject, such as a data length that doesn't match: every field that can be read is
+/// reported along with its position and raw bytes. Only when the input ends early or a
+/// field can't be decoded at all does the breakdown stop, and [`Layout::error`] says why.
+/// Entry data is read but not decompressed.
+///
+/// # Arguments
+///
+/// * `reader` - The archive, from its first byte.
+///
+/// # Returns
+///
+/// The [`Layout`], whose `Display` implementation prints an annotated hex dump.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// let file = std::fs::File::open("plugin.obby")?;
+/// print!("{}", obsidian_lib::format::describe(std::io::BufReader::new(file)));
+/// # Ok(())
+/// # }
+/// ```
+pub fn describe<R: Read>(reader: R) -> Layout {
+    let mut describer = Describer {
+        reader: BinaryReader::new(Recorder { inner: reader, offset: 0, recorded: Vec::new() }),
+        fields: Vec::new(),
+    };
+    let error = describer.describe_all().err();
+    Layout { fields: describer.fields, error }
+}
+
+struct Describer<R: Read> {
+    reader: BinaryReader<Recorder<R>>,
+    fields: Vec<FieldLayout>,
+}
+
+impl<R: Read> Describer<R> {
+    fn describe_all(&mut self) -> io::Result<()> {
+        let magic = self.field("magic", |reader| reader.read_bytes(MAGIC.len()), |magic| format!("{:?}", String::from_utf8_lossy(magic)))?;
+        if magic != MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Not an .obby archive"));
+        }
+        self.string("api_version")?;
+        self.field("hash", |reader| reader.read_bytes(HASH_LEN), |hash| format!("SHA-384 {}", to_hex(hash)))?;
+        let signed = self.field("signed", BinaryReader::read_u8, |signed| if *signed != 0 { "yes" } else { "no" }.to_string())?;
+        if signed != 0 {
+            self.field("signature", |reader| reader.read_bytes(SIGNATURE_LEN), |_| "RSA signature of the hash".to_string())?;
+        }
+        let data_length = self.field("data_length", BinaryReader::read_i32, |length| format!("{} bytes", length))?;
+        let data_section_start = self.offset();
+        let data_length_field = self.fields.len() - 1;
+
+        self.string("plugin_assembly")?;
+        self.string("plugin_version")?;
+        let count = self.field("entry_count", |reader| reader.read_length("entry count"), |count| format!("{} entries", count))?;
+        let mut table = Vec::new();
+        for index in 0..count {
+            let name = self.string(&format!("entry[{}].name", index))?;
+            let length = self.field(&format!("entry[{}].length", index), |reader| reader.read_length("entry length"), |length| {
+                format!("{} bytes", length)
+            })?;
+            let stored = self.field(
+                &format!("entry[{}].compressed_length", index),
+                |reader| reader.read_length("entry compressed length"),
+                |stored| format!("{} bytes, {}", stored, if *stored != length { "compressed" } else { "stored" }),
+            )?;
+            table.push((name, length, stored));
+        }
+
+        for (index, (name, length, stored)) in table.into_iter().enumerate() {
+            let offset = self.offset();
+            let kind = if stored != length { "compressed" } else { "stored" };
+            let preview = self.skip(stored as u64)?;
+            self.fields.push(FieldLayout {
+                offset,
+                len: stored as u64,
+                name: format!("data[{}]", index),
+                value: format!("{} ({}, {} bytes)", name, kind, length),
+                bytes: preview,
+            });
+        }
+        let entries_end = self.offset();
+        let trailing = self.skip(u64::MAX)?;
+        let trailing_len = self.offset() - entries_end;
+        if trailing_len > 0 {
+            self.fields.push(FieldLayout {
+                offset: entries_end,
+                len: trailing_len,
+                name: "trailing".to_string(),
+                value: "data after the last entry".to_string(),
+                bytes: trailing,
+            });
+        }
+
+        let actual = self.offset() - data_section_start;
+        if actual != data_length as u64 || data_length < 0 {
+            self.fields[data_length_field].value = format!("{} bytes, but {} follow", data_length, actual);
+        }
+        Ok(())
+    }
+
+    /// Reads a field with `read` and records it, described by `describe`
+    fn field<T>(
+        &mut self,
+        name: &str,
+        read: impl FnOnce(&mut BinaryReader<Recorder<R>>) -> io::Result<T>,
+        describe: impl FnOnce(&T) -> String,
+    ) -> io::Result<T> {
+        let offset = self.offset();
+        self.reader.get_mut().recorded.clear();
+        let value = read(&mut self.reader)?;
+        let recorded = &self.reader.get_mut().recorded;
+        self.fields.push(FieldLayout {
+            offset,
+            len: recorded.len() as u64,
+            name: name.to_string(),
+            value: describe(&value),
+            bytes: recorded[..recorded.len().min(PREVIEW_LEN)].to_vec(),
+        });
+        Ok(value)
+    }
+
+    fn string(&mut self, name: &str) -> io::Result<String> {
+        self.field(name, BinaryReader::read_string, |value| format!("{:?}", value))
+    }
+
+    /// Skips up to `len` bytes, returning the first [`PREVIEW_LEN`] of them
+    fn skip(&mut self, len: u64) -> io::Result<Vec<u8>> {
+        let recorder = self.reader.get_mut();
+        let mut preview = Vec::new();
+        (&mut recorder.inner).take(len.min(PREVIEW_LEN as u64)).read_to_end(&mut preview)?;
+        let rest = len.saturating_sub(preview.len() as u64);
+        let skipped = io::copy(&mut (&mut recorder.inner).take(rest), &mut io::sink())?;
+        recorder.offset += preview.len() as u64 + skipped;
+        if len != u64::MAX && preview.len() as u64 + skipped < len {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Archive ends inside entry data"));
+        }
+        Ok(preview)
+    }
+
+    fn offset(&mut self) -> u64 {
+        self.reader.get_mut().offset
+    }
+}
+
+/// Counts the bytes read, keeping a copy of those read since `recorded` was last cleared
+struct Recorder<R> {
+    inner: R,
+    offset: u64,
+    recorded: Vec<u8>,
+}
+
+impl<R: Read> Read for Recorder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.offset += read as u64;
+        self.recorded.extend_from_slice(&buf[..read]);
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+
+    #[test]
+    fn test_describe() {
+        let buffer = ObbyTestBuilder::new()
+            .plugin("Plugin", "1.0.0")
+            .entry("plugin.json", br#"{"id": "plugin", "id2": "plugin", "id3": "plugin"}"#)
+            .stored_entry("main.js", b"js")
+            .build();
+        let layout = describe(&buffer[..]);
+        assert!(layout.error.is_none());
+        let names: Vec<&str> = layout.fields.iter().map(|field| field.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "magic", "api_version", "hash", "signed", "data_length", "plugin_assembly", "plugin_version", "entry_count",
+                "entry[0].name", "entry[0].length", "entry[0].compressed_length",
+                "entry[1].name", "entry[1].length", "entry[1].compressed_length",
+                "data[0]", "data[1]",
+            ]
+        );
+        // Fields are contiguous and cover the whole archive
+        let mut offset = 0;
+        for field in &layout.fields {
+            assert_eq!(field.offset, offset, "{}", field.name);
+            offset += field.len;
+        }
+        assert_eq!(offset, buffer.len() as u64);
+        assert_eq!(layout.fields[0].bytes, b"OBBY");
+        assert_eq!(layout.fields[12].value, "2 bytes");
+        assert_eq!(layout.fields[15].value, "main.js (stored, 2 bytes)");
+        assert_eq!(layout.fields[15].bytes, b"js");
+        assert!(layout.to_string().contains("plugin_assembly"));
+
+        // Broken archives are described up to the problem
+        let layout = describe(&buffer[..buffer.len() - 1]);
+        assert_eq!(layout.error.unwrap().kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(layout.fields.len(), 15);
+
+        let padded = [&buffer[..], b"pad"].concat();
+        let layout = describe(&padded[..]);
+        assert!(layout.error.is_none());
+        assert_eq!(layout.fields.last().unwrap().name, "trailing");
+        assert_eq!(layout.fields[4].value, format!("{} bytes, but {} follow", buffer.len() - 63, buffer.len() - 60));
+    }
+}
@@ -0,0 +1,37 @@
+//! Low-level building blocks of the `.obby` format
+//!
+//! Most users only need [`crate::ObbyArchive`] and [`crate::ObbyWriter`]. These modules are
+//! for tooling that reads or writes related binary files with the same conventions.
+//!
+//! # Layout
+//!
+//! An archive is laid out as follows, using the encodings in [`wire`]:
+//!
+//! | Field            | Encoding                                    |
+//! |------------------|---------------------------------------------|
+//! | magic            | the 4 bytes `OBBY`                          |
+//! | API version      | string                                      |
+//! | hash             | 48 bytes, SHA-384 of the data section       |
+//! | signed           | 1 byte, non-zero if a signature follows     |
+//! | signature        | 384 bytes, only present if signed           |
+//! | data length      | `i32`, length of the data section           |
+//! | assembly name    | string, first field of the data section     |
+//! | plugin version   | string                                      |
+//! | entry count      | `i32`                                       |
+//! | entry table      | per entry: name (string), length (`i32`), stored length (`i32`) |
+//! | entry data       | each entry's stored bytes, in table order   |
+//! | extras           | written by this crate only on request, see [`crate::EntryExtra`] |
+//!
+//! An entry is compressed exactly when its stored length differs from its length; see
+//! [`crate::codec`] for how the codec is chosen.
+//!
+//! Table rows are not length-prefixed and the header carries no format version, so a
+//! row can't carry extra fields: one with an extra field couldn't be told apart from
+//! the start of the next row. This crate can instead keep per-entry extras such as
+//! timestamps in a block after the entry data, which is not part of the format that
+//! Obsidian writes; see [`crate::EntryExtra`].
+
+mod describe;
+pub mod wire;
+
+pub use describe::{describe, FieldLayout, Layout, PREVIEW_LEN};
@@ -0,0 +1,271 @@
+//! C#-compatible binary serialization primitives
+//!
+//! `.obby` files are written with .NET's `BinaryWriter`: integers are little-endian and
+//! strings are UTF-8 prefixed with their byte length as a 7-bit encoded integer. The
+//! [`BinaryReader`] and [`BinaryWriter`] here follow the same rules, so they can be used
+//! for other files produced by the same tooling.
+//!
+//! # Example
+//!
+//! ```
+//! use obsidian_lib::format::wire::{BinaryReader, BinaryWriter};
+//!
+//! # fn main() -> std::io::Result<()> {
+//! let mut writer = BinaryWriter::new(Vec::new());
+//! writer.write_string("plugin.json")?;
+//! writer.write_i32(42)?;
+//! let bytes = writer.into_inner();
+//!
+//! let mut reader = BinaryReader::new(&bytes[..]);
+//! assert_eq!(reader.read_string()?, "plugin.json");
+//! assert_eq!(reader.read_i32()?, 42);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::io::{self, Read, Write};
+
+/// Upper bound for buffers sized from lengths declared in the input, before the data is read
+pub(crate) const MAX_PREALLOCATION: usize = 64 * 1024;
+
+/// Reader for C#-compatible binary data
+///
+/// Lengths read from the input are untrusted: reads never allocate much more than the
+/// data actually present, and truncated input fails with `UnexpectedEof`.
+#[derive(Debug)]
+pub struct BinaryReader<R: Read> {
+    reader: R,
+}
+
+impl<R: Read> BinaryReader<R> {
+    /// Creates a new instance of `BinaryReader`
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The reader to be used for reading bytes.
+    pub fn new(reader: R) -> Self {
+        BinaryReader { reader }
+    }
+
+    /// Returns a mutable reference to the underlying reader
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Consumes the `BinaryReader`, returning the underlying reader
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Reads a single byte from the reader
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the byte if successful, or an error if reading fails.
+    pub fn read_u8(&mut self) -> io::Result<u8> {
+        let mut byte = [0u8; 1];
+        self.reader.read_exact(&mut byte)?;
+        Ok(byte[0])
+    }
+
+    /// Reads a specific number of bytes from the reader
+    ///
+    /// # Arguments
+    ///
+    /// * `length` - The number of bytes to read.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a `Vec<u8>` of the read bytes if successful, or an error if reading fails.
+    pub fn read_bytes(&mut self, length: usize) -> io::Result<Vec<u8>> {
+        // Lengths come from untrusted input, so only trust them as far as the data goes
+        let mut buffer = Vec::with_capacity(length.min(MAX_PREALLOCATION));
+        (&mut self.reader).take(length as u64).read_to_end(&mut buffer)?;
+        if buffer.len() != length {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "failed to fill whole buffer"));
+        }
+        Ok(buffer)
+    }
+
+    /// Reads a little-endian 32-bit integer from the reader
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the integer if successful, or an error if reading fails.
+    pub fn read_i32(&mut self) -> io::Result<i32> {
+        let mut bytes = [0u8; 4];
+        self.reader.read_exact(&mut bytes)?;
+        Ok(i32::from_le_bytes(bytes))
+    }
+
+    /// Reads a 32-bit length or count field, rejecting negative values
+    ///
+    /// # Arguments
+    ///
+    /// * `field` - What the value describes, used in the error message.
+    pub fn read_length(&mut self, field: &str) -> io::Result<i32> {
+        let value = self.read_i32()?;
+        if value < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Negative {} in archive: {}", field, value),
+            ));
+        }
+        Ok(value)
+    }
+
+    /// Reads a 7-bit encoded integer, as written by .NET's `Write7BitEncodedInt`
+    ///
+    /// The value is stored in 7-bit chunks, least significant first, with the high bit of
+    /// each byte marking that another byte follows. Like .NET, encodings longer than five
+    /// bytes or values that overflow 32 bits are rejected.
+    pub fn read_7bit_encoded_int(&mut self) -> io::Result<i32> {
+        let mut value = 0u32;
+        for step in 0..5 {
+            let byte = self.read_u8()?;
+            if step == 4 && byte > 0x0F {
+                break;
+            }
+            value |= ((byte & 0x7F) as u32) << (step * 7);
+            if byte & 0x80 == 0 {
+                return Ok(value as i32);
+            }
+        }
+        Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid 7-bit encoded integer"))
+    }
+
+    /// Reads a C#-style encoded string
+    ///
+    /// The string is encoded with a length prefix in variable-length encoding, where the length
+    /// is encoded using 7-bit chunks. Like .NET's `BinaryReader`, prefixes longer than five bytes
+    /// or lengths above `i32::MAX` are rejected, and invalid UTF-8 is replaced with U+FFFD.
+    pub fn read_string(&mut self) -> io::Result<String> {
+        let buf = self.read_string_bytes()?;
+        Ok(String::from_utf8_lossy(&buf).into_owned())
+    }
+
+    /// Reads a C#-style encoded string as raw bytes, without decoding them
+    ///
+    /// Lets callers decide how to handle invalid UTF-8.
+    pub fn read_string_bytes(&mut self) -> io::Result<Vec<u8>> {
+        let length = self.read_7bit_encoded_int()?;
+        if length < 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid 7-bit encoded string length"));
+        }
+        self.read_bytes(length as usize)
+    }
+}
+
+/// Writer for C#-compatible binary data
+///
+/// The counterpart of [`BinaryReader`].
+#[derive(Debug)]
+pub struct BinaryWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> BinaryWriter<W> {
+    /// Creates a new instance of `BinaryWriter`
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - The sink to write to.
+    pub fn new(writer: W) -> Self {
+        BinaryWriter { writer }
+    }
+
+    /// Returns a mutable reference to the underlying writer
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Consumes the `BinaryWriter`, returning the underlying writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Writes a single byte
+    pub fn write_u8(&mut self, value: u8) -> io::Result<()> {
+        self.writer.write_all(&[value])
+    }
+
+    /// Writes raw bytes without a length prefix
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.writer.write_all(bytes)
+    }
+
+    /// Writes a little-endian 32-bit integer
+    pub fn write_i32(&mut self, value: i32) -> io::Result<()> {
+        self.writer.write_all(&value.to_le_bytes())
+    }
+
+    /// Writes a 7-bit encoded integer, as read by .NET's `Read7BitEncodedInt`
+    pub fn write_7bit_encoded_int(&mut self, value: i32) -> io::Result<()> {
+        let mut value = value as u32;
+        loop {
+            let mut byte = (value & 0x7F) as u8;
+            value >>= 7;
+            if value != 0 {
+                byte |= 0x80;
+            }
+            self.write_u8(byte)?;
+            if value == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Writes a C#-style string: a 7-bit encoded byte count followed by UTF-8 bytes
+    ///
+    /// Strings longer than `i32::MAX` bytes can't be represented and are rejected.
+    pub fn write_string(&mut self, value: &str) -> io::Result<()> {
+        let length = i32::try_from(value.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "String is too long to encode"))?;
+        self.write_7bit_encoded_int(length)?;
+        self.write_bytes(value.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_7bit_encoded_int() {
+        for (value, encoded) in [
+            (0, vec![0x00]),
+            (127, vec![0x7F]),
+            (128, vec![0x80, 0x01]),
+            (300, vec![0xAC, 0x02]),
+            (i32::MAX, vec![0xFF, 0xFF, 0xFF, 0xFF, 0x07]),
+            (-1, vec![0xFF, 0xFF, 0xFF, 0xFF, 0x0F]),
+        ] {
+            let mut writer = BinaryWriter::new(Vec::new());
+            writer.write_7bit_encoded_int(value).unwrap();
+            assert_eq!(writer.into_inner(), encoded);
+            assert_eq!(BinaryReader::new(&encoded[..]).read_7bit_encoded_int().unwrap(), value);
+        }
+
+        let overlong = [0x80, 0x80, 0x80, 0x80, 0x10];
+        assert!(BinaryReader::new(&overlong[..]).read_7bit_encoded_int().is_err());
+    }
+
+    #[test]
+    fn test_string_round_trip() {
+        let mut writer = BinaryWriter::new(Vec::new());
+        writer.write_string("").unwrap();
+        writer.write_string(&"ü".repeat(100)).unwrap();
+        let bytes = writer.into_inner();
+
+        let mut reader = BinaryReader::new(&bytes[..]);
+        assert_eq!(reader.read_string().unwrap(), "");
+        assert_eq!(reader.read_string().unwrap(), "ü".repeat(100));
+    }
+
+    #[test]
+    fn test_negative_string_length() {
+        let bytes = [0xFF, 0xFF, 0xFF, 0xFF, 0x0F];
+        let err = BinaryReader::new(&bytes[..]).read_string().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,159 @@
+//! Hashing entry contents while they are extracted
+
+use std::fmt;
+use std::io::{self, Read, Seek};
+
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use crate::format::wire::MAX_PREALLOCATION;
+use crate::{lookup_entry, to_hex, ObbyArchive};
+
+/// A hash algorithm for [`ObbyArchive::extract_entry_hashed`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum HashAlgo {
+    /// SHA-256
+    Sha256,
+    /// SHA-384, the algorithm the archive header uses
+    Sha384,
+    /// SHA-512
+    Sha512,
+}
+
+/// A digest computed by [`ObbyArchive::extract_entry_hashed`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EntryDigest {
+    /// The algorithm used
+    pub algo: HashAlgo,
+    /// The raw digest bytes
+    pub bytes: Vec<u8>,
+}
+
+impl EntryDigest {
+    /// Formats the digest as lowercase hex
+    pub fn to_hex(&self) -> String {
+        to_hex(&self.bytes)
+    }
+}
+
+impl fmt::Display for EntryDigest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+/// An in-progress hash for one of the [`HashAlgo`]s
+enum Hasher {
+    Sha256(Sha256),
+    Sha384(Sha384),
+    Sha512(Sha512),
+}
+
+impl Hasher {
+    fn new(algo: HashAlgo) -> Self {
+        match algo {
+            HashAlgo::Sha256 => Hasher::Sha256(Sha256::new()),
+            HashAlgo::Sha384 => Hasher::Sha384(Sha384::new()),
+            HashAlgo::Sha512 => Hasher::Sha512(Sha512::new()),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha256(hasher) => hasher.update(data),
+            Hasher::Sha384(hasher) => hasher.update(data),
+            Hasher::Sha512(hasher) => hasher.update(data),
+        }
+    }
+
+    fn finish(self) -> EntryDigest {
+        let (algo, bytes) = match self {
+            Hasher::Sha256(hasher) => (HashAlgo::Sha256, hasher.finalize().to_vec()),
+            Hasher::Sha384(hasher) => (HashAlgo::Sha384, hasher.finalize().to_vec()),
+            Hasher::Sha512(hasher) => (HashAlgo::Sha512, hasher.finalize().to_vec()),
+        };
+        EntryDigest { algo, bytes }
+    }
+}
+
+impl<R: Read + Seek> ObbyArchive<R> {
+    /// Extracts an entry and hashes its decompressed contents in the same pass
+    ///
+    /// # Arguments
+    ///
+    /// * `entry_name` - The name of the entry to extract.
+    /// * `algo` - The hash algorithm to use.
+    ///
+    /// # Returns
+    ///
+    /// The entry's decompressed data and its digest, or an `io::Error` as with
+    /// [`ObbyArchive::extract_entry`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use obsidian_lib::HashAlgo;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut archive = obsidian_lib::open("plugin.obby")?;
+    /// let (data, digest) = archive.extract_entry_hashed("main.js", HashAlgo::Sha256)?;
+    /// println!("{} bytes, sha256 {}", data.len(), digest);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn extract_entry_hashed(&mut self, entry_name: &str, algo: HashAlgo) -> io::Result<(Vec<u8>, EntryDigest)> {
+        let length = lookup_entry(&self.entries, entry_name)?.length as usize;
+        let mut hasher = Hasher::new(algo);
+        let mut data = Vec::with_capacity(length.min(MAX_PREALLOCATION));
+        let result = self.entry_reader(entry_name).and_then(|mut reader| {
+            let mut buffer = [0u8; 64 * 1024];
+            loop {
+                let read = reader.read(&mut buffer)?;
+                if read == 0 {
+                    return Ok(());
+                }
+                hasher.update(&buffer[..read]);
+                data.extend_from_slice(&buffer[..read]);
+            }
+        });
+        self.decompression_error(entry_name, result)?;
+        if data.len() != length {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("Entry '{}' is truncated", entry_name),
+            ));
+        }
+        Ok((data, hasher.finish()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+
+    #[test]
+    fn test_extract_entry_hashed() {
+        let buffer = ObbyTestBuilder::new()
+            .stored_entry("a.txt", b"abc")
+            .entry("b.txt", &[b'b'; 10_000])
+            .build();
+        let mut archive = ObbyArchive::from_slice(&buffer).unwrap();
+
+        let (data, digest) = archive.extract_entry_hashed("a.txt", HashAlgo::Sha256).unwrap();
+        assert_eq!(data, b"abc");
+        assert_eq!(digest.to_hex(), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+
+        let (data, digest) = archive.extract_entry_hashed("b.txt", HashAlgo::Sha384).unwrap();
+        assert_eq!(data, vec![b'b'; 10_000]);
+        assert_eq!(digest.bytes, Sha384::digest(&data).to_vec());
+        assert_eq!(digest.algo, HashAlgo::Sha384);
+
+        let mut truncated = buffer.clone();
+        truncated.truncate(buffer.len() - 10);
+        let mut archive = ObbyArchive::new(std::io::Cursor::new(truncated)).unwrap();
+        assert!(archive.extract_entry_hashed("b.txt", HashAlgo::Sha512).is_err());
+    }
+}
@@ -0,0 +1,148 @@
+//! On-disk cache of downloaded archives and manifests
+//!
+//! Enabled with [`crate::RemoteOptions::set_cache_dir`]. The cache directory holds:
+//!
+//! * `urls/<sha256 of the URL>.json` - the validator (`ETag` or `Last-Modified`) the
+//!   server last sent for a URL, the SHA-384 from the archive's header, whether that
+//!   hash was checked against the data and, if the whole archive was downloaded, its
+//!   SHA-256, so later requests can be made conditional.
+//! * `archives/<sha256>.obby` - whole archives downloaded with `fetch_with_options`.
+//! * `entries/<sha384>/<sha256 of the entry name>` - single entries, such as manifests,
+//!   of archives whose hash was verified.
+//! * `responses/<sha256 of the URL and validator>/<sha256 of the entry name>` - single
+//!   entries of archives whose hash wasn't verified, such as the manifests returned by
+//!   `fetch_plugin_json_with_options`, which never sees the whole archive.
+//!
+//! Entries of verified archives are keyed by the hash of the archive's data section
+//! rather than by URL, so they are reused when mirrors serve the same archive. A hash
+//! that was only read from a header is never used as a key, as any server can claim
+//! another archive's hash. The cache is best effort: failing to write it never fails a
+//! download, and a damaged or missing file is a cache miss.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::cas::write_atomically;
+use crate::to_hex;
+
+/// Key under which an archive's manifest is cached, whatever its entry is called
+pub(crate) const MANIFEST_KEY: &str = "plugin.json";
+
+/// What the cache knows about a URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CachedUrl {
+    /// The `ETag` or, failing that, the `Last-Modified` value of the last response
+    pub(crate) validator: Option<String>,
+    /// SHA-384 of the archive's data section, as lowercase hex
+    pub(crate) hash: String,
+    /// Whether `hash` was checked against the archive's data
+    pub(crate) verified: bool,
+    /// SHA-256 of the whole archive, as lowercase hex, if it is cached
+    pub(crate) archive: Option<String>,
+}
+
+impl CachedUrl {
+    /// Makes `request` conditional, so the server answers `304 Not Modified` if the
+    /// cached response is still current
+    pub(crate) fn make_conditional(&self, request: ureq::Request) -> ureq::Request {
+        match self.validator.as_deref() {
+            Some(etag) if etag.starts_with('"') || etag.starts_with("W/") => request.set("If-None-Match", etag),
+            Some(date) => request.set("If-Modified-Since", date),
+            None => request,
+        }
+    }
+}
+
+/// A cache directory
+pub(crate) struct HttpCache {
+    dir: PathBuf,
+}
+
+impl HttpCache {
+    pub(crate) fn new(dir: &Path) -> Self {
+        HttpCache { dir: dir.to_path_buf() }
+    }
+
+    /// Returns what is known about `url`
+    pub(crate) fn url(&self, url: &str) -> Option<CachedUrl> {
+        let record: Value = serde_json::from_slice(&fs::read(self.url_path(url)).ok()?).ok()?;
+        // Guards against hand-edited records pointing outside the cache
+        let hash = record["hash"].as_str().filter(|hash| is_hex(hash, 96))?;
+        let archive = record["archive"].as_str().filter(|sha256| is_hex(sha256, 64));
+        if record["url"].as_str() != Some(url) {
+            return None;
+        }
+        Some(CachedUrl {
+            validator: record["validator"].as_str().map(str::to_string),
+            hash: hash.to_string(),
+            verified: record["verified"].as_bool().unwrap_or(false),
+            archive: archive.map(str::to_string),
+        })
+    }
+
+    pub(crate) fn store_url(&self, url: &str, cached: &CachedUrl) {
+        let record = json!({
+            "url": url,
+            "validator": cached.validator,
+            "hash": cached.hash,
+            "verified": cached.verified,
+            "archive": cached.archive,
+        });
+        let _ = write_atomically(&self.url_path(url), record.to_string().as_bytes());
+    }
+
+    /// Returns the archive with the given SHA-256, if it is cached and intact
+    pub(crate) fn archive(&self, sha256: &str) -> Option<Vec<u8>> {
+        let data = fs::read(self.archive_path(sha256)).ok()?;
+        (to_hex(&Sha256::digest(&data)) == sha256).then_some(data)
+    }
+
+    /// Stores a whole archive and returns its SHA-256
+    pub(crate) fn store_archive(&self, data: &[u8]) -> String {
+        let sha256 = to_hex(&Sha256::digest(data));
+        let _ = write_atomically(&self.archive_path(&sha256), data);
+        sha256
+    }
+
+    /// Returns the entry `name` of the archive described by `cached`, last served at `url`
+    pub(crate) fn entry(&self, url: &str, cached: &CachedUrl, name: &str) -> Option<Vec<u8>> {
+        fs::read(self.entry_path(url, cached, name)?).ok()
+    }
+
+    /// Stores the entry `name` of the archive described by `cached`, served at `url`
+    ///
+    /// Entries of unverified archives are only stored if the server sent a validator,
+    /// as without one they could never be reused.
+    pub(crate) fn store_entry(&self, url: &str, cached: &CachedUrl, name: &str, data: &[u8]) {
+        if let Some(path) = self.entry_path(url, cached, name) {
+            let _ = write_atomically(&path, data);
+        }
+    }
+
+    fn url_path(&self, url: &str) -> PathBuf {
+        self.dir.join("urls").join(format!("{}.json", to_hex(&Sha256::digest(url))))
+    }
+
+    fn archive_path(&self, sha256: &str) -> PathBuf {
+        self.dir.join("archives").join(format!("{}.obby", sha256))
+    }
+
+    fn entry_path(&self, url: &str, cached: &CachedUrl, name: &str) -> Option<PathBuf> {
+        let dir = match (cached.verified, &cached.validator) {
+            (true, _) => self.dir.join("entries").join(&cached.hash),
+            (false, Some(validator)) => {
+                let response = to_hex(&Sha256::digest(format!("{}\n{}", url, validator)));
+                self.dir.join("responses").join(response)
+            }
+            (false, None) => return None,
+        };
+        Some(dir.join(to_hex(&Sha256::digest(name))))
+    }
+}
+
+fn is_hex(value: &str, len: usize) -> bool {
+    value.len() == len && value.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
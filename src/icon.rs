@@ -0,0 +1,118 @@
+//! Locating the plugin's icon
+
+use std::io::{self, Read, Seek};
+
+use crate::{mime, tree, ObbyArchive};
+
+/// Conventional icon locations tried when the manifest doesn't name one, in order
+pub const ICON_NAMES: &[&str] = &[
+    "icon.png",
+    "icon.svg",
+    "icon.webp",
+    "icon.jpg",
+    "icon.ico",
+    "assets/icon.png",
+    "assets/icon.svg",
+];
+
+/// Manifest fields that may hold the icon's path, in order
+#[cfg(feature = "json")]
+const MANIFEST_ICON_FIELDS: &[&str] = &["icon", "iconPath"];
+
+/// A plugin's icon, returned by [`ObbyArchive::extract_icon`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PluginIcon {
+    /// Name of the entry the icon was read from
+    pub entry: String,
+    /// The icon's data
+    pub data: Vec<u8>,
+    /// MIME type detected from the data, such as `"image/png"`
+    pub mime: &'static str,
+}
+
+impl<R: Read + Seek> ObbyArchive<R> {
+    /// Finds and extracts the plugin's icon
+    ///
+    /// The path in the manifest's `icon` (or `iconPath`) field is used if it names an
+    /// entry; otherwise each of [`ICON_NAMES`] is tried. Paths are compared ignoring
+    /// case, a leading `./` and the separator style, since manifests are often written
+    /// by hand. A missing or unparsable manifest only skips the first step, as does
+    /// building without the `json` feature.
+    ///
+    /// # Returns
+    ///
+    /// The [`PluginIcon`], or an `io::Error` of kind `NotFound` if the archive has no icon.
+    pub fn extract_icon(&mut self) -> io::Result<PluginIcon> {
+        let declared = self.declared_icon_path();
+        let entry = declared
+            .iter()
+            .map(String::as_str)
+            .chain(ICON_NAMES.iter().copied())
+            .find_map(|candidate| self.order.iter().find(|name| same_path(name, candidate)).cloned())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No icon found in archive"))?;
+
+        let data = self.extract_entry(&entry)?;
+        let mime = mime::sniff(&data[..data.len().min(mime::MIME_SNIFF_LEN)], &entry);
+        Ok(PluginIcon { entry, data, mime })
+    }
+
+    /// Reads the icon path declared in the manifest, if there is one
+    #[cfg(feature = "json")]
+    fn declared_icon_path(&mut self) -> Option<String> {
+        let manifest = self.find_manifest().ok()?.to_string();
+        let json: serde_json::Value = serde_json::from_slice(&self.extract_entry(&manifest).ok()?).ok()?;
+        MANIFEST_ICON_FIELDS
+            .iter()
+            .find_map(|field| json.get(field)?.as_str().map(str::to_string))
+    }
+
+    /// Reading the manifest needs the `json` feature
+    #[cfg(not(feature = "json"))]
+    fn declared_icon_path(&mut self) -> Option<String> {
+        None
+    }
+}
+
+/// Compares entry paths component by component, ignoring case and `.` components
+fn same_path(a: &str, b: &str) -> bool {
+    let components = |path| tree::components(path).filter(|component| *component != ".");
+    components(a).map(str::to_lowercase).eq(components(b).map(str::to_lowercase))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+
+    #[cfg(feature = "json")]
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn test_icon_from_manifest() {
+        let buffer = ObbyTestBuilder::new()
+            .entry("plugin.json", br#"{"id": "x", "icon": "./Assets/Logo.png"}"#)
+            .entry("icon.png", b"fallback")
+            .entry("assets/logo.png", PNG)
+            .build();
+        let icon = ObbyArchive::from_bytes(buffer).unwrap().extract_icon().unwrap();
+        assert_eq!(icon.entry, "assets/logo.png");
+        assert_eq!(icon.data, PNG);
+        assert_eq!(icon.mime, "image/png");
+    }
+
+    #[test]
+    fn test_icon_fallbacks() {
+        let buffer = ObbyTestBuilder::new()
+            .entry("plugin.json", br#"{"icon": "missing.png"}"#)
+            .entry("assets/icon.svg", b"<svg></svg>")
+            .build();
+        let icon = ObbyArchive::from_bytes(buffer).unwrap().extract_icon().unwrap();
+        assert_eq!(icon.entry, "assets/icon.svg");
+        assert_eq!(icon.mime, "image/svg+xml");
+
+        let buffer = ObbyTestBuilder::new().entry("plugin.json", b"not json").build();
+        let err = ObbyArchive::from_bytes(buffer).unwrap().extract_icon().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,379 @@
+//! Repository indexes: the list of plugins and versions a plugin repository offers
+//!
+//! An index is a JSON document served next to the archives it lists, which
+//! `Registry` (with the `registry` feature) reads to search and download
+//! plugins:
+//!
+//! ```json
+//! {
+//!   "format": 1,
+//!   "plugins": [
+//!     {
+//!       "id": "my-plugin",
+//!       "name": "My Plugin",
+//!       "versions": [
+//!         {
+//!           "version": "1.0.0",
+//!           "download": "my-plugin-1.0.0.obby",
+//!           "size": 48213,
+//!           "sha256": "…",
+//!           "hash": "…",
+//!           "api_version": "1.0.0",
+//!           "assembly": "MyPlugin",
+//!           "signed": true,
+//!           "manifest": { "id": "my-plugin", "version": "1.0.0" }
+//!         }
+//!       ]
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! `download` is resolved against the URL the index was loaded from unless it is an
+//! absolute URL itself. [`RepositoryIndex::build`] generates an index for a directory of
+//! archives, so any static file server can host a repository.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io;
+use std::path::{Component, Path};
+
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::{scan, to_hex, ApiVersion};
+
+/// Version of the index format written by this crate
+pub const INDEX_FORMAT: u64 = 1;
+
+/// A plugin repository's index
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RepositoryIndex {
+    /// Every plugin in the repository, ordered by ID
+    pub plugins: Vec<IndexedPlugin>,
+}
+
+/// A plugin in a [`RepositoryIndex`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct IndexedPlugin {
+    /// The manifest's `id`, or the assembly name if the manifest has none
+    pub id: String,
+    /// The manifest's `name` in the newest version, if present
+    pub name: Option<String>,
+    /// The available versions, oldest first
+    pub versions: Vec<IndexedVersion>,
+}
+
+/// A downloadable version of an [`IndexedPlugin`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct IndexedVersion {
+    /// The manifest's `version`, or the version in the header if the manifest has none
+    pub version: String,
+    /// Where to download the archive, relative to the index
+    pub download: String,
+    /// Size of the archive file in bytes
+    pub size: u64,
+    /// SHA-256 of the archive file, as lowercase hex
+    pub sha256: String,
+    /// SHA-384 of the archive's data section, from its header, as lowercase hex
+    pub hash: String,
+    /// The Obsidian API version the plugin was built against
+    pub api_version: String,
+    /// The plugin's assembly name
+    pub assembly: String,
+    /// Whether the archive carries a signature
+    pub signed: bool,
+    /// The parsed manifest, or `null` if the archive has none
+    pub manifest: Value,
+}
+
+impl RepositoryIndex {
+    /// Scans a directory recursively and indexes every `.obby` archive in it
+    ///
+    /// Each archive is listed under its manifest's `id` and `version`, falling back to
+    /// the assembly name and header version, with its path relative to `root` as the
+    /// download location. Writing the index to `root/index.json` thus turns the
+    /// directory into a repository that [`RepositoryIndex::from_json`] readers, such as
+    /// the `registry` feature's client, can use.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The directory holding the archives.
+    ///
+    /// # Returns
+    ///
+    /// The `RepositoryIndex`, with plugins ordered by ID and versions oldest first. Fails
+    /// with the path in the message if an archive can't be read, and with kind
+    /// `InvalidData` if two archives have the same ID and version.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use obsidian_lib::index::RepositoryIndex;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let index = RepositoryIndex::build("repository")?;
+    /// std::fs::write("repository/index.json", serde_json::to_vec_pretty(&index.to_json())?)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn build<P: AsRef<Path>>(root: P) -> io::Result<Self> {
+        let root = root.as_ref();
+        std::fs::read_dir(root)?;
+        let mut plugins: BTreeMap<String, Vec<(Option<String>, IndexedVersion)>> = BTreeMap::new();
+        for (path, result) in scan::walk(root) {
+            let (id, name, version) = result
+                .and_then(|_| index_archive(root, &path))
+                .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+            let versions = plugins.entry(id).or_default();
+            if let Some((_, other)) = versions.iter().find(|(_, other)| other.version == version.version) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{} and {} are both version {}", other.download, version.download, version.version),
+                ));
+            }
+            versions.push((name, version));
+        }
+
+        let plugins = plugins
+            .into_iter()
+            .map(|(id, mut versions)| {
+                versions.sort_by(|(_, a), (_, b)| {
+                    let key = |version: &IndexedVersion| version.version.parse::<ApiVersion>().ok();
+                    key(a).cmp(&key(b)).then_with(|| a.version.cmp(&b.version))
+                });
+                let name = versions.last().and_then(|(name, _)| name.clone());
+                IndexedPlugin { id, name, versions: versions.into_iter().map(|(_, version)| version).collect() }
+            })
+            .collect();
+        Ok(RepositoryIndex { plugins })
+    }
+
+    /// Parses an index from JSON
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The index document.
+    ///
+    /// # Returns
+    ///
+    /// The `RepositoryIndex`, or an `io::Error` of kind `InvalidData` if the document
+    /// isn't a valid index or uses a newer format than this crate understands.
+    pub fn from_json(data: &[u8]) -> io::Result<Self> {
+        let value: Value = serde_json::from_slice(data).map_err(|e| invalid(&e.to_string()))?;
+        match value["format"].as_u64() {
+            Some(format) if format <= INDEX_FORMAT => {}
+            Some(format) => return Err(invalid(&format!("unsupported format {}", format))),
+            None => return Err(invalid("missing 'format'")),
+        }
+        let plugins = value["plugins"].as_array().ok_or_else(|| invalid("missing 'plugins'"))?;
+        Ok(RepositoryIndex { plugins: plugins.iter().map(IndexedPlugin::from_json).collect::<io::Result<_>>()? })
+    }
+
+    /// Returns the index as a JSON value in the format [`RepositoryIndex::from_json`] reads
+    pub fn to_json(&self) -> Value {
+        json!({
+            "format": INDEX_FORMAT,
+            "plugins": self.plugins.iter().map(IndexedPlugin::to_json).collect::<Vec<_>>(),
+        })
+    }
+
+    /// Returns the plugin with the given ID, ignoring ASCII case
+    pub fn plugin(&self, id: &str) -> Option<&IndexedPlugin> {
+        self.plugins.iter().find(|plugin| plugin.id.eq_ignore_ascii_case(id))
+    }
+}
+
+impl IndexedPlugin {
+    /// Returns the newest version
+    ///
+    /// Versions are compared numerically as `major.minor.patch`; versions that can't be
+    /// parsed, and ties, are ordered as listed, so a later entry wins.
+    pub fn latest(&self) -> Option<&IndexedVersion> {
+        self.versions
+            .iter()
+            .enumerate()
+            .max_by_key(|(i, version)| (version.version.parse::<ApiVersion>().ok(), *i))
+            .map(|(_, version)| version)
+    }
+
+    /// Returns the given version
+    pub fn version(&self, version: &str) -> Option<&IndexedVersion> {
+        self.versions.iter().find(|candidate| candidate.version == version)
+    }
+
+    fn from_json(value: &Value) -> io::Result<Self> {
+        let versions = value["versions"].as_array().ok_or_else(|| invalid("missing 'versions'"))?;
+        Ok(IndexedPlugin {
+            id: string(value, "id")?,
+            name: value["name"].as_str().map(str::to_string),
+            versions: versions.iter().map(IndexedVersion::from_json).collect::<io::Result<_>>()?,
+        })
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "name": self.name,
+            "versions": self.versions.iter().map(IndexedVersion::to_json).collect::<Vec<_>>(),
+        })
+    }
+}
+
+impl IndexedVersion {
+    fn from_json(value: &Value) -> io::Result<Self> {
+        Ok(IndexedVersion {
+            version: string(value, "version")?,
+            download: string(value, "download")?,
+            size: value["size"].as_u64().ok_or_else(|| invalid("missing 'size'"))?,
+            sha256: string(value, "sha256")?,
+            hash: string(value, "hash")?,
+            api_version: value["api_version"].as_str().unwrap_or_default().to_string(),
+            assembly: value["assembly"].as_str().unwrap_or_default().to_string(),
+            signed: value["signed"].as_bool().unwrap_or(false),
+            manifest: value["manifest"].clone(),
+        })
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "version": self.version,
+            "download": self.download,
+            "size": self.size,
+            "sha256": self.sha256,
+            "hash": self.hash,
+            "api_version": self.api_version,
+            "assembly": self.assembly,
+            "signed": self.signed,
+            "manifest": self.manifest,
+        })
+    }
+}
+
+/// Reads the ID, manifest name and index entry of the archive at `path`
+fn index_archive(root: &Path, path: &Path) -> io::Result<(String, Option<String>, IndexedVersion)> {
+    let mut hasher = Sha256::new();
+    let size = io::copy(&mut File::open(path)?, &mut hasher)?;
+    let mut archive = crate::open(path)?;
+    let manifest = match archive.find_manifest() {
+        Ok(name) => {
+            let name = name.to_string();
+            serde_json::from_slice(&archive.extract_entry(&name)?)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid {}: {}", name, e)))?
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Value::Null,
+        Err(e) => return Err(e),
+    };
+    let field = |name: &str| manifest.get(name).and_then(Value::as_str).map(str::to_string);
+    let download = path
+        .strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/");
+
+    let metadata = archive.metadata();
+    let version = IndexedVersion {
+        version: field("version").unwrap_or_else(|| metadata.plugin_version.clone()),
+        download,
+        size,
+        sha256: to_hex(&hasher.finalize()),
+        hash: to_hex(&metadata.hash),
+        api_version: metadata.api_version.clone(),
+        assembly: metadata.plugin_assembly.clone(),
+        signed: metadata.signature.is_some(),
+        manifest: manifest.clone(),
+    };
+    let id = field("id").unwrap_or_else(|| metadata.plugin_assembly.clone());
+    Ok((id, field("name"), version))
+}
+
+fn string(value: &Value, field: &str) -> io::Result<String> {
+    value[field].as_str().map(str::to_string).ok_or_else(|| invalid(&format!("missing '{}'", field)))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("Invalid repository index: {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(version: &str) -> IndexedVersion {
+        IndexedVersion {
+            version: version.to_string(),
+            download: format!("alpha-{}.obby", version),
+            size: 10,
+            sha256: "ab".repeat(32),
+            hash: "cd".repeat(48),
+            api_version: "1.0.0".to_string(),
+            assembly: "Alpha".to_string(),
+            signed: false,
+            manifest: json!({ "id": "alpha", "version": version }),
+        }
+    }
+
+    #[test]
+    fn test_build_index() {
+        use crate::testing::ObbyTestBuilder;
+        use std::fs;
+
+        let dir = tempfile::tempdir().unwrap();
+        let plugin = |manifest: &str| {
+            ObbyTestBuilder::new().plugin("Alpha", "0.1.0").entry("plugin.json", manifest.as_bytes()).build()
+        };
+        fs::create_dir(dir.path().join("old")).unwrap();
+        fs::write(dir.path().join("alpha.obby"), plugin(r#"{"id": "alpha", "name": "Alpha 2", "version": "2.0.0"}"#)).unwrap();
+        fs::write(dir.path().join("old/alpha.obby"), plugin(r#"{"id": "alpha", "name": "Alpha", "version": "1.10.0"}"#)).unwrap();
+        let no_id = ObbyTestBuilder::new().plugin("Beta", "0.3.0").entry("plugin.json", br#"{"name": "Beta"}"#).build();
+        fs::write(dir.path().join("beta.obby"), no_id).unwrap();
+        fs::write(dir.path().join("index.json"), "{}").unwrap();
+
+        let index = RepositoryIndex::build(dir.path()).unwrap();
+        let ids: Vec<&str> = index.plugins.iter().map(|plugin| plugin.id.as_str()).collect();
+        assert_eq!(ids, ["Beta", "alpha"]);
+        assert_eq!(index.plugins[0].versions[0].version, "0.3.0");
+        let alpha = index.plugin("alpha").unwrap();
+        assert_eq!(alpha.name.as_deref(), Some("Alpha 2"));
+        let versions: Vec<(&str, &str)> = alpha.versions.iter().map(|v| (v.version.as_str(), v.download.as_str())).collect();
+        assert_eq!(versions, [("1.10.0", "old/alpha.obby"), ("2.0.0", "alpha.obby")]);
+        let file = fs::read(dir.path().join("alpha.obby")).unwrap();
+        assert_eq!(alpha.versions[1].size, file.len() as u64);
+        assert_eq!(alpha.versions[1].sha256, to_hex(&Sha256::digest(&file)));
+        assert_eq!(alpha.versions[1].manifest["name"], "Alpha 2");
+        assert_eq!(RepositoryIndex::from_json(index.to_json().to_string().as_bytes()).unwrap(), index);
+
+        fs::write(dir.path().join("copy.obby"), plugin(r#"{"id": "alpha", "version": "2.0.0"}"#)).unwrap();
+        assert_eq!(RepositoryIndex::build(dir.path()).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_index_round_trip() {
+        let index = RepositoryIndex {
+            plugins: vec![IndexedPlugin {
+                id: "alpha".to_string(),
+                name: Some("Alpha".to_string()),
+                versions: vec![version("1.10.0"), version("1.9.0"), version("nightly")],
+            }],
+        };
+        let parsed = RepositoryIndex::from_json(index.to_json().to_string().as_bytes()).unwrap();
+        assert_eq!(parsed, index);
+
+        let plugin = parsed.plugin("ALPHA").unwrap();
+        assert_eq!(plugin.latest().unwrap().version, "1.10.0");
+        assert_eq!(plugin.version("1.9.0").unwrap().download, "alpha-1.9.0.obby");
+        assert_eq!(parsed.plugin("beta"), None);
+
+        for invalid in [r#"{"plugins": []}"#, r#"{"format": 99, "plugins": []}"#, r#"{"format": 1, "plugins": [{"id": "a"}]}"#] {
+            assert_eq!(RepositoryIndex::from_json(invalid.as_bytes()).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
@@ -0,0 +1,324 @@
+//! Pluggable checks run over every entry of an archive
+//!
+//! Registries typically want to scan uploads for malware, enforce size limits and
+//! reject file types they don't accept. An [`EntryInspector`] sees each entry's
+//! metadata and decompressed contents as they stream past, and records
+//! [`Finding`]s; [`crate::ObbyArchive::inspect_all`] runs any number of inspectors in
+//! a single pass over the archive and collects what they found.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use obsidian_lib::inspect::{EntryInspector, ForbiddenExtensions, MaxEntrySize};
+//!
+//! # fn main() -> std::io::Result<()> {
+//! let mut archive = obsidian_lib::open("upload.obby")?;
+//! let mut inspectors: Vec<Box<dyn EntryInspector>> = vec![
+//!     Box::new(MaxEntrySize(10 * 1024 * 1024)),
+//!     Box::new(ForbiddenExtensions::new(&["exe", "bat", "ps1"])),
+//! ];
+//! let report = archive.inspect_all(&mut inspectors)?;
+//! if report.is_rejected() {
+//!     for finding in &report.findings {
+//!         eprintln!("{}: {}", finding.entry, finding.message);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::io::{self, Read, Seek};
+
+use crate::{HeaderEntry, ObbyArchive};
+
+/// Size of the chunks entry data is passed to inspectors in
+const CHUNK_LEN: usize = 64 * 1024;
+
+/// A check run over each entry by [`crate::ObbyArchive::inspect_all`]
+///
+/// For every entry, `begin_entry` is called first, then `update` with consecutive
+/// chunks of the decompressed data, then `finish_entry`. Only `finish_entry` is
+/// required; inspectors that judge entries by name or size alone can ignore the data.
+pub trait EntryInspector {
+    /// Called before an entry's data is passed to `update`
+    fn begin_entry(&mut self, _entry: &HeaderEntry) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Called with the next chunk of the current entry's decompressed data
+    fn update(&mut self, _chunk: &[u8]) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Called once the current entry's data has been passed in full
+    ///
+    /// Push a [`Finding`] to `findings` for each problem with the entry.
+    fn finish_entry(&mut self, entry: &HeaderEntry, findings: &mut Vec<Finding>);
+}
+
+/// How serious a [`Finding`] is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Worth reporting, but the archive is acceptable
+    Warning,
+    /// The archive should not be accepted
+    Reject,
+}
+
+/// A problem an inspector found with an entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// Name of the entry the finding is about
+    pub entry: String,
+    /// How serious the finding is
+    pub severity: Severity,
+    /// Human-readable description
+    pub message: String,
+}
+
+impl Finding {
+    /// Creates a [`Severity::Warning`] finding
+    pub fn warning(entry: &str, message: impl Into<String>) -> Self {
+        Finding {
+            entry: entry.to_string(),
+            severity: Severity::Warning,
+            message: message.into(),
+        }
+    }
+
+    /// Creates a [`Severity::Reject`] finding
+    pub fn reject(entry: &str, message: impl Into<String>) -> Self {
+        Finding {
+            entry: entry.to_string(),
+            severity: Severity::Reject,
+            message: message.into(),
+        }
+    }
+}
+
+/// Everything found by [`crate::ObbyArchive::inspect_all`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InspectionReport {
+    /// Findings in archive order
+    pub findings: Vec<Finding>,
+}
+
+impl InspectionReport {
+    /// Whether no inspector reported anything
+    pub fn is_clean(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    /// Whether any finding is a [`Severity::Reject`]
+    pub fn is_rejected(&self) -> bool {
+        self.findings.iter().any(|finding| finding.severity == Severity::Reject)
+    }
+}
+
+impl<I: EntryInspector + ?Sized> EntryInspector for &mut I {
+    fn begin_entry(&mut self, entry: &HeaderEntry) -> io::Result<()> {
+        (**self).begin_entry(entry)
+    }
+
+    fn update(&mut self, chunk: &[u8]) -> io::Result<()> {
+        (**self).update(chunk)
+    }
+
+    fn finish_entry(&mut self, entry: &HeaderEntry, findings: &mut Vec<Finding>) {
+        (**self).finish_entry(entry, findings)
+    }
+}
+
+impl<I: EntryInspector + ?Sized> EntryInspector for Box<I> {
+    fn begin_entry(&mut self, entry: &HeaderEntry) -> io::Result<()> {
+        (**self).begin_entry(entry)
+    }
+
+    fn update(&mut self, chunk: &[u8]) -> io::Result<()> {
+        (**self).update(chunk)
+    }
+
+    fn finish_entry(&mut self, entry: &HeaderEntry, findings: &mut Vec<Finding>) {
+        (**self).finish_entry(entry, findings)
+    }
+}
+
+/// Runs several inspectors side by side, in order
+impl<I: EntryInspector> EntryInspector for Vec<I> {
+    fn begin_entry(&mut self, entry: &HeaderEntry) -> io::Result<()> {
+        self.iter_mut().try_for_each(|inspector| inspector.begin_entry(entry))
+    }
+
+    fn update(&mut self, chunk: &[u8]) -> io::Result<()> {
+        self.iter_mut().try_for_each(|inspector| inspector.update(chunk))
+    }
+
+    fn finish_entry(&mut self, entry: &HeaderEntry, findings: &mut Vec<Finding>) {
+        for inspector in self {
+            inspector.finish_entry(entry, findings);
+        }
+    }
+}
+
+/// Rejects entries whose decompressed size exceeds a limit in bytes
+#[derive(Debug, Clone, Copy)]
+pub struct MaxEntrySize(pub u64);
+
+impl EntryInspector for MaxEntrySize {
+    fn finish_entry(&mut self, entry: &HeaderEntry, findings: &mut Vec<Finding>) {
+        if entry.length > self.0 {
+            findings.push(Finding::reject(
+                &entry.name,
+                format!("{} bytes exceeds the limit of {} bytes", entry.length, self.0),
+            ));
+        }
+    }
+}
+
+/// Rejects entries with any of the given file extensions, compared case-insensitively
+#[derive(Debug, Clone)]
+pub struct ForbiddenExtensions {
+    extensions: Vec<String>,
+}
+
+impl ForbiddenExtensions {
+    /// Creates the inspector from extensions without the leading dot, such as `"exe"`
+    pub fn new(extensions: &[&str]) -> Self {
+        ForbiddenExtensions {
+            extensions: extensions.iter().map(|ext| ext.to_ascii_lowercase()).collect(),
+        }
+    }
+}
+
+impl EntryInspector for ForbiddenExtensions {
+    fn finish_entry(&mut self, entry: &HeaderEntry, findings: &mut Vec<Finding>) {
+        let file_name = crate::tree::components(&entry.name).last().unwrap_or("");
+        if let Some((_, ext)) = file_name.rsplit_once('.') {
+            if self.extensions.contains(&ext.to_ascii_lowercase()) {
+                findings.push(Finding::reject(&entry.name, format!("files of type .{} are not allowed", ext)));
+            }
+        }
+    }
+}
+
+impl<R: Read + Seek> ObbyArchive<R> {
+    /// Streams every entry through an inspector and collects its findings
+    ///
+    /// Each entry is decompressed once, in chunks, no matter how many inspectors are
+    /// combined (pass a `Vec` of them). Entries are visited in archive order.
+    ///
+    /// # Arguments
+    ///
+    /// * `inspector` - The inspector, or a `Vec` of inspectors, to run.
+    ///
+    /// # Returns
+    ///
+    /// An [`InspectionReport`] with every finding, or an `io::Error` if an entry can't be
+    /// read or an inspector fails.
+    pub fn inspect_all<I: EntryInspector>(&mut self, mut inspector: I) -> io::Result<InspectionReport> {
+        let mut report = InspectionReport::default();
+        let mut chunk = vec![0u8; CHUNK_LEN];
+
+        for name in self.order.clone() {
+            let info = &self.entries[&name];
+            let entry = HeaderEntry {
+                name,
+                offset: info.offset,
+                length: info.length as u64,
+                compressed_length: info.compressed_length as u64,
+            };
+            inspector.begin_entry(&entry)?;
+
+            let mut reader = self.entry_reader(&entry.name)?;
+            loop {
+                let read = match reader.read(&mut chunk) {
+                    Ok(0) => break,
+                    Ok(read) => read,
+                    Err(e) => {
+                        drop(reader);
+                        return self.decompression_error(&entry.name, Err(e));
+                    }
+                };
+                inspector.update(&chunk[..read])?;
+            }
+            drop(reader);
+
+            inspector.finish_entry(&entry, &mut report.findings);
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+
+    /// Warns about entries containing the bytes `MZ` anywhere but at the start
+    #[derive(Default)]
+    struct EmbeddedExecutable {
+        position: u64,
+        previous: Option<u8>,
+        found: bool,
+    }
+
+    impl EntryInspector for EmbeddedExecutable {
+        fn begin_entry(&mut self, _entry: &HeaderEntry) -> io::Result<()> {
+            *self = EmbeddedExecutable::default();
+            Ok(())
+        }
+
+        fn update(&mut self, chunk: &[u8]) -> io::Result<()> {
+            for &byte in chunk {
+                if self.previous == Some(b'M') && byte == b'Z' && self.position > 1 {
+                    self.found = true;
+                }
+                self.previous = Some(byte);
+                self.position += 1;
+            }
+            Ok(())
+        }
+
+        fn finish_entry(&mut self, entry: &HeaderEntry, findings: &mut Vec<Finding>) {
+            if self.found {
+                findings.push(Finding::warning(&entry.name, "contains an embedded executable header"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_inspect_all() {
+        let mut payload = vec![0u8; 100_000];
+        payload[CHUNK_LEN - 1..CHUNK_LEN + 1].copy_from_slice(b"MZ");
+        let buffer = ObbyTestBuilder::new()
+            .entry("plugin.json", b"{}")
+            .entry("assets/data.bin", &payload)
+            .stored_entry("tools/RUN.EXE", b"MZ")
+            .build();
+        let mut archive = ObbyArchive::from_bytes(buffer).unwrap();
+
+        let inspectors: Vec<Box<dyn EntryInspector>> = vec![
+            Box::new(MaxEntrySize(50_000)),
+            Box::new(ForbiddenExtensions::new(&["exe"])),
+            Box::new(EmbeddedExecutable::default()),
+        ];
+        let report = archive.inspect_all(inspectors).unwrap();
+        assert!(report.is_rejected());
+        let summary: Vec<_> = report
+            .findings
+            .iter()
+            .map(|finding| (finding.entry.as_str(), finding.severity))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("assets/data.bin", Severity::Reject),
+                ("assets/data.bin", Severity::Warning),
+                ("tools/RUN.EXE", Severity::Reject),
+            ]
+        );
+
+        let report = archive.inspect_all(MaxEntrySize(u64::MAX)).unwrap();
+        assert!(report.is_clean());
+    }
+}
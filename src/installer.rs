@@ -0,0 +1,683 @@
+//! Installing plugins into, and removing them from, a server's plugins directory
+//!
+//! [`install`] places a plugin under its ID, either as the `.obby` file itself or as
+//! its extracted files, and records an [`InstallReceipt`] in
+//! `<plugins_dir>/.obby-receipts/<id>.json`. The receipt is what [`uninstall`] uses to
+//! clean up, and what [`installed`] lists, so plugins put there by hand are never
+//! touched. [`upgrade`] replaces an installed plugin with a new version while keeping
+//! the previous one in `<plugins_dir>/.obby-backups/<id>/`, so that [`rollback`] can
+//! bring it back if the new version fails to load.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use obsidian_lib::installer::{self, InstallLayout};
+//!
+//! # fn main() -> std::io::Result<()> {
+//! let mut archive = obsidian_lib::open("downloads/my-plugin.obby")?;
+//! let receipt = installer::install(&mut archive, "server/plugins", InstallLayout::Archive)?;
+//! println!("installed {} {} at {}", receipt.id, receipt.version, receipt.path);
+//!
+//! installer::uninstall("my-plugin", "server/plugins")?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::{json, Value};
+
+use crate::output::OutputFile;
+use crate::manifest::PluginManifest;
+use crate::{to_hex, validate_relative_path, ObbyArchive, MANIFEST_NAMES};
+
+/// Directory below the plugins directory holding the receipts
+pub const RECEIPTS_DIR: &str = ".obby-receipts";
+
+/// Directory below the plugins directory holding the versions replaced by [`upgrade`]
+pub const BACKUPS_DIR: &str = ".obby-backups";
+
+/// Name of the receipt inside a plugin's backup directory
+const BACKUP_RECEIPT: &str = "receipt.json";
+
+/// How an installed plugin is laid out in the plugins directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum InstallLayout {
+    /// The archive itself, as `<id>.obby`
+    Archive,
+    /// The archive's entries, extracted into the directory `<id>`
+    Extracted,
+}
+
+impl InstallLayout {
+    fn name(self) -> &'static str {
+        match self {
+            InstallLayout::Archive => "archive",
+            InstallLayout::Extracted => "extracted",
+        }
+    }
+}
+
+/// The record of an installed plugin
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct InstallReceipt {
+    /// The plugin's ID: the manifest's `id`, or the assembly name if it has none
+    pub id: String,
+    /// The plugin version from the archive header
+    pub version: String,
+    /// The assembly name from the archive header
+    pub assembly: String,
+    /// How the plugin was installed
+    pub layout: InstallLayout,
+    /// Where the plugin was installed, relative to the plugins directory
+    pub path: String,
+    /// SHA-384 of the archive's data section, from its header, as lowercase hex
+    pub archive_hash: String,
+    /// When the plugin was installed, in seconds since the Unix epoch
+    pub installed_at: u64,
+}
+
+impl InstallReceipt {
+    fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "version": self.version,
+            "assembly": self.assembly,
+            "layout": self.layout.name(),
+            "path": self.path,
+            "archive_hash": self.archive_hash,
+            "installed_at": self.installed_at,
+        })
+    }
+
+    fn from_json(data: &[u8]) -> io::Result<Self> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid install receipt: {}", message));
+        let value: Value = serde_json::from_slice(data).map_err(|e| invalid(&e.to_string()))?;
+        let string = |field: &str| {
+            value[field].as_str().map(str::to_string).ok_or_else(|| invalid(&format!("missing '{}'", field)))
+        };
+        let layout = match value["layout"].as_str() {
+            Some("archive") => InstallLayout::Archive,
+            Some("extracted") => InstallLayout::Extracted,
+            _ => return Err(invalid("unknown layout")),
+        };
+        let receipt = InstallReceipt {
+            id: string("id")?,
+            version: string("version")?,
+            assembly: string("assembly")?,
+            layout,
+            path: string("path")?,
+            archive_hash: string("archive_hash")?,
+            installed_at: value["installed_at"].as_u64().unwrap_or(0),
+        };
+        // The path is deleted on uninstall, so a damaged or edited receipt must not
+        // point anywhere but where install would have put the plugin
+        check_id(&receipt.id)?;
+        validate_relative_path(Path::new(&receipt.path))?;
+        if receipt.path != install_path(&receipt.id, receipt.layout) {
+            return Err(invalid(&format!("unexpected path '{}'", receipt.path)));
+        }
+        Ok(receipt)
+    }
+}
+
+/// Installs a plugin into a plugins directory
+///
+/// The plugin is placed at `<id>.obby` or `<id>/`, depending on `layout`, and only
+/// then is its receipt written. Both the archive and the extracted directory are
+/// written under a temporary name and renamed into place, so a failed install leaves
+/// nothing behind.
+///
+/// # Arguments
+///
+/// * `archive` - The plugin to install.
+/// * `plugins_dir` - The server's plugins directory. It is created if it doesn't exist.
+/// * `layout` - Whether to install the archive itself or its extracted files.
+///
+/// # Returns
+///
+/// The [`InstallReceipt`], or an `io::Error` of kind `AlreadyExists` if a plugin with
+/// the same ID is already installed, whether by this module or by hand, or its
+/// destination is taken. An ID that isn't a safe file name is rejected with kind
+/// `InvalidData`.
+pub fn install<R: Read + Seek, P: AsRef<Path>>(
+    archive: &mut ObbyArchive<R>,
+    plugins_dir: P,
+    layout: InstallLayout,
+) -> io::Result<InstallReceipt> {
+    let plugins_dir = plugins_dir.as_ref();
+    let id = plugin_id(archive)?;
+    if let Some(existing) = receipt(&id, plugins_dir)? {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("Plugin '{}' {} is already installed", existing.id, existing.version),
+        ));
+    }
+    if let Some(other) = untracked_conflict(&id, plugins_dir)? {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("Plugin '{}' is already present as {}", id, other.display()),
+        ));
+    }
+
+    let path = install_path(&id, layout);
+    if plugins_dir.join(&path).exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", plugins_dir.join(&path).display()),
+        ));
+    }
+    fs::create_dir_all(plugins_dir)?;
+    place(archive, &plugins_dir.join(&path), layout)?;
+
+    let receipt = InstallReceipt {
+        id,
+        version: archive.metadata.plugin_version.clone(),
+        assembly: archive.metadata.plugin_assembly.clone(),
+        layout,
+        path,
+        archive_hash: to_hex(&archive.metadata.hash),
+        installed_at: now(),
+    };
+    write_receipt(&receipt, plugins_dir)?;
+    Ok(receipt)
+}
+
+/// Removes an installed plugin and its receipt
+///
+/// # Arguments
+///
+/// * `id` - The plugin's ID, as in its receipt.
+/// * `plugins_dir` - The server's plugins directory.
+///
+/// # Returns
+///
+/// The receipt of the removed plugin, or an `io::Error` of kind `NotFound` if no
+/// plugin with that ID was installed with [`install`].
+pub fn uninstall<P: AsRef<Path>>(id: &str, plugins_dir: P) -> io::Result<InstallReceipt> {
+    let plugins_dir = plugins_dir.as_ref();
+    let receipt = receipt(id, plugins_dir)?
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Plugin '{}' is not installed", id)))?;
+    remove_installed(&receipt, plugins_dir)?;
+    fs::remove_file(receipt_path(&receipt.id, plugins_dir))?;
+    remove_backup(&receipt.id, plugins_dir)?;
+    Ok(receipt)
+}
+
+/// Replaces an installed plugin with another version, keeping the current one as a backup
+///
+/// The new version is installed with the same layout as the current one. It is
+/// written in full under a temporary name first; only then is the current version
+/// moved into a new backup directory and the new one renamed into its place, so the
+/// plugin is missing for no longer than two renames take. If the second rename fails,
+/// the current version is moved back. Once the new version is in place, any older
+/// backup of the plugin is replaced, so only one version can be rolled back to; a
+/// failed upgrade leaves the older backup as it was.
+///
+/// # Arguments
+///
+/// * `archive` - The new version of the plugin.
+/// * `plugins_dir` - The server's plugins directory.
+///
+/// # Returns
+///
+/// The receipt of the new version, or an `io::Error` of kind `NotFound` if no plugin
+/// with the archive's ID is installed.
+///
+/// # Example
+///
+/// ```no_run
+/// use obsidian_lib::installer;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut archive = obsidian_lib::open("downloads/my-plugin-2.0.0.obby")?;
+/// let receipt = installer::upgrade(&mut archive, "server/plugins")?;
+/// # let loaded = true;
+/// if !loaded {
+///     installer::rollback(&receipt.id, "server/plugins")?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn upgrade<R: Read + Seek, P: AsRef<Path>>(archive: &mut ObbyArchive<R>, plugins_dir: P) -> io::Result<InstallReceipt> {
+    let plugins_dir = plugins_dir.as_ref();
+    let id = plugin_id(archive)?;
+    let current = receipt(&id, plugins_dir)?
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Plugin '{}' is not installed", id)))?;
+    let target = plugins_dir.join(&current.path);
+    let staged = sibling(&target, "upgrading");
+    place(archive, &staged, current.layout)?;
+
+    // The older backup stays until the swap succeeded, so a failed upgrade can't lose it
+    let backups = backup_dir(&id, plugins_dir);
+    let pending = sibling(&backups, "pending");
+    let swapped = back_up(&current, &pending, plugins_dir).and_then(|()| fs::rename(&staged, &target));
+    if let Err(e) = swapped {
+        let _ = remove_path(&staged);
+        // If the current version can't be moved back, it is left in the pending backup
+        let moved = pending.join(&current.path);
+        if !moved.exists() || fs::rename(&moved, &target).is_ok() {
+            let _ = remove_path(&pending);
+        }
+        return Err(e);
+    }
+
+    let receipt = InstallReceipt {
+        id,
+        version: archive.metadata.plugin_version.clone(),
+        assembly: archive.metadata.plugin_assembly.clone(),
+        layout: current.layout,
+        path: current.path,
+        archive_hash: to_hex(&archive.metadata.hash),
+        installed_at: now(),
+    };
+    write_receipt(&receipt, plugins_dir)?;
+    remove_path(&backups)?;
+    fs::rename(&pending, &backups)?;
+    Ok(receipt)
+}
+
+/// Restores the version of a plugin that [`upgrade`] replaced
+///
+/// The current version is deleted and the backup moved back into place, along with
+/// its receipt. Afterwards there is no backup left, so a plugin can only be rolled
+/// back once per upgrade.
+///
+/// # Arguments
+///
+/// * `id` - The plugin's ID.
+/// * `plugins_dir` - The server's plugins directory.
+///
+/// # Returns
+///
+/// The receipt of the restored version, or an `io::Error` of kind `NotFound` if the
+/// plugin has no backup.
+pub fn rollback<P: AsRef<Path>>(id: &str, plugins_dir: P) -> io::Result<InstallReceipt> {
+    let plugins_dir = plugins_dir.as_ref();
+    let previous = backup(id, plugins_dir)?
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Plugin '{}' has no backup to roll back to", id)))?;
+    let target = plugins_dir.join(&previous.path);
+    // Move the failed version aside first, so it can be put back if the restore fails
+    let failed = sibling(&target, "rollback");
+    if target.exists() {
+        fs::rename(&target, &failed)?;
+    }
+    if let Err(e) = fs::rename(backup_dir(id, plugins_dir).join(&previous.path), &target) {
+        let _ = fs::rename(&failed, &target);
+        return Err(e);
+    }
+    let _ = remove_path(&failed);
+
+    write_receipt(&previous, plugins_dir)?;
+    remove_backup(id, plugins_dir)?;
+    Ok(previous)
+}
+
+/// Reads the receipt of the version [`rollback`] would restore
+///
+/// # Arguments
+///
+/// * `id` - The plugin's ID.
+/// * `plugins_dir` - The server's plugins directory.
+///
+/// # Returns
+///
+/// The backed-up version's receipt, or `None` if the plugin has no backup.
+pub fn backup<P: AsRef<Path>>(id: &str, plugins_dir: P) -> io::Result<Option<InstallReceipt>> {
+    check_id(id)?;
+    match fs::read(backup_dir(id, plugins_dir.as_ref()).join(BACKUP_RECEIPT)) {
+        Ok(data) => InstallReceipt::from_json(&data).map(Some),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Reads the receipt of an installed plugin
+///
+/// # Arguments
+///
+/// * `id` - The plugin's ID.
+/// * `plugins_dir` - The server's plugins directory.
+///
+/// # Returns
+///
+/// The receipt, `None` if the plugin isn't installed, or an `io::Error` if the
+/// receipt can't be read.
+pub fn receipt<P: AsRef<Path>>(id: &str, plugins_dir: P) -> io::Result<Option<InstallReceipt>> {
+    check_id(id)?;
+    match fs::read(receipt_path(id, plugins_dir.as_ref())) {
+        Ok(data) => match InstallReceipt::from_json(&data)? {
+            receipt if receipt.id == id => Ok(Some(receipt)),
+            receipt => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid install receipt: '{}' is filed under '{}'", receipt.id, id),
+            )),
+        },
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Lists the plugins installed with [`install`], sorted by ID
+///
+/// # Arguments
+///
+/// * `plugins_dir` - The server's plugins directory.
+pub fn installed<P: AsRef<Path>>(plugins_dir: P) -> io::Result<Vec<InstallReceipt>> {
+    let dir = plugins_dir.as_ref().join(RECEIPTS_DIR);
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut receipts = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            receipts.push(InstallReceipt::from_json(&fs::read(&path)?)?);
+        }
+    }
+    receipts.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(receipts)
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs())
+}
+
+fn backup_dir(id: &str, plugins_dir: &Path) -> PathBuf {
+    plugins_dir.join(BACKUPS_DIR).join(id)
+}
+
+/// Moves an installed version and its receipt into the backup directory `dir`
+fn back_up(current: &InstallReceipt, dir: &Path, plugins_dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let mut out = OutputFile::new(dir.join(BACKUP_RECEIPT));
+    serde_json::to_writer_pretty(&mut out, &current.to_json())?;
+    out.flush()?;
+    fs::rename(plugins_dir.join(&current.path), dir.join(&current.path))
+}
+
+fn remove_backup(id: &str, plugins_dir: &Path) -> io::Result<()> {
+    remove_path(&backup_dir(id, plugins_dir))
+}
+
+/// Deletes a file or directory, succeeding if it doesn't exist
+fn remove_path(path: &Path) -> io::Result<()> {
+    let result = match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(path),
+        Ok(_) => fs::remove_file(path),
+        Err(e) => Err(e),
+    };
+    match result {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+/// Returns the ID a plugin is installed under
+pub(crate) fn plugin_id<R: Read + Seek>(archive: &mut ObbyArchive<R>) -> io::Result<String> {
+    let id = match archive.plugin_manifest() {
+        Ok(manifest) => manifest.id,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+    let id = id.unwrap_or_else(|| archive.metadata.plugin_assembly.clone());
+    check_id(&id)?;
+    Ok(id)
+}
+
+/// Ensures an ID can be used as a file name in the plugins directory
+fn check_id(id: &str) -> io::Result<()> {
+    if id.contains(['/', '\\']) || id.starts_with('.') {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Plugin ID '{}' isn't a valid file name", id)));
+    }
+    validate_relative_path(Path::new(id))
+}
+
+/// Returns where a plugin is installed, relative to the plugins directory
+pub(crate) fn install_path(id: &str, layout: InstallLayout) -> String {
+    match layout {
+        InstallLayout::Archive => format!("{}.obby", id),
+        InstallLayout::Extracted => id.to_string(),
+    }
+}
+
+pub(crate) fn receipt_path(id: &str, plugins_dir: &Path) -> PathBuf {
+    plugins_dir.join(RECEIPTS_DIR).join(format!("{}.json", id))
+}
+
+pub(crate) fn write_receipt(receipt: &InstallReceipt, plugins_dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(plugins_dir.join(RECEIPTS_DIR))?;
+    let mut out = OutputFile::new(receipt_path(&receipt.id, plugins_dir));
+    serde_json::to_writer_pretty(&mut out, &receipt.to_json())?;
+    out.flush()
+}
+
+/// Writes a plugin to `target`, through a temporary name so it appears all at once
+pub(crate) fn place<R: Read + Seek>(archive: &mut ObbyArchive<R>, target: &Path, layout: InstallLayout) -> io::Result<()> {
+    match layout {
+        InstallLayout::Archive => {
+            let mut out = OutputFile::new(target);
+            copy_archive(archive, &mut out)?;
+            out.flush()
+        }
+        InstallLayout::Extracted => {
+            let temp = sibling(target, "installing");
+            let result = archive.extract_all(&temp).and_then(|_| fs::rename(&temp, target));
+            if result.is_err() {
+                let _ = fs::remove_dir_all(&temp);
+            }
+            result
+        }
+    }
+}
+
+/// Deletes the files of an installed plugin
+pub(crate) fn remove_installed(receipt: &InstallReceipt, plugins_dir: &Path) -> io::Result<()> {
+    let path = plugins_dir.join(&receipt.path);
+    let result = match receipt.layout {
+        InstallLayout::Archive => fs::remove_file(&path),
+        InstallLayout::Extracted => fs::remove_dir_all(&path),
+    };
+    match result {
+        // Already gone, e.g. deleted by hand
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+/// Returns a hidden path next to `path` for work in progress
+pub(crate) fn sibling(path: &Path, purpose: &str) -> PathBuf {
+    let mut name = std::ffi::OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(format!(".{}.{}", purpose, std::process::id()));
+    path.with_file_name(name)
+}
+
+/// Copies the bytes of an archive, without any trailing data, to `out`
+fn copy_archive<R: Read + Seek>(archive: &mut ObbyArchive<R>, out: &mut impl Write) -> io::Result<()> {
+    let end = archive.reader.seek(SeekFrom::End(0))? - archive.trailing_len;
+    archive.reader.seek(SeekFrom::Start(archive.start_pos))?;
+    let copied = io::copy(&mut (&mut archive.reader).take(end - archive.start_pos), out)?;
+    if copied != end - archive.start_pos {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Archive ended while copying"));
+    }
+    Ok(())
+}
+
+/// Looks for a plugin with the same ID that wasn't installed with a receipt
+///
+/// IDs are compared ignoring case, as they are on case-insensitive file systems. Both
+/// archives and extracted directories are checked; a directory conflicts if its name
+/// or its manifest's ID matches.
+fn untracked_conflict(id: &str, plugins_dir: &Path) -> io::Result<Option<PathBuf>> {
+    let entries = match fs::read_dir(plugins_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    for entry in entries {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let conflicts = if path.is_dir() {
+            // Hidden directories are this module's own, and work in progress
+            !name.starts_with('.')
+                && (name.eq_ignore_ascii_case(id) || extracted_id(&path).is_some_and(|other| other.eq_ignore_ascii_case(id)))
+        } else if path.extension().is_some_and(|ext| ext == "obby") {
+            // Unreadable archives can't be loaded by the server either, so they don't conflict
+            let other = crate::open(&path).and_then(|mut other| plugin_id(&mut other));
+            other.is_ok_and(|other| other.eq_ignore_ascii_case(id))
+        } else {
+            false
+        };
+        if conflicts {
+            return Ok(Some(path));
+        }
+    }
+    Ok(None)
+}
+
+/// Returns the ID in the manifest of an extracted plugin, if it has a readable one
+fn extracted_id(dir: &Path) -> Option<String> {
+    let data = MANIFEST_NAMES.iter().find_map(|name| fs::read(dir.join(name)).ok())?;
+    PluginManifest::from_json(&data).ok()?.id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+
+    fn plugin(id: &str, version: &str) -> ObbyArchive<io::Cursor<Vec<u8>>> {
+        let json = format!(r#"{{"id": "{}", "version": "{}"}}"#, id, version);
+        let buffer = ObbyTestBuilder::new()
+            .plugin("Plugin", version)
+            .entry("plugin.json", json.as_bytes())
+            .entry("lib/Plugin.dll", b"MZ")
+            .build();
+        ObbyArchive::from_bytes(buffer).unwrap()
+    }
+
+    #[test]
+    fn test_install_and_uninstall() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugins = dir.path();
+
+        let receipt = install(&mut plugin("alpha", "1.0.0"), plugins, InstallLayout::Archive).unwrap();
+        assert_eq!((receipt.id.as_str(), receipt.path.as_str()), ("alpha", "alpha.obby"));
+        let mut installed_archive = crate::open(plugins.join("alpha.obby")).unwrap();
+        assert_eq!(installed_archive.extract_entry("lib/Plugin.dll").unwrap(), b"MZ");
+
+        let receipt = install(&mut plugin("beta", "2.0.0"), plugins, InstallLayout::Extracted).unwrap();
+        assert_eq!(fs::read(plugins.join("beta/lib/Plugin.dll")).unwrap(), b"MZ");
+        assert_eq!(receipt.version, "2.0.0");
+
+        let error = install(&mut plugin("alpha", "1.1.0"), plugins, InstallLayout::Extracted).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+        let ids: Vec<String> = installed(plugins).unwrap().into_iter().map(|receipt| receipt.id).collect();
+        assert_eq!(ids, vec!["alpha", "beta"]);
+
+        assert_eq!(uninstall("beta", plugins).unwrap().layout, InstallLayout::Extracted);
+        assert!(!plugins.join("beta").exists());
+        assert_eq!(uninstall("beta", plugins).unwrap_err().kind(), io::ErrorKind::NotFound);
+        uninstall("alpha", plugins).unwrap();
+        assert!(installed(plugins).unwrap().is_empty());
+        assert_eq!(fs::read_dir(plugins).unwrap().count(), 1);
+        // An edited receipt can't make uninstall delete anything outside the plugin
+        let outside = dir.path().join("outside");
+        fs::create_dir(&outside).unwrap();
+        for path in ["../outside", "outside", "/tmp"] {
+            install(&mut plugin("gamma", "1.0.0"), plugins, InstallLayout::Extracted).unwrap();
+            let receipt_file = receipt_path("gamma", plugins);
+            let edited = fs::read_to_string(&receipt_file).unwrap().replace(r#""path": "gamma""#, &format!(r#""path": "{}""#, path));
+            fs::write(&receipt_file, edited).unwrap();
+            assert_eq!(uninstall("gamma", plugins).unwrap_err().kind(), io::ErrorKind::InvalidData);
+            assert!(outside.exists());
+            fs::remove_file(&receipt_file).unwrap();
+            fs::remove_dir_all(plugins.join("gamma")).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_upgrade_and_rollback() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugins = dir.path();
+        let version = |plugins: &Path| {
+            let manifest: Value = serde_json::from_slice(&fs::read(plugins.join("alpha/plugin.json")).unwrap()).unwrap();
+            manifest["version"].as_str().unwrap().to_string()
+        };
+        assert_eq!(upgrade(&mut plugin("alpha", "2.0.0"), plugins).unwrap_err().kind(), io::ErrorKind::NotFound);
+
+        install(&mut plugin("alpha", "1.0.0"), plugins, InstallLayout::Extracted).unwrap();
+        assert_eq!(backup("alpha", plugins).unwrap(), None);
+        let upgraded = upgrade(&mut plugin("alpha", "2.0.0"), plugins).unwrap();
+        assert_eq!((upgraded.version.as_str(), upgraded.layout), ("2.0.0", InstallLayout::Extracted));
+        assert_eq!(version(plugins), "2.0.0");
+        assert_eq!(receipt("alpha", plugins).unwrap().unwrap().version, "2.0.0");
+        assert_eq!(backup("alpha", plugins).unwrap().unwrap().version, "1.0.0");
+
+        // Upgrading again replaces the backup
+        upgrade(&mut plugin("alpha", "3.0.0"), plugins).unwrap();
+        assert_eq!(backup("alpha", plugins).unwrap().unwrap().version, "2.0.0");
+
+        let restored = rollback("alpha", plugins).unwrap();
+        assert_eq!(restored.version, "2.0.0");
+        assert_eq!(version(plugins), "2.0.0");
+        assert_eq!(receipt("alpha", plugins).unwrap().unwrap(), restored);
+        assert_eq!(rollback("alpha", plugins).unwrap_err().kind(), io::ErrorKind::NotFound);
+
+        upgrade(&mut plugin("alpha", "4.0.0"), plugins).unwrap();
+        uninstall("alpha", plugins).unwrap();
+        assert_eq!(backup("alpha", plugins).unwrap(), None);
+        let leftovers: Vec<_> = fs::read_dir(plugins).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+        assert_eq!(leftovers.len(), 2, "{:?}", leftovers);
+    }
+
+    #[test]
+    fn test_install_conflicts() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugins = dir.path();
+        // A plugin copied in by hand, under another file name
+        let manual = ObbyTestBuilder::new().entry("plugin.json", br#"{"id": "Alpha"}"#).build();
+        fs::write(plugins.join("manual.obby"), manual).unwrap();
+        let error = install(&mut plugin("alpha", "1.0.0"), plugins, InstallLayout::Archive).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+
+        let error = install(&mut plugin("../escape", "1.0.0"), plugins, InstallLayout::Archive).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // Extracted by hand, under a differently cased name or another name entirely
+        fs::create_dir(plugins.join("Beta")).unwrap();
+        let error = install(&mut plugin("beta", "1.0.0"), plugins, InstallLayout::Archive).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+        fs::create_dir(plugins.join("gamma-plugin")).unwrap();
+        fs::write(plugins.join("gamma-plugin/plugin.json"), br#"{"id": "Gamma"}"#).unwrap();
+        let error = install(&mut plugin("gamma", "1.0.0"), plugins, InstallLayout::Extracted).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+        install(&mut plugin("delta", "1.0.0"), plugins, InstallLayout::Extracted).unwrap();
+    }
+
+    #[test]
+    fn test_failed_upgrade_keeps_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugins = dir.path();
+        install(&mut plugin("alpha", "1.0.0"), plugins, InstallLayout::Archive).unwrap();
+        upgrade(&mut plugin("alpha", "2.0.0"), plugins).unwrap();
+
+        // Block the new backup directory so the upgrade fails after staging
+        let pending = sibling(&backup_dir("alpha", plugins), "pending");
+        fs::write(&pending, b"").unwrap();
+        assert!(upgrade(&mut plugin("alpha", "3.0.0"), plugins).is_err());
+        assert_eq!(backup("alpha", plugins).unwrap().unwrap().version, "1.0.0");
+        assert_eq!(receipt("alpha", plugins).unwrap().unwrap().version, "2.0.0");
+        assert_eq!(crate::open(plugins.join("alpha.obby")).unwrap().metadata().plugin_version, "2.0.0");
+        assert!(!pending.exists());
+    }
+}
@@ -23,11 +23,205 @@
 //! # Ok(())
 //! # }
 //! ```
+//!
+//! # Crate layout
+//!
+//! The crate root holds the core types for reading and writing archives, such as
+//! [`ObbyArchive`], [`ObbyWriter`] and [`ObbyError`]. Larger subsystems live in their own
+//! modules: [`manifest`] for `plugin.json`, [`catalog`] for plugin directories,
+//! [`inspect`] for content checks, [`codec`] and [`format`] for the format itself, and
+//! [`delta`] for updates. [`prelude`] re-exports what most programs need:
+//!
+//! ```
+//! use obsidian_lib::prelude::*;
+//! ```
+
+extern crate alloc;
 
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::{self, Read, Seek, SeekFrom};
-use std::path::Path;
+use std::io::{self, BufReader, Cursor, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use format::wire::{BinaryReader, BinaryWriter, MAX_PREALLOCATION};
+use error::EntryTooLarge;
+use extra::EXTRA_MAGIC;
+use options::EntryFilter;
+use sha2::{Digest, Sha256, Sha384};
+
+/// Emits a `tracing` event when the `tracing` feature is enabled, and nothing otherwise
+macro_rules! trace_event {
+    ($level:ident, $($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($arg)+);
+    };
+}
+
+mod auto;
+mod builder;
+mod cache;
+#[cfg(feature = "json")]
+mod cas;
+#[cfg(feature = "json")]
+mod checksums;
+#[cfg(feature = "json")]
+pub mod catalog;
+pub mod codec;
+mod compat;
+#[cfg(feature = "json")]
+mod consistency;
+mod dedupe;
+pub mod delta;
+mod diff;
+#[cfg(feature = "json")]
+mod editor;
+#[cfg(feature = "encryption")]
+mod encryption;
+mod entry;
+mod error;
+mod extra;
+mod extract;
+pub mod format;
+mod hash;
+mod icon;
+#[cfg(feature = "json")]
+pub mod index;
+pub mod inspect;
+#[cfg(feature = "json")]
+pub mod installer;
+#[cfg(feature = "http")]
+mod http;
+#[cfg(feature = "http")]
+mod http_cache;
+mod license;
+#[cfg(feature = "json")]
+pub mod manifest;
+mod memory;
+mod merge;
+mod mime;
+#[cfg(feature = "registry")]
+mod mirror;
+mod options;
+mod output;
+mod overlay;
+mod parallel;
+mod plan;
+#[cfg(feature = "json")]
+pub mod policy;
+pub mod prelude;
+#[cfg(feature = "registry")]
+mod registry;
+#[cfg(feature = "http")]
+mod remote;
+mod report;
+mod sanitize;
+#[cfg(feature = "http-serve")]
+mod serve;
+mod scan;
+mod search;
+mod slice;
+mod spool;
+mod split;
+mod source;
+mod stats;
+#[cfg(feature = "object_store")]
+mod store;
+mod stream;
+mod text;
+#[cfg(feature = "image")]
+mod thumbnail;
+mod tree;
+mod warning;
+#[cfg(feature = "notify")]
+mod watch;
+mod writer;
+#[cfg(feature = "signing")]
+pub mod signing;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+pub use auto::{open_auto, open_auto_with_options, AutoSource};
+pub use builder::{ObbyArchiveBuilder, ObbyWriterBuilder};
+pub use cache::CachedObbyArchive;
+#[cfg(feature = "json")]
+pub use cas::{store_object_path, StoreManifest, StoredEntry};
+#[cfg(feature = "json")]
+pub use checksums::CHECKSUMS_NAME;
+pub use compat::{check_api_compat, ApiVersion, Compat};
+#[cfg(feature = "json")]
+pub use consistency::{ConsistencyField, ConsistencyReport, Mismatch};
+pub use dedupe::{dedupe, DedupeAnalyzer, DedupeReport, DuplicateGroup, Occurrence};
+pub use diff::{diff_archives, ArchiveDiff, EntryDiff};
+#[cfg(feature = "json")]
+pub use editor::ObbyEditor;
+#[cfg(feature = "encryption")]
+pub use encryption::{Decryptor, EncryptionSecret, ENCRYPTION_NAME};
+pub use entry::EntryGuard;
+pub use error::ObbyError;
+pub use extra::{EntryExtra, MODE_KEY, MODIFIED_KEY};
+pub use extract::{ExtractOptions, OverwritePolicy};
+pub use hash::{EntryDigest, HashAlgo};
+#[cfg(feature = "http")]
+pub use http::{download_file, download_files, fetch, fetch_plugin_json, fetch_plugin_json_with_options, fetch_with_options};
+pub use icon::{PluginIcon, ICON_NAMES};
+pub use license::{LicenseFile, LicenseFileKind, LicenseReport, SpdxHeader};
+#[cfg(feature = "json")]
+pub use manifest::extract_manifests;
+pub use memory::MemoryArchive;
+pub use merge::{merge, merge_with_options, ConflictPolicy, MergeOptions};
+#[cfg(feature = "registry")]
+pub use mirror::{SyncFailure, SyncReport, SyncedVersion};
+pub use options::{ObbyReadOptions, DEFAULT_BUFFER_SIZE};
+pub use output::OutputFile;
+pub use overlay::ObbyOverlay;
+pub use plan::{Collision, ExtractPlan, PlannedFile};
+#[cfg(feature = "registry")]
+pub use registry::{Registry, INDEX_NAME};
+#[cfg(feature = "http")]
+pub use remote::{RemoteOptions, RemoteProxy, DEFAULT_CONCURRENCY, DEFAULT_RETRIES};
+pub use report::{ManifestReport, ReportEntry};
+pub use sanitize::{validate_relative_path, SanitizePolicy};
+#[cfg(feature = "http-serve")]
+pub use serve::serve_request;
+pub use scan::scan_dir;
+pub use slice::{SliceCursor, SliceEntry, SliceError, SliceErrorKind, SliceReader};
+pub use split::SplitRule;
+pub use source::{ObbySource, SourceReader, DEFAULT_BLOCK_SIZE};
+pub use stats::{ArchiveStats, ExtractStats};
+#[cfg(feature = "object_store")]
+pub use store::ObjectStoreSource;
+pub use stream::{ObbyStreamEntry, ObbyStreamReader};
+pub use text::{TextEncoding, TextPreview};
+pub use tree::EntryTree;
+pub use warning::ParseWarning;
+pub use writer::{normalize_entry_name, validate_entry_name, ObbyWriter};
+#[cfg(feature = "wasm")]
+pub use wasm::{WasmObbyArchive, WasmObbyError, WasmObbyErrorKind, WasmObbyParser};
+pub use flate2::Compression;
+
+/// Magic bytes at the start of every `.obby` file
+const MAGIC: &[u8; 4] = b"OBBY";
+
+/// API version written by [`ObbyWriter`] unless overridden
+const DEFAULT_API_VERSION: &str = "1.0.0";
+
+/// Length of the SHA-384 hash of the data section
+const HASH_LEN: usize = 48;
+
+/// Length of the RSA-3072 signature present in signed archives
+const SIGNATURE_LEN: usize = 384;
+
+/// Entry names under which plugins store their manifest, in order of preference
+///
+/// Current plugins use `plugin.json`; the others are found in some older plugins.
+pub const MANIFEST_NAMES: &[&str] = &["plugin.json", "Plugin.json", "manifest.json"];
+
+/// Number of leading bytes of a compressed payload handed to codecs for sniffing
+const CODEC_SNIFF_LEN: usize = 16;
 
 /// Main reader struct for working with .obby files from any source
 ///
@@ -40,9 +234,42 @@ use std::path::Path;
 /// * `R`: A type that implements both `Read` and `Seek` traits, such as `std::fs::File` or `std::io::Cursor`.
 #[derive(Debug)]
 pub struct ObbyArchive<R: Read + Seek> {
+    metadata: ObbyMetadata,
     entries: HashMap<String, EntryInfo>,
+    order: Vec<String>,
     reader: R,
+    /// Position of the magic bytes in `reader`
+    start_pos: u64,
+    /// Position of the hashed data section (plugin info, entry table and entry data)
+    data_section_pos: u64,
     data_start_pos: u64,
+    /// Number of bytes after the last entry's data and extras
+    trailing_len: u64,
+    /// Extra fields of the entries that have any
+    extras: HashMap<String, EntryExtra>,
+    options: ObbyReadOptions,
+    warnings: Vec<ParseWarning>,
+}
+
+/// Header metadata of an `.obby` archive
+///
+/// This holds everything stored in front of the entry table: the API version the plugin
+/// targets, the integrity hash, the optional signature and the plugin's assembly info.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ObbyMetadata {
+    /// The Obsidian API version the plugin was built against
+    pub api_version: String,
+    /// SHA-384 hash of the data section
+    pub hash: Vec<u8>,
+    /// RSA signature of the hash, if the archive is signed
+    pub signature: Option<Vec<u8>>,
+    /// Declared length of the data section in bytes
+    pub data_length: i32,
+    /// The plugin's assembly name
+    pub plugin_assembly: String,
+    /// The plugin's version
+    pub plugin_version: String,
 }
 
 #[derive(Debug)]
@@ -52,80 +279,358 @@ struct EntryInfo {
     compressed_length: i32,
 }
 
-struct BinaryReader<R: Read> {
-    reader: R,
+/// Everything stored in front of the entry data
+struct ParsedHeader {
+    metadata: ObbyMetadata,
+    /// Offset of the data section from the start of the archive
+    data_section_offset: u64,
+    /// Entries in table order, including any duplicate names
+    table: Vec<(String, EntryInfo)>,
+    /// Total stored size of all entries in the table, including filtered ones
+    entry_data_len: u64,
+    /// Extra fields of each entry, in block order, set by [`ParsedHeader::check_layout`]
+    extras: Vec<(String, EntryExtra)>,
+    /// Length of the extras block, set by [`ParsedHeader::check_layout`]
+    extras_len: u64,
+    /// Number of bytes after the last entry's data and extras, set by [`ParsedHeader::check_layout`]
+    trailing_len: u64,
+    /// Oddities found so far
+    warnings: Vec<ParseWarning>,
 }
 
-impl<R: Read> BinaryReader<R> {
-    /// Creates a new instance of `BinaryReader`
+impl ParsedHeader {
+    /// Indexes the table by name, returning the index and the names in table order
     ///
-    /// This function initializes a new binary reader from the provided `reader`.
-    ///
-    /// # Arguments
+    /// When a name appears more than once, the last occurrence wins.
+    fn into_index(self, normalize_names: bool) -> (ObbyMetadata, HashMap<String, EntryInfo>, Vec<String>) {
+        let mut entries = HashMap::new();
+        let mut order = Vec::new();
+        for (name, info) in self.table {
+            let name = if normalize_names { normalize_entry_name(&name) } else { name };
+            if !entries.contains_key(&name) {
+                order.push(name.clone());
+            }
+            entries.insert(name, info);
+        }
+        (self.metadata, entries, order)
+    }
+
+    /// Reads the extras block, if any, and checks the declared layout against the actual size of the source
     ///
-    /// * `reader` - The reader to be used for reading bytes.
+    /// If the options are strict, the first warning found while parsing or here is
+    /// returned as an error, and if they reject trailing data, so is a `TrailingData`
+    /// warning. A data length that stops right where the extras or trailing data start
+    /// isn't a mismatch.
     ///
-    /// # Returns
+    /// # Arguments
     ///
-    /// A new `BinaryReader` instance.
-    fn new(reader: R) -> Self {
-        BinaryReader { reader }
+    /// * `reader` - The source.
+    /// * `data_section_pos` - Position of the data section in the source.
+    /// * `data_start_pos` - Position of the first entry's data in the source.
+    /// * `end` - Position of the end of the source.
+    /// * `options` - The options the archive is opened with.
+    fn check_layout<R: Read + Seek>(
+        &mut self,
+        reader: &mut R,
+        data_section_pos: u64,
+        data_start_pos: u64,
+        end: u64,
+        options: &ObbyReadOptions,
+    ) -> io::Result<()> {
+        let data_end = data_start_pos + self.entry_data_len;
+        self.read_extras(reader, data_end, end)?;
+        let entries_end = data_end + self.extras_len;
+        self.trailing_len = end.saturating_sub(entries_end);
+
+        let actual = end.saturating_sub(data_section_pos);
+        let declared = self.metadata.data_length;
+        let covers_entries = end > data_end
+            && u64::try_from(declared).is_ok_and(|declared| [data_end, entries_end].contains(&(data_section_pos + declared)));
+        if (actual != declared as u64 || declared < 0) && !covers_entries {
+            self.warnings.push(ParseWarning::DataLengthMismatch { declared, actual });
+        }
+        if self.trailing_len > 0 {
+            self.warnings.push(ParseWarning::TrailingData { length: self.trailing_len });
+        }
+
+        let fatal = self.warnings.iter().find(|warning| {
+            options.strict() || (options.reject_trailing_data() && matches!(warning, ParseWarning::TrailingData { .. }))
+        });
+        match fatal {
+            Some(warning) => Err(io::Error::new(io::ErrorKind::InvalidData, warning.to_string())),
+            None => Ok(()),
+        }
     }
 
-    /// Reads a single byte from the reader
-    ///
-    /// # Returns
+    /// Reads the extras block starting at `pos`, if there is one
     ///
-    /// A `Result` containing the byte if successful, or an error if reading fails.
-    fn read_u8(&mut self) -> io::Result<u8> {
-        let mut byte = [0u8; 1];
-        self.reader.read_exact(&mut byte)?;
-        Ok(byte[0])
+    /// A damaged block is reported as a warning and left as trailing data.
+    fn read_extras<R: Read + Seek>(&mut self, reader: &mut R, pos: u64, end: u64) -> io::Result<()> {
+        if end.saturating_sub(pos) < EXTRA_MAGIC.len() as u64 {
+            return Ok(());
+        }
+        reader.seek(SeekFrom::Start(pos))?;
+        let block = BufReader::new(reader.take(end - pos));
+        let mut block = BinaryReader::new(CountingReader { inner: block, count: 0 });
+        let mut warnings = Vec::new();
+        match extra::read_extras(&mut block, &mut warnings) {
+            Ok(Some(records)) => {
+                self.extras = records;
+                self.extras_len = block.get_mut().count;
+                self.warnings.append(&mut warnings);
+            }
+            Ok(None) => {}
+            Err(e) if matches!(e.kind(), io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof) => {
+                self.warnings.push(ParseWarning::InvalidExtraFields { message: e.to_string() });
+            }
+            Err(e) => return Err(e),
+        }
+        Ok(())
     }
+}
 
-    /// Reads a specific number of bytes from the reader
-    ///
-    /// # Arguments
-    ///
-    /// * `length` - The number of bytes to read.
-    ///
-    /// # Returns
-    ///
-    /// A `Result` containing a `Vec<u8>` of the read bytes if successful, or an error if reading fails.
-    fn read_bytes(&mut self, length: usize) -> io::Result<Vec<u8>> {
-        let mut buffer = vec![0u8; length];
-        self.reader.read_exact(&mut buffer)?;
-        Ok(buffer)
+/// Reads the header, metadata and entry table, leaving `reader` at the start of the entry data
+#[cfg_attr(feature = "tracing", tracing::instrument(name = "obby.parse_header", level = "debug", skip_all, err(level = "debug")))]
+fn read_header<R: Read>(reader: R) -> io::Result<ParsedHeader> {
+    read_header_filtered(reader, None, 0)
+}
+
+/// Like [`read_header`], but only keeps table rows accepted by `filter`
+///
+/// `base_offset` is the position of the archive in its source, added to the offsets
+/// reported in parse errors.
+fn read_header_filtered<R: Read>(reader: R, filter: Option<&EntryFilter>, base_offset: u64) -> io::Result<ParsedHeader> {
+    let mut fields = FieldReader {
+        reader: BinaryReader::new(CountingReader { inner: reader, count: 0 }),
+        base_offset,
+        warnings: Vec::new(),
+    };
+
+    // Verify header
+    fields.read("magic", |reader| {
+        let mut header = [0u8; 4];
+        reader.get_mut().read_exact(&mut header)?;
+        if &header != MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid plugin header"));
+        }
+        Ok(())
+    })?;
+
+    // Read metadata
+    let api_version = fields.read_string("API version", || "api_version".to_string())?;
+    let hash = fields.read("hash", |reader| reader.read_bytes(HASH_LEN))?;
+
+    // Read signature (if present)
+    let is_signed = fields.read("signed", BinaryReader::read_u8)?;
+    let signature = if is_signed != 0 {
+        Some(fields.read("signature", |reader| reader.read_bytes(SIGNATURE_LEN))?)
+    } else {
+        None
+    };
+
+    // Read data length and plugin info
+    let data_length = fields.read("data_length", BinaryReader::read_i32)?;
+    let data_section_offset = fields.reader.get_mut().count;
+    let plugin_assembly = fields.read_string("assembly name", || "plugin_assembly".to_string())?;
+    let plugin_version = fields.read_string("plugin version", || "plugin_version".to_string())?;
+
+    // Read entries
+    let entry_count = fields.read("entry_count", |reader| reader.read_length("entry count"))?;
+    let mut table = Vec::new();
+    let mut current_offset = 0u64;
+
+    for index in 0..entry_count {
+        let name = fields.read_string("entry name", || format!("entry[{}].name", index))?;
+        let length = fields.read_with(
+            || format!("entry[{}].length", index),
+            |reader| reader.read_length("entry length"),
+        )?;
+        let compressed_length = fields.read_with(
+            || format!("entry[{}].compressed_length", index),
+            |reader| reader.read_length("entry compressed length"),
+        )?;
+
+        if filter.is_none_or(|filter| filter.matches(&name)) {
+            table.push((name, EntryInfo {
+                offset: current_offset,
+                length,
+                compressed_length,
+            }));
+        }
+
+        current_offset += compressed_length as u64;
     }
+    let FieldReader { warnings, .. } = fields;
 
-    /// Reads a 32-bit integer from the reader
-    ///
-    /// # Returns
-    ///
-    /// A `Result` containing the integer if successful, or an error if reading fails.
-    fn read_i32(&mut self) -> io::Result<i32> {
-        let mut bytes = [0u8; 4];
-        self.reader.read_exact(&mut bytes)?;
-        Ok(i32::from_le_bytes(bytes))
+    trace_event!(
+        debug,
+        api_version = %api_version,
+        plugin_assembly = %plugin_assembly,
+        plugin_version = %plugin_version,
+        signed = signature.is_some(),
+        entry_count,
+        data_bytes = current_offset,
+        "parsed archive header"
+    );
+
+    Ok(ParsedHeader {
+        metadata: ObbyMetadata {
+            api_version,
+            hash,
+            signature,
+            data_length,
+            plugin_assembly,
+            plugin_version,
+        },
+        data_section_offset,
+        table,
+        entry_data_len: current_offset,
+        extras: Vec::new(),
+        extras_len: 0,
+        trailing_len: 0,
+        warnings,
+    })
+}
+
+/// Reads header fields, attaching the field name and its offset to any error
+struct FieldReader<R: Read> {
+    reader: BinaryReader<CountingReader<R>>,
+    /// Position of the archive in its source
+    base_offset: u64,
+    /// Strings that weren't valid UTF-8
+    warnings: Vec<ParseWarning>,
+}
+
+impl<R: Read> FieldReader<R> {
+    /// Reads the field named `field` with `read`
+    fn read<T>(&mut self, field: &str, read: impl FnOnce(&mut BinaryReader<CountingReader<R>>) -> io::Result<T>) -> io::Result<T> {
+        self.read_with(|| field.to_string(), read)
+    }
+
+    /// Like [`FieldReader::read`], but only builds the field name if reading fails
+    fn read_with<T>(
+        &mut self,
+        field: impl FnOnce() -> String,
+        read: impl FnOnce(&mut BinaryReader<CountingReader<R>>) -> io::Result<T>,
+    ) -> io::Result<T> {
+        let offset = self.base_offset + self.reader.get_mut().count;
+        read(&mut self.reader).map_err(|source| ObbyError::Parse { field: field(), offset, source }.into_io())
+    }
+
+    /// Reads a string, replacing invalid UTF-8 and recording a warning described as `kind`
+    fn read_string(&mut self, kind: &'static str, field: impl FnOnce() -> String) -> io::Result<String> {
+        let bytes = self.read_with(field, BinaryReader::read_string_bytes)?;
+        Ok(String::from_utf8(bytes).unwrap_or_else(|e| {
+            let value = String::from_utf8_lossy(e.as_bytes()).into_owned();
+            self.warnings.push(ParseWarning::InvalidUtf8 { field: kind, value: value.clone() });
+            value
+        }))
+    }
+}
+
+/// Returns the offset of the data section from the start of an archive with `metadata`
+fn data_section_offset(metadata: &ObbyMetadata) -> io::Result<u64> {
+    let mut api_version = BinaryWriter::new(Vec::new());
+    api_version.write_string(&metadata.api_version)?;
+    let signature_len = if metadata.signature.is_some() { SIGNATURE_LEN } else { 0 };
+    Ok((MAGIC.len() + api_version.into_inner().len() + HASH_LEN + 1 + signature_len + 4) as u64)
+}
+
+/// Reader adapter that counts the bytes read through it
+struct CountingReader<R> {
+    inner: R,
+    count: u64,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count += read as u64;
+        Ok(read)
+    }
+}
+
+/// Header and entry table of an archive, returned by [`parse_header`]
+///
+/// This is everything needed to locate entries. With the `serde` feature enabled it
+/// implements `Serialize` and `Deserialize`, so the index of an archive can be stored
+/// and later handed to [`ObbyArchive::from_header`] to reopen the archive without
+/// parsing it again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ObbyHeader {
+    /// The header metadata
+    pub metadata: ObbyMetadata,
+    /// The entry table in archive order, including any duplicate names
+    pub entries: Vec<HeaderEntry>,
+    /// Number of bytes the header and entry table occupy, i.e. where the entry data starts
+    pub data_offset: u64,
+}
+
+/// One row of the entry table in an [`ObbyHeader`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HeaderEntry {
+    /// The entry's name
+    pub name: String,
+    /// Offset of the entry's data from the start of the entry data
+    pub offset: u64,
+    /// Decompressed size in bytes
+    pub length: u64,
+    /// Size as stored in the archive
+    pub compressed_length: u64,
+}
+
+impl HeaderEntry {
+    /// Whether the entry is stored compressed
+    pub fn is_compressed(&self) -> bool {
+        self.compressed_length != self.length
     }
 }
 
-/// Reads a C#-style encoded string from the reader
+/// Parses an archive's header and entry table without reading any entry data
+///
+/// Only `Read` is required, so this works on pipes, sockets and other streaming sources
+/// where building an [`ObbyArchive`] isn't possible. Parsing stops right after the entry
+/// table; a reader passed as `&mut reader` is left at the start of the entry data.
+///
+/// # Arguments
 ///
-/// The string is encoded with a length prefix in variable-length encoding, where the length
-/// is encoded using 7-bit chunks.
-fn read_csharp_string<R: Read>(reader: &mut BinaryReader<R>) -> io::Result<String> {
-    let mut string_len = 0;
-    let mut done = false;
-    let mut step = 0;
-    while !done {
-        let byte = reader.read_u8()?;
-        string_len |= ((byte & 0x7F) as u32) << (step * 7);
-        done = (byte & 0x80) == 0;
-        step += 1;
-    }
-    let buf = reader.read_bytes(string_len as usize)?;
-    Ok(String::from_utf8_lossy(&buf).to_string())
+/// * `reader` - The source, positioned at the start of the archive.
+///
+/// # Returns
+///
+/// The [`ObbyHeader`], or an `io::Error` if the header is malformed or truncated.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// let header = obsidian_lib::parse_header(std::io::stdin().lock())?;
+/// println!("{} {}", header.metadata.plugin_assembly, header.metadata.plugin_version);
+/// for entry in &header.entries {
+///     println!("{} ({} bytes)", entry.name, entry.length);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn parse_header<R: Read>(reader: R) -> io::Result<ObbyHeader> {
+    let mut counting = CountingReader { inner: reader, count: 0 };
+    let header = read_header(&mut counting)?;
+    Ok(ObbyHeader {
+        metadata: header.metadata,
+        entries: header
+            .table
+            .into_iter()
+            .map(|(name, info)| HeaderEntry {
+                name,
+                offset: info.offset,
+                length: info.length as u64,
+                compressed_length: info.compressed_length as u64,
+            })
+            .collect(),
+        data_offset: counting.count,
+    })
 }
 
 impl<R: Read + Seek> ObbyArchive<R> {
@@ -152,214 +657,904 @@ impl<R: Read + Seek> ObbyArchive<R> {
     /// let file = File::open("plugin.obby").unwrap();
     /// let archive = ObbyArchive::new(file).unwrap();
     /// ```
-    pub fn new(mut reader: R) -> io::Result<Self> {
-        let mut binary_reader = BinaryReader::new(&mut reader);
+    pub fn new(reader: R) -> io::Result<Self> {
+        Self::with_options(reader, ObbyReadOptions::default())
+    }
 
-        // Verify header
-        let mut header = [0u8; 4];
-        binary_reader.reader.read_exact(&mut header)?;
-        if &header != b"OBBY" {
-            return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid plugin header"));
-        }
+    /// Creates a new `ObbyArchive` with custom read options
+    ///
+    /// This behaves like [`ObbyArchive::new`] but lets the caller configure how the
+    /// archive is read, for example which decompression codecs are available.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Any type that implements the `Read` and `Seek` traits (e.g., `File`, `Cursor`).
+    /// * `options` - The `ObbyReadOptions` to use for this archive.
+    pub fn with_options(mut reader: R, options: ObbyReadOptions) -> io::Result<Self> {
+        let start_pos = reader.stream_position()?;
+        let (mut header, data_start_pos) = if options.buffer_size() > 0 {
+            let mut buffered = BufReader::with_capacity(options.buffer_size(), &mut reader);
+            let header = read_header_filtered(&mut buffered, options.entry_filter(), start_pos)?;
+            // Accounts for bytes still sitting in the buffer
+            let data_start_pos = buffered.stream_position()?;
+            (header, data_start_pos)
+        } else {
+            let header = read_header_filtered(&mut reader, options.entry_filter(), start_pos)?;
+            (header, reader.stream_position()?)
+        };
+        let end = reader.seek(SeekFrom::End(0))?;
+        header.check_layout(&mut reader, start_pos + header.data_section_offset, data_start_pos, end, &options)?;
+        Ok(Self::from_parts(reader, options, header, start_pos, data_start_pos))
+    }
 
-        // Read metadata
-        let _api_version = read_csharp_string(&mut binary_reader)?;
-        let _hash = binary_reader.read_bytes(48)?;
+    /// Opens an archive from a previously saved [`ObbyHeader`], without parsing the source
+    ///
+    /// Only the size of the source is checked against the header: it must hold every
+    /// entry's data, and is otherwise subject to the usual layout checks. So `header` must come
+    /// from [`parse_header`] or [`ObbyArchive::header`] on the same archive. Entry data
+    /// is read from `reader` as usual, and [`ObbyArchive::verify_hash`] still detects
+    /// data that doesn't match the header's hash.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The archive, positioned at its start.
+    /// * `header` - The saved header.
+    /// * `options` - The `ObbyReadOptions` to use for this archive.
+    ///
+    /// # Returns
+    ///
+    /// The `ObbyArchive`, or an `io::Error` of kind `InvalidData` if the header doesn't fit
+    /// the source or holds sizes the format can't express.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use obsidian_lib::{ObbyArchive, ObbyReadOptions};
+    /// use std::fs::File;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let header = obsidian_lib::open("plugin.obby")?.header();
+    /// // ... store the header, then later:
+    /// let archive = ObbyArchive::from_header(File::open("plugin.obby")?, header, ObbyReadOptions::default())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_header(mut reader: R, header: ObbyHeader, options: ObbyReadOptions) -> io::Result<Self> {
+        let start_pos = reader.stream_position()?;
+        let mut table = Vec::with_capacity(header.entries.len());
+        let mut entry_data_len = 0u64;
+        for entry in header.entries {
+            let length = i32::try_from(entry.length);
+            let compressed_length = i32::try_from(entry.compressed_length);
+            let (Ok(length), Ok(compressed_length)) = (length, compressed_length) else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Entry '{}' is too large for the format", entry.name),
+                ));
+            };
+            entry_data_len = entry_data_len.max(entry.offset + compressed_length as u64);
+            if options.entry_filter().is_none_or(|filter| filter.matches(&entry.name)) {
+                table.push((entry.name, EntryInfo { offset: entry.offset, length, compressed_length }));
+            }
+        }
 
-        // Read signature (if present)
-        let mut is_signed = [0u8; 1];
-        binary_reader.reader.read_exact(&mut is_signed)?;
-        if is_signed[0] != 0 {
-            let _signature = binary_reader.read_bytes(384)?;
+        let mut parsed = ParsedHeader {
+            data_section_offset: data_section_offset(&header.metadata)?,
+            metadata: header.metadata,
+            table,
+            entry_data_len,
+            extras: Vec::new(),
+            extras_len: 0,
+            trailing_len: 0,
+            warnings: Vec::new(),
+        };
+        let data_start_pos = start_pos + header.data_offset;
+        let end = reader.seek(SeekFrom::End(0))?;
+        if end < data_start_pos + parsed.entry_data_len {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "The source is too short for the header's entries"));
         }
+        parsed.check_layout(&mut reader, start_pos + parsed.data_section_offset, data_start_pos, end, &options)?;
+        Ok(Self::from_parts(reader, options, parsed, start_pos, data_start_pos))
+    }
 
-        // Read data length and plugin info
-        let _data_length = binary_reader.read_i32()?;
-        let _plugin_assembly = read_csharp_string(&mut binary_reader)?;
-        let _plugin_version = read_csharp_string(&mut binary_reader)?;
+    /// Assembles an archive from a parsed header and the reader it was read from
+    fn from_parts(reader: R, options: ObbyReadOptions, mut header: ParsedHeader, start_pos: u64, data_start_pos: u64) -> Self {
+        let data_section_pos = start_pos + header.data_section_offset;
+        let trailing_len = header.trailing_len;
+        let warnings = std::mem::take(&mut header.warnings);
+        let extras = std::mem::take(&mut header.extras);
+        let (metadata, entries, order) = header.into_index(options.normalize_names());
+        let extras = extras
+            .into_iter()
+            .map(|(name, extra)| if options.normalize_names() { (normalize_entry_name(&name), extra) } else { (name, extra) })
+            .filter(|(name, _)| entries.contains_key(name))
+            .collect();
 
-        // Read entries
-        let entry_count = binary_reader.read_i32()? as usize;
-        let mut entries = HashMap::new();
-        let mut current_offset = 0u64;
+        ObbyArchive {
+            metadata,
+            entries,
+            order,
+            reader,
+            start_pos,
+            data_section_pos,
+            data_start_pos,
+            trailing_len,
+            extras,
+            options,
+            warnings,
+        }
+    }
 
-        for _ in 0..entry_count {
-            let name = read_csharp_string(&mut binary_reader)?;
-            let length = binary_reader.read_i32()?;
-            let compressed_length = binary_reader.read_i32()?;
+    /// Returns the archive's header metadata
+    ///
+    /// # Returns
+    ///
+    /// A reference to the `ObbyMetadata` read when the archive was opened.
+    pub fn metadata(&self) -> &ObbyMetadata {
+        &self.metadata
+    }
 
-            entries.insert(name, EntryInfo {
-                offset: current_offset,
-                length,
-                compressed_length,
-            });
+    /// Returns the structural oddities found while opening the archive
+    ///
+    /// Always empty if the archive was opened with [`ObbyReadOptions::set_strict`], since
+    /// then any oddity makes opening fail.
+    pub fn warnings(&self) -> &[ParseWarning] {
+        &self.warnings
+    }
 
-            current_offset += compressed_length as u64;
-        }
+    /// Returns the extra fields stored for an entry, such as its modification time
+    ///
+    /// # Arguments
+    ///
+    /// * `entry_name` - The name of the entry.
+    ///
+    /// # Returns
+    ///
+    /// The entry's fields, or `None` if it has none or doesn't exist.
+    pub fn entry_extra(&self, entry_name: &str) -> Option<&EntryExtra> {
+        self.extras.get(entry_name)
+    }
 
-        let data_start_pos = reader.stream_position()?;
+    /// Returns the number of bytes after the last entry's data and extras
+    ///
+    /// These bytes belong to no entry. Archives with trailing data fail to open with
+    /// [`ObbyReadOptions::set_reject_trailing_data`] or [`ObbyReadOptions::set_strict`].
+    pub fn trailing_data_len(&self) -> u64 {
+        self.trailing_len
+    }
 
-        Ok(ObbyArchive {
-            entries,
-            reader,
-            data_start_pos,
-        })
+    /// Reads the bytes after the last entry's data, such as junk or a signature appended by a packer
+    ///
+    /// # Returns
+    ///
+    /// The trailing bytes, empty if there are none, or an `io::Error` if reading fails.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use obsidian_lib::ObbyArchive;
+    /// use std::fs::File;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut archive = ObbyArchive::new(File::open("plugin.obby")?)?;
+    /// println!("{} trailing bytes", archive.trailing_data()?.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn trailing_data(&mut self) -> io::Result<Vec<u8>> {
+        let end = self.reader.seek(SeekFrom::End(0))?;
+        self.reader.seek(SeekFrom::Start(end - self.trailing_len))?;
+        let mut data = Vec::new();
+        self.reader.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    /// Returns the archive's header and entry table, for saving and passing to [`ObbyArchive::from_header`]
+    ///
+    /// Entries are listed in table order. If the table names an entry more than once,
+    /// only the copy the archive uses is included.
+    pub fn header(&self) -> ObbyHeader {
+        ObbyHeader {
+            metadata: self.metadata.clone(),
+            entries: self
+                .order
+                .iter()
+                .map(|name| {
+                    let info = &self.entries[name];
+                    HeaderEntry {
+                        name: name.clone(),
+                        offset: info.offset,
+                        length: info.length as u64,
+                        compressed_length: info.compressed_length as u64,
+                    }
+                })
+                .collect(),
+            data_offset: self.data_start_pos - self.start_pos,
+        }
     }
 
     /// Returns a list of all entries in the archive
     ///
-    /// This function returns a vector of the entry names in the `.obby` archive.
+    /// This function returns a vector of the entry names in the `.obby` archive,
+    /// in the order they appear in the entry table.
     ///
     /// # Returns
     ///
-    /// A `Vec<String>` containing the names of all entries.
+    /// A `Vec<String>` containing the names of all entries. [`ObbyArchive::entry_names`]
+    /// returns the same names without copying them.
     pub fn list_entries(&self) -> Vec<String> {
-        self.entries.keys().cloned().collect()
+        self.order.clone()
     }
 
-    /// Extracts a specific entry by name
+    /// Iterates over the names of all entries without allocating
     ///
-    /// This function extracts a specific entry from the `.obby` archive based on its name.
-    /// The entry data is returned as a vector of bytes.
+    /// Yields the same names as [`ObbyArchive::list_entries`], in entry table order,
+    /// borrowed from the archive.
     ///
-    /// # Arguments
+    /// # Example
     ///
-    /// * `entry_name` - The name of the entry to extract.
+    /// ```no_run
+    /// let archive = obsidian_lib::open("plugin.obby").unwrap();
+    /// let has_styles = archive.entry_names().any(|name| name.ends_with(".css"));
+    /// ```
+    pub fn entry_names(&self) -> impl ExactSizeIterator<Item = &str> + '_ {
+        self.order.iter().map(String::as_str)
+    }
+
+    /// Checks the data section against the SHA-384 hash stored in the header
+    ///
+    /// The hash covers the data section, everything after the header from the plugin info
+    /// on, so this reads the whole archive once. The section's length is taken from the
+    /// header, so [trailing data](ObbyArchive::trailing_data) is hashed only if the
+    /// header counts it; a length that runs past the source is ignored.
     ///
     /// # Returns
     ///
-    /// A `Result` containing a `Vec<u8>` of the extracted entry's data if successful, or an `io::Error` if there was an issue extracting it.
-    pub fn extract_entry(&mut self, entry_name: &str) -> io::Result<Vec<u8>> {
-        let entry = self.entries.get(entry_name).ok_or_else(|| {
-            io::Error::new(
-                io::ErrorKind::NotFound,
-                format!("Entry '{}' not found in archive", entry_name),
-            )
-        })?;
-
-        // Seek to the entry's position
-        self.reader.seek(SeekFrom::Start(self.data_start_pos + entry.offset))?;
+    /// `Ok(())` if the hash matches, or an `io::Error` of kind `InvalidData` carrying
+    /// [`ObbyError::HashMismatch`] if it doesn't.
+    pub fn verify_hash(&mut self) -> io::Result<()> {
+        let available = self.reader.seek(SeekFrom::End(0))?.saturating_sub(self.data_section_pos);
+        let hashed_len = u64::try_from(self.metadata.data_length).map_or(available, |declared| declared.min(available));
+        self.reader.seek(SeekFrom::Start(self.data_section_pos))?;
+        let mut hasher = Sha384::new();
+        io::copy(&mut (&mut self.reader).take(hashed_len), &mut hasher)?;
+        let actual = hasher.finalize().to_vec();
 
-        // Read the compressed data
-        let mut reader = BinaryReader::new(&mut self.reader);
-        let compressed_data = reader.read_bytes(entry.compressed_length as usize)?;
+        if actual != self.metadata.hash {
+            trace_event!(debug, "data section hash mismatch");
+            return Err(ObbyError::HashMismatch {
+                expected: self.metadata.hash.clone(),
+                actual,
+            }
+            .into_io());
+        }
+        Ok(())
+    }
 
-        // Decompress if necessary
-        if entry.compressed_length != entry.length {
-            let mut decompressed_data = Vec::new();
-            let mut decoder = flate2::read::DeflateDecoder::new(&compressed_data[..]);
-            decoder.read_to_end(&mut decompressed_data)?;
-            Ok(decompressed_data)
-        } else {
-            Ok(compressed_data)
+    /// Finds the entry holding the plugin manifest
+    ///
+    /// Tries each of [`MANIFEST_NAMES`] in turn.
+    ///
+    /// # Returns
+    ///
+    /// The name of the manifest entry, or an `io::Error` of kind `NotFound` carrying
+    /// [`ObbyError::ManifestNotFound`] with the archive's root entries.
+    pub fn find_manifest(&self) -> io::Result<&str> {
+        find_manifest_in(&self.order)
+    }
+
+    /// Builds an inventory of the archive: metadata plus size and SHA-256 of every entry
+    ///
+    /// Every entry is decompressed to compute its hash.
+    ///
+    /// # Returns
+    ///
+    /// A [`ManifestReport`] with entries in archive order.
+    pub fn manifest_report(&mut self) -> io::Result<ManifestReport> {
+        let mut entries = Vec::with_capacity(self.order.len());
+        for name in self.order.clone() {
+            let data = self.extract_entry(&name)?;
+            let info = &self.entries[&name];
+            entries.push(ReportEntry {
+                size: info.length as u64,
+                compressed_size: info.compressed_length as u64,
+                compressed: info.is_compressed(),
+                sha256: to_hex(&Sha256::digest(&data)),
+                name,
+            });
         }
+
+        Ok(ManifestReport {
+            api_version: self.metadata.api_version.clone(),
+            plugin_assembly: self.metadata.plugin_assembly.clone(),
+            plugin_version: self.metadata.plugin_version.clone(),
+            signed: self.metadata.signature.is_some(),
+            hash: to_hex(&self.metadata.hash),
+            entries,
+        })
     }
-}
 
-/// Opens an .obby file from a path
-///
-/// This is a convenience function that creates an `ObbyArchive` from a file path.
-///
-/// # Arguments
-///
-/// * `path` - The path to the `.obby` file.
-pub fn open<P: AsRef<Path>>(path: P) -> io::Result<ObbyArchive<File>> {
-    let file = File::open(path)?;
-    ObbyArchive::new(file)
-}
+    /// Computes a digest of the archive's contents that ignores how they are stored
+    ///
+    /// The SHA-256 covers the entry names in sorted order, each followed by the entry's
+    /// decompressed data, with every field prefixed by its length as a little-endian
+    /// `u64`. Compression, entry order, header metadata and the signature don't affect
+    /// it, so two archives with the same digest hold the same files even if one was
+    /// recompressed or re-signed.
+    ///
+    /// # Returns
+    ///
+    /// The digest as lowercase hex.
+    pub fn content_digest(&mut self) -> io::Result<String> {
+        let mut names = self.order.clone();
+        names.sort();
 
-#[cfg(feature = "wasm")]
-use wasm_bindgen::prelude::*;
-#[cfg(feature = "wasm")]
-use std::io::Cursor;
-use js_sys::Uint8Array;
+        let mut hasher = Sha256::new();
+        for name in names {
+            let data = self.extract_entry(&name)?;
+            hasher.update((name.len() as u64).to_le_bytes());
+            hasher.update(name.as_bytes());
+            hasher.update((data.len() as u64).to_le_bytes());
+            hasher.update(&data);
+        }
+        Ok(to_hex(&hasher.finalize()))
+    }
 
-/// A wrapper struct for the WebAssembly environment to interact with `.obby` files
-///
-/// This struct provides a WASM-compatible interface for working with `.obby` archives.
-#[wasm_bindgen]
-pub struct WasmObbyArchive {
-    inner: ObbyArchive<Cursor<Vec<u8>>>
-}
+    /// Returns size statistics for the archive
+    ///
+    /// Computed from the entry table alone, so this is cheap even for large archives.
+    ///
+    /// # Returns
+    ///
+    /// An [`ArchiveStats`] with total sizes, the largest entry and counts by extension.
+    pub fn stats(&self) -> ArchiveStats {
+        ArchiveStats::from_entries(self.order.iter().map(|name| (name.as_str(), &self.entries[name])))
+    }
 
-#[wasm_bindgen]
-impl WasmObbyArchive {
-    #[wasm_bindgen(constructor)]
-    /// Creates a new `WasmObbyArchive` instance from a byte buffer
+    /// Returns the entries inside a directory, at any depth
+    ///
+    /// Paths are compared component by component, treating `/` and `\` alike, so
+    /// `"assets"` and `"assets/"` both match `assets/icons/logo.png` but not `assets2/a.png`.
     ///
     /// # Arguments
     ///
-    /// * `buffer` - A byte slice representing the `.obby` file contents.
+    /// * `prefix` - The directory path. An empty string matches every entry.
     ///
     /// # Returns
     ///
-    /// A `WasmObbyArchive` instance.
-    pub fn new(buffer: &[u8]) -> Result<WasmObbyArchive, JsValue> {
-        let cursor = Cursor::new(buffer.to_vec());
-        let inner = ObbyArchive::new(cursor)
-            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    /// The matching entry names, in archive order.
+    pub fn entries_under(&self, prefix: &str) -> Vec<String> {
+        self.order
+            .iter()
+            .filter(|name| tree::is_under(name, prefix))
+            .cloned()
+            .collect()
+    }
 
-        Ok(WasmObbyArchive { inner })
+    /// Returns every directory that contains at least one entry
+    ///
+    /// Directories aren't stored in the archive; they are derived from the entry names.
+    ///
+    /// # Returns
+    ///
+    /// The directory paths, sorted and joined with `/`, e.g. `["assets", "assets/icons"]`.
+    pub fn directories(&self) -> Vec<String> {
+        tree::directories(self.order.iter().map(String::as_str))
     }
 
-    #[wasm_bindgen]
-    /// Lists all entries in the `.obby` archive
+    /// Returns the archive's entries as a file tree
     ///
     /// # Returns
     ///
-    /// A JavaScript array of strings representing the names of all entries.
-    pub fn list_entries(&self) -> Box<[JsValue]> {
-        self.inner
-            .list_entries()
-            .into_iter()
-            .map(JsValue::from)
-            .collect::<Vec<_>>()
-            .into_boxed_slice()
+    /// An [`EntryTree::Dir`] root whose descendants mirror the entry paths.
+    pub fn tree(&self) -> EntryTree {
+        EntryTree::from_names(self.order.iter().map(String::as_str))
     }
 
-    #[wasm_bindgen]
     /// Extracts a specific entry by name
     ///
+    /// This function extracts a specific entry from the `.obby` archive based on its name.
+    /// The entry data is returned as a vector of bytes.
+    ///
     /// # Arguments
     ///
     /// * `entry_name` - The name of the entry to extract.
     ///
     /// # Returns
     ///
-    /// A `Uint8Array` containing the entry's data.
-    pub fn extract_entry(&mut self, entry_name: &str) -> Result<Uint8Array, JsValue> {
-        let data = self.inner
-            .extract_entry(entry_name)
-            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    /// A `Result` containing a `Vec<u8>` of the extracted entry's data if successful, or an `io::Error` if there was an issue extracting it.
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "obby.extract_entry", level = "debug", skip(self), err(level = "debug")))]
+    pub fn extract_entry(&mut self, entry_name: &str) -> io::Result<Vec<u8>> {
+        let entry = lookup_entry(&self.entries, entry_name)?;
+        let compressed_data = read_raw(&mut self.reader, self.data_start_pos, entry)?;
 
-        Ok(Uint8Array::from(&data[..]))
+        // Decompress if necessary
+        if entry.is_compressed() {
+            decompress(&self.options, entry_name, entry.length, &compressed_data)
+        } else {
+            Ok(compressed_data)
+        }
     }
 
-    #[wasm_bindgen]
-    /// Extracts and returns the contents of the `plugin.json` file from the `.obby` archive
+    /// Extracts an entry along with details of how it was extracted
+    ///
+    /// This does the same work as [`ObbyArchive::extract_entry`], additionally timing it.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry_name` - The name of the entry to extract.
     ///
     /// # Returns
     ///
-    /// A `Result<String, JsValue>` containing the parsed JSON string if successful.
-    pub fn extract_plugin_json(&mut self) -> Result<String, JsValue> {
-        let data = self.extract_entry("plugin.json")?;
-        let text = String::from_utf8(data.to_vec())
-            .map_err(|e| JsValue::from_str(&e.to_string()))?;
-        Ok(text)
+    /// The entry's data and an [`ExtractStats`] with its sizes and the time taken.
+    pub fn extract_entry_info(&mut self, entry_name: &str) -> io::Result<(Vec<u8>, ExtractStats)> {
+        #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+        let started = std::time::Instant::now();
+        let data = self.extract_entry(entry_name)?;
+        #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+        let elapsed = started.elapsed();
+        #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+        let elapsed = std::time::Duration::ZERO;
+
+        let entry = &self.entries[entry_name];
+        let stats = ExtractStats {
+            compressed_size: entry.compressed_length as u64,
+            decompressed_size: data.len() as u64,
+            decompressed: entry.is_compressed(),
+            elapsed,
+        };
+        Ok((data, stats))
+    }
+
+    /// Returns where an entry's stored (possibly compressed) bytes lie in the source
+    ///
+    /// Offsets are positions in the reader the archive was opened from, so for a file
+    /// they are file offsets. With them, a remote archive can be read selectively: fetch
+    /// the header with a first ranged request, then just the ranges of the entries needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry_name` - The name of the entry.
+    ///
+    /// # Returns
+    ///
+    /// The half-open byte range of the entry's data, or an `io::Error` of kind `NotFound`.
+    pub fn entry_byte_range(&self, entry_name: &str) -> io::Result<Range<u64>> {
+        let entry = lookup_entry(&self.entries, entry_name)?;
+        let start = self.data_start_pos + entry.offset;
+        Ok(start..start + entry.compressed_length as u64)
+    }
+
+    /// Returns an entry's declared length and its data as stored, without decompressing it
+    pub(crate) fn raw_entry(&mut self, entry_name: &str) -> io::Result<(i32, Vec<u8>)> {
+        let entry = lookup_entry(&self.entries, entry_name)?;
+        Ok((entry.length, read_raw(&mut self.reader, self.data_start_pos, entry)?))
+    }
+
+    /// Returns a reader over an entry's decompressed contents
+    ///
+    /// Errors while reading aren't wrapped in [`ObbyError::Decompression`]; callers do that.
+    pub(crate) fn entry_reader(&mut self, entry_name: &str) -> io::Result<Box<dyn Read + '_>> {
+        let entry = lookup_entry(&self.entries, entry_name)?;
+        self.reader.seek(SeekFrom::Start(self.data_start_pos + entry.offset))?;
+        let raw = (&mut self.reader).take(entry.compressed_length as u64);
+        if entry.is_compressed() {
+            decoding_reader(&self.options, raw, entry.length)
+        } else {
+            Ok(Box::new(raw))
+        }
+    }
+
+    /// Wraps a failure to read a compressed entry in [`ObbyError::Decompression`]
+    pub(crate) fn decompression_error<T>(&self, entry_name: &str, result: io::Result<T>) -> io::Result<T> {
+        match result {
+            Err(source) if self.entries.get(entry_name).is_some_and(EntryInfo::is_compressed) => {
+                Err(ObbyError::decompression(entry_name, source))
+            }
+            result => result,
+        }
+    }
+
+    /// Reads a byte range of an entry's decompressed contents
+    ///
+    /// Stored entries are read directly at the requested offset. Compressed entries are
+    /// decompressed from the start, but only up to the end of the range, and the bytes
+    /// before `offset` are discarded without being buffered.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry_name` - The name of the entry.
+    /// * `offset` - Position of the first byte to read, within the decompressed entry.
+    /// * `len` - Maximum number of bytes to read. Ranges that extend past the end of the
+    ///   entry are truncated, as with HTTP range requests.
+    ///
+    /// # Returns
+    ///
+    /// The requested bytes, or an `io::Error` of kind `InvalidInput` if `offset` lies past
+    /// the end of the entry.
+    pub fn read_entry_range(&mut self, entry_name: &str, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+        let entry = lookup_entry(&self.entries, entry_name)?;
+        let length = entry.length as u64;
+        if offset > length {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Offset {} is past the end of entry '{}' ({} bytes)", offset, entry_name, length),
+            ));
+        }
+        let len = (len as u64).min(length - offset);
+
+        let mut data = Vec::with_capacity((len as usize).min(MAX_PREALLOCATION));
+        if entry.is_compressed() {
+            self.reader.seek(SeekFrom::Start(self.data_start_pos + entry.offset))?;
+            let raw = (&mut self.reader).take(entry.compressed_length as u64);
+            decoding_reader(&self.options, raw, entry.length)
+                .and_then(|mut decoder| {
+                    io::copy(&mut (&mut decoder).take(offset), &mut io::sink())?;
+                    decoder.take(len).read_to_end(&mut data)
+                })
+                .map_err(|source| ObbyError::decompression(entry_name, source))?;
+        } else {
+            self.reader.seek(SeekFrom::Start(self.data_start_pos + entry.offset + offset))?;
+            (&mut self.reader).take(len).read_to_end(&mut data)?;
+        }
+
+        if data.len() as u64 != len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("Entry '{}' is shorter than its declared length", entry_name),
+            ));
+        }
+        Ok(data)
+    }
+
+    /// Guesses an entry's MIME type from its first bytes
+    ///
+    /// Only the start of the entry is read and decompressed, so this is cheap even for
+    /// large entries. Binary formats are recognised by their signatures (PE/DLL, PNG,
+    /// JPEG, WAV, ...); text is reported as JSON or SVG when it looks like it, and
+    /// otherwise by extension, falling back to `text/plain`.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry_name` - The name of the entry.
+    ///
+    /// # Returns
+    ///
+    /// A MIME type such as `"image/png"`, or `"application/octet-stream"` if unknown.
+    pub fn entry_mime(&mut self, entry_name: &str) -> io::Result<&'static str> {
+        let entry = lookup_entry(&self.entries, entry_name)?;
+        self.reader.seek(SeekFrom::Start(self.data_start_pos + entry.offset))?;
+        let raw = (&mut self.reader).take(entry.compressed_length as u64);
+
+        let mut prefix = Vec::with_capacity(mime::MIME_SNIFF_LEN);
+        if entry.is_compressed() {
+            decoding_reader(&self.options, raw, entry.length)
+                .and_then(|decoder| decoder.take(mime::MIME_SNIFF_LEN as u64).read_to_end(&mut prefix))
+                .map_err(|source| ObbyError::decompression(entry_name, source))?;
+        } else {
+            raw.take(mime::MIME_SNIFF_LEN as u64).read_to_end(&mut prefix)?;
+        }
+        Ok(mime::sniff(&prefix, entry_name))
+    }
+
+    /// Extracts every entry into a directory
+    ///
+    /// Entry names are mapped to paths with the archive's [`SanitizePolicy`] (see
+    /// [`ObbyReadOptions::set_sanitize_policy`]). All names are checked before anything
+    /// is written, so an archive with a rejected name leaves `dest` untouched.
+    /// Existing files are overwritten; [`ObbyArchive::extract_all_plan`] reports what
+    /// would be written and replaced beforehand, and
+    /// [`ObbyArchive::extract_all_with_options`] can skip or keep them instead.
+    ///
+    /// # Guarantees
+    ///
+    /// Whatever the archive contains, extraction only creates and replaces files below
+    /// `dest`:
+    ///
+    /// - every path passes [`validate_relative_path`], so it has no `..`, absolute or
+    ///   drive components and no Windows device names such as `CON` or `NUL`;
+    /// - symlinks below `dest` are never followed: a symlinked directory on the way to
+    ///   an entry fails with [`ObbyError::SymlinkInPath`] and a symlink where the entry
+    ///   goes is replaced by the extracted file, leaving its target untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `dest` - The directory to extract into. It is created if it doesn't exist.
+    ///
+    /// # Returns
+    ///
+    /// The paths of the written files, in archive order.
+    pub fn extract_all<P: AsRef<Path>>(&mut self, dest: P) -> io::Result<Vec<PathBuf>> {
+        self.extract_all_with_options(dest, &ExtractOptions::default())
+    }
+}
+
+impl ObbyArchive<Cursor<Vec<u8>>> {
+    /// Creates an `ObbyArchive` from an owned in-memory buffer
+    ///
+    /// Shorthand for `ObbyArchive::new(Cursor::new(bytes))`.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The complete contents of an `.obby` file.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use obsidian_lib::ObbyArchive;
+    ///
+    /// let bytes = std::fs::read("plugin.obby").unwrap();
+    /// let mut archive = ObbyArchive::from_bytes(bytes).unwrap();
+    /// ```
+    pub fn from_bytes(bytes: Vec<u8>) -> io::Result<Self> {
+        ObbyArchive::new(Cursor::new(bytes))
     }
 }
 
+impl<'a> ObbyArchive<Cursor<&'a [u8]>> {
+    /// Creates an `ObbyArchive` that borrows an in-memory buffer
+    ///
+    /// Shorthand for `ObbyArchive::new(Cursor::new(bytes))`. Nothing is copied, and
+    /// [`ObbyArchive::entry_bytes`] can return stored entries as slices of `bytes`.
+    /// [`ObbyArchive::from_slice_reader`] skips the read buffer and lets borrowed
+    /// entries outlive the archive.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The complete contents of an `.obby` file.
+    pub fn from_slice(bytes: &'a [u8]) -> io::Result<Self> {
+        ObbyArchive::new(Cursor::new(bytes))
+    }
+}
+
+impl<T: AsRef<[u8]>> ObbyArchive<Cursor<T>> {
+    /// Returns an entry's data, borrowing it from the underlying buffer when possible
+    ///
+    /// For archives held in memory (a `Vec<u8>`, a byte slice, or a memory map such as
+    /// `memmap2::Mmap` wrapped in a `Cursor`), stored entries are returned as
+    /// `Cow::Borrowed` without copying. Only compressed entries allocate, for the
+    /// decompressed output.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry_name` - The name of the entry to read.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the entry's data, or an `io::Error` if the entry doesn't exist or is truncated.
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "obby.entry_bytes", level = "debug", skip(self), err(level = "debug")))]
+    pub fn entry_bytes(&self, entry_name: &str) -> io::Result<Cow<'_, [u8]>> {
+        entry_bytes_in(&self.options, &self.entries, self.reader.get_ref().as_ref(), self.data_start_pos, entry_name)
+    }
+}
+
+/// Returns an entry's data from an in-memory archive, borrowing stored entries from `buffer`
+fn entry_bytes_in<'a>(
+    options: &ObbyReadOptions,
+    entries: &HashMap<String, EntryInfo>,
+    buffer: &'a [u8],
+    data_start_pos: u64,
+    entry_name: &str,
+) -> io::Result<Cow<'a, [u8]>> {
+    let entry = lookup_entry(entries, entry_name)?;
+
+    let start = data_start_pos + entry.offset;
+    let end = start + entry.compressed_length as u64;
+    if end > buffer.len() as u64 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("Entry '{}' extends past the end of the archive", entry_name),
+        ));
+    }
+    let raw = &buffer[start as usize..end as usize];
+
+    if entry.is_compressed() {
+        decompress(options, entry_name, entry.length, raw).map(Cow::Owned)
+    } else {
+        Ok(Cow::Borrowed(raw))
+    }
+}
+
+impl EntryInfo {
+    /// Compressed entries are marked by a stored size that differs from the real size
+    fn is_compressed(&self) -> bool {
+        self.compressed_length != self.length
+    }
+}
+
+/// Looks up an entry by name, producing a `NotFound` error if it is missing
+fn lookup_entry<'a>(entries: &'a HashMap<String, EntryInfo>, entry_name: &str) -> io::Result<&'a EntryInfo> {
+    let entry = entries.get(entry_name).ok_or_else(|| {
+        trace_event!(debug, entry = entry_name, "entry not found");
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Entry '{}' not found in archive", entry_name),
+        )
+    })?;
+    trace_event!(
+        trace,
+        entry = entry_name,
+        offset = entry.offset,
+        length = entry.length,
+        compressed_length = entry.compressed_length,
+        "found entry"
+    );
+    Ok(entry)
+}
+
+/// Decompresses an entry's payload with the codec selected by `options`
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "obby.decompress", level = "debug", skip_all, fields(compressed_bytes = compressed_data.len()), err(level = "debug"))
+)]
+fn decompress(options: &ObbyReadOptions, entry_name: &str, length: i32, compressed_data: &[u8]) -> io::Result<Vec<u8>> {
+    let codec = options.codecs().select(&compressed_data[..compressed_data.len().min(CODEC_SNIFF_LEN)]);
+    let mut decompressed_data = Vec::with_capacity((length as usize).min(MAX_PREALLOCATION));
+    check_entry_size(options, length)?;
+    codec
+        .decoder(Box::new(compressed_data))
+        .and_then(|decoder| DeclaredLength::new(decoder, length).read_to_end(&mut decompressed_data))
+        .map_err(|source| ObbyError::decompression(entry_name, source))?;
+    trace_event!(
+        debug,
+        codec = codec.name(),
+        decompressed_bytes = decompressed_data.len(),
+        "decompressed entry"
+    );
+    Ok(decompressed_data)
+}
+
+/// Formats bytes as lowercase hex
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Returns the first of [`MANIFEST_NAMES`] present in `names`
+fn find_manifest_in(names: &[String]) -> io::Result<&str> {
+    MANIFEST_NAMES
+        .iter()
+        .find_map(|candidate| names.iter().find(|name| name == candidate))
+        .map(String::as_str)
+        .ok_or_else(|| {
+            trace_event!(debug, "no manifest entry found");
+            ObbyError::ManifestNotFound {
+                root_entries: names
+                    .iter()
+                    .filter(|name| tree::components(name).nth(1).is_none())
+                    .cloned()
+                    .collect(),
+            }
+            .into_io()
+        })
+}
+
+/// Wraps a compressed payload in a reader that yields the decompressed data
+///
+/// The codec is chosen from the payload's first bytes, which are then replayed to it, so
+/// `raw` doesn't need to be seekable. The output is held to the entry's declared `length`.
+fn decoding_reader<'a>(options: &ObbyReadOptions, mut raw: impl Read + 'a, length: i32) -> io::Result<Box<dyn Read + 'a>> {
+    check_entry_size(options, length)?;
+    let mut prefix = Vec::with_capacity(CODEC_SNIFF_LEN);
+    (&mut raw).take(CODEC_SNIFF_LEN as u64).read_to_end(&mut prefix)?;
+    let codec = options.codecs().select(&prefix);
+    let decoder = codec.decoder(Box::new(Cursor::new(prefix).chain(raw)))?;
+    Ok(Box::new(DeclaredLength::new(decoder, length)))
+}
+
+/// Rejects entries whose declared size exceeds [`ObbyReadOptions::max_entry_size`]
+fn check_entry_size(options: &ObbyReadOptions, length: i32) -> io::Result<()> {
+    match options.max_entry_size() {
+        Some(max) if length as u64 > max => Err(io::Error::new(io::ErrorKind::InvalidData, EntryTooLarge { length, max })),
+        _ => Ok(()),
+    }
+}
+
+/// Decoder adapter that yields exactly an entry's declared number of bytes
+///
+/// Decompressed data is never trusted to stop on its own: output beyond the declared
+/// length is an error rather than being read, which stops decompression bombs, and
+/// so is a stream that ends early.
+struct DeclaredLength<R> {
+    inner: R,
+    remaining: u64,
+}
+
+impl<R: Read> DeclaredLength<R> {
+    fn new(inner: R, length: i32) -> Self {
+        DeclaredLength { inner, remaining: length as u64 }
+    }
+}
+
+impl<R: Read> Read for DeclaredLength<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.remaining == 0 {
+            return match self.inner.read(&mut [0u8; 1])? {
+                0 => Ok(0),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Decompressed data is longer than the declared length",
+                )),
+            };
+        }
+
+        let max = (buf.len() as u64).min(self.remaining) as usize;
+        let read = self.inner.read(&mut buf[..max])?;
+        if read == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Decompressed data is shorter than the declared length",
+            ));
+        }
+        self.remaining -= read as u64;
+        Ok(read)
+    }
+}
+
+/// Reads an entry's data as stored in the archive, without decompressing it
+fn read_raw<R: Read + Seek>(reader: &mut R, data_start_pos: u64, entry: &EntryInfo) -> io::Result<Vec<u8>> {
+    reader.seek(SeekFrom::Start(data_start_pos + entry.offset))?;
+    BinaryReader::new(reader).read_bytes(entry.compressed_length as usize)
+}
+
+/// Decodes an entry's stored bytes that were fetched without an `ObbyArchive`
+///
+/// This is the counterpart of [`ObbyArchive::entry_byte_range`]: given the bytes from
+/// that range (from a ranged HTTP request or a cache, say) and the entry's declared size,
+/// it returns the entry's contents. As in the archive format, data whose length equals
+/// `expected_len` is taken to be stored uncompressed; anything else is decompressed with
+/// the default codecs and must produce exactly `expected_len` bytes.
+///
+/// # Arguments
+///
+/// * `compressed` - The entry's bytes as stored in the archive.
+/// * `expected_len` - The entry's decompressed size from the entry table.
+///
+/// # Returns
+///
+/// The entry's contents, or an `io::Error` of kind `InvalidData` if they can't be decoded
+/// to the expected size.
+pub fn decompress_entry_bytes(compressed: &[u8], expected_len: usize) -> io::Result<Vec<u8>> {
+    let length = i32::try_from(expected_len).map_err(|_| {
+        io::Error::new(io::ErrorKind::InvalidInput, "Expected length exceeds the format's size limit")
+    })?;
+    if compressed.len() == expected_len {
+        return Ok(compressed.to_vec());
+    }
+
+    let mut data = Vec::with_capacity(expected_len.min(MAX_PREALLOCATION));
+    decoding_reader(&ObbyReadOptions::default(), compressed, length)
+        .and_then(|mut decoder| decoder.read_to_end(&mut data))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(data)
+}
+
+/// Opens an .obby file from a path
+///
+/// This is a convenience function that creates an `ObbyArchive` from a file path.
+///
+/// # Arguments
+///
+/// * `path` - The path to the `.obby` file.
+pub fn open<P: AsRef<Path>>(path: P) -> io::Result<ObbyArchive<File>> {
+    let file = File::open(path)?;
+    ObbyArchive::new(file)
+}
+
 /// Convenience function to extract and parse the `plugin.json` file from an `.obby` archive
 ///
-/// This function opens the `.obby` file, extracts the `plugin.json` entry, and returns
-/// the contents of the file as a `String`.
+/// This function opens the `.obby` file, extracts the manifest entry (see
+/// [`ObbyArchive::find_manifest`]), and returns the contents of the file as a `String`.
 ///
 /// # Arguments
 ///
 /// * `path` - Path to the `.obby` file.
 pub fn extract_plugin_json<P: AsRef<Path>>(path: P) -> io::Result<String> {
     let mut archive = open(path)?;
-    let data = archive.extract_entry("plugin.json")?;
+    let manifest = archive.find_manifest()?.to_string();
+    let data = archive.extract_entry(&manifest)?;
     String::from_utf8(data)
         .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
 }
@@ -369,7 +1564,7 @@ mod tests {
     use super::*;
     use std::io::{Cursor, Write};
     use tempfile::NamedTempFile;
-    use flate2::{write::DeflateEncoder, Compression};
+    use crate::testing::ObbyTestBuilder;
 
     fn create_test_plugin_json() -> String {
         r#"{
@@ -388,6 +1583,525 @@ mod tests {
         buffer
     }
 
+    /// Builds a minimal unsigned archive where every entry is deflate-compressed
+    fn build_test_obby(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        entries
+            .iter()
+            .fold(ObbyTestBuilder::new(), |builder, (name, data)| builder.entry(name, data))
+            .build()
+    }
+
+    #[test]
+    fn test_extract_compressed_entry() {
+        let json = create_test_plugin_json();
+        let icon = vec![7u8; 1024];
+        let buffer = build_test_obby(&[("plugin.json", json.as_bytes()), ("icon.png", &icon)]);
+
+        let mut archive = ObbyArchive::new(Cursor::new(buffer)).unwrap();
+        assert_eq!(archive.extract_entry("icon.png").unwrap(), icon);
+        assert_eq!(archive.extract_entry("plugin.json").unwrap(), json.as_bytes());
+    }
+
+    #[test]
+    fn test_missing_entry() {
+        let buffer = build_test_obby(&[("plugin.json", b"{}")]);
+        let mut archive = ObbyArchive::new(Cursor::new(buffer)).unwrap();
+        let err = archive.extract_entry("missing.dll").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_extract_plugin_json_from_path() {
+        let json = create_test_plugin_json();
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&build_test_obby(&[("plugin.json", json.as_bytes())])).unwrap();
+
+        assert_eq!(extract_plugin_json(file.path()).unwrap(), json);
+    }
+
+    #[test]
+    fn test_metadata_and_entry_order() {
+        let buffer = build_test_obby(&[("b.dll", b"b"), ("a.json", b"a"), ("c.png", b"c")]);
+        let archive = ObbyArchive::new(Cursor::new(buffer)).unwrap();
+
+        let metadata = archive.metadata();
+        assert_eq!(metadata.api_version, "1.0.0");
+        assert_eq!(metadata.plugin_assembly, "TestPlugin");
+        assert_eq!(metadata.plugin_version, "1.0.0.0");
+        assert_eq!(metadata.signature, None);
+        assert_eq!(archive.list_entries(), vec!["b.dll", "a.json", "c.png"]);
+        assert!(archive.entry_names().eq(["b.dll", "a.json", "c.png"]));
+    }
+
+    #[test]
+    fn test_parse_header() {
+        let buffer = build_test_obby(&[("plugin.json", b"{}"), ("main.js", &[b'a'; 100])]);
+        let mut reader = &buffer[..];
+        let header = parse_header(&mut reader).unwrap();
+        assert_eq!(header.metadata.plugin_assembly, "TestPlugin");
+        assert_eq!(
+            header.entries.iter().map(|e| e.name.as_str()).collect::<Vec<_>>(),
+            vec!["plugin.json", "main.js"]
+        );
+        assert!(header.entries[1].is_compressed());
+        assert_eq!(header.entries[1].offset, header.entries[0].compressed_length);
+
+        // The reader is left at the entry data
+        let data_len = header.entries.iter().map(|e| e.compressed_length).sum::<u64>();
+        assert_eq!(reader.len() as u64, data_len);
+        assert_eq!(header.data_offset + data_len, buffer.len() as u64);
+    }
+
+    #[test]
+    fn test_overlong_string_length_is_rejected() {
+        let mut buffer = b"OBBY".to_vec();
+        buffer.extend_from_slice(&[0xFF; 8]);
+        let err = parse_header(&buffer[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_huge_declared_length_does_not_preallocate() {
+        // A string claiming to be i32::MAX bytes long in a tiny buffer
+        let mut buffer = b"OBBY".to_vec();
+        buffer.extend_from_slice(&[0xFF, 0xFF, 0xFF, 0xFF, 0x07, b'x']);
+        let err = parse_header(&buffer[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_negative_lengths_are_rejected() {
+        let mut buffer = build_test_obby(&[("plugin.json", b"{}")]);
+        // Patch the entry count (right after the assembly and version strings) to -1
+        let count_pos = buffer.windows(7).position(|w| w == b"1.0.0.0").unwrap() + 7;
+        buffer[count_pos..count_pos + 4].copy_from_slice(&(-1i32).to_le_bytes());
+        let err = ObbyArchive::new(Cursor::new(buffer)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_buffered_and_unbuffered_parse_agree() {
+        let json = create_test_plugin_json();
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&build_test_obby(&[("a.dll", b"aaaa"), ("plugin.json", json.as_bytes())])).unwrap();
+
+        for buffer_size in [0, 3, DEFAULT_BUFFER_SIZE] {
+            let mut options = ObbyReadOptions::default();
+            options.set_buffer_size(buffer_size);
+            let mut archive = ObbyArchive::with_options(File::open(file.path()).unwrap(), options).unwrap();
+            assert_eq!(archive.extract_entry("plugin.json").unwrap(), json.as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_entry_bytes_borrows_stored_entries() {
+        let icon = vec![7u8; 1024];
+        let buffer = ObbyTestBuilder::new()
+            .stored_entry("plugin.json", b"{}")
+            .entry("icon.png", &icon)
+            .build();
+        let archive = ObbyArchive::new(Cursor::new(&buffer[..])).unwrap();
+
+        let stored = archive.entry_bytes("plugin.json").unwrap();
+        assert!(matches!(stored, Cow::Borrowed(_)));
+        assert_eq!(&*stored, b"{}");
+
+        let compressed = archive.entry_bytes("icon.png").unwrap();
+        assert!(matches!(compressed, Cow::Owned(_)));
+        assert_eq!(&*compressed, &icon[..]);
+    }
+
+    #[test]
+    fn test_entry_bytes_rejects_truncated_archive() {
+        let mut buffer = ObbyTestBuilder::new().stored_entry("plugin.json", b"{\"id\": 1}").build();
+        buffer.truncate(buffer.len() - 1);
+        let archive = ObbyArchive::new(Cursor::new(buffer)).unwrap();
+        let err = archive.entry_bytes("plugin.json").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_from_header() {
+        for signature in [None, Some(&[7u8; 384][..])] {
+            let mut builder = ObbyTestBuilder::new().entry("plugin.json", b"{}").entry("main.js", &[b'a'; 300]);
+            if let Some(signature) = signature {
+                builder = builder.signature(signature);
+            }
+            let buffer = builder.build();
+            let archive = ObbyArchive::from_slice(&buffer).unwrap();
+            let header = archive.header();
+            assert_eq!(header, parse_header(&buffer[..]).unwrap());
+
+            #[cfg(all(feature = "serde", feature = "json"))]
+            let header: ObbyHeader = serde_json::from_str(&serde_json::to_string(&header).unwrap()).unwrap();
+            let mut reopened = ObbyArchive::from_header(Cursor::new(&buffer), header, ObbyReadOptions::default()).unwrap();
+            assert_eq!(reopened.data_section_pos, archive.data_section_pos);
+            assert_eq!(reopened.list_entries(), vec!["plugin.json", "main.js"]);
+            assert_eq!(reopened.extract_entry("main.js").unwrap(), vec![b'a'; 300]);
+            reopened.verify_hash().unwrap();
+
+            let other = ObbyTestBuilder::new().entry("plugin.json", b"{}").build();
+            assert!(ObbyArchive::from_header(Cursor::new(&other), archive.header(), ObbyReadOptions::default()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_parse_error_context() {
+        let mut buffer = ObbyTestBuilder::new().entry("a.txt", b"a").entry("b.txt", b"b").build();
+        let field_pos = buffer.windows(5).position(|w| w == b"b.txt").unwrap() + 5 + 4;
+        buffer[field_pos..field_pos + 4].copy_from_slice(&(-1i32).to_le_bytes());
+
+        let mut source = Cursor::new([&b"pad"[..], &buffer].concat());
+        source.set_position(3);
+        let err = ObbyArchive::new(source).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        match ObbyError::from_io(&err) {
+            Some(ObbyError::Parse { field, offset, .. }) => {
+                assert_eq!(field, "entry[1].compressed_length");
+                assert_eq!(*offset, 3 + field_pos as u64);
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+
+        let err = ObbyArchive::from_slice(&buffer[..10]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert!(err.to_string().starts_with("Failed to read hash at offset 0x"));
+    }
+
+    #[test]
+    fn test_parse_warnings() {
+        let mut buffer = ObbyTestBuilder::new().stored_entry("cafX.txt", b"x").build();
+        let name_pos = buffer.windows(8).position(|w| w == b"cafX.txt").unwrap();
+        buffer[name_pos + 3] = 0xE9;
+        buffer.extend_from_slice(b"junk");
+
+        let archive = ObbyArchive::from_slice(&buffer).unwrap();
+        assert_eq!(archive.list_entries(), vec!["caf\u{FFFD}.txt"]);
+        assert_eq!(
+            archive.warnings(),
+            &[
+                ParseWarning::InvalidUtf8 { field: "entry name", value: "caf\u{FFFD}.txt".to_string() },
+                ParseWarning::TrailingData { length: 4 },
+            ]
+        );
+        assert!(ObbyArchive::from_slice(&ObbyTestBuilder::new().build()).unwrap().warnings().is_empty());
+
+        let mut options = ObbyReadOptions::default();
+        options.set_strict(true);
+        let err = ObbyArchive::with_options(Cursor::new(&buffer), options).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_trailing_data() {
+        let mut buffer = ObbyTestBuilder::new().entry("plugin.json", b"{}").build();
+        buffer.extend_from_slice(b"junk");
+        let mut archive = ObbyArchive::from_slice(&buffer).unwrap();
+        assert_eq!(archive.warnings(), &[ParseWarning::TrailingData { length: 4 }]);
+        assert_eq!(archive.trailing_data_len(), 4);
+        assert_eq!(archive.trailing_data().unwrap(), b"junk");
+        assert_eq!(archive.extract_entry("plugin.json").unwrap(), b"{}");
+        archive.verify_hash().unwrap();
+
+        let mut rejecting = ObbyReadOptions::default();
+        rejecting.set_reject_trailing_data(true);
+        let err = ObbyArchive::with_options(Cursor::new(&buffer), rejecting.clone()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let mut strict = ObbyReadOptions::default();
+        strict.set_strict(true);
+        assert!(ObbyArchive::with_options(Cursor::new(&buffer), strict.clone()).is_err());
+
+        // A data length counting the trailing bytes is accepted too, but one that's simply wrong is a mismatch
+        let length_pos = buffer.len() - 4 - parse_header(&buffer[..]).unwrap().metadata.data_length as usize - 4;
+        let declared = i32::from_le_bytes(buffer[length_pos..length_pos + 4].try_into().unwrap());
+        buffer[length_pos..length_pos + 4].copy_from_slice(&(declared + 4).to_le_bytes());
+        let archive = ObbyArchive::from_slice(&buffer).unwrap();
+        assert_eq!(archive.trailing_data_len(), 4);
+        assert_eq!(archive.warnings(), &[ParseWarning::TrailingData { length: 4 }]);
+        buffer[length_pos..length_pos + 4].copy_from_slice(&(declared + 2).to_le_bytes());
+        let archive = ObbyArchive::from_slice(&buffer).unwrap();
+        assert!(matches!(archive.warnings()[0], ParseWarning::DataLengthMismatch { .. }));
+        assert!(ObbyArchive::with_options(Cursor::new(&buffer), strict).is_err());
+
+        let empty = ObbyTestBuilder::new().build();
+        assert_eq!(ObbyArchive::from_slice(&empty).unwrap().trailing_data_len(), 0);
+        assert!(ObbyArchive::with_options(Cursor::new(&empty), rejecting).is_ok());
+    }
+
+    /// Builds an archive holding one compressed entry, then overwrites its declared length
+    fn with_declared_length(data: &[u8], length: i32) -> Vec<u8> {
+        let mut buffer = ObbyTestBuilder::new().entry("main.js", data).build();
+        let length_pos = buffer.windows(7).position(|w| w == b"main.js").unwrap() + 7;
+        buffer[length_pos..length_pos + 4].copy_from_slice(&length.to_le_bytes());
+        buffer
+    }
+
+    #[test]
+    fn test_decompression_is_held_to_declared_length() {
+        // Inflates to more than declared
+        let mut archive = ObbyArchive::from_bytes(with_declared_length(&[b'a'; 4096], 100)).unwrap();
+        let err = archive.extract_entry("main.js").unwrap_err();
+        assert!(matches!(ObbyError::from_io(&err), Some(ObbyError::Decompression { .. })));
+        assert!(archive.entry_bytes("main.js").is_err());
+        // Only the first bytes are needed, so the excess isn't noticed
+        assert_eq!(archive.read_entry_range("main.js", 0, 10).unwrap(), vec![b'a'; 10]);
+
+        // Inflates to less than declared
+        let buffer = with_declared_length(&[b'a'; 4096], 5000);
+        assert!(ObbyArchive::from_slice(&buffer).unwrap().extract_entry("main.js").is_err());
+        let mut stream = ObbyStreamReader::new(&buffer[..]).unwrap();
+        assert!(stream.extract_entry("main.js").is_err());
+    }
+
+    #[test]
+    fn test_extract_entry_info() {
+        let buffer = ObbyTestBuilder::new()
+            .entry("main.js", &[b'a'; 4096])
+            .stored_entry("plugin.json", b"{}")
+            .build();
+        let mut archive = ObbyArchive::from_bytes(buffer).unwrap();
+
+        let (data, stats) = archive.extract_entry_info("main.js").unwrap();
+        assert_eq!(data, vec![b'a'; 4096]);
+        assert!(stats.decompressed);
+        assert_eq!(stats.decompressed_size, 4096);
+        assert!(stats.compressed_size < 4096);
+
+        let (_, stats) = archive.extract_entry_info("plugin.json").unwrap();
+        assert!(!stats.decompressed);
+        assert_eq!(stats.compressed_size, stats.decompressed_size);
+        assert!(archive.extract_entry_info("missing").is_err());
+    }
+
+    #[test]
+    fn test_entry_byte_range() {
+        let buffer = ObbyTestBuilder::new()
+            .stored_entry("plugin.json", b"{}")
+            .entry("main.js", &[b'a'; 300])
+            .build();
+        let archive = ObbyArchive::from_slice(&buffer).unwrap();
+
+        let range = archive.entry_byte_range("plugin.json").unwrap();
+        assert_eq!(&buffer[range.start as usize..range.end as usize], b"{}");
+        let range = archive.entry_byte_range("main.js").unwrap();
+        assert_eq!(range.end, buffer.len() as u64);
+        assert_eq!(archive.entry_byte_range("missing").unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_decompress_entry_bytes() {
+        let buffer = ObbyTestBuilder::new()
+            .stored_entry("plugin.json", b"{}")
+            .entry("main.js", &[b'a'; 300])
+            .build();
+        let archive = ObbyArchive::from_slice(&buffer).unwrap();
+
+        for (name, expected) in [("plugin.json", &b"{}"[..]), ("main.js", &[b'a'; 300][..])] {
+            let range = archive.entry_byte_range(name).unwrap();
+            let raw = &buffer[range.start as usize..range.end as usize];
+            assert_eq!(decompress_entry_bytes(raw, expected.len()).unwrap(), expected);
+        }
+
+        let range = archive.entry_byte_range("main.js").unwrap();
+        let raw = &buffer[range.start as usize..range.end as usize];
+        assert_eq!(decompress_entry_bytes(raw, 299).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_max_entry_size() {
+        let buffer = ObbyTestBuilder::new().entry("main.js", &[b'a'; 4096]).build();
+        let mut options = ObbyReadOptions::default();
+        options.set_max_entry_size(Some(4095));
+        let mut archive = ObbyArchive::with_options(Cursor::new(&buffer[..]), options.clone()).unwrap();
+        // A size limit is reported as such on every path, not as corrupt data
+        let err = archive.extract_entry("main.js").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(ObbyError::from_io(&err).is_none(), "{:?}", err);
+        let mut data = Vec::new();
+        let err = archive.get("main.js").unwrap().read_to_end(&mut data).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(ObbyError::from_io(&err).is_none(), "{:?}", err);
+        let err = archive.read_entry_range("main.js", 0, 16).unwrap_err();
+        assert!(ObbyError::from_io(&err).is_none(), "{:?}", err);
+
+        options.set_max_entry_size(Some(4096));
+        let mut archive = ObbyArchive::with_options(Cursor::new(&buffer[..]), options).unwrap();
+        assert_eq!(archive.extract_entry("main.js").unwrap().len(), 4096);
+    }
+
+    #[test]
+    fn test_corrupt_entry_reports_decompression_error() {
+        // Declared as compressed (lengths differ) but the payload isn't valid deflate
+        let mut buffer = ObbyTestBuilder::new().stored_entry("plugin.json", &[0xFF; 8]).build();
+        let length_pos = buffer.windows(11).position(|w| w == b"plugin.json").unwrap() + 11;
+        buffer[length_pos..length_pos + 4].copy_from_slice(&64i32.to_le_bytes());
+
+        let mut archive = ObbyArchive::new(Cursor::new(buffer)).unwrap();
+        let err = archive.extract_entry("plugin.json").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(matches!(
+            ObbyError::from_io(&err),
+            Some(ObbyError::Decompression { entry, .. }) if entry == "plugin.json"
+        ));
+    }
+
+    #[test]
+    fn test_extract_all() {
+        let buffer = build_test_obby(&[("plugin.json", b"{}"), ("assets/logo.png", b"png")]);
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut archive = ObbyArchive::new(Cursor::new(buffer)).unwrap();
+        let written = archive.extract_all(dir.path()).unwrap();
+        assert_eq!(written.len(), 2);
+        assert_eq!(std::fs::read(dir.path().join("assets").join("logo.png")).unwrap(), b"png");
+    }
+
+    #[test]
+    fn test_extract_all_rejects_unsafe_names() {
+        let buffer = build_test_obby(&[("plugin.json", b"{}"), ("../escape.txt", b"gotcha")]);
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("out");
+
+        let mut archive = ObbyArchive::new(Cursor::new(buffer)).unwrap();
+        let err = archive.extract_all(&dest).unwrap_err();
+        assert!(matches!(
+            ObbyError::from_io(&err),
+            Some(ObbyError::UnsafePath { entry }) if entry == "../escape.txt"
+        ));
+        // Nothing is written when any name is rejected
+        assert!(!dest.exists());
+        assert!(!dir.path().join("escape.txt").exists());
+    }
+
+    #[test]
+    fn test_read_entry_range() {
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        let buffer = ObbyTestBuilder::new()
+            .entry("compressed.bin", &data)
+            .stored_entry("stored.bin", &data)
+            .build();
+
+        let mut archive = ObbyArchive::new(Cursor::new(buffer)).unwrap();
+        for name in ["compressed.bin", "stored.bin"] {
+            assert_eq!(archive.read_entry_range(name, 5000, 100).unwrap(), &data[5000..5100]);
+            // Ranges past the end are truncated
+            assert_eq!(archive.read_entry_range(name, 9990, 100).unwrap(), &data[9990..]);
+            assert!(archive.read_entry_range(name, 10_000, 1).unwrap().is_empty());
+            let err = archive.read_entry_range(name, 10_001, 1).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn test_entry_mime() {
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+        let buffer = ObbyTestBuilder::new()
+            .entry("plugin.json", create_test_plugin_json().as_bytes())
+            .stored_entry("icon.png", png)
+            .entry("Plugin.dll", &[b"MZ".as_slice(), &[0u8; 4096]].concat())
+            .build();
+
+        let mut archive = ObbyArchive::new(Cursor::new(buffer)).unwrap();
+        assert_eq!(archive.entry_mime("plugin.json").unwrap(), "application/json");
+        assert_eq!(archive.entry_mime("icon.png").unwrap(), "image/png");
+        assert_eq!(archive.entry_mime("Plugin.dll").unwrap(), "application/vnd.microsoft.portable-executable");
+        assert_eq!(archive.entry_mime("missing").unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_from_bytes_and_slice() {
+        let buffer = build_test_obby(&[("plugin.json", b"{}")]);
+
+        let mut borrowed = ObbyArchive::from_slice(&buffer).unwrap();
+        assert_eq!(borrowed.extract_entry("plugin.json").unwrap(), b"{}");
+
+        let mut owned = ObbyArchive::from_bytes(buffer).unwrap();
+        assert_eq!(owned.extract_entry("plugin.json").unwrap(), b"{}");
+    }
+
+    #[test]
+    fn test_find_manifest() {
+        let buffer = build_test_obby(&[("Plugin.json", b"{}"), ("manifest.json", b"[]")]);
+        let archive = ObbyArchive::from_bytes(buffer).unwrap();
+        assert_eq!(archive.find_manifest().unwrap(), "Plugin.json");
+
+        let buffer = build_test_obby(&[("Plugin.dll", b"MZ"), ("assets/plugin.json", b"{}")]);
+        let archive = ObbyArchive::from_bytes(buffer).unwrap();
+        let err = archive.find_manifest().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert!(matches!(
+            ObbyError::from_io(&err),
+            Some(ObbyError::ManifestNotFound { root_entries }) if root_entries == &["Plugin.dll"]
+        ));
+    }
+
+    #[test]
+    fn test_verify_hash() {
+        let mut buffer = build_test_obby(&[("plugin.json", b"{}")]);
+        ObbyArchive::from_slice(&buffer).unwrap().verify_hash().unwrap();
+        ObbyArchive::from_bytes(load_test_obby_bytes()).unwrap().verify_hash().unwrap();
+
+        *buffer.last_mut().unwrap() ^= 0xFF;
+        let err = ObbyArchive::from_slice(&buffer).unwrap().verify_hash().unwrap_err();
+        assert!(matches!(ObbyError::from_io(&err), Some(ObbyError::HashMismatch { .. })));
+    }
+
+    #[test]
+    fn test_content_digest() {
+        let mut writer = ObbyWriter::new(Vec::new(), "TestPlugin", "1.0.0");
+        writer.add_entry_with_compression("b.js", &[b'b'; 300], Compression::none()).unwrap();
+        writer.add_entry("a.js", &[b'a'; 300]).unwrap();
+        let stored = writer.finish().unwrap();
+
+        let mut writer = ObbyWriter::new(Vec::new(), "Renamed", "2.0.0");
+        writer.set_compression(Compression::best());
+        writer.add_entry("a.js", &[b'a'; 300]).unwrap();
+        writer.add_entry("b.js", &[b'b'; 300]).unwrap();
+        let recompressed = writer.finish().unwrap();
+        assert_ne!(stored.len(), recompressed.len());
+
+        let digest = ObbyArchive::from_bytes(stored).unwrap().content_digest().unwrap();
+        assert_eq!(digest, ObbyArchive::from_bytes(recompressed).unwrap().content_digest().unwrap());
+
+        // Moving a byte between the name and the data must change the digest
+        let shifted = build_test_obby(&[("a.jsa", &[b'a'; 299]), ("b.js", &[b'b'; 300])]);
+        assert_ne!(digest, ObbyArchive::from_bytes(shifted).unwrap().content_digest().unwrap());
+    }
+
+    #[test]
+    fn test_entry_filter() {
+        let buffer = build_test_obby(&[("Plugin.dll", &[1u8; 64]), ("assets/a.png", b"a"), ("plugin.json", b"{}")]);
+
+        let mut options = ObbyReadOptions::default();
+        options.set_entry_filter(|name| name == "plugin.json");
+        let mut archive = ObbyArchive::with_options(Cursor::new(&buffer[..]), options).unwrap();
+        assert_eq!(archive.list_entries(), vec!["plugin.json"]);
+        // Offsets of later entries still account for the skipped ones
+        assert_eq!(archive.extract_entry("plugin.json").unwrap(), b"{}");
+        assert_eq!(archive.extract_entry("Plugin.dll").unwrap_err().kind(), io::ErrorKind::NotFound);
+
+        let mut options = ObbyReadOptions::default();
+        options.set_entry_prefix("assets/");
+        let archive = ObbyArchive::with_options(Cursor::new(&buffer[..]), options).unwrap();
+        assert_eq!(archive.list_entries(), vec!["assets/a.png"]);
+    }
+
+    #[test]
+    fn test_manifest_report() {
+        let buffer = build_test_obby(&[("plugin.json", b"{}"), ("empty", b"")]);
+        let report = ObbyArchive::from_bytes(buffer).unwrap().manifest_report().unwrap();
+        assert_eq!(report.plugin_assembly, "TestPlugin");
+        assert!(!report.signed);
+        assert_eq!(report.hash.len(), HASH_LEN * 2);
+        assert_eq!(report.entries.len(), 2);
+        assert_eq!(report.entries[0].name, "plugin.json");
+        assert_eq!(report.entries[0].size, 2);
+        assert_eq!(
+            report.entries[1].sha256,
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
     #[test]
     fn test_memory_buffer() {
         let buffer = load_test_obby_bytes();
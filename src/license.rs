@@ -0,0 +1,269 @@
+//! Detecting the licenses a plugin ships under
+
+use std::io::{self, Read, Seek};
+
+use crate::{mime, tree, ObbyArchive};
+
+/// File names (without extension, ignoring case) that hold license texts
+const LICENSE_STEMS: &[&str] = &["license", "licence", "copying", "unlicense"];
+
+/// File names (without extension, ignoring case) that hold attribution notices
+const NOTICE_STEMS: &[&str] = &["notice", "third-party-notices", "thirdpartynotices", "third_party_notices"];
+
+/// How much of an entry is searched for an `SPDX-License-Identifier` header
+const HEADER_SCAN_LEN: u64 = 4096;
+
+/// How much of a license file is read to identify the license
+const LICENSE_SCAN_LEN: u64 = 64 * 1024;
+
+const SPDX_TAG: &str = "SPDX-License-Identifier:";
+
+/// Phrases that identify well-known license texts, checked in order; every phrase of
+/// an entry must appear
+const KNOWN_TEXTS: &[(&str, &[&str])] = &[
+    ("AGPL-3.0", &["GNU AFFERO GENERAL PUBLIC LICENSE", "Version 3"]),
+    ("LGPL-3.0", &["GNU LESSER GENERAL PUBLIC LICENSE", "Version 3"]),
+    ("LGPL-2.1", &["GNU LESSER GENERAL PUBLIC LICENSE", "Version 2.1"]),
+    ("GPL-3.0", &["GNU GENERAL PUBLIC LICENSE", "Version 3"]),
+    ("GPL-2.0", &["GNU GENERAL PUBLIC LICENSE", "Version 2"]),
+    ("MPL-2.0", &["Mozilla Public License", "2.0"]),
+    ("Apache-2.0", &["Apache License", "Version 2.0"]),
+    ("Unlicense", &["This is free and unencumbered software released into the public domain"]),
+    ("ISC", &["Permission to use, copy, modify, and/or distribute this software for any purpose"]),
+    ("MIT", &["Permission is hereby granted, free of charge"]),
+    ("BSD-3-Clause", &["Redistribution and use in source and binary forms", "Neither the name"]),
+    ("BSD-2-Clause", &["Redistribution and use in source and binary forms"]),
+    ("CC0-1.0", &["CC0 1.0 Universal"]),
+];
+
+/// What a file found by [`ObbyArchive::detect_licenses`] contains
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum LicenseFileKind {
+    /// A license text, such as `LICENSE` or `COPYING`
+    License,
+    /// Attribution notices, such as `NOTICE` or `THIRD-PARTY-NOTICES.txt`
+    Notice,
+}
+
+/// A license or notice file in the archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LicenseFile {
+    /// Name of the entry
+    pub entry: String,
+    /// Whether it is a license text or notices
+    pub kind: LicenseFileKind,
+    /// SPDX identifier of the license, if the text was recognised
+    pub spdx_id: Option<String>,
+}
+
+/// An `SPDX-License-Identifier` header found in a text entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SpdxHeader {
+    /// Name of the entry
+    pub entry: String,
+    /// The SPDX license expression, such as `MIT OR Apache-2.0`
+    pub expression: String,
+}
+
+/// Licensing information found in an archive; returned by [`ObbyArchive::detect_licenses`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LicenseReport {
+    /// The `license` field of the plugin manifest, if it has one; always `None` without the `json` feature
+    pub manifest_license: Option<String>,
+    /// License and notice files, in archive order
+    pub files: Vec<LicenseFile>,
+    /// SPDX headers of text entries, in archive order
+    pub spdx_headers: Vec<SpdxHeader>,
+}
+
+impl LicenseReport {
+    /// Returns whether any license was declared or found
+    ///
+    /// Notice files alone don't count, since they attribute third-party code rather than
+    /// license the plugin.
+    pub fn has_license(&self) -> bool {
+        self.manifest_license.is_some()
+            || !self.spdx_headers.is_empty()
+            || self.files.iter().any(|file| file.kind == LicenseFileKind::License)
+    }
+
+    /// Returns every distinct license identifier or expression found, in the order
+    /// manifest, license files, SPDX headers
+    pub fn licenses(&self) -> Vec<&str> {
+        let mut licenses: Vec<&str> = Vec::new();
+        let found = self
+            .manifest_license
+            .iter()
+            .chain(self.files.iter().filter_map(|file| file.spdx_id.as_ref()))
+            .chain(self.spdx_headers.iter().map(|header| &header.expression));
+        for license in found {
+            if !licenses.contains(&license.as_str()) {
+                licenses.push(license);
+            }
+        }
+        licenses
+    }
+}
+
+impl<R: Read + Seek> ObbyArchive<R> {
+    /// Collects the licensing information in the archive
+    ///
+    /// This looks at the manifest's `license` field, at `LICENSE`, `COPYING`, `NOTICE`
+    /// and similar files anywhere in the archive, whose texts are matched against
+    /// well-known licenses, and at `SPDX-License-Identifier` headers near the start of
+    /// text entries. Detection is heuristic: an unrecognised license file is reported
+    /// without an identifier, so registries can still tell it apart from a missing one.
+    ///
+    /// # Returns
+    ///
+    /// The [`LicenseReport`], or an `io::Error` if an entry can't be read.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut archive = obsidian_lib::open("plugin.obby")?;
+    /// let report = archive.detect_licenses()?;
+    /// if !report.has_license() {
+    ///     eprintln!("warning: plugin has no license");
+    /// }
+    /// println!("{}", report.licenses().join(", "));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn detect_licenses(&mut self) -> io::Result<LicenseReport> {
+        let mut report = LicenseReport {
+            manifest_license: self.manifest_license(),
+            ..LicenseReport::default()
+        };
+
+        for name in self.order.clone() {
+            let kind = license_file_kind(&name);
+            let scan_len = if kind.is_some() { LICENSE_SCAN_LEN } else { HEADER_SCAN_LEN };
+            let prefix = self.entry_reader(&name).and_then(|reader| {
+                let mut prefix = Vec::new();
+                reader.take(scan_len).read_to_end(&mut prefix)?;
+                Ok(prefix)
+            });
+            let prefix = self.decompression_error(&name, prefix)?;
+            if !mime::is_text(&prefix[..prefix.len().min(mime::MIME_SNIFF_LEN)]) {
+                continue;
+            }
+            let text = String::from_utf8_lossy(&prefix);
+
+            let expression = spdx_expression(&text);
+            if let Some(kind) = kind {
+                let spdx_id = expression.clone().or_else(|| identify(&text).map(str::to_string));
+                report.files.push(LicenseFile { entry: name.clone(), kind, spdx_id });
+            } else if let Some(expression) = expression {
+                report.spdx_headers.push(SpdxHeader { entry: name, expression });
+            }
+        }
+        Ok(report)
+    }
+
+    /// Reads the `license` field of the plugin manifest
+    #[cfg(feature = "json")]
+    fn manifest_license(&mut self) -> Option<String> {
+        let manifest = self.plugin_manifest().ok()?;
+        manifest.extra.get("license")?.as_str().map(str::to_string)
+    }
+
+    /// Reading the manifest needs the `json` feature
+    #[cfg(not(feature = "json"))]
+    fn manifest_license(&mut self) -> Option<String> {
+        None
+    }
+}
+
+/// Tells license and notice files apart from other entries by their file name
+fn license_file_kind(name: &str) -> Option<LicenseFileKind> {
+    let file_name = tree::components(name).last()?.to_ascii_lowercase();
+    let stem = match file_name.split_once('.') {
+        // `LICENSE.md`, but also `LICENSE.MIT` or `COPYING.LESSER`
+        Some((stem, _)) if !stem.is_empty() => stem,
+        _ => file_name.as_str(),
+    };
+    let stem = stem.trim_end_matches(|c: char| c == '-' || c == '_' || c.is_ascii_digit());
+    if LICENSE_STEMS.contains(&stem) || LICENSE_STEMS.iter().any(|license| stem.starts_with(&format!("{}-", license))) {
+        Some(LicenseFileKind::License)
+    } else if NOTICE_STEMS.contains(&stem) {
+        Some(LicenseFileKind::Notice)
+    } else {
+        None
+    }
+}
+
+/// Returns the expression of the first `SPDX-License-Identifier` line in `text`
+fn spdx_expression(text: &str) -> Option<String> {
+    text.lines().find_map(|line| {
+        let (_, rest) = line.split_once(SPDX_TAG)?;
+        // Strip the closing of block comments, as in `/* SPDX-License-Identifier: MIT */`
+        let expression = rest.trim().trim_end_matches("*/").trim_end_matches("-->").trim();
+        (!expression.is_empty()).then(|| expression.to_string())
+    })
+}
+
+/// Matches a license text against [`KNOWN_TEXTS`]
+fn identify(text: &str) -> Option<&'static str> {
+    // License texts are often rewrapped, so compare with normalized whitespace
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    KNOWN_TEXTS
+        .iter()
+        .find(|(_, phrases)| phrases.iter().all(|phrase| text.contains(phrase)))
+        .map(|(id, _)| *id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+
+    #[test]
+    fn test_detect_licenses() {
+        let buffer = ObbyTestBuilder::new()
+            .entry("plugin.json", br#"{"id": "plugin", "license": "MIT"}"#)
+            .entry("LICENSE", b"MIT License\n\nPermission is hereby granted,\nfree of charge, to any person")
+            .entry("lib/COPYING.LESSER", b"GNU LESSER GENERAL PUBLIC LICENSE\n Version 3, 29 June 2007")
+            .entry("THIRD-PARTY-NOTICES.txt", b"This plugin includes code from...")
+            .entry("LICENSE.custom", b"All rights reserved.")
+            .entry("main.js", b"// SPDX-License-Identifier: MIT OR Apache-2.0\nconsole.log(1)")
+            .entry("style.css", b"/* SPDX-License-Identifier: CC0-1.0 */")
+            .entry("licenses.js", b"const licenses = []")
+            .entry("icon.png", b"\x89PNG\r\n\x1a\nSPDX-License-Identifier: MIT")
+            .build();
+        let report = ObbyArchive::from_bytes(buffer).unwrap().detect_licenses().unwrap();
+        assert!(report.has_license());
+        #[cfg(feature = "json")]
+        assert_eq!(report.manifest_license.as_deref(), Some("MIT"));
+        let files: Vec<_> = report.files.iter().map(|file| (file.entry.as_str(), file.kind, file.spdx_id.as_deref())).collect();
+        assert_eq!(
+            files,
+            vec![
+                ("LICENSE", LicenseFileKind::License, Some("MIT")),
+                ("lib/COPYING.LESSER", LicenseFileKind::License, Some("LGPL-3.0")),
+                ("THIRD-PARTY-NOTICES.txt", LicenseFileKind::Notice, None),
+                ("LICENSE.custom", LicenseFileKind::License, None),
+            ]
+        );
+        let headers: Vec<_> = report.spdx_headers.iter().map(|header| (header.entry.as_str(), header.expression.as_str())).collect();
+        assert_eq!(headers, vec![("main.js", "MIT OR Apache-2.0"), ("style.css", "CC0-1.0")]);
+        assert_eq!(report.licenses(), vec!["MIT", "LGPL-3.0", "MIT OR Apache-2.0", "CC0-1.0"]);
+    }
+
+    #[test]
+    fn test_missing_license() {
+        let buffer = ObbyTestBuilder::new()
+            .entry("plugin.json", br#"{"id": "plugin"}"#)
+            .entry("NOTICE", b"Portions copyright others")
+            .build();
+        let report = ObbyArchive::from_bytes(buffer).unwrap().detect_licenses().unwrap();
+        assert!(!report.has_license());
+        assert!(report.licenses().is_empty());
+    }
+}
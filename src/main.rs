@@ -1,21 +1,426 @@
 use std::io::SeekFrom;
 use std::io::Seek;
-use obsidian_lib::ObbyArchive;
+use obsidian_lib::{Compression, ExtractOptions, ObbyArchive, ObbyReadOptions, ObbyWriter, OverwritePolicy, SanitizePolicy};
 use std::fs::File;
-use std::io::{self, Read, Cursor};
-use std::env;
+use std::io::{self, Read, Write, Cursor};
+use std::path::{Path, PathBuf};
+use clap::{CommandFactory, Parser};
+use std::process::ExitCode;
+use rsa::RsaPublicKey;
 
-fn main() -> io::Result<()> {
-    // Parse command-line arguments
-    let args: Vec<String> = env::args().collect();
+mod batch;
+mod cli;
+mod exit;
+// Shared with the library, which doesn't export it
+#[path = "parallel.rs"]
+mod parallel;
+mod scaffold;
+#[cfg(feature = "tui")]
+mod browse;
+#[cfg(all(feature = "fuse", unix))]
+mod mount;
 
-    if args.len() != 2 {
-        eprintln!("Usage: {} <file_path>", args[0]);
-        return Ok(());
+use cli::{Cli, Command, ErrorFormat, OnExisting};
+use exit::Failure;
+
+fn main() -> ExitCode {
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        // Help and version requests
+        Err(e) if !e.use_stderr() => e.exit(),
+        Err(e) => {
+            // Parsing failed, so look for the flag by hand
+            let args: Vec<String> = std::env::args().collect();
+            let json = args.windows(2).any(|pair| pair[0] == "--error-format" && pair[1] == "json")
+                || args.iter().any(|arg| arg == "--error-format=json");
+            if !json {
+                let _ = e.print();
+                return ExitCode::from(Failure::Usage.code());
+            }
+            // The first paragraph of clap's message, without the usage hints after it
+            let rendered = e.render().to_string();
+            let message = rendered
+                .lines()
+                .take_while(|line| !line.is_empty())
+                .map(str::trim)
+                .collect::<Vec<_>>()
+                .join(" ");
+            return exit::report(Failure::Usage, message.trim_start_matches("error: "), ErrorFormat::Json);
+        }
+    };
+
+    let error_format = cli.error_format;
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => exit::report(Failure::of(&e), &e.to_string(), error_format),
+    }
+}
+
+fn run(cli: Cli) -> io::Result<()> {
+    match cli.command {
+        Some(Command::Cat { file, entry }) => cat(&file, &entry),
+        Some(Command::List { file, json, csv }) => list(&file, json, csv),
+        Some(Command::Diff { old, new, json }) => diff(&old, &new, json),
+        Some(Command::Dedupe { files, json }) => dedupe(files, json),
+        Some(Command::Index { dir, output }) => index(&dir, output.as_deref()),
+        #[cfg(feature = "registry")]
+        Some(Command::Sync { from, to, key, headers, proxy, json }) => {
+            sync(&from, &to, key.as_deref(), &headers, proxy, json)
+        }
+        Some(Command::Explain { file }) => explain(&file),
+        Some(Command::Batch { jobs }) => batch::batch(&jobs),
+        #[cfg(feature = "tui")]
+        Some(Command::Browse { file }) => browse::browse(&file),
+        #[cfg(all(feature = "fuse", unix))]
+        Some(Command::Mount { file, mountpoint }) => mount::mount(&file, &mountpoint),
+        Some(Command::Extract { file, out, allow_dotfiles, overwrite, exclude }) => {
+            extract(&file, &out, allow_dotfiles, overwrite, exclude)
+        }
+        Some(Command::VerifySig { file, key }) => verify_sig(&file, &key),
+        Some(Command::Create { output, dir, assembly, version, api_version, level, key }) => {
+            create(&output, &dir, &assembly, &version, &api_version, level, key.as_deref())
+        }
+        Some(Command::New { id, dir, template, name, author }) => {
+            let dir = dir.unwrap_or_else(|| PathBuf::from(&id));
+            scaffold::new_plugin(&id, &dir, template, name.as_deref(), author)
+        }
+        Some(Command::Completions { shell }) => {
+            let mut script = Vec::new();
+            clap_complete::generate(shell, &mut Cli::command(), "obby", &mut script);
+            write_stdout(&script)
+        }
+        Some(Command::Repack { input, output, level, key }) => repack(&input, &output, level, key.as_deref()),
+        None => match cli.file {
+            Some(path) => run_examples(&path),
+            None => {
+                eprintln!("Usage: obby <file_path> | obby <COMMAND>");
+                Ok(())
+            }
+        },
+    }
+}
+
+/// Writes a single entry to stdout without any decoration, so it can be piped
+fn cat(path: &Path, entry: &str) -> io::Result<()> {
+    let mut archive = obsidian_lib::open(path)?;
+    write_stdout(&archive.extract_entry(entry)?)
+}
+
+/// Writes `data` to stdout, treating a closed pipe as success
+fn write_stdout(data: &[u8]) -> io::Result<()> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    match out.write_all(data).and_then(|_| out.flush()) {
+        // A closed pipe (e.g. `obby cat ... | head`) is not an error for us
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => result,
+    }
+}
+
+/// Prints the entries of `path` as a table, a JSON report or CSV
+fn list(path: &Path, json: bool, csv: bool) -> io::Result<()> {
+    let report = obsidian_lib::open(path)?.manifest_report()?;
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    if json {
+        serde_json::to_writer_pretty(&mut out, &report)?;
+        return writeln!(out);
+    }
+    if csv {
+        return report.write_csv(&mut out);
+    }
+
+    writeln!(out, "{:>10} {:>10}  name", "size", "stored")?;
+    for entry in &report.entries {
+        writeln!(out, "{:>10} {:>10}  {}", entry.size, entry.compressed_size, entry.name)?;
+    }
+    Ok(())
+}
+
+/// Prints the field-by-field layout of `file`
+fn explain(file: &Path) -> io::Result<()> {
+    let mut layout = obsidian_lib::format::describe(io::BufReader::new(File::open(file)?));
+    let error = layout.error.take();
+    print!("{}", layout);
+    error.map_or(Ok(()), Err)
+}
+
+/// Prints the entry changes from `old` to `new`
+fn diff(old: &Path, new: &Path, json: bool) -> io::Result<()> {
+    let diff = obsidian_lib::diff_archives(&mut obsidian_lib::open(old)?, &mut obsidian_lib::open(new)?)?;
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    if json {
+        serde_json::to_writer_pretty(&mut out, &diff)?;
+        return writeln!(out);
+    }
+
+    if diff.version_changed() {
+        writeln!(out, "version: {} -> {}", diff.old_version, diff.new_version)?;
+    }
+    for entry in &diff.added {
+        writeln!(out, "+ {} ({} bytes)", entry.name, entry.size_delta())?;
+    }
+    for entry in &diff.removed {
+        writeln!(out, "- {} ({} bytes)", entry.name, -entry.size_delta())?;
+    }
+    for entry in &diff.modified {
+        writeln!(
+            out,
+            "M {} ({} -> {} bytes, {:+})",
+            entry.name,
+            entry.old_size.unwrap_or(0),
+            entry.new_size.unwrap_or(0),
+            entry.size_delta()
+        )?;
+    }
+    if diff.is_empty() {
+        writeln!(out, "No entry changes")?;
+    }
+    Ok(())
+}
+
+/// Reports payloads shared between the archives in `files`
+fn dedupe(files: Vec<PathBuf>, json: bool) -> io::Result<()> {
+    let report = obsidian_lib::dedupe(files)?;
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    if json {
+        serde_json::to_writer_pretty(&mut out, &report)?;
+        return writeln!(out);
+    }
+
+    for group in &report.duplicates {
+        writeln!(out, "{} bytes x{} ({})", group.size, group.occurrences.len(), &group.sha256[..16])?;
+        for occurrence in &group.occurrences {
+            writeln!(out, "  {}: {}", occurrence.archive, occurrence.entry)?;
+        }
+    }
+    writeln!(
+        out,
+        "{} entries in {} archives, {} distinct payloads; deduplication would save {} of {} bytes ({:.1}%)",
+        report.entries,
+        report.archives,
+        report.unique_payloads,
+        report.savings(),
+        report.total_size,
+        report.savings_ratio() * 100.0
+    )
+}
+
+/// Writes the repository index of `dir` to `output`, or to stdout
+fn index(dir: &Path, output: Option<&Path>) -> io::Result<()> {
+    let index = obsidian_lib::index::RepositoryIndex::build(dir)?;
+    let mut json = serde_json::to_vec_pretty(&index.to_json())?;
+    json.push(b'\n');
+    match output {
+        Some(output) => {
+            let mut out = obsidian_lib::OutputFile::new(output);
+            out.write_all(&json)?;
+            out.flush()?;
+            let versions: usize = index.plugins.iter().map(|plugin| plugin.versions.len()).sum();
+            eprintln!("Indexed {} plugins ({} versions) into {}", index.plugins.len(), versions, output.display());
+            Ok(())
+        }
+        None => write_stdout(&json),
+    }
+}
+
+/// Mirrors the repository whose index is at `from` into `to`
+#[cfg(feature = "registry")]
+fn sync(from: &str, to: &Path, key: Option<&Path>, headers: &[String], proxy: Option<String>, json: bool) -> io::Result<()> {
+    let mut options = obsidian_lib::RemoteOptions::default();
+    for header in headers {
+        let (name, value) = header.split_once(':').ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("Invalid header {:?}, expected `Name: value`", header))
+        })?;
+        options.add_header(name.trim(), value.trim());
+    }
+    if let Some(proxy) = proxy {
+        options.set_proxy(match proxy.as_str() {
+            "system" => obsidian_lib::RemoteProxy::System,
+            _ => obsidian_lib::RemoteProxy::Url(proxy),
+        });
+    }
+    let mut registry = obsidian_lib::Registry::from_index_url_with_options(from, options)?;
+    if let Some(key) = key {
+        registry = registry.with_trusted_keys(load_keys(key)?.0);
+    }
+    let report = registry.sync_to(to)?;
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    if json {
+        serde_json::to_writer_pretty(&mut out, &report)?;
+        writeln!(out)?;
+    } else {
+        for synced in &report.downloaded {
+            writeln!(out, "downloaded {} {}", synced.id, synced.version)?;
+        }
+        for failure in &report.failed {
+            writeln!(out, "failed     {} {}: {}", failure.id, failure.version, failure.error)?;
+        }
+        writeln!(
+            out,
+            "{} downloaded, {} unchanged, {} failed; index written to {}",
+            report.downloaded.len(),
+            report.unchanged.len(),
+            report.failed.len(),
+            report.index.display()
+        )?;
+    }
+    if report.failed.is_empty() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("{} plugin versions couldn't be mirrored", report.failed.len())))
+    }
+}
+
+/// Extracts all entries of `path` into `out`, rejecting unsafe entry names
+fn extract(path: &Path, out: &Path, allow_dotfiles: bool, overwrite: OnExisting, exclude: Vec<String>) -> io::Result<()> {
+    let mut options = ObbyReadOptions::default();
+    if allow_dotfiles {
+        options.set_sanitize_policy(SanitizePolicy::AllowDotfiles);
+    }
+    let mut extract_options = ExtractOptions::default();
+    extract_options.set_overwrite_policy(match overwrite {
+        OnExisting::Error => OverwritePolicy::Error,
+        OnExisting::Skip => OverwritePolicy::Skip,
+        OnExisting::Overwrite => OverwritePolicy::Overwrite,
+        OnExisting::Rename => OverwritePolicy::RenameWithSuffix,
+    });
+    for name in exclude {
+        extract_options.exclude(name);
+    }
+    let mut archive = ObbyArchive::with_options(File::open(path)?, options)?;
+    let written = archive.extract_all_with_options(out, &extract_options)?;
+
+    for file in &written {
+        println!("{}", file.display());
+    }
+    Ok(())
+}
+
+/// Verifies the signature of `path` against the key or keyring directory at `key_path`
+fn verify_sig(path: &Path, key_path: &Path) -> io::Result<()> {
+    let (keys, loaded_files) = load_keys(key_path)?;
+
+    let mut archive = obsidian_lib::open(path)?;
+    let index = obsidian_lib::signing::verify_signature(&mut archive, &keys)?;
+    let metadata = archive.metadata();
+    println!("Signature OK: {}", path.display());
+    println!("  Plugin:      {} {}", metadata.plugin_assembly, metadata.plugin_version);
+    println!("  API version: {}", metadata.api_version);
+    println!("  Signed by:   {}", loaded_files[index].display());
+    println!("  Fingerprint: {}", obsidian_lib::signing::key_fingerprint(&keys[index])?);
+    Ok(())
+}
+
+/// Loads the public key at `key_path`, or every `.pem` key in it if it is a directory
+///
+/// Returns the keys along with the files they were loaded from. Unreadable keys in a
+/// directory are skipped with a warning.
+fn load_keys(key_path: &Path) -> io::Result<(Vec<RsaPublicKey>, Vec<PathBuf>)> {
+    let key_files = if key_path.is_dir() {
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(key_path)? {
+            let file = entry?.path();
+            if file.extension().is_some_and(|ext| ext == "pem") {
+                files.push(file);
+            }
+        }
+        files.sort();
+        files
+    } else {
+        vec![key_path.to_path_buf()]
+    };
+
+    let mut keys = Vec::new();
+    let mut loaded_files = Vec::new();
+    for file in key_files {
+        match obsidian_lib::signing::load_public_key(&file) {
+            Ok(key) => {
+                keys.push(key);
+                loaded_files.push(file);
+            }
+            Err(e) => eprintln!("warning: skipping {}: {}", file.display(), e),
+        }
+    }
+    if keys.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("No usable public keys in {}", key_path.display()),
+        ));
+    }
+    Ok((keys, loaded_files))
+}
+
+/// Packages `dir` into a new archive at `output`
+fn create(
+    output: &Path,
+    dir: &Path,
+    assembly: &str,
+    version: &str,
+    api_version: &str,
+    level: u32,
+    key: Option<&Path>,
+) -> io::Result<()> {
+    let mut writer = ObbyWriter::create(output, assembly, version);
+    writer.set_api_version(api_version);
+    writer.set_compression(Compression::new(level));
+    if let Some(key) = key {
+        writer.set_signing_key(obsidian_lib::signing::load_private_key(key)?)?;
     }
+    writer.add_dir(dir)?;
+    writer.finish()?;
 
-    let path = &args[1];
+    println!("Created {}", output.display());
+    Ok(())
+}
+
+/// Rewrites `input` to `output` with every entry recompressed at `level`
+///
+/// Without a `level`, entries are copied as stored. Header metadata and entries' extra
+/// fields are carried over. The hash is recomputed, so an existing signature can't be
+/// kept; pass `key` to sign the new archive.
+fn repack(input: &Path, output: &Path, level: Option<u32>, key: Option<&Path>) -> io::Result<()> {
+    let mut archive = obsidian_lib::open(input)?;
+    let metadata = archive.metadata().clone();
+    if metadata.signature.is_some() && key.is_none() {
+        eprintln!("warning: {} is signed; the repacked archive will be unsigned", input.display());
+    }
+
+    let names = archive.list_entries();
+    let mut writer = ObbyWriter::create(output, &metadata.plugin_assembly, &metadata.plugin_version);
+    writer.set_api_version(&metadata.api_version);
+    writer.set_write_extras(names.iter().any(|name| archive.entry_extra(name).is_some()));
+    if let Some(key) = key {
+        writer.set_signing_key(obsidian_lib::signing::load_private_key(key)?)?;
+    }
+    for name in &names {
+        match level {
+            Some(level) => {
+                let data = archive.extract_entry(name)?;
+                writer.add_entry_with_compression(name, &data, Compression::new(level))?;
+                if let Some(extra) = archive.entry_extra(name) {
+                    writer.set_entry_extra(name, extra.clone())?;
+                }
+            }
+            None => writer.copy_entry_from(&mut archive, name)?,
+        }
+    }
+    writer.finish()?;
+
+    let before = std::fs::metadata(input)?.len();
+    let after = std::fs::metadata(output)?.len();
+    println!("Repacked {} -> {} ({} -> {} bytes)", input.display(), output.display(), before, after);
+    Ok(())
+}
 
+fn run_examples(path: &Path) -> io::Result<()> {
     // Example 1: Reading into memory buffer
     println!("Example 1: Memory Buffer");
     {
@@ -84,4 +489,4 @@ fn main() -> io::Result<()> {
     }
 
     Ok(())
-}
\ No newline at end of file
+}
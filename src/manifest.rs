@@ -0,0 +1,314 @@
+//! The plugin manifest stored in `plugin.json`
+
+use std::io::{self, Read, Seek};
+use std::path::PathBuf;
+use std::thread;
+
+use serde_json::{Map, Value};
+
+use crate::parallel::in_parallel;
+use crate::ObbyArchive;
+
+/// A parsed plugin manifest, returned by [`ObbyArchive::plugin_manifest`]
+///
+/// The well-known fields are parsed into their own members, all optional since
+/// hand-written manifests often leave some out. Every other field is kept in `extra`,
+/// so [`PluginManifest::to_json`] writes back everything that was read.
+///
+/// With the `serde` feature enabled the manifest implements `Serialize`, producing the
+/// same JSON as [`PluginManifest::to_value`].
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// let mut archive = obsidian_lib::open("plugin.obby")?;
+/// let manifest = archive.plugin_manifest()?;
+/// println!("{} {}", manifest.id.as_deref().unwrap_or("?"), manifest.version.as_deref().unwrap_or("?"));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(rename_all = "camelCase"))]
+pub struct PluginManifest {
+    /// The plugin's unique ID (`id`)
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub id: Option<String>,
+    /// The display name (`name`)
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub name: Option<String>,
+    /// The plugin's version (`version`)
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub version: Option<String>,
+    /// The plugin's authors (`authors`); a single string is read as one author
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub authors: Vec<String>,
+    /// A short description (`description`)
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub description: Option<String>,
+    /// The project's home page (`projectUrl`)
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub project_url: Option<String>,
+    /// All other fields, in the order they were read
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub extra: Map<String, Value>,
+}
+
+impl PluginManifest {
+    /// Parses a manifest from JSON
+    ///
+    /// # Arguments
+    ///
+    /// * `json` - The contents of `plugin.json`.
+    ///
+    /// # Returns
+    ///
+    /// The `PluginManifest`, or an `io::Error` of kind `InvalidData` if `json` isn't a
+    /// JSON object or a well-known field has the wrong type.
+    pub fn from_json(json: &[u8]) -> io::Result<PluginManifest> {
+        let value: Value = serde_json::from_slice(json).map_err(|e| invalid(format!("Invalid manifest JSON: {}", e)))?;
+        PluginManifest::from_value(value)
+    }
+
+    /// Builds a manifest from an already parsed JSON value
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The manifest, which must be a JSON object.
+    pub fn from_value(value: Value) -> io::Result<PluginManifest> {
+        let Value::Object(mut extra) = value else {
+            return Err(invalid("Manifest is not a JSON object".to_string()));
+        };
+        let mut take_string = |field: &str| match extra.remove(field) {
+            None | Some(Value::Null) => Ok(None),
+            Some(Value::String(value)) => Ok(Some(value)),
+            Some(other) => Err(invalid(format!("Manifest field '{}' should be a string, found {}", field, other))),
+        };
+        let id = take_string("id")?;
+        let name = take_string("name")?;
+        let version = take_string("version")?;
+        let description = take_string("description")?;
+        let project_url = take_string("projectUrl")?;
+        let authors = match extra.remove("authors") {
+            None | Some(Value::Null) => Vec::new(),
+            Some(Value::String(author)) => vec![author],
+            Some(Value::Array(authors)) => authors
+                .into_iter()
+                .map(|author| match author {
+                    Value::String(author) => Ok(author),
+                    other => Err(invalid(format!("Manifest author should be a string, found {}", other))),
+                })
+                .collect::<io::Result<_>>()?,
+            Some(other) => return Err(invalid(format!("Manifest field 'authors' should be a list, found {}", other))),
+        };
+        Ok(PluginManifest { id, name, version, authors, description, project_url, extra })
+    }
+
+    /// Converts the manifest back to a JSON value
+    ///
+    /// Well-known fields come first, followed by `extra`; unset fields are left out.
+    pub fn to_value(&self) -> Value {
+        let mut object = Map::new();
+        let mut put = |field: &str, value: Option<Value>| {
+            if let Some(value) = value {
+                object.insert(field.to_string(), value);
+            }
+        };
+        put("id", self.id.clone().map(Value::String));
+        put("name", self.name.clone().map(Value::String));
+        put("version", self.version.clone().map(Value::String));
+        put(
+            "authors",
+            (!self.authors.is_empty()).then(|| self.authors.iter().cloned().map(Value::String).collect()),
+        );
+        put("description", self.description.clone().map(Value::String));
+        put("projectUrl", self.project_url.clone().map(Value::String));
+        object.extend(self.extra.iter().map(|(key, value)| (key.clone(), value.clone())));
+        Value::Object(object)
+    }
+
+    /// Serializes the manifest as pretty-printed JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.to_value()).expect("JSON values always serialize")
+    }
+
+    /// Sets the plugin's version (`version`)
+    pub fn set_version(&mut self, version: &str) {
+        self.version = Some(version.to_string());
+    }
+
+    /// Sets a field by its JSON name, well-known or not
+    ///
+    /// Well-known fields such as `id` or `projectUrl` are checked and stored in their
+    /// members, everything else goes to `extra`. Setting a field to `null` clears it.
+    ///
+    /// # Arguments
+    ///
+    /// * `field` - The field's name in `plugin.json`.
+    /// * `value` - The new value.
+    ///
+    /// # Returns
+    ///
+    /// An `io::Error` of kind `InvalidData` if a well-known field gets a value of the
+    /// wrong type, in which case the manifest is left unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use obsidian_lib::manifest::PluginManifest;
+    ///
+    /// let mut manifest = PluginManifest::from_json(br#"{"id": "my-plugin"}"#).unwrap();
+    /// manifest.set_field("projectUrl", "https://example.com").unwrap();
+    /// manifest.set_field("minApi", 3).unwrap();
+    /// assert_eq!(manifest.project_url.as_deref(), Some("https://example.com"));
+    /// assert!(manifest.set_field("id", 3).is_err());
+    /// ```
+    pub fn set_field(&mut self, field: &str, value: impl Into<Value>) -> io::Result<()> {
+        let Value::Object(mut object) = self.to_value() else {
+            unreachable!("manifests serialize to objects")
+        };
+        object.insert(field.to_string(), value.into());
+        *self = PluginManifest::from_value(Value::Object(object))?;
+        Ok(())
+    }
+
+    /// Removes a field by its JSON name, returning its previous value
+    pub fn remove_field(&mut self, field: &str) -> Option<Value> {
+        let value = self.to_value().get(field).cloned();
+        match field {
+            "id" => self.id = None,
+            "name" => self.name = None,
+            "version" => self.version = None,
+            "authors" => self.authors.clear(),
+            "description" => self.description = None,
+            "projectUrl" => self.project_url = None,
+            _ => return self.extra.shift_remove(field),
+        }
+        value
+    }
+}
+
+impl<R: Read + Seek> ObbyArchive<R> {
+    /// Extracts and parses the plugin's manifest
+    ///
+    /// The manifest entry is located with [`ObbyArchive::find_manifest`].
+    ///
+    /// # Returns
+    ///
+    /// The [`PluginManifest`], or an `io::Error` if there is no manifest or it isn't
+    /// a valid manifest.
+    pub fn plugin_manifest(&mut self) -> io::Result<PluginManifest> {
+        let name = self.find_manifest()?.to_string();
+        PluginManifest::from_json(&self.extract_entry(&name)?)
+    }
+}
+
+/// Reads the plugin manifests of many archives, opening several at once
+///
+/// Indexing a large plugin collection is dominated by opening each file and parsing
+/// its header, so the archives are spread over one thread per available CPU. Where
+/// threads aren't available, such as on `wasm32-unknown-unknown`, they are read one
+/// after the other.
+///
+/// # Arguments
+///
+/// * `paths` - The archives to read.
+///
+/// # Returns
+///
+/// One result per path, in the order the paths were given: the path with its
+/// [`PluginManifest`], or the `io::Error` from opening the archive or reading its
+/// manifest. A failing archive doesn't affect the others.
+///
+/// # Example
+///
+/// ```no_run
+/// let paths = obsidian_lib::scan_dir("plugins").filter_map(|result| result.ok()).map(|(path, _)| path);
+/// for result in obsidian_lib::extract_manifests(paths) {
+///     match result {
+///         Ok((path, manifest)) => println!("{}: {:?}", path.display(), manifest.id),
+///         Err(e) => eprintln!("{}", e),
+///     }
+/// }
+/// ```
+pub fn extract_manifests<I: IntoIterator<Item = PathBuf>>(paths: I) -> Vec<io::Result<(PathBuf, PluginManifest)>> {
+    let paths: Vec<PathBuf> = paths.into_iter().collect();
+    let read = |path: &PathBuf| -> io::Result<(PathBuf, PluginManifest)> {
+        let manifest = crate::open(path)?.plugin_manifest()?;
+        Ok((path.clone(), manifest))
+    };
+    in_parallel(&paths, thread::available_parallelism().map_or(1, |n| n.get()), read)
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+
+    #[test]
+    fn test_extract_manifests() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut paths = Vec::new();
+        for i in 0..20 {
+            let path = dir.path().join(format!("{}.obby", i));
+            let json = format!(r#"{{"id": "plugin-{}"}}"#, i);
+            std::fs::write(&path, ObbyTestBuilder::new().entry("plugin.json", json.as_bytes()).build()).unwrap();
+            paths.push(path);
+        }
+        paths.insert(5, dir.path().join("missing.obby"));
+
+        let results = extract_manifests(paths.clone());
+        assert_eq!(results.len(), 21);
+        assert_eq!(results[5].as_ref().unwrap_err().kind(), io::ErrorKind::NotFound);
+        let (path, manifest) = results[20].as_ref().unwrap();
+        assert_eq!(path, &paths[20]);
+        assert_eq!(manifest.id.as_deref(), Some("plugin-19"));
+    }
+
+    #[test]
+    fn test_manifest_mutation() {
+        let mut manifest = PluginManifest::from_json(br#"{"id": "a", "minApi": 3, "tags": []}"#).unwrap();
+        manifest.set_version("1.0.1");
+        manifest.set_field("authors", vec!["Ann", "Bob"]).unwrap();
+        manifest.set_field("minApi", 4).unwrap();
+        assert!(manifest.set_field("version", true).is_err());
+        assert_eq!(manifest.version.as_deref(), Some("1.0.1"));
+        assert_eq!(manifest.authors, vec!["Ann", "Bob"]);
+        assert_eq!(manifest.remove_field("id"), Some(Value::from("a")));
+        assert_eq!(manifest.remove_field("minApi"), Some(Value::from(4)));
+        assert_eq!(manifest.remove_field("missing"), None);
+        assert_eq!(manifest.to_value(), serde_json::json!({"version": "1.0.1", "authors": ["Ann", "Bob"], "tags": []}));
+    }
+
+    #[test]
+    fn test_plugin_manifest() {
+        let json = br#"{"name": "Template Plugin", "id": "change_me", "version": "1.0.0", "authors": ["Obsidian Team"], "projectUrl": "https://example.com", "minApi": 3}"#;
+        let buffer = ObbyTestBuilder::new().entry("plugin.json", json).build();
+        let manifest = ObbyArchive::from_slice(&buffer).unwrap().plugin_manifest().unwrap();
+        assert_eq!(manifest.id.as_deref(), Some("change_me"));
+        assert_eq!(manifest.authors, vec!["Obsidian Team"]);
+        assert_eq!(manifest.project_url.as_deref(), Some("https://example.com"));
+        assert_eq!(manifest.extra["minApi"], 3);
+
+        let round_trip = PluginManifest::from_json(manifest.to_json().as_bytes()).unwrap();
+        assert_eq!(round_trip, manifest);
+        assert_eq!(
+            manifest.to_value().as_object().unwrap().keys().collect::<Vec<_>>(),
+            vec!["id", "name", "version", "authors", "projectUrl", "minApi"]
+        );
+        #[cfg(feature = "serde")]
+        assert_eq!(serde_json::to_value(&manifest).unwrap(), manifest.to_value());
+    }
+
+    #[test]
+    fn test_invalid_manifest() {
+        assert_eq!(PluginManifest::from_json(b"[1]").unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert!(PluginManifest::from_json(br#"{"version": 1}"#).is_err());
+        let single = PluginManifest::from_json(br#"{"authors": "Me"}"#).unwrap();
+        assert_eq!(single.authors, vec!["Me"]);
+    }
+}
@@ -0,0 +1,135 @@
+//! Fully loaded, owned archives
+
+use std::collections::HashMap;
+use std::io::{self, Read, Seek};
+use std::sync::Arc;
+
+use crate::{ObbyArchive, ObbyMetadata};
+
+/// An archive whose entries have all been decompressed into memory
+///
+/// Created with [`ObbyArchive::into_memory`]. Since every entry was read and
+/// decompressed up front, lookups can't fail and need no `&mut`. The data is shared
+/// behind `Arc`s, so cloning is cheap, and the archive is `Send + Sync`, which makes it
+/// suitable for caching a loaded plugin in shared server state.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// let memory = obsidian_lib::open("plugin.obby")?.into_memory()?;
+/// let shared = std::sync::Arc::new(memory);
+/// let handle = std::thread::spawn({
+///     let shared = shared.clone();
+///     move || shared.get("main.js").map(<[u8]>::len)
+/// });
+/// println!("{:?}", handle.join().unwrap());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct MemoryArchive {
+    metadata: ObbyMetadata,
+    order: Vec<String>,
+    entries: HashMap<String, Arc<[u8]>>,
+}
+
+impl MemoryArchive {
+    /// Returns the archive's header metadata
+    pub fn metadata(&self) -> &ObbyMetadata {
+        &self.metadata
+    }
+
+    /// Returns the names of all entries, in the order they appear in the entry table
+    pub fn list_entries(&self) -> Vec<String> {
+        self.order.clone()
+    }
+
+    /// Returns an entry's decompressed data, or `None` if there is no such entry
+    ///
+    /// # Arguments
+    ///
+    /// * `entry_name` - The name of the entry.
+    pub fn get(&self, entry_name: &str) -> Option<&[u8]> {
+        self.entries.get(entry_name).map(|data| &data[..])
+    }
+
+    /// Returns a shared handle to an entry's data, which can outlive the archive
+    ///
+    /// # Arguments
+    ///
+    /// * `entry_name` - The name of the entry.
+    pub fn get_shared(&self, entry_name: &str) -> Option<Arc<[u8]>> {
+        self.entries.get(entry_name).cloned()
+    }
+
+    /// Returns whether the archive has an entry with the given name
+    pub fn contains(&self, entry_name: &str) -> bool {
+        self.entries.contains_key(entry_name)
+    }
+
+    /// Returns the number of entries
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    /// Returns whether the archive has no entries
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    /// Returns the total decompressed size of all entries in bytes
+    pub fn total_size(&self) -> u64 {
+        self.entries.values().map(|data| data.len() as u64).sum()
+    }
+}
+
+impl<R: Read + Seek> ObbyArchive<R> {
+    /// Reads and decompresses every entry, returning an owned [`MemoryArchive`]
+    ///
+    /// The reader is dropped afterwards, so a file handle isn't kept open.
+    ///
+    /// # Returns
+    ///
+    /// The `MemoryArchive`, or the first `io::Error` hit while extracting an entry.
+    pub fn into_memory(mut self) -> io::Result<MemoryArchive> {
+        let mut entries = HashMap::with_capacity(self.order.len());
+        for name in &self.order.clone() {
+            let data: Arc<[u8]> = self.extract_entry(name)?.into();
+            entries.insert(name.clone(), data);
+        }
+        Ok(MemoryArchive {
+            metadata: self.metadata,
+            order: self.order,
+            entries,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+
+    #[test]
+    fn test_into_memory() {
+        fn assert_shareable<T: Clone + Send + Sync>(_: &T) {}
+
+        let buffer = ObbyTestBuilder::new()
+            .plugin("Plugin", "2.0.0")
+            .stored_entry("plugin.json", b"{}")
+            .entry("main.js", &[b'a'; 1000])
+            .build();
+        let memory = ObbyArchive::from_slice(&buffer).unwrap().into_memory().unwrap();
+        assert_shareable(&memory);
+
+        assert_eq!(memory.metadata().plugin_version, "2.0.0");
+        assert_eq!(memory.list_entries(), vec!["plugin.json", "main.js"]);
+        assert_eq!(memory.get("main.js"), Some(&[b'a'; 1000][..]));
+        assert_eq!(memory.get("missing.js"), None);
+        assert_eq!(memory.total_size(), 1002);
+
+        let clone = memory.clone();
+        assert!(Arc::ptr_eq(&clone.get_shared("main.js").unwrap(), &memory.get_shared("main.js").unwrap()));
+    }
+}
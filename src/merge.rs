@@ -0,0 +1,278 @@
+//! Combining several archives into one
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Read, Seek, Write};
+#[cfg(feature = "json")]
+use std::sync::Arc;
+
+#[cfg(feature = "json")]
+use crate::manifest::PluginManifest;
+use crate::{ObbyArchive, ObbyWriter};
+
+/// What [`merge`] does when several archives contain an entry with the same name
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Fails with `AlreadyExists`
+    #[default]
+    Error,
+    /// Keeps the entry from the first archive that has it
+    FirstWins,
+    /// Keeps the entry from the last archive that has it
+    LastWins,
+}
+
+/// Combines the manifests of the merged archives into one
+#[cfg(feature = "json")]
+type ManifestMerger = Arc<dyn Fn(&[PluginManifest]) -> io::Result<PluginManifest> + Send + Sync>;
+
+/// Options for [`merge_with_options`]
+///
+/// # Example
+///
+/// ```no_run
+/// use obsidian_lib::{merge_with_options, ConflictPolicy, MergeOptions};
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut options = MergeOptions::default();
+/// options.set_conflict_policy(ConflictPolicy::LastWins);
+/// # #[cfg(feature = "json")]
+/// // Keep the base plugin's manifest, but list every pack's ID
+/// options.set_manifest_merger(|manifests| {
+///     let mut merged = manifests[0].clone();
+///     let ids: Vec<_> = manifests.iter().filter_map(|manifest| manifest.id.clone()).collect();
+///     merged.extra.insert("includes".to_string(), ids.into());
+///     Ok(merged)
+/// });
+/// let mut archives = vec![obsidian_lib::open("plugin.obby")?, obsidian_lib::open("pack.obby")?];
+/// let out = std::fs::File::create("combined.obby")?;
+/// merge_with_options(&mut archives, &options, out)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct MergeOptions {
+    conflict_policy: ConflictPolicy,
+    #[cfg(feature = "json")]
+    manifest_merger: Option<ManifestMerger>,
+}
+
+impl MergeOptions {
+    /// Returns what happens to entries that several archives contain
+    pub fn conflict_policy(&self) -> ConflictPolicy {
+        self.conflict_policy
+    }
+
+    /// Sets what happens to entries that several archives contain
+    ///
+    /// Defaults to [`ConflictPolicy::Error`].
+    pub fn set_conflict_policy(&mut self, policy: ConflictPolicy) {
+        self.conflict_policy = policy;
+    }
+
+    /// Builds the merged archive's manifest from the manifests of all archives
+    ///
+    /// `merger` receives the parsed manifest of every archive that has one, in archive
+    /// order. Its result is written under the first archive's manifest name, and the
+    /// manifest entries themselves are left out of conflict handling. Without a merger,
+    /// manifests are ordinary entries subject to the [`ConflictPolicy`].
+    #[cfg(feature = "json")]
+    pub fn set_manifest_merger<F>(&mut self, merger: F)
+    where
+        F: Fn(&[PluginManifest]) -> io::Result<PluginManifest> + Send + Sync + 'static,
+    {
+        self.manifest_merger = Some(Arc::new(merger));
+    }
+}
+
+impl fmt::Debug for MergeOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("MergeOptions");
+        debug.field("conflict_policy", &self.conflict_policy);
+        #[cfg(feature = "json")]
+        debug.field("manifest_merger", &self.manifest_merger.as_ref().map(|_| ".."));
+        debug.finish()
+    }
+}
+
+/// Combines several archives, such as a plugin and its extension packs, into one
+///
+/// Shorthand for [`merge_with_options`] with only a conflict policy set.
+///
+/// # Arguments
+///
+/// * `archives` - The archives to merge, in order of precedence for the policy.
+/// * `conflict` - What to do with entries that several archives contain.
+/// * `out` - Where the merged archive is written.
+pub fn merge<R, W>(archives: &mut [ObbyArchive<R>], conflict: ConflictPolicy, out: W) -> io::Result<W>
+where
+    R: Read + Seek,
+    W: Write,
+{
+    let mut options = MergeOptions::default();
+    options.set_conflict_policy(conflict);
+    merge_with_options(archives, &options, out)
+}
+
+/// Combines several archives into one, with control over conflicts and manifests
+///
+/// The merged archive takes its API version, assembly name and version from the first
+/// archive. Entries are copied without recompression, in the order they first appear.
+/// Nothing is written to `out` unless the merge succeeds. The result is unsigned.
+///
+/// # Arguments
+///
+/// * `archives` - The archives to merge. There must be at least one.
+/// * `options` - The `MergeOptions` to use.
+/// * `out` - Where the merged archive is written.
+///
+/// # Returns
+///
+/// `out`, once the archive was written to it, or an `io::Error` of kind
+/// `AlreadyExists` if two archives share an entry under [`ConflictPolicy::Error`].
+pub fn merge_with_options<R, W>(archives: &mut [ObbyArchive<R>], options: &MergeOptions, out: W) -> io::Result<W>
+where
+    R: Read + Seek,
+    W: Write,
+{
+    let Some(first) = archives.first() else {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "No archives to merge"));
+    };
+    let metadata = first.metadata().clone();
+    let mut writer = ObbyWriter::new(out, &metadata.plugin_assembly, &metadata.plugin_version);
+    writer.set_api_version(&metadata.api_version);
+
+    // The merged manifest, and each archive's manifest entry to leave out
+    let mut manifest_names = vec![None; archives.len()];
+    let merged_manifest = merge_manifests(archives, options, &mut manifest_names)?;
+
+    // Which archive each entry is taken from
+    let mut sources: Vec<(String, usize)> = Vec::new();
+    let mut slots: HashMap<String, usize> = HashMap::new();
+    for (index, archive) in archives.iter().enumerate() {
+        for name in archive.entry_names() {
+            if manifest_names[index].as_deref() == Some(name) {
+                continue;
+            }
+            match slots.get(name) {
+                None => {
+                    slots.insert(name.to_string(), sources.len());
+                    sources.push((name.to_string(), index));
+                }
+                Some(&slot) => match options.conflict_policy {
+                    ConflictPolicy::Error => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::AlreadyExists,
+                            format!("Entry '{}' is in archives {} and {}", name, sources[slot].1 + 1, index + 1),
+                        ))
+                    }
+                    ConflictPolicy::FirstWins => {}
+                    ConflictPolicy::LastWins => sources[slot].1 = index,
+                },
+            }
+        }
+    }
+
+    if let Some((name, manifest)) = merged_manifest {
+        writer.add_entry(&name, &manifest)?;
+    }
+    for (name, index) in sources {
+        writer.copy_entry_from(&mut archives[index], &name)?;
+    }
+    writer.finish()
+}
+
+/// Runs the manifest merger, if one is set, recording each archive's manifest name
+///
+/// Returns the merged manifest's entry name and contents.
+#[cfg(feature = "json")]
+fn merge_manifests<R: Read + Seek>(
+    archives: &mut [ObbyArchive<R>],
+    options: &MergeOptions,
+    manifest_names: &mut [Option<String>],
+) -> io::Result<Option<(String, Vec<u8>)>> {
+    let Some(merger) = &options.manifest_merger else {
+        return Ok(None);
+    };
+    let mut manifests = Vec::new();
+    for (archive, manifest_name) in archives.iter_mut().zip(manifest_names.iter_mut()) {
+        if let Ok(name) = archive.find_manifest() {
+            *manifest_name = Some(name.to_string());
+            manifests.push(archive.plugin_manifest()?);
+        }
+    }
+    match manifest_names.iter().flatten().next() {
+        Some(name) => Ok(Some((name.clone(), merger(&manifests)?.to_json().into_bytes()))),
+        None => Ok(None),
+    }
+}
+
+/// Without the `json` feature there are no manifest mergers, so manifests are ordinary entries
+#[cfg(not(feature = "json"))]
+fn merge_manifests<R: Read + Seek>(
+    _archives: &mut [ObbyArchive<R>],
+    _options: &MergeOptions,
+    _manifest_names: &mut [Option<String>],
+) -> io::Result<Option<(String, Vec<u8>)>> {
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+    use std::io::Cursor;
+
+    fn archives() -> Vec<ObbyArchive<Cursor<Vec<u8>>>> {
+        let base = ObbyTestBuilder::new()
+            .plugin("Base", "1.0.0")
+            .entry("plugin.json", br#"{"id": "base"}"#)
+            .entry("main.js", b"base")
+            .build();
+        let pack = ObbyTestBuilder::new()
+            .entry("plugin.json", br#"{"id": "pack"}"#)
+            .entry("main.js", b"pack")
+            .entry("assets/pack.png", b"png")
+            .build();
+        vec![ObbyArchive::from_bytes(base).unwrap(), ObbyArchive::from_bytes(pack).unwrap()]
+    }
+
+    #[test]
+    fn test_conflict_policies() {
+        let err = merge(&mut archives(), ConflictPolicy::Error, Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+
+        let merged = merge(&mut archives(), ConflictPolicy::FirstWins, Vec::new()).unwrap();
+        let mut merged = ObbyArchive::from_bytes(merged).unwrap();
+        assert_eq!(merged.metadata().plugin_assembly, "Base");
+        assert_eq!(merged.list_entries(), vec!["plugin.json", "main.js", "assets/pack.png"]);
+        assert_eq!(merged.extract_entry("main.js").unwrap(), b"base");
+
+        let merged = merge(&mut archives(), ConflictPolicy::LastWins, Vec::new()).unwrap();
+        let mut merged = ObbyArchive::from_bytes(merged).unwrap();
+        merged.verify_hash().unwrap();
+        assert_eq!(merged.extract_entry("main.js").unwrap(), b"pack");
+        assert_eq!(merged.extract_entry("plugin.json").unwrap(), br#"{"id": "pack"}"#);
+
+        let mut none: Vec<ObbyArchive<Cursor<Vec<u8>>>> = Vec::new();
+        assert!(merge(&mut none, ConflictPolicy::Error, Vec::new()).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn test_manifest_merger() {
+        let mut options = MergeOptions::default();
+        options.set_manifest_merger(|manifests| {
+            let mut merged = manifests[0].clone();
+            merged.description = Some(manifests.iter().filter_map(|m| m.id.clone()).collect::<Vec<_>>().join("+"));
+            Ok(merged)
+        });
+        // Manifests don't count as conflicts, but the other entries still do
+        assert!(merge_with_options(&mut archives(), &options, Vec::new()).is_err());
+        options.set_conflict_policy(ConflictPolicy::FirstWins);
+        let merged = merge_with_options(&mut archives(), &options, Vec::new()).unwrap();
+        let manifest = ObbyArchive::from_bytes(merged).unwrap().plugin_manifest().unwrap();
+        assert_eq!(manifest.id.as_deref(), Some("base"));
+        assert_eq!(manifest.description.as_deref(), Some("base+pack"));
+    }
+}
@@ -0,0 +1,111 @@
+//! Content-type detection from leading bytes
+
+/// Number of decompressed bytes inspected by [`sniff`]
+pub(crate) const MIME_SNIFF_LEN: usize = 512;
+
+/// Fallback for binary data that matches no known signature
+pub(crate) const OCTET_STREAM: &str = "application/octet-stream";
+
+/// Signatures checked against the start of the data, in order
+const SIGNATURES: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xFF\xD8\xFF", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"\x00\x00\x01\x00", "image/x-icon"),
+    (b"OggS", "audio/ogg"),
+    (b"ID3", "audio/mpeg"),
+    (b"fLaC", "audio/flac"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"\x1F\x8B", "application/gzip"),
+    (b"%PDF-", "application/pdf"),
+    (b"\x00asm", "application/wasm"),
+    (b"OBBY", "application/x-obby"),
+    (b"wOFF", "font/woff"),
+    (b"wOF2", "font/woff2"),
+    (b"OTTO", "font/otf"),
+    (b"\x00\x01\x00\x00", "font/ttf"),
+    (b"MZ", "application/vnd.microsoft.portable-executable"),
+];
+
+/// Text formats recognised by file extension once the data is known to be text
+const TEXT_EXTENSIONS: &[(&str, &str)] = &[
+    ("css", "text/css"),
+    ("js", "text/javascript"),
+    ("mjs", "text/javascript"),
+    ("html", "text/html"),
+    ("htm", "text/html"),
+    ("md", "text/markdown"),
+    ("csv", "text/csv"),
+    ("xml", "application/xml"),
+    ("yml", "application/yaml"),
+    ("yaml", "application/yaml"),
+    ("toml", "application/toml"),
+];
+
+/// Guesses the MIME type of an entry from its first bytes, using the name only to tell text formats apart
+pub(crate) fn sniff(prefix: &[u8], name: &str) -> &'static str {
+    if let Some((_, mime)) = SIGNATURES.iter().find(|(magic, _)| prefix.starts_with(magic)) {
+        return mime;
+    }
+    if prefix.len() >= 12 && prefix.starts_with(b"RIFF") {
+        match &prefix[8..12] {
+            b"WAVE" => return "audio/wav",
+            b"WEBP" => return "image/webp",
+            _ => {}
+        }
+    }
+
+    if !is_text(prefix) {
+        return OCTET_STREAM;
+    }
+    let text = String::from_utf8_lossy(prefix);
+    let trimmed = text.trim_start_matches('\u{FEFF}').trim_start();
+    if trimmed.starts_with("<svg") || (trimmed.starts_with("<?xml") && trimmed.contains("<svg")) {
+        return "image/svg+xml";
+    }
+    if trimmed.starts_with('{') || trimmed.starts_with('[') {
+        return "application/json";
+    }
+
+    let extension = name.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase());
+    TEXT_EXTENSIONS
+        .iter()
+        .find(|(ext, _)| extension.as_deref() == Some(*ext))
+        .map_or("text/plain", |(_, mime)| mime)
+}
+
+/// Whether `prefix` looks like UTF-8 text, allowing for a code point cut off at the end
+pub(crate) fn is_text(prefix: &[u8]) -> bool {
+    if prefix.is_empty() || prefix.iter().any(|&b| b == 0 || (b < 0x20 && !b"\t\n\r\x0C".contains(&b))) {
+        return false;
+    }
+    match std::str::from_utf8(prefix) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none() && prefix.len() - e.valid_up_to() < 4,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sniff() {
+        assert_eq!(sniff(b"MZ\x90\x00\x03\x00", "Plugin.dll"), "application/vnd.microsoft.portable-executable");
+        assert_eq!(sniff(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR", "icon"), "image/png");
+        assert_eq!(sniff(b"RIFF\x24\x08\0\0WAVEfmt ", "click.wav"), "audio/wav");
+        assert_eq!(sniff(b"  \n{\"id\": 1}", "plugin.json"), "application/json");
+        assert_eq!(sniff(b"<svg xmlns=\"http://www.w3.org/2000/svg\">", "logo.svg"), "image/svg+xml");
+        assert_eq!(sniff(b"body { color: red; }", "style.CSS"), "text/css");
+        assert_eq!(sniff("# Überschrift".as_bytes(), "README"), "text/plain");
+        assert_eq!(sniff(b"\x01\x02\x03", "blob.bin"), OCTET_STREAM);
+        assert_eq!(sniff(b"", "empty"), OCTET_STREAM);
+    }
+
+    #[test]
+    fn test_text_cut_mid_character() {
+        let text = "aü".as_bytes();
+        assert_eq!(sniff(&text[..2], "notes.md"), "text/markdown");
+    }
+}
@@ -0,0 +1,277 @@
+//! Mirroring a registry into a local directory
+//!
+//! Enabled with the `registry` feature.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::http::Throttle;
+use crate::index::{IndexedPlugin, IndexedVersion, RepositoryIndex};
+use crate::output::OutputFile;
+use crate::parallel::in_parallel;
+use crate::registry::{Registry, INDEX_NAME};
+use crate::validate_relative_path;
+
+/// A plugin version handled by [`Registry::sync_to`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SyncedVersion {
+    /// The plugin's ID
+    pub id: String,
+    /// The version
+    pub version: String,
+}
+
+/// A plugin version [`Registry::sync_to`] couldn't mirror
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SyncFailure {
+    /// The plugin's ID
+    pub id: String,
+    /// The version
+    pub version: String,
+    /// Why it failed
+    pub error: String,
+}
+
+/// What [`Registry::sync_to`] did
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SyncReport {
+    /// Path of the mirror's index
+    pub index: PathBuf,
+    /// Versions that were new or changed and have been downloaded
+    pub downloaded: Vec<SyncedVersion>,
+    /// Versions already in the mirror
+    pub unchanged: Vec<SyncedVersion>,
+    /// Versions that couldn't be downloaded or verified
+    pub failed: Vec<SyncFailure>,
+}
+
+impl Registry {
+    /// Copies every plugin version in the registry into a local directory
+    ///
+    /// Each archive is stored as `<id>/<version>/<file name>` in `mirror_dir`, and the mirror gets
+    /// its own `index.json` listing them, so the directory can be served as a registry
+    /// itself or read with [`RepositoryIndex::from_json`]. Versions the mirror's index
+    /// already lists with the same SHA-256 and whose file has the expected size are not
+    /// downloaded again; everything else is downloaded and verified as by
+    /// [`Registry::download`].
+    ///
+    /// Up to [`crate::RemoteOptions::concurrency`] archives are downloaded at once. A
+    /// version that fails is reported in [`SyncReport::failed`] and doesn't stop the
+    /// others. If an earlier copy of it is in the mirror, that copy stays listed.
+    /// Archives that are no longer in the registry are dropped from the mirror's index
+    /// but their files are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `mirror_dir` - The mirror's directory. It is created if it doesn't exist.
+    ///
+    /// # Returns
+    ///
+    /// The [`SyncReport`], or an `io::Error` if the mirror's existing index can't be
+    /// read or the new one can't be written.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use obsidian_lib::Registry;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let registry = Registry::from_index_url("https://plugins.example.com/approved.json")?;
+    /// let report = registry.sync_to("mirror")?;
+    /// println!("{} downloaded, {} unchanged", report.downloaded.len(), report.unchanged.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn sync_to<P: AsRef<Path>>(&self, mirror_dir: P) -> io::Result<SyncReport> {
+        let mirror_dir = mirror_dir.as_ref();
+        fs::create_dir_all(mirror_dir)?;
+        let index_path = mirror_dir.join(INDEX_NAME);
+        let previous = match fs::read(&index_path) {
+            Ok(data) => RepositoryIndex::from_json(&data)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => RepositoryIndex::default(),
+            Err(e) => return Err(e),
+        };
+
+        // Decide what to do with every version first, so the downloads can run in parallel
+        let mut planned = Vec::new();
+        let mut downloads = Vec::new();
+        let mut taken = HashSet::new();
+        for plugin in &self.index().plugins {
+            for version in &plugin.versions {
+                let old = previous.plugin(&plugin.id).and_then(|old| old.version(&version.version));
+                // Paths are compared ignoring case, as the mirror may be on such a file system
+                let local = local_path(&plugin.id, &version.version, &version.download).and_then(|local| {
+                    match taken.insert(local.to_lowercase()) {
+                        true => Ok(local),
+                        false => Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("'{}' is listed more than once", local))),
+                    }
+                });
+                let plan = match local {
+                    Ok(local) if old.is_some_and(|old| is_current(mirror_dir, old, &local, version)) => Plan::Unchanged(local),
+                    Ok(local) => {
+                        downloads.push((version, local.clone()));
+                        Plan::Download(local)
+                    }
+                    Err(e) => Plan::Unsafe(e),
+                };
+                // A version that can't be stored doesn't keep an earlier copy listed either,
+                // or a duplicate listing would appear twice in the mirror's index
+                let old = old.filter(|_| !matches!(plan, Plan::Unsafe(_)));
+                planned.push((plugin, version, old, plan));
+            }
+        }
+        let throttle = Throttle::new(self.options().rate_limit());
+        let mut downloaded = in_parallel(&downloads, self.options().concurrency(), |(version, local)| {
+            self.download_verified(version, &throttle).and_then(|data| write_file(&mirror_dir.join(local), &data))
+        })
+        .into_iter();
+
+        let mut report = SyncReport {
+            index: index_path.clone(),
+            downloaded: Vec::new(),
+            unchanged: Vec::new(),
+            failed: Vec::new(),
+        };
+        let mut mirrored = RepositoryIndex::default();
+        for (plugin, version, old, plan) in planned {
+            let synced = SyncedVersion { id: plugin.id.clone(), version: version.version.clone() };
+            let result = match plan {
+                Plan::Unchanged(local) => {
+                    report.unchanged.push(synced);
+                    Ok(local)
+                }
+                Plan::Download(local) => match downloaded.next().expect("one result per download") {
+                    Ok(()) => {
+                        report.downloaded.push(synced);
+                        Ok(local)
+                    }
+                    Err(e) => Err((synced, e)),
+                },
+                Plan::Unsafe(e) => Err((synced, e)),
+            };
+            let entry = match result {
+                Ok(local) => Some(IndexedVersion { download: local, ..version.clone() }),
+                Err((synced, e)) => {
+                    report.failed.push(SyncFailure { id: synced.id, version: synced.version, error: e.to_string() });
+                    old.filter(|old| is_present(mirror_dir, old)).cloned()
+                }
+            };
+            let Some(entry) = entry else {
+                continue;
+            };
+            match mirrored.plugins.last_mut() {
+                Some(last) if last.id == plugin.id => last.versions.push(entry),
+                _ => mirrored.plugins.push(IndexedPlugin { versions: vec![entry], ..plugin.clone() }),
+            }
+        }
+
+        let mut out = OutputFile::new(&index_path);
+        serde_json::to_writer_pretty(&mut out, &mirrored.to_json())?;
+        out.flush()?;
+        Ok(report)
+    }
+}
+
+/// What [`Registry::sync_to`] does with a version
+enum Plan {
+    /// Keep the copy at this path
+    Unchanged(String),
+    /// Download it to this path
+    Download(String),
+    /// Skip it, since it can't be stored safely or another version is stored at its path
+    Unsafe(io::Error),
+}
+
+/// Whether the mirror's copy `old` is at `local` and has the registry's content
+fn is_current(mirror_dir: &Path, old: &IndexedVersion, local: &str, version: &IndexedVersion) -> bool {
+    old.download == local && old.sha256 == version.sha256 && is_present(mirror_dir, old)
+}
+
+/// Returns where a version is stored in the mirror, as `<id>/<version>/<file name>`
+///
+/// The version keeps apart archives whose URLs differ only in the query string.
+fn local_path(id: &str, version: &str, download: &str) -> io::Result<String> {
+    let without_query = download.split(['?', '#']).next().unwrap_or_default();
+    let file_name = without_query.rsplit('/').next().unwrap_or_default();
+    for name in [id, version, file_name] {
+        let unsafe_name = name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.');
+        if unsafe_name || validate_relative_path(Path::new(name)).is_err() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("'{}' isn't a safe file name", name)));
+        }
+    }
+    Ok(format!("{}/{}/{}", id, version, file_name))
+}
+
+/// Whether a mirrored version's file exists with the size the index gives
+fn is_present(mirror_dir: &Path, version: &IndexedVersion) -> bool {
+    fs::metadata(mirror_dir.join(&version.download)).is_ok_and(|metadata| metadata.len() == version.size)
+}
+
+fn write_file(path: &Path, data: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut out = OutputFile::new(path);
+    out.write_all(data)?;
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::tests::{alpha, indexed, serve};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_sync_to() {
+        let mirror = tempfile::tempdir().unwrap();
+        let (old, new) = (alpha("1.0.0"), alpha("1.1.0"));
+        let mut broken = indexed("1.2.0", &new);
+        broken.download = "files/missing.obby".to_string();
+        let index = RepositoryIndex {
+            plugins: vec![IndexedPlugin {
+                id: "alpha".to_string(),
+                name: None,
+                versions: vec![indexed("1.0.0", &old), indexed("1.1.0", &new), broken, indexed("1.0.0", &old)],
+            }],
+        };
+        let files = HashMap::from([
+            ("repo/approved.json".to_string(), index.to_json().to_string().into_bytes()),
+            ("repo/files/alpha-1.0.0.obby".to_string(), old.clone()),
+            ("repo/files/alpha-1.1.0.obby".to_string(), new),
+        ]);
+        let registry = Registry::from_index_url(&format!("{}/repo/approved.json", serve(files))).unwrap();
+
+        let report = registry.sync_to(mirror.path()).unwrap();
+        assert_eq!((report.downloaded.len(), report.unchanged.len(), report.failed.len()), (2, 0, 2));
+        assert_eq!(report.failed[0].version, "1.2.0");
+        // The second listing of 1.0.0 would overwrite the first
+        assert!(report.failed[1].error.contains("more than once"), "{}", report.failed[1].error);
+        assert_eq!(fs::read(mirror.path().join("alpha/1.0.0/alpha-1.0.0.obby")).unwrap(), old);
+
+        let local = RepositoryIndex::from_json(&fs::read(&report.index).unwrap()).unwrap();
+        let versions = &local.plugin("alpha").unwrap().versions;
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[1].download, "alpha/1.1.0/alpha-1.1.0.obby");
+
+        // A second run only retries what failed, and a damaged copy is downloaded again
+        fs::write(mirror.path().join("alpha/1.1.0/alpha-1.1.0.obby"), b"truncated").unwrap();
+        let report = registry.sync_to(mirror.path()).unwrap();
+        assert_eq!(report.unchanged, vec![SyncedVersion { id: "alpha".into(), version: "1.0.0".into() }]);
+        assert_eq!(report.downloaded, vec![SyncedVersion { id: "alpha".into(), version: "1.1.0".into() }]);
+        assert_eq!(report.failed.len(), 2);
+        let local = RepositoryIndex::from_json(&fs::read(&report.index).unwrap()).unwrap();
+        assert_eq!(local.plugin("alpha").unwrap().versions.len(), 2);
+
+        assert_eq!(local_path("alpha", "1.0.0", "https://cdn.example.com/a/alpha.obby?token=1").unwrap(), "alpha/1.0.0/alpha.obby");
+        assert_ne!(local_path("alpha", "1", "dl/alpha.obby?v=1").unwrap(), local_path("alpha", "2", "dl/alpha.obby?v=2").unwrap());
+        assert!(local_path("../alpha", "1.0.0", "alpha.obby").is_err());
+        assert!(local_path("alpha", "../1.0.0", "alpha.obby").is_err());
+        assert!(local_path("alpha", "1.0.0", "files/").is_err());
+    }
+}
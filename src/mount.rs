@@ -0,0 +1,236 @@
+//! `obby mount`: exposes an archive as a read-only filesystem
+//!
+//! Built with the `fuse` feature, on Linux and macOS.
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use fuser::{
+    Config, Errno, FileAttr, FileHandle, FileType, Filesystem, FopenFlags, Generation, INodeNo, LockOwner, MountOption,
+    OpenFlags, ReplyAttr, ReplyData, ReplyDirectory, ReplyEmpty, ReplyEntry, ReplyOpen, Request,
+};
+use obsidian_lib::{EntryTree, ObbyArchive};
+
+/// How long the kernel may cache attributes; the archive never changes while mounted
+const TTL: Duration = Duration::from_secs(60);
+
+/// A file or directory, addressed by its inode number minus one
+struct Node {
+    parent: u64,
+    name: String,
+    kind: NodeKind,
+}
+
+enum NodeKind {
+    /// Inode numbers of the children
+    Dir(Vec<u64>),
+    File { entry: String, size: u64 },
+}
+
+struct ArchiveFs {
+    archive: Mutex<ObbyArchive<File>>,
+    nodes: Vec<Node>,
+    /// Decompressed contents of open files, by file handle
+    open_files: Mutex<HashMap<u64, Arc<Vec<u8>>>>,
+    next_handle: AtomicU64,
+    /// Owner and modification time shown for everything, taken from the archive file
+    uid: u32,
+    gid: u32,
+    mtime: SystemTime,
+}
+
+/// Mounts the archive at `path` on `mountpoint` until it is unmounted
+pub fn mount(path: &Path, mountpoint: &Path) -> io::Result<()> {
+    let archive = obsidian_lib::open(path)?;
+    let file_metadata = std::fs::metadata(path)?;
+    let sizes: HashMap<String, u64> = archive.header().entries.into_iter().map(|entry| (entry.name, entry.length)).collect();
+    let mut nodes = vec![Node { parent: 1, name: String::new(), kind: NodeKind::Dir(Vec::new()) }];
+    add_children(&archive.tree(), 1, &sizes, &mut nodes);
+
+    let fs = ArchiveFs {
+        archive: Mutex::new(archive),
+        nodes,
+        open_files: Mutex::new(HashMap::new()),
+        next_handle: AtomicU64::new(1),
+        uid: file_metadata.uid(),
+        gid: file_metadata.gid(),
+        mtime: file_metadata.modified()?,
+    };
+    let mut config = Config::default();
+    config.mount_options.extend([
+        MountOption::RO,
+        MountOption::FSName(format!("obby:{}", path.display())),
+        MountOption::Subtype("obby".to_string()),
+        MountOption::DefaultPermissions,
+    ]);
+    eprintln!(
+        "Mounted {} at {}; unmount with `umount {}` (or `fusermount -u` on Linux)",
+        path.display(),
+        mountpoint.display(),
+        mountpoint.display()
+    );
+    fuser::mount(fs, mountpoint, &config)
+}
+
+/// Adds the children of `dir` below the directory with inode number `parent`
+fn add_children(dir: &EntryTree, parent: u64, sizes: &HashMap<String, u64>, nodes: &mut Vec<Node>) {
+    for child in dir.children() {
+        // Such names can't be looked up, and would shadow the real `.` and `..`
+        if matches!(child.name(), "." | "..") {
+            continue;
+        }
+        let ino = nodes.len() as u64 + 1;
+        let kind = match child {
+            EntryTree::Dir { .. } => NodeKind::Dir(Vec::new()),
+            EntryTree::File { path, .. } => NodeKind::File {
+                entry: path.clone(),
+                size: sizes.get(path).copied().unwrap_or(0),
+            },
+        };
+        nodes.push(Node { parent, name: child.name().to_string(), kind });
+        if let NodeKind::Dir(children) = &mut nodes[parent as usize - 1].kind {
+            children.push(ino);
+        }
+        if child.is_dir() {
+            add_children(child, ino, sizes, nodes);
+        }
+    }
+}
+
+impl ArchiveFs {
+    fn node(&self, ino: INodeNo) -> Result<&Node, Errno> {
+        let index = u64::from(ino).checked_sub(1).ok_or(Errno::ENOENT)?;
+        self.nodes.get(index as usize).ok_or(Errno::ENOENT)
+    }
+
+    fn attr(&self, ino: u64) -> FileAttr {
+        let (kind, size, perm, nlink) = match &self.nodes[ino as usize - 1].kind {
+            NodeKind::Dir(_) => (FileType::Directory, 0, 0o555, 2),
+            NodeKind::File { size, .. } => (FileType::RegularFile, *size, 0o444, 1),
+        };
+        FileAttr {
+            ino: INodeNo(ino),
+            size,
+            blocks: size.div_ceil(512),
+            atime: self.mtime,
+            mtime: self.mtime,
+            ctime: self.mtime,
+            crtime: self.mtime,
+            kind,
+            perm,
+            nlink,
+            uid: self.uid,
+            gid: self.gid,
+            rdev: 0,
+            flags: 0,
+            blksize: 4096,
+        }
+    }
+}
+
+impl Filesystem for ArchiveFs {
+    fn lookup(&self, _req: &Request, parent: INodeNo, name: &OsStr, reply: ReplyEntry) {
+        let found = self.node(parent).and_then(|parent| match &parent.kind {
+            NodeKind::Dir(children) => children
+                .iter()
+                .copied()
+                .find(|&child| OsStr::new(&self.nodes[child as usize - 1].name) == name)
+                .ok_or(Errno::ENOENT),
+            NodeKind::File { .. } => Err(Errno::ENOTDIR),
+        });
+        match found {
+            Ok(ino) => reply.entry(&TTL, &self.attr(ino), Generation(0)),
+            Err(e) => reply.error(e),
+        }
+    }
+
+    fn getattr(&self, _req: &Request, ino: INodeNo, _fh: Option<FileHandle>, reply: ReplyAttr) {
+        match self.node(ino) {
+            Ok(_) => reply.attr(&TTL, &self.attr(ino.into())),
+            Err(e) => reply.error(e),
+        }
+    }
+
+    fn open(&self, _req: &Request, ino: INodeNo, _flags: OpenFlags, reply: ReplyOpen) {
+        let entry = match self.node(ino) {
+            Ok(Node { kind: NodeKind::File { entry, .. }, .. }) => entry,
+            Ok(_) => return reply.error(Errno::EISDIR),
+            Err(e) => return reply.error(e),
+        };
+        // Compressed entries can't be read from the middle, so the whole entry is
+        // decompressed once and reads are served from memory until it is closed
+        let data = self.archive.lock().unwrap().extract_entry(entry);
+        match data {
+            Ok(data) => {
+                let handle = self.next_handle.fetch_add(1, Ordering::Relaxed);
+                self.open_files.lock().unwrap().insert(handle, Arc::new(data));
+                reply.opened(FileHandle(handle), FopenFlags::FOPEN_KEEP_CACHE);
+            }
+            Err(e) => {
+                eprintln!("obby mount: can't read {}: {}", entry, e);
+                reply.error(Errno::EIO);
+            }
+        }
+    }
+
+    fn read(
+        &self,
+        _req: &Request,
+        _ino: INodeNo,
+        fh: FileHandle,
+        offset: u64,
+        size: u32,
+        _flags: OpenFlags,
+        _lock_owner: Option<LockOwner>,
+        reply: ReplyData,
+    ) {
+        let Some(data) = self.open_files.lock().unwrap().get(&u64::from(fh)).cloned() else {
+            return reply.error(Errno::EBADF);
+        };
+        let start = (offset as usize).min(data.len());
+        let end = start.saturating_add(size as usize).min(data.len());
+        reply.data(&data[start..end]);
+    }
+
+    fn release(
+        &self,
+        _req: &Request,
+        _ino: INodeNo,
+        fh: FileHandle,
+        _flags: OpenFlags,
+        _lock_owner: Option<LockOwner>,
+        _flush: bool,
+        reply: ReplyEmpty,
+    ) {
+        self.open_files.lock().unwrap().remove(&u64::from(fh));
+        reply.ok();
+    }
+
+    fn readdir(&self, _req: &Request, ino: INodeNo, _fh: FileHandle, offset: u64, mut reply: ReplyDirectory) {
+        let node = match self.node(ino) {
+            Ok(node) => node,
+            Err(e) => return reply.error(e),
+        };
+        let NodeKind::Dir(children) = &node.kind else {
+            return reply.error(Errno::ENOTDIR);
+        };
+        let listing = [(u64::from(ino), ".".to_string()), (node.parent, "..".to_string())]
+            .into_iter()
+            .chain(children.iter().map(|&child| (child, self.nodes[child as usize - 1].name.clone())));
+        for (index, (child, name)) in listing.enumerate().skip(offset as usize) {
+            let kind = self.attr(child).kind;
+            // The offset passed back is that of the next entry
+            if reply.add(INodeNo(child), index as u64 + 1, kind, name) {
+                break;
+            }
+        }
+        reply.ok();
+    }
+}
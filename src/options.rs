@@ -0,0 +1,218 @@
+//! Options controlling how archives are read
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::codec::{Codec, CodecRegistry};
+use crate::SanitizePolicy;
+
+/// Options for opening an [`crate::ObbyArchive`]
+///
+/// `ObbyArchive::new` uses `ObbyReadOptions::default()`; pass a customised value to
+/// [`crate::ObbyArchive::with_options`] to change how archives are read.
+///
+/// # Example
+///
+/// ```no_run
+/// use obsidian_lib::{ObbyArchive, ObbyReadOptions};
+/// use obsidian_lib::codec::GzipCodec;
+/// use std::fs::File;
+///
+/// let mut options = ObbyReadOptions::default();
+/// options.codecs_mut().set_fallback(GzipCodec);
+/// let archive = ObbyArchive::with_options(File::open("plugin.obby").unwrap(), options).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct ObbyReadOptions {
+    codecs: CodecRegistry,
+    buffer_size: usize,
+    sanitize_policy: SanitizePolicy,
+    entry_filter: Option<EntryFilter>,
+    max_entry_size: Option<u64>,
+    strict: bool,
+    reject_trailing_data: bool,
+    normalize_names: bool,
+}
+
+/// Predicate deciding which entries are indexed
+#[derive(Clone)]
+pub(crate) struct EntryFilter(Arc<dyn Fn(&str) -> bool + Send + Sync>);
+
+impl EntryFilter {
+    pub(crate) fn matches(&self, entry_name: &str) -> bool {
+        (self.0)(entry_name)
+    }
+}
+
+impl fmt::Debug for EntryFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EntryFilter(..)")
+    }
+}
+
+/// Default size of the read buffer used while parsing the entry table
+pub const DEFAULT_BUFFER_SIZE: usize = 8 * 1024;
+
+impl Default for ObbyReadOptions {
+    fn default() -> Self {
+        ObbyReadOptions {
+            codecs: CodecRegistry::default(),
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            sanitize_policy: SanitizePolicy::default(),
+            entry_filter: None,
+            max_entry_size: None,
+            strict: false,
+            reject_trailing_data: false,
+            normalize_names: false,
+        }
+    }
+}
+
+impl ObbyReadOptions {
+    /// Returns the codecs used to decompress entries
+    pub fn codecs(&self) -> &CodecRegistry {
+        &self.codecs
+    }
+
+    /// Returns the codec registry for modification
+    pub fn codecs_mut(&mut self) -> &mut CodecRegistry {
+        &mut self.codecs
+    }
+
+    /// Registers an additional decompression codec
+    ///
+    /// This is shorthand for `options.codecs_mut().register(codec)`.
+    pub fn register_codec<C: Codec + 'static>(&mut self, codec: C) {
+        self.codecs.register(codec);
+    }
+
+    /// Returns the size of the read buffer used while parsing the entry table
+    pub fn buffer_size(&self) -> usize {
+        self.buffer_size
+    }
+
+    /// Sets the size of the read buffer used while parsing the entry table
+    ///
+    /// The entry table is made of many small fields, so reading it straight from an
+    /// unbuffered source such as `File` costs one read call per field. Defaults to
+    /// [`DEFAULT_BUFFER_SIZE`]; `0` disables buffering, which only makes sense for
+    /// sources that are already in memory or buffered.
+    pub fn set_buffer_size(&mut self, buffer_size: usize) {
+        self.buffer_size = buffer_size;
+    }
+
+    /// Returns the policy used to turn entry names into paths when extracting to disk
+    pub fn sanitize_policy(&self) -> SanitizePolicy {
+        self.sanitize_policy
+    }
+
+    /// Sets the policy used to turn entry names into paths when extracting to disk
+    ///
+    /// Defaults to [`SanitizePolicy::Strict`].
+    pub fn set_sanitize_policy(&mut self, policy: SanitizePolicy) {
+        self.sanitize_policy = policy;
+    }
+
+    /// Only indexes entries for which `filter` returns `true`
+    ///
+    /// The entry table still has to be read to locate entry data, but entries that
+    /// don't match are dropped while parsing instead of being kept in the archive's
+    /// index. To the resulting `ObbyArchive` they don't exist: they aren't listed and
+    /// can't be extracted. This keeps memory use low when indexing many archives for a
+    /// single entry. [`crate::ObbyStreamReader`] ignores this setting.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use obsidian_lib::{ObbyArchive, ObbyReadOptions};
+    /// use std::fs::File;
+    ///
+    /// let mut options = ObbyReadOptions::default();
+    /// options.set_entry_filter(|name| name == "plugin.json");
+    /// let archive = ObbyArchive::with_options(File::open("plugin.obby").unwrap(), options).unwrap();
+    /// ```
+    pub fn set_entry_filter<F>(&mut self, filter: F)
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        self.entry_filter = Some(EntryFilter(Arc::new(filter)));
+    }
+
+    /// Only indexes entries whose names start with `prefix`
+    ///
+    /// Shorthand for [`ObbyReadOptions::set_entry_filter`] with a prefix test.
+    pub fn set_entry_prefix(&mut self, prefix: &str) {
+        let prefix = prefix.to_string();
+        self.set_entry_filter(move |name| name.starts_with(&prefix));
+    }
+
+    /// Removes any entry filter, so all entries are indexed
+    pub fn clear_entry_filter(&mut self) {
+        self.entry_filter = None;
+    }
+
+    /// Returns the largest declared entry size that will be decompressed
+    pub fn max_entry_size(&self) -> Option<u64> {
+        self.max_entry_size
+    }
+
+    /// Refuses to decompress entries that declare more than `max_entry_size` bytes
+    ///
+    /// Decompressed output is always capped at the size declared in the entry table, so
+    /// this bounds the memory a single extraction can use. Defaults to `None`, allowing
+    /// any size the format can express (up to 2 GiB).
+    pub fn set_max_entry_size(&mut self, max_entry_size: Option<u64>) {
+        self.max_entry_size = max_entry_size;
+    }
+
+    /// Returns whether structural oddities make opening fail
+    pub fn strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Rejects archives with structural oddities instead of opening them
+    ///
+    /// Invalid UTF-8 in header strings, a data length that doesn't match the data
+    /// section and bytes after the last entry are normally tolerated and collected into
+    /// [`crate::ObbyArchive::warnings`]. When strict, the first of them makes opening fail
+    /// with `InvalidData`, which suits validation pipelines. Defaults to `false`.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Returns whether bytes after the last entry make opening fail
+    pub fn reject_trailing_data(&self) -> bool {
+        self.reject_trailing_data
+    }
+
+    /// Rejects archives with bytes after the last entry, such as junk or a signature appended by a packer
+    ///
+    /// Such bytes are normally accepted: they are available from
+    /// [`crate::ObbyArchive::trailing_data`] and reported in [`crate::ObbyArchive::warnings`],
+    /// and the header's data length may either include them or stop at the end of the
+    /// last entry. When set, they make opening fail with `InvalidData` while other
+    /// oddities are still tolerated. [`ObbyReadOptions::set_strict`] implies this.
+    /// Defaults to `false`.
+    pub fn set_reject_trailing_data(&mut self, reject_trailing_data: bool) {
+        self.reject_trailing_data = reject_trailing_data;
+    }
+
+    /// Returns whether entry names are normalized when indexing
+    pub fn normalize_names(&self) -> bool {
+        self.normalize_names
+    }
+
+    /// Indexes entries under their [normalized](crate::normalize_entry_name) names
+    ///
+    /// Archives packed on Windows sometimes store names like `.\lib\main.dll`; with
+    /// this enabled the entry is listed and extracted as `lib/main.dll`. If two names
+    /// normalize to the same one, the later entry wins, as with duplicate names. Entry
+    /// filters still see the names as stored. Defaults to `false`.
+    pub fn set_normalize_names(&mut self, normalize_names: bool) {
+        self.normalize_names = normalize_names;
+    }
+
+    pub(crate) fn entry_filter(&self) -> Option<&EntryFilter> {
+        self.entry_filter.as_ref()
+    }
+}
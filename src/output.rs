@@ -0,0 +1,166 @@
+//! Writing archives to files without leaving partial output behind
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Distinguishes temporary files created by one process
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// A file sink that only replaces its target once everything was written
+///
+/// Created with [`crate::ObbyWriter::create`]. Data goes to a temporary file next to the
+/// target, which is renamed over the target when the sink is flushed, as
+/// [`crate::ObbyWriter::finish`] does once the whole archive is written. If writing fails
+/// midway, for example because the disk is full, or the sink is dropped without being
+/// flushed, the temporary file is removed and the target is left as it was.
+///
+/// Targets that exist but aren't regular files, such as named pipes or `/dev/stdout`,
+/// can't be replaced by a rename and are written directly, as are all targets once
+/// [`OutputFile::set_atomic`] is turned off. Nothing is opened until the first write.
+#[derive(Debug)]
+pub struct OutputFile {
+    target: PathBuf,
+    atomic: bool,
+    state: State,
+}
+
+#[derive(Debug)]
+enum State {
+    Unopened,
+    /// Writing to `temp`, which replaces the target on flush
+    Temp { file: File, temp: PathBuf },
+    /// Writing to the target itself
+    Direct(File),
+}
+
+impl OutputFile {
+    /// Creates a sink for `target`, atomic by default
+    pub fn new<P: AsRef<Path>>(target: P) -> Self {
+        OutputFile {
+            target: target.as_ref().to_path_buf(),
+            atomic: true,
+            state: State::Unopened,
+        }
+    }
+
+    /// Returns the path the archive is written to
+    pub fn path(&self) -> &Path {
+        &self.target
+    }
+
+    /// Chooses between writing through a temporary file and writing to the target directly
+    ///
+    /// Only takes effect before the first write. Defaults to `true`.
+    pub fn set_atomic(&mut self, atomic: bool) {
+        self.atomic = atomic;
+    }
+
+    fn open(&mut self) -> io::Result<&mut File> {
+        if let State::Unopened = self.state {
+            let existing = fs::metadata(&self.target).ok();
+            self.state = if self.atomic && existing.as_ref().is_none_or(|metadata| metadata.is_file()) {
+                let (file, temp) = create_temp(&self.target)?;
+                if let Some(metadata) = existing {
+                    // Keep the replaced file's permissions
+                    let _ = file.set_permissions(metadata.permissions());
+                }
+                State::Temp { file, temp }
+            } else {
+                State::Direct(File::create(&self.target)?)
+            };
+        }
+        match &mut self.state {
+            State::Temp { file, .. } | State::Direct(file) => Ok(file),
+            State::Unopened => unreachable!("opened above"),
+        }
+    }
+}
+
+impl Write for OutputFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.open()?.write(buf)
+    }
+
+    /// Flushes the data to disk and, in atomic mode, moves it into place
+    fn flush(&mut self) -> io::Result<()> {
+        self.open()?;
+        match std::mem::replace(&mut self.state, State::Unopened) {
+            State::Temp { file, temp } => {
+                let result = file.sync_all().and_then(|()| fs::rename(&temp, &self.target));
+                if let Err(e) = result {
+                    let _ = fs::remove_file(&temp);
+                    return Err(e);
+                }
+                self.state = State::Direct(file);
+                Ok(())
+            }
+            state => {
+                self.state = state;
+                self.open()?.flush()
+            }
+        }
+    }
+}
+
+impl Drop for OutputFile {
+    fn drop(&mut self) {
+        if let State::Temp { temp, .. } = &self.state {
+            let _ = fs::remove_file(temp);
+        }
+    }
+}
+
+/// Creates a new, hidden file in the same directory as `target`
+pub(crate) fn create_temp(target: &Path) -> io::Result<(File, PathBuf)> {
+    let name = target.file_name().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, format!("{} is not a file path", target.display()))
+    })?;
+    loop {
+        let mut temp_name = std::ffi::OsString::from(".");
+        temp_name.push(name);
+        temp_name.push(format!(".{}.{}.tmp", std::process::id(), TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)));
+        let temp = target.with_file_name(temp_name);
+        match OpenOptions::new().read(true).write(true).create_new(true).open(&temp) {
+            Ok(file) => return Ok((file, temp)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_atomic_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("plugin.obby");
+        fs::write(&target, b"old").unwrap();
+
+        let mut output = OutputFile::new(&target);
+        output.write_all(b"new").unwrap();
+        assert_eq!(fs::read(&target).unwrap(), b"old");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+        output.flush().unwrap();
+        assert_eq!(fs::read(&target).unwrap(), b"new");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        // Dropped before the flush: the target is untouched and the temporary file removed
+        let mut output = OutputFile::new(&target);
+        output.write_all(b"partial").unwrap();
+        drop(output);
+        assert_eq!(fs::read(&target).unwrap(), b"new");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        crate::ObbyWriter::create(&target, "Plugin", "1.0.0").finish().unwrap();
+        assert!(crate::open(&target).unwrap().list_entries().is_empty());
+
+        let mut output = OutputFile::new(&target);
+        output.set_atomic(false);
+        output.write_all(b"direct").unwrap();
+        assert_eq!(fs::read(&target).unwrap(), b"direct");
+    }
+}
@@ -0,0 +1,165 @@
+//! Layering several archives into one view
+
+use std::collections::HashSet;
+use std::fmt;
+use std::io::{self, Read, Seek};
+
+use crate::ObbyArchive;
+
+/// The parts of `ObbyArchive` an overlay needs, independent of the reader type
+trait Layer {
+    fn contains(&self, entry_name: &str) -> bool;
+    fn names(&self) -> &[String];
+    fn extract(&mut self, entry_name: &str) -> io::Result<Vec<u8>>;
+}
+
+impl<R: Read + Seek> Layer for ObbyArchive<R> {
+    fn contains(&self, entry_name: &str) -> bool {
+        self.entries.contains_key(entry_name)
+    }
+
+    fn names(&self) -> &[String] {
+        &self.order
+    }
+
+    fn extract(&mut self, entry_name: &str) -> io::Result<Vec<u8>> {
+        self.extract_entry(entry_name)
+    }
+}
+
+/// Several archives presented as one, such as a base plugin plus a localization pack
+///
+/// Lookups go through the layers from the top down, so an entry in a higher layer
+/// shadows entries of the same name below it. [`ObbyOverlay::push`] adds a layer on top;
+/// [`ObbyOverlay::push_under`] adds a fallback layer at the bottom. Layers may use
+/// different reader types.
+///
+/// # Example
+///
+/// ```no_run
+/// use obsidian_lib::ObbyOverlay;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut overlay = ObbyOverlay::new();
+/// overlay.push(obsidian_lib::open("plugin.obby")?);
+/// overlay.push(obsidian_lib::open("plugin-de.obby")?);
+///
+/// // Served from the localization pack if it has one, otherwise from the plugin
+/// let strings = overlay.extract_entry("lang/strings.json")?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct ObbyOverlay<'a> {
+    /// Layers from the bottom (lowest precedence) to the top
+    layers: Vec<Box<dyn Layer + 'a>>,
+}
+
+impl<'a> ObbyOverlay<'a> {
+    /// Creates an overlay without any layers
+    pub fn new() -> Self {
+        ObbyOverlay { layers: Vec::new() }
+    }
+
+    /// Adds an archive on top of the existing layers, so its entries take precedence
+    pub fn push<R: Read + Seek + 'a>(&mut self, archive: ObbyArchive<R>) {
+        self.layers.push(Box::new(archive));
+    }
+
+    /// Adds an archive below the existing layers, so it is only used for entries they lack
+    pub fn push_under<R: Read + Seek + 'a>(&mut self, archive: ObbyArchive<R>) {
+        self.layers.insert(0, Box::new(archive));
+    }
+
+    /// Returns the number of layers
+    pub fn layer_count(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Returns which layer an entry would be read from
+    ///
+    /// # Arguments
+    ///
+    /// * `entry_name` - The name of the entry.
+    ///
+    /// # Returns
+    ///
+    /// The index of the layer, counting from the bottom, or `None` if no layer has the entry.
+    pub fn layer_of(&self, entry_name: &str) -> Option<usize> {
+        self.layers.iter().rposition(|layer| layer.contains(entry_name))
+    }
+
+    /// Returns the names of all entries visible through the overlay
+    ///
+    /// Entries of the bottom layer come first, in archive order, followed by the entries
+    /// each higher layer adds. Shadowed names are listed once.
+    pub fn list_entries(&self) -> Vec<String> {
+        let mut seen = HashSet::new();
+        self.layers
+            .iter()
+            .flat_map(|layer| layer.names())
+            .filter(|name| seen.insert(name.as_str()))
+            .cloned()
+            .collect()
+    }
+
+    /// Extracts an entry from the highest layer that contains it
+    ///
+    /// # Arguments
+    ///
+    /// * `entry_name` - The name of the entry to extract.
+    ///
+    /// # Returns
+    ///
+    /// The entry's data, or an `io::Error` of kind `NotFound` if no layer has the entry.
+    pub fn extract_entry(&mut self, entry_name: &str) -> io::Result<Vec<u8>> {
+        match self.layer_of(entry_name) {
+            Some(index) => self.layers[index].extract(entry_name),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Entry '{}' not found in any layer", entry_name),
+            )),
+        }
+    }
+}
+
+impl fmt::Debug for ObbyOverlay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ObbyOverlay")
+            .field("layers", &self.layers.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_precedence() {
+        let base = ObbyTestBuilder::new()
+            .entry("plugin.json", b"base")
+            .entry("lang/en.json", b"english")
+            .build();
+        let pack = ObbyTestBuilder::new()
+            .entry("lang/en.json", b"patched english")
+            .entry("lang/de.json", b"deutsch")
+            .build();
+        let fallback = ObbyTestBuilder::new().entry("plugin.json", b"fallback").build();
+
+        let mut overlay = ObbyOverlay::new();
+        overlay.push(ObbyArchive::new(Cursor::new(base)).unwrap());
+        overlay.push(ObbyArchive::new(Cursor::new(&pack[..])).unwrap());
+        overlay.push_under(ObbyArchive::new(Cursor::new(fallback)).unwrap());
+
+        assert_eq!(overlay.list_entries(), vec!["plugin.json", "lang/en.json", "lang/de.json"]);
+        assert_eq!(overlay.extract_entry("plugin.json").unwrap(), b"base");
+        assert_eq!(overlay.extract_entry("lang/en.json").unwrap(), b"patched english");
+        assert_eq!(overlay.layer_of("lang/de.json"), Some(2));
+
+        let err = overlay.extract_entry("missing").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+}
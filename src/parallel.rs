@@ -0,0 +1,49 @@
+//! Running work over a slice on a few scoped threads
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// Runs `f` on every item on up to `threads` threads and returns the results in order
+///
+/// Threads take the next unclaimed item as they finish one, so a few slow items don't
+/// hold up the rest. With one thread or item, everything runs on the calling thread.
+#[cfg_attr(not(any(feature = "json", feature = "http")), allow(dead_code))]
+pub(crate) fn in_parallel<T: Sync, R: Send>(items: &[T], threads: usize, f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let threads = threads.max(1).min(items.len());
+    if threads <= 1 {
+        return items.iter().map(f).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(items.len()));
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(index) else {
+                    break;
+                };
+                let result = f(item);
+                results.lock().unwrap().push((index, result));
+            });
+        }
+    });
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_in_parallel_keeps_order() {
+        let items: Vec<u64> = (0..100).collect();
+        let expected: Vec<u64> = items.iter().map(|n| n * n).collect();
+        assert_eq!(in_parallel(&items, 4, |n| n * n), expected);
+        assert_eq!(in_parallel(&items, 0, |n| n * n), expected);
+        assert!(in_parallel(&[] as &[u64], 4, |n| *n).is_empty());
+    }
+}
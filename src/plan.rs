@@ -0,0 +1,255 @@
+//! Dry runs of [`ObbyArchive::extract_all`]
+
+use std::collections::HashMap;
+use std::io::{self, Read, Seek};
+use std::path::{Path, PathBuf};
+
+use crate::{ObbyArchive, ObbyError};
+
+/// What [`ObbyArchive::extract_all`] would write, returned by [`ObbyArchive::extract_all_plan`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ExtractPlan {
+    /// The files that would be written, in archive order
+    pub files: Vec<PlannedFile>,
+    /// Entries whose names the sanitize policy rejects
+    pub rejected: Vec<String>,
+    /// Paths that clash with each other or with what is already on disk
+    pub collisions: Vec<Collision>,
+    /// Total decompressed size of the files
+    pub total_bytes: u64,
+}
+
+/// One file of an [`ExtractPlan`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PlannedFile {
+    /// The entry's name
+    pub entry: String,
+    /// Where the entry would be written
+    pub path: PathBuf,
+    /// The entry's decompressed size
+    pub size: u64,
+}
+
+/// A problem found while planning an extraction
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub enum Collision {
+    /// Several entries map to the same path; the last one written wins
+    Duplicate {
+        /// The shared path
+        path: PathBuf,
+        /// The entries, in archive order
+        entries: Vec<String>,
+    },
+    /// Entries map to paths that differ only in case, which are the same file on
+    /// case-insensitive file systems such as the Windows and macOS defaults
+    CaseOnly {
+        /// The paths, in archive order
+        paths: Vec<PathBuf>,
+    },
+    /// A file already exists at the path and would be overwritten
+    Exists {
+        /// The existing file
+        path: PathBuf,
+        /// The entry that would replace it
+        entry: String,
+    },
+    /// The entry can't be written because a directory is in the way, or because a file,
+    /// on disk or earlier in the archive, sits where one of its parent directories must go
+    Blocked {
+        /// The path in the way
+        path: PathBuf,
+        /// The entry that can't be written
+        entry: String,
+    },
+}
+
+impl ExtractPlan {
+    /// Whether extracting would write every entry without replacing or losing any file
+    pub fn is_clean(&self) -> bool {
+        self.rejected.is_empty() && self.collisions.is_empty()
+    }
+}
+
+impl<R: Read + Seek> ObbyArchive<R> {
+    /// Works out what [`ObbyArchive::extract_all`] would do, without writing anything
+    ///
+    /// Entry names are mapped with the archive's [`crate::SanitizePolicy`], and the
+    /// resulting paths are checked against each other and against `dest`, so callers can
+    /// ask before overwriting files. Unlike `extract_all`, rejected names are listed
+    /// instead of failing. Nothing is decompressed; sizes come from the entry table.
+    ///
+    /// # Arguments
+    ///
+    /// * `dest` - The directory that would be extracted into. It doesn't need to exist.
+    ///
+    /// # Returns
+    ///
+    /// The [`ExtractPlan`], or an `io::Error` if `dest` can't be inspected.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut archive = obsidian_lib::open("plugin.obby")?;
+    /// let plan = archive.extract_all_plan("plugins/my-plugin")?;
+    /// if plan.is_clean() {
+    ///     archive.extract_all("plugins/my-plugin")?;
+    /// } else {
+    ///     println!("{:#?}", plan.collisions);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn extract_all_plan<P: AsRef<Path>>(&self, dest: P) -> io::Result<ExtractPlan> {
+        let dest = dest.as_ref();
+        let policy = self.options.sanitize_policy();
+        let mut plan = ExtractPlan::default();
+        let mut relative = Vec::new();
+        for name in &self.order {
+            match policy.sanitize(name) {
+                Ok(path) => {
+                    let size = self.entries[name].length as u64;
+                    plan.total_bytes += size;
+                    plan.files.push(PlannedFile { entry: name.clone(), path: dest.join(&path), size });
+                    relative.push(path);
+                }
+                Err(e) if matches!(ObbyError::from_io(&e), Some(ObbyError::UnsafePath { .. })) => {
+                    plan.rejected.push(name.clone());
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut collisions = entry_collisions(dest, &plan.files, &relative);
+
+        // Clashes with what is already on disk
+        let mut blocked_dirs = HashMap::new();
+        for file in &plan.files {
+            let parent = file.path.parent().unwrap_or(dest);
+            if let Some(path) = blocking_dir(parent, dest, &mut blocked_dirs)? {
+                collisions.push(Collision::Blocked { path, entry: file.entry.clone() });
+                continue;
+            }
+            match file.path.symlink_metadata() {
+                Ok(metadata) if metadata.is_dir() => {
+                    collisions.push(Collision::Blocked { path: file.path.clone(), entry: file.entry.clone() })
+                }
+                Ok(_) => collisions.push(Collision::Exists { path: file.path.clone(), entry: file.entry.clone() }),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        plan.collisions = collisions;
+        Ok(plan)
+    }
+}
+
+/// Finds clashes between the entries of `files`, whose paths relative to `dest` are `relative`
+pub(crate) fn entry_collisions(dest: &Path, files: &[PlannedFile], relative: &[PathBuf]) -> Vec<Collision> {
+    let mut by_path: HashMap<&Path, Vec<&PlannedFile>> = HashMap::new();
+    let mut by_folded: HashMap<String, Vec<&Path>> = HashMap::new();
+    for (file, path) in files.iter().zip(relative) {
+        let same = by_path.entry(path).or_default();
+        if same.is_empty() {
+            by_folded.entry(path.to_string_lossy().to_lowercase()).or_default().push(path);
+        }
+        same.push(file);
+    }
+    let mut collisions = Vec::new();
+    for (file, path) in files.iter().zip(relative) {
+        let same = &by_path[path.as_path()];
+        if same.len() > 1 && same[0].entry == file.entry {
+            collisions.push(Collision::Duplicate {
+                path: file.path.clone(),
+                entries: same.iter().map(|file| file.entry.clone()).collect(),
+            });
+        }
+        let folded = &by_folded[&path.to_string_lossy().to_lowercase()];
+        if folded.len() > 1 && folded[0] == path {
+            collisions.push(Collision::CaseOnly { paths: folded.iter().map(|path| dest.join(path)).collect() });
+        }
+        if let Some(parent) = path.ancestors().skip(1).find(|parent| by_path.contains_key(parent)) {
+            collisions.push(Collision::Blocked { path: dest.join(parent), entry: file.entry.clone() });
+        }
+    }
+    collisions
+}
+
+/// Finds a non-directory on disk at `dir` or one of its ancestors up to `dest`
+///
+/// Results are cached in `cache`, since entries tend to share directories.
+fn blocking_dir(dir: &Path, dest: &Path, cache: &mut HashMap<PathBuf, Option<PathBuf>>) -> io::Result<Option<PathBuf>> {
+    if let Some(result) = cache.get(dir) {
+        return Ok(result.clone());
+    }
+    let blocked_parent = match dir.parent() {
+        Some(parent) if dir != dest && parent.starts_with(dest) => blocking_dir(parent, dest, cache)?,
+        _ => None,
+    };
+    let result = match blocked_parent {
+        Some(path) => Some(path),
+        None => match dir.symlink_metadata() {
+            Ok(metadata) if !metadata.is_dir() => Some(dir.to_path_buf()),
+            Ok(_) => None,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        },
+    };
+    cache.insert(dir.to_path_buf(), result.clone());
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+
+    #[test]
+    fn test_extract_all_plan() {
+        let buffer = ObbyTestBuilder::new()
+            .entry("plugin.json", b"{}")
+            .entry("lib/a.dll", b"aaaa")
+            .entry("lib\\a.dll", b"bb")
+            .entry("README.md", b"x")
+            .entry("readme.md", b"y")
+            .entry("main.js", b"js")
+            .entry("main.js/inner.txt", b"z")
+            .entry("../evil", b"!")
+            .build();
+        let archive = ObbyArchive::from_bytes(buffer).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("plugin.json"), b"old").unwrap();
+
+        let plan = archive.extract_all_plan(dir.path()).unwrap();
+        assert!(!plan.is_clean());
+        assert_eq!(plan.files.len(), 7);
+        assert_eq!(plan.total_bytes, 2 + 4 + 2 + 1 + 1 + 2 + 1);
+        assert_eq!(plan.rejected, vec!["../evil"]);
+        let lib = dir.path().join("lib").join("a.dll");
+        assert_eq!(
+            plan.collisions,
+            vec![
+                Collision::Duplicate { path: lib, entries: vec!["lib/a.dll".to_string(), "lib\\a.dll".to_string()] },
+                Collision::CaseOnly { paths: vec![dir.path().join("README.md"), dir.path().join("readme.md")] },
+                Collision::Blocked { path: dir.path().join("main.js"), entry: "main.js/inner.txt".to_string() },
+                Collision::Exists { path: dir.path().join("plugin.json"), entry: "plugin.json".to_string() },
+            ]
+        );
+        // Nothing was written
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        std::fs::remove_file(dir.path().join("plugin.json")).unwrap();
+        std::fs::write(dir.path().join("lib"), b"a file").unwrap();
+        let plan = archive.extract_all_plan(dir.path()).unwrap();
+        let blocked = Collision::Blocked { path: dir.path().join("lib"), entry: "lib\\a.dll".to_string() };
+        assert_eq!(plan.collisions.last(), Some(&blocked));
+
+        let clean = ObbyArchive::from_bytes(ObbyTestBuilder::new().entry("a/b.txt", b"b").build()).unwrap();
+        assert!(clean.extract_all_plan(dir.path().join("fresh")).unwrap().is_clean());
+    }
+}
@@ -0,0 +1,403 @@
+//! Declarative acceptance rules for registries
+//!
+//! Most registries check the same things before accepting an upload: that it isn't
+//! too large, contains only file types they serve, has a complete manifest, is signed
+//! and doesn't smuggle in native executables. A [`Policy`] lists such [`Rule`]s, and
+//! [`Policy::check`] runs them all and reports every violation rather than stopping at
+//! the first.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use obsidian_lib::policy::{Policy, Rule};
+//!
+//! # fn main() -> std::io::Result<()> {
+//! let policy = Policy::new()
+//!     .with(Rule::MaxTotalSize(50 * 1024 * 1024))
+//!     .with(Rule::AllowedExtensions(vec!["dll".into(), "json".into(), "md".into(), "png".into()]))
+//!     .with(Rule::RequiredManifestFields(vec!["id".into(), "version".into(), "authors".into()]))
+//!     .with(Rule::RequireSignature)
+//!     .with(Rule::NoNativeBinaries);
+//!
+//! let mut archive = obsidian_lib::open("upload.obby")?;
+//! let report = policy.check(&mut archive)?;
+//! if !report.passed() {
+//!     for violation in &report.violations {
+//!         eprintln!("{}", violation);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+use std::io::{self, Read, Seek};
+
+use serde_json::Value;
+
+use crate::inspect::{EntryInspector, Finding};
+use crate::{tree, HeaderEntry, ObbyArchive};
+
+#[cfg(feature = "signing")]
+use rsa::RsaPublicKey;
+
+/// How much of each entry is kept to recognise executable headers
+const SNIFF_LEN: usize = 4096;
+
+/// A requirement an archive must meet; see [`Policy`]
+#[derive(Debug, Clone)]
+pub enum Rule {
+    /// The decompressed sizes of all entries may add up to at most this many bytes
+    MaxTotalSize(u64),
+    /// No entry may decompress to more than this many bytes
+    MaxEntrySize(u64),
+    /// Every entry must have one of these file extensions, without the leading dot and
+    /// compared case-insensitively; include `""` to allow files without an extension
+    AllowedExtensions(Vec<String>),
+    /// The plugin manifest must exist and have these fields, neither `null` nor empty
+    RequiredManifestFields(Vec<String>),
+    /// The archive must carry a signature; use [`Rule::SignedBy`] to also check who made it
+    RequireSignature,
+    /// The archive must be signed by one of these keys, and its hash must match
+    #[cfg(feature = "signing")]
+    SignedBy(Vec<RsaPublicKey>),
+    /// No entry may be a native executable or library: ELF, Mach-O, or a PE file that
+    /// isn't a .NET assembly
+    NoNativeBinaries,
+}
+
+impl Rule {
+    /// Returns the rule's name as used in reports, such as `"max-entry-size"`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rule::MaxTotalSize(_) => "max-total-size",
+            Rule::MaxEntrySize(_) => "max-entry-size",
+            Rule::AllowedExtensions(_) => "allowed-extensions",
+            Rule::RequiredManifestFields(_) => "required-manifest-fields",
+            Rule::RequireSignature => "require-signature",
+            #[cfg(feature = "signing")]
+            Rule::SignedBy(_) => "signed-by",
+            Rule::NoNativeBinaries => "no-native-binaries",
+        }
+    }
+
+    /// Whether the rule is checked against each entry rather than the archive as a whole
+    fn is_per_entry(&self) -> bool {
+        matches!(self, Rule::MaxEntrySize(_) | Rule::AllowedExtensions(_) | Rule::NoNativeBinaries)
+    }
+}
+
+/// A rule an archive broke; part of a [`PolicyReport`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Violation {
+    /// Name of the rule, as returned by [`Rule::name`]
+    pub rule: &'static str,
+    /// The entry at fault, or `None` if the violation concerns the whole archive
+    pub entry: Option<String>,
+    /// Human-readable description
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.entry {
+            Some(entry) => write!(f, "[{}] {}: {}", self.rule, entry, self.message),
+            None => write!(f, "[{}] {}", self.rule, self.message),
+        }
+    }
+}
+
+/// The outcome of [`Policy::check`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PolicyReport {
+    /// Every violation, archive-wide rules first in policy order, then entries in archive order
+    pub violations: Vec<Violation>,
+}
+
+impl PolicyReport {
+    /// Whether the archive met every rule
+    pub fn passed(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// A set of [`Rule`]s to check archives against
+#[derive(Debug, Clone, Default)]
+pub struct Policy {
+    rules: Vec<Rule>,
+}
+
+impl Policy {
+    /// Creates a policy without any rules, which every archive passes
+    pub fn new() -> Self {
+        Policy::default()
+    }
+
+    /// Adds a rule
+    pub fn with(mut self, rule: Rule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Returns the rules in the order they were added
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Checks an archive against every rule
+    ///
+    /// Entries are only decompressed if a rule needs their contents, and then in a single
+    /// pass with [`ObbyArchive::inspect_all`].
+    ///
+    /// # Arguments
+    ///
+    /// * `archive` - The archive to check.
+    ///
+    /// # Returns
+    ///
+    /// A [`PolicyReport`] listing every violation, or an `io::Error` if the archive can't
+    /// be read. A missing or invalid manifest, signature or hash counts as a violation,
+    /// not an error.
+    pub fn check<R: Read + Seek>(&self, archive: &mut ObbyArchive<R>) -> io::Result<PolicyReport> {
+        let mut report = PolicyReport::default();
+        for rule in self.rules.iter().filter(|rule| !rule.is_per_entry()) {
+            if let Some(message) = check_archive(rule, archive)? {
+                report.violations.push(Violation { rule: rule.name(), entry: None, message });
+            }
+        }
+
+        let mut entry_rules = EntryRules {
+            rules: self.rules.iter().filter(|rule| rule.is_per_entry()).collect(),
+            prefix: Vec::new(),
+            violations: Vec::new(),
+        };
+        if entry_rules.rules.iter().any(|rule| matches!(rule, Rule::NoNativeBinaries)) {
+            archive.inspect_all(&mut entry_rules)?;
+        } else if !entry_rules.rules.is_empty() {
+            for entry in archive.header().entries {
+                entry_rules.finish_entry(&entry, &mut Vec::new());
+            }
+        }
+        report.violations.append(&mut entry_rules.violations);
+        Ok(report)
+    }
+}
+
+/// Checks an archive-wide rule, returning what is wrong
+fn check_archive<R: Read + Seek>(rule: &Rule, archive: &mut ObbyArchive<R>) -> io::Result<Option<String>> {
+    let message = match rule {
+        Rule::MaxTotalSize(limit) => {
+            let total = archive.stats().uncompressed_size;
+            (total > *limit).then(|| format!("entries total {} bytes, exceeding the limit of {} bytes", total, limit))
+        }
+        Rule::RequiredManifestFields(fields) => match archive.plugin_manifest() {
+            Ok(manifest) => {
+                let manifest = manifest.to_value();
+                let missing: Vec<&str> = fields
+                    .iter()
+                    .filter(|field| is_blank(manifest.get(field.as_str())))
+                    .map(String::as_str)
+                    .collect();
+                (!missing.is_empty()).then(|| format!("manifest is missing {}", missing.join(", ")))
+            }
+            Err(e) if matches!(e.kind(), io::ErrorKind::NotFound | io::ErrorKind::InvalidData) => Some(e.to_string()),
+            Err(e) => return Err(e),
+        },
+        Rule::RequireSignature => archive.metadata().signature.is_none().then(|| "archive is not signed".to_string()),
+        #[cfg(feature = "signing")]
+        Rule::SignedBy(keys) => match crate::signing::verify_signature(archive, keys) {
+            Ok(_) => None,
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => Some(e.to_string()),
+            Err(e) => return Err(e),
+        },
+        Rule::MaxEntrySize(_) | Rule::AllowedExtensions(_) | Rule::NoNativeBinaries => None,
+    };
+    Ok(message)
+}
+
+/// Whether a manifest field is absent or has no content
+fn is_blank(value: Option<&Value>) -> bool {
+    match value {
+        None | Some(Value::Null) => true,
+        Some(Value::String(value)) => value.trim().is_empty(),
+        Some(Value::Array(values)) => values.is_empty(),
+        Some(Value::Object(values)) => values.is_empty(),
+        Some(_) => false,
+    }
+}
+
+/// Runs the per-entry rules of a policy as an inspector
+struct EntryRules<'a> {
+    rules: Vec<&'a Rule>,
+    /// The first [`SNIFF_LEN`] bytes of the current entry
+    prefix: Vec<u8>,
+    violations: Vec<Violation>,
+}
+
+impl EntryInspector for EntryRules<'_> {
+    fn begin_entry(&mut self, _entry: &HeaderEntry) -> io::Result<()> {
+        self.prefix.clear();
+        Ok(())
+    }
+
+    fn update(&mut self, chunk: &[u8]) -> io::Result<()> {
+        let wanted = SNIFF_LEN - self.prefix.len();
+        self.prefix.extend_from_slice(&chunk[..chunk.len().min(wanted)]);
+        Ok(())
+    }
+
+    fn finish_entry(&mut self, entry: &HeaderEntry, _findings: &mut Vec<Finding>) {
+        for rule in &self.rules {
+            let message = match rule {
+                Rule::MaxEntrySize(limit) => (entry.length > *limit)
+                    .then(|| format!("{} bytes exceeds the limit of {} bytes", entry.length, limit)),
+                Rule::AllowedExtensions(allowed) => {
+                    let ext = extension(&entry.name);
+                    (!allowed.iter().any(|allowed| allowed.eq_ignore_ascii_case(&ext))).then(|| match ext.as_str() {
+                        "" => "files without an extension are not allowed".to_string(),
+                        ext => format!("files of type .{} are not allowed", ext),
+                    })
+                }
+                Rule::NoNativeBinaries => native_format(&self.prefix).map(|format| format!("is a native {} binary", format)),
+                _ => None,
+            };
+            if let Some(message) = message {
+                self.violations.push(Violation { rule: rule.name(), entry: Some(entry.name.clone()), message });
+            }
+        }
+    }
+}
+
+/// Returns the lowercased extension of an entry's file name, or `""` if it has none
+fn extension(name: &str) -> String {
+    let file_name = tree::components(name).last().unwrap_or("");
+    match file_name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => ext.to_ascii_lowercase(),
+        _ => String::new(),
+    }
+}
+
+/// Recognises native executables and libraries by their header
+fn native_format(data: &[u8]) -> Option<&'static str> {
+    let u32_at = |pos: usize, read: fn([u8; 4]) -> u32| data.get(pos..pos + 4).map(|bytes| read(bytes.try_into().unwrap()));
+    match data {
+        [0x7F, b'E', b'L', b'F', ..] => Some("ELF"),
+        [0xFE, 0xED, 0xFA, 0xCE | 0xCF, ..] | [0xCE | 0xCF, 0xFA, 0xED, 0xFE, ..] => Some("Mach-O"),
+        // Universal Mach-O binaries share their magic with Java classes, whose version
+        // takes the place of the (small) architecture count
+        [0xCA, 0xFE, 0xBA, 0xBE, ..] if u32_at(4, u32::from_be_bytes).is_some_and(|count| count < 45) => Some("Mach-O"),
+        [b'M', b'Z', ..] => (!is_managed_pe(data)).then_some("PE"),
+        _ => None,
+    }
+}
+
+/// Whether a PE file has a CLR header, i.e. is a .NET assembly
+///
+/// Anything that can't be parsed from `data` is treated as native.
+fn is_managed_pe(data: &[u8]) -> bool {
+    let u16_at = |pos: usize| data.get(pos..pos + 2).map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]));
+    let u32_at = |pos: usize| data.get(pos..pos + 4).map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()));
+    let managed = || {
+        let pe = u32_at(0x3C)? as usize;
+        if data.get(pe..pe + 4)? != b"PE\0\0" {
+            return None;
+        }
+        // The optional header follows the 4-byte signature and the 20-byte COFF header
+        let optional = pe + 24;
+        let (count_pos, directories) = match u16_at(optional)? {
+            0x10B => (optional + 92, optional + 96),
+            0x20B => (optional + 108, optional + 112),
+            _ => return None,
+        };
+        // Data directory 14 locates the CLR runtime header
+        Some(u32_at(count_pos)? > 14 && u32_at(directories + 14 * 8)? != 0)
+    };
+    managed().unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+
+    /// Builds a minimal PE32 header, with a CLR directory if `managed`
+    fn pe(managed: bool) -> Vec<u8> {
+        let mut data = vec![0u8; 0x200];
+        data[..2].copy_from_slice(b"MZ");
+        data[0x3C] = 0x80;
+        data[0x80..0x84].copy_from_slice(b"PE\0\0");
+        data[0x98..0x9A].copy_from_slice(&0x10Bu16.to_le_bytes());
+        data[0x98 + 92] = 16;
+        if managed {
+            data[0x98 + 96 + 14 * 8] = 0x20;
+        }
+        data
+    }
+
+    fn summary(report: &PolicyReport) -> Vec<(&str, Option<&str>)> {
+        report.violations.iter().map(|violation| (violation.rule, violation.entry.as_deref())).collect()
+    }
+
+    #[test]
+    fn test_check() {
+        let buffer = ObbyTestBuilder::new()
+            .entry("plugin.json", br#"{"id": "plugin", "version": "", "authors": []}"#)
+            .entry("Plugin.dll", &pe(true))
+            .entry("native/helper.dll", &pe(false))
+            .entry("native/libhelper.so", b"\x7fELF\x02\x01\x01")
+            .stored_entry("assets/big.bin", &[0u8; 600])
+            .entry("Makefile", b"all:")
+            .build();
+        let mut archive = ObbyArchive::from_bytes(buffer).unwrap();
+
+        let policy = Policy::new()
+            .with(Rule::MaxTotalSize(1000))
+            .with(Rule::MaxEntrySize(1024))
+            .with(Rule::AllowedExtensions(vec!["json".into(), "DLL".into(), "bin".into(), "so".into()]))
+            .with(Rule::RequiredManifestFields(vec!["id".into(), "version".into(), "authors".into()]))
+            .with(Rule::RequireSignature)
+            .with(Rule::NoNativeBinaries);
+        let report = policy.check(&mut archive).unwrap();
+        assert!(!report.passed());
+        assert_eq!(
+            summary(&report),
+            vec![
+                ("max-total-size", None),
+                ("required-manifest-fields", None),
+                ("require-signature", None),
+                ("no-native-binaries", Some("native/helper.dll")),
+                ("no-native-binaries", Some("native/libhelper.so")),
+                ("allowed-extensions", Some("Makefile")),
+            ]
+        );
+        assert_eq!(report.violations[1].message, "manifest is missing version, authors");
+        assert_eq!(report.violations[3].to_string(), "[no-native-binaries] native/helper.dll: is a native PE binary");
+
+        let report = Policy::new().with(Rule::MaxEntrySize(512)).check(&mut archive).unwrap();
+        assert_eq!(summary(&report), vec![("max-entry-size", Some("assets/big.bin"))]);
+        assert!(Policy::new().check(&mut archive).unwrap().passed());
+    }
+
+    #[test]
+    fn test_missing_manifest() {
+        let buffer = ObbyTestBuilder::new().entry("main.js", b"js").build();
+        let mut archive = ObbyArchive::from_bytes(buffer).unwrap();
+        let report = Policy::new()
+            .with(Rule::RequiredManifestFields(vec!["id".into()]))
+            .check(&mut archive)
+            .unwrap();
+        assert_eq!(summary(&report), vec![("required-manifest-fields", None)]);
+    }
+
+    #[test]
+    fn test_native_format() {
+        assert_eq!(native_format(b"\xCF\xFA\xED\xFE"), Some("Mach-O"));
+        assert_eq!(native_format(b"\xCA\xFE\xBA\xBE\0\0\0\x02"), Some("Mach-O"));
+        // A Java class file, version 52
+        assert_eq!(native_format(b"\xCA\xFE\xBA\xBE\0\0\0\x34"), None);
+        assert_eq!(native_format(b"MZ"), Some("PE"));
+        assert_eq!(native_format(b"Mozilla"), None);
+    }
+}
@@ -0,0 +1,17 @@
+//! The most commonly used types, for glob import
+//!
+//! ```no_run
+//! use obsidian_lib::prelude::*;
+//!
+//! # fn main() -> std::io::Result<()> {
+//! # #[cfg(feature = "json")] {
+//! let mut archive: ObbyArchive<_> = obsidian_lib::open("plugin.obby")?;
+//! let manifest: PluginManifest = archive.plugin_manifest()?;
+//! # }
+//! # Ok(())
+//! # }
+//! ```
+
+#[cfg(feature = "json")]
+pub use crate::manifest::PluginManifest;
+pub use crate::{ObbyArchive, ObbyError, ObbyWriter};
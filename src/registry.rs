@@ -0,0 +1,328 @@
+//! Searching a plugin repository and downloading plugins from it
+//!
+//! Enabled with the `registry` feature.
+
+use std::io::{self, Cursor};
+
+#[cfg(feature = "signing")]
+use rsa::RsaPublicKey;
+use sha2::{Digest, Sha256};
+
+use crate::http::{download, download_throttled, Throttle};
+use crate::index::{IndexedPlugin, IndexedVersion, RepositoryIndex};
+use crate::{to_hex, ObbyArchive, RemoteOptions};
+
+/// Name of the index below a registry's base URL
+pub const INDEX_NAME: &str = "index.json";
+
+/// A client for a plugin registry
+///
+/// A registry is any HTTP(S) location serving a [`RepositoryIndex`] as `index.json`,
+/// with the archives it lists, so a static file server is enough. The index is
+/// downloaded by [`Registry::connect`] and kept until [`Registry::refresh`]; searching
+/// and listing versions don't touch the network.
+///
+/// Every download is checked against the size and SHA-256 in the index and against
+/// the hash in its own header. With the `signing` feature, trusted keys can be set
+/// with [`Registry::with_trusted_keys`] to also require a valid signature. Requests
+/// are retried as configured by the [`RemoteOptions`] passed to
+/// [`Registry::connect_with_options`].
+///
+/// # Example
+///
+/// ```no_run
+/// use obsidian_lib::Registry;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let registry = Registry::connect("https://plugins.example.com")?;
+/// for plugin in registry.search("chat") {
+///     println!("{} {:?}", plugin.id, plugin.latest().map(|version| &version.version));
+/// }
+/// let mut archive = registry.download("chat-filter", None)?;
+/// println!("{:?}", archive.list_entries());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Registry {
+    index_url: String,
+    base_url: String,
+    index: RepositoryIndex,
+    options: RemoteOptions,
+    #[cfg(feature = "signing")]
+    trusted_keys: Vec<RsaPublicKey>,
+}
+
+impl Registry {
+    /// Downloads a registry's index
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - The `http://` or `https://` URL the registry is served from; the
+    ///   index is read from `<base_url>/index.json`.
+    ///
+    /// # Returns
+    ///
+    /// The `Registry`, or an `io::Error` if the index can't be downloaded or parsed.
+    pub fn connect(base_url: &str) -> io::Result<Registry> {
+        Registry::connect_with_options(base_url, RemoteOptions::default())
+    }
+
+    /// Downloads a registry's index, making all requests with custom options
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - The URL the registry is served from, as for [`Registry::connect`].
+    /// * `options` - Retry, concurrency, rate limit, header, authentication and proxy settings.
+    pub fn connect_with_options(base_url: &str, options: RemoteOptions) -> io::Result<Registry> {
+        let index_url = format!("{}/{}", base_url.trim_end_matches('/'), INDEX_NAME);
+        Registry::from_index_url_with_options(&index_url, options)
+    }
+
+    /// Downloads an index from anywhere
+    ///
+    /// Relative download locations in the index are resolved against the directory
+    /// the index is in.
+    ///
+    /// # Arguments
+    ///
+    /// * `index_url` - The `http://` or `https://` URL of the index document.
+    ///
+    /// # Returns
+    ///
+    /// The `Registry`, or an `io::Error` if the index can't be downloaded or parsed.
+    pub fn from_index_url(index_url: &str) -> io::Result<Registry> {
+        Registry::from_index_url_with_options(index_url, RemoteOptions::default())
+    }
+
+    /// Downloads an index from anywhere, making all requests with custom options
+    ///
+    /// # Arguments
+    ///
+    /// * `index_url` - The URL of the index document, as for [`Registry::from_index_url`].
+    /// * `options` - Retry, concurrency, rate limit, header, authentication and proxy settings.
+    pub fn from_index_url_with_options(index_url: &str, options: RemoteOptions) -> io::Result<Registry> {
+        let index = RepositoryIndex::from_json(&download(index_url, &options)?)?;
+        let base_url = index_url.rsplit_once('/').map_or(index_url, |(base, _)| base).to_string();
+        Ok(Registry {
+            index_url: index_url.to_string(),
+            base_url,
+            index,
+            options,
+            #[cfg(feature = "signing")]
+            trusted_keys: Vec::new(),
+        })
+    }
+
+    /// Requires downloads to be signed by one of `keys`
+    #[cfg(feature = "signing")]
+    pub fn with_trusted_keys(mut self, keys: Vec<RsaPublicKey>) -> Self {
+        self.trusted_keys = keys;
+        self
+    }
+
+    /// The URL relative download locations are resolved against, without a trailing slash
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// The options requests are made with
+    pub fn options(&self) -> &RemoteOptions {
+        &self.options
+    }
+
+    /// The index as last downloaded
+    pub fn index(&self) -> &RepositoryIndex {
+        &self.index
+    }
+
+    /// Downloads the index again
+    pub fn refresh(&mut self) -> io::Result<()> {
+        self.index = RepositoryIndex::from_json(&download(&self.index_url, &self.options)?)?;
+        Ok(())
+    }
+
+    /// Returns the plugins whose ID or name contains `query`, ignoring case
+    ///
+    /// An empty query matches every plugin.
+    pub fn search(&self, query: &str) -> Vec<&IndexedPlugin> {
+        let query = query.to_lowercase();
+        self.index
+            .plugins
+            .iter()
+            .filter(|plugin| {
+                plugin.id.to_lowercase().contains(&query)
+                    || plugin.name.as_ref().is_some_and(|name| name.to_lowercase().contains(&query))
+            })
+            .collect()
+    }
+
+    /// Returns the versions of a plugin, oldest first
+    ///
+    /// # Returns
+    ///
+    /// The versions, or an `io::Error` of kind `NotFound` if the registry has no
+    /// plugin with that ID.
+    pub fn versions(&self, id: &str) -> io::Result<&[IndexedVersion]> {
+        Ok(&self.plugin(id)?.versions)
+    }
+
+    /// Downloads and verifies a version of a plugin
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The plugin's ID.
+    /// * `version` - The version to download, or `None` for the newest, see
+    ///   [`IndexedPlugin::latest`].
+    ///
+    /// # Returns
+    ///
+    /// The archive, held in memory. An unknown plugin or version fails with kind
+    /// `NotFound`, and a download that doesn't match the index, its own header hash or,
+    /// if trusted keys are set, any of them, fails with kind `InvalidData`.
+    pub fn download(&self, id: &str, version: Option<&str>) -> io::Result<ObbyArchive<Cursor<Vec<u8>>>> {
+        let plugin = self.plugin(id)?;
+        let indexed = match version {
+            Some(version) => plugin.version(version),
+            None => plugin.latest(),
+        }
+        .ok_or_else(|| {
+            let version = version.unwrap_or("any version");
+            io::Error::new(io::ErrorKind::NotFound, format!("Plugin '{}' has no {} in the registry", plugin.id, version))
+        })?;
+        let throttle = Throttle::new(self.options.rate_limit());
+        ObbyArchive::from_bytes(self.download_verified(indexed, &throttle)?)
+    }
+
+    /// Downloads the archive of a version and runs the checks of [`Registry::download`] on it
+    pub(crate) fn download_verified(&self, indexed: &IndexedVersion, throttle: &Throttle) -> io::Result<Vec<u8>> {
+        let url = self.resolve(&indexed.download);
+        let data = download_throttled(&url, &self.options, throttle)?;
+        let mismatch = |what: &str| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("{} doesn't match the registry's {}", url, what))
+        };
+        if data.len() as u64 != indexed.size {
+            return Err(mismatch("size"));
+        }
+        if to_hex(&Sha256::digest(&data)) != indexed.sha256.to_ascii_lowercase() {
+            return Err(mismatch("SHA-256"));
+        }
+        let mut archive = ObbyArchive::new(Cursor::new(&data[..]))?;
+        if to_hex(&archive.metadata.hash) != indexed.hash.to_ascii_lowercase() {
+            return Err(mismatch("header hash"));
+        }
+        archive.verify_hash()?;
+        #[cfg(feature = "signing")]
+        if !self.trusted_keys.is_empty() {
+            crate::signing::verify_signature(&mut archive, &self.trusted_keys)?;
+        }
+        Ok(data)
+    }
+
+    /// Resolves a download location from the index against the base URL
+    pub fn resolve(&self, download: &str) -> String {
+        if download.contains("://") {
+            download.to_string()
+        } else {
+            format!("{}/{}", self.base_url, download.trim_start_matches('/'))
+        }
+    }
+
+    fn plugin(&self, id: &str) -> io::Result<&IndexedPlugin> {
+        self.index
+            .plugin(id)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Plugin '{}' isn't in the registry", id)))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::index::IndexedVersion;
+    use crate::testing::ObbyTestBuilder;
+    use std::collections::HashMap;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Serves `files` by path on a local port until the test ends and returns the base URL
+    pub(crate) fn serve(files: HashMap<String, Vec<u8>>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request = String::new();
+                reader.read_line(&mut request).unwrap();
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                let path = request.split(' ').nth(1).unwrap_or_default().trim_start_matches('/');
+                let (status, body) = match files.get(path) {
+                    Some(body) => ("200 OK", body.as_slice()),
+                    None => ("404 Not Found", &[][..]),
+                };
+                let _ = write!(stream, "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", status, body.len());
+                let _ = stream.write_all(body);
+            }
+        });
+        url
+    }
+
+    pub(crate) fn indexed(version: &str, data: &[u8]) -> IndexedVersion {
+        let archive = ObbyArchive::from_bytes(data.to_vec()).unwrap();
+        IndexedVersion {
+            version: version.to_string(),
+            download: format!("files/alpha-{}.obby", version),
+            size: data.len() as u64,
+            sha256: to_hex(&Sha256::digest(data)),
+            hash: to_hex(&archive.metadata.hash),
+            api_version: archive.metadata.api_version.clone(),
+            assembly: archive.metadata.plugin_assembly.clone(),
+            signed: false,
+            manifest: serde_json::Value::Null,
+        }
+    }
+
+    /// Builds version `version` of the plugin `alpha`
+    pub(crate) fn alpha(version: &str) -> Vec<u8> {
+        ObbyTestBuilder::new()
+            .plugin("Alpha", version)
+            .entry("plugin.json", format!(r#"{{"id": "alpha", "version": "{}"}}"#, version).as_bytes())
+            .build()
+    }
+
+    #[test]
+    fn test_registry() {
+        let (old, new) = (alpha("1.0.0"), alpha("1.1.0"));
+        let mut tampered = indexed("1.2.0", &new);
+        tampered.download = "files/alpha-1.1.0.obby".to_string();
+        tampered.sha256 = "00".repeat(32);
+        let index = RepositoryIndex {
+            plugins: vec![IndexedPlugin {
+                id: "alpha".to_string(),
+                name: Some("Alpha Chat".to_string()),
+                versions: vec![indexed("1.0.0", &old), indexed("1.1.0", &new), tampered],
+            }],
+        };
+        let files = HashMap::from([
+            (INDEX_NAME.to_string(), index.to_json().to_string().into_bytes()),
+            ("files/alpha-1.0.0.obby".to_string(), old),
+            ("files/alpha-1.1.0.obby".to_string(), new),
+        ]);
+        let registry = Registry::connect(&(serve(files) + "/")).unwrap();
+
+        assert_eq!(registry.search("CHAT").len(), 1);
+        assert!(registry.search("beta").is_empty());
+        assert_eq!(registry.versions("alpha").unwrap().len(), 3);
+        assert_eq!(registry.versions("beta").unwrap_err().kind(), io::ErrorKind::NotFound);
+
+        let archive = registry.download("alpha", Some("1.0.0")).unwrap();
+        assert_eq!(archive.metadata.plugin_version, "1.0.0");
+        assert_eq!(registry.download("alpha", None).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(registry.download("alpha", Some("2.0.0")).unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert_eq!(registry.resolve("https://cdn.example.com/a.obby"), "https://cdn.example.com/a.obby");
+    }
+}
@@ -0,0 +1,270 @@
+//! Options controlling how archives are downloaded
+//!
+//! Enabled with the `http` feature.
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use base64::Engine;
+
+/// Options for downloading archives and indexes over HTTP(S)
+///
+/// Used by [`crate::fetch_with_options`], [`crate::download_file`],
+/// [`crate::download_files`] and, with the `registry` feature, the registry client.
+/// Plugin mirrors are often flaky, so by default failed requests are retried a few
+/// times with exponential backoff, and interrupted transfers resume where they stopped
+/// if the server supports byte ranges.
+///
+/// For sources behind authentication, extra headers such as API tokens and HTTP basic
+/// auth credentials are sent with every request, and requests can go through a proxy.
+/// Credentials are left out of the `Debug` output. Requests made with the same options,
+/// or clones of them, reuse open connections.
+///
+/// With a cache directory, archives and manifests are kept on disk between runs and
+/// re-requested conditionally, so unchanged archives aren't downloaded again.
+///
+/// # Example
+///
+/// ```no_run
+/// use obsidian_lib::{RemoteOptions, RemoteProxy};
+/// use std::time::Duration;
+///
+/// let mut options = RemoteOptions::default();
+/// options.set_retries(5);
+/// options.set_initial_backoff(Duration::from_secs(1));
+/// options.set_rate_limit(Some(512 * 1024));
+/// options.add_header("Authorization", "Bearer 0123456789abcdef");
+/// options.set_proxy(RemoteProxy::System);
+/// options.set_cache_dir(Some("obby-cache".into()));
+/// let archive = obsidian_lib::fetch_with_options("https://example.com/plugin.obby", &options).unwrap();
+/// ```
+#[derive(Clone)]
+pub struct RemoteOptions {
+    retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    concurrency: usize,
+    rate_limit: Option<u64>,
+    headers: Vec<(String, String)>,
+    basic_auth: Option<(String, String)>,
+    proxy: RemoteProxy,
+    cache_dir: Option<PathBuf>,
+    /// Built on first use and shared by clones, so requests reuse connections
+    agent: OnceLock<ureq::Agent>,
+}
+
+/// How requests reach the server, chosen with [`RemoteOptions::set_proxy`]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum RemoteProxy {
+    /// Connect directly, ignoring any proxy configured in the environment
+    #[default]
+    Direct,
+    /// Use the proxy in the `ALL_PROXY`, `HTTPS_PROXY` or `HTTP_PROXY` environment
+    /// variables, or connect directly if none is set
+    System,
+    /// Use this proxy, as `[http|socks5]://[user:password@]host[:port]`; SOCKS proxies
+    /// need ureq's `socks-proxy` feature
+    Url(String),
+}
+
+/// Number of retries after the first attempt unless overridden
+pub const DEFAULT_RETRIES: u32 = 3;
+
+/// Number of parallel downloads unless overridden
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+impl Default for RemoteOptions {
+    fn default() -> Self {
+        RemoteOptions {
+            retries: DEFAULT_RETRIES,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            concurrency: DEFAULT_CONCURRENCY,
+            rate_limit: None,
+            headers: Vec::new(),
+            basic_auth: None,
+            proxy: RemoteProxy::Direct,
+            cache_dir: None,
+            agent: OnceLock::new(),
+        }
+    }
+}
+
+impl fmt::Debug for RemoteOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Header values and passwords are often secrets
+        let header_names: Vec<&str> = self.headers.iter().map(|(name, _)| name.as_str()).collect();
+        f.debug_struct("RemoteOptions")
+            .field("retries", &self.retries)
+            .field("initial_backoff", &self.initial_backoff)
+            .field("max_backoff", &self.max_backoff)
+            .field("concurrency", &self.concurrency)
+            .field("rate_limit", &self.rate_limit)
+            .field("headers", &header_names)
+            .field("basic_auth_user", &self.basic_auth.as_ref().map(|(user, _)| user))
+            .field("proxy", &self.proxy)
+            .field("cache_dir", &self.cache_dir)
+            .finish()
+    }
+}
+
+impl RemoteOptions {
+    /// Returns how many times a failed request is retried
+    pub fn retries(&self) -> u32 {
+        self.retries
+    }
+
+    /// Sets how many times a failed request is retried
+    ///
+    /// Connection failures, interrupted transfers, `408`, `429` and `5xx` responses are
+    /// retried; other errors, such as `404`, fail at once. `0` disables retrying.
+    pub fn set_retries(&mut self, retries: u32) {
+        self.retries = retries;
+    }
+
+    /// Returns the delay before the first retry
+    pub fn initial_backoff(&self) -> Duration {
+        self.initial_backoff
+    }
+
+    /// Sets the delay before the first retry
+    ///
+    /// The delay doubles with every further retry, up to [`RemoteOptions::max_backoff`].
+    /// A `Retry-After` header in seconds takes precedence, within the same limit.
+    pub fn set_initial_backoff(&mut self, backoff: Duration) {
+        self.initial_backoff = backoff;
+    }
+
+    /// Returns the longest delay between retries
+    pub fn max_backoff(&self) -> Duration {
+        self.max_backoff
+    }
+
+    /// Sets the longest delay between retries
+    pub fn set_max_backoff(&mut self, backoff: Duration) {
+        self.max_backoff = backoff;
+    }
+
+    /// Returns how many downloads run at once
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    /// Sets how many downloads run at once when several are requested together
+    ///
+    /// Values below 1 are treated as 1.
+    pub fn set_concurrency(&mut self, concurrency: usize) {
+        self.concurrency = concurrency;
+    }
+
+    /// Returns the bandwidth limit in bytes per second, if any
+    pub fn rate_limit(&self) -> Option<u64> {
+        self.rate_limit
+    }
+
+    /// Limits the bandwidth used, in bytes per second
+    ///
+    /// Downloads started together share the limit. `None`, the default, doesn't limit
+    /// the bandwidth.
+    pub fn set_rate_limit(&mut self, bytes_per_second: Option<u64>) {
+        self.rate_limit = bytes_per_second.filter(|&rate| rate > 0);
+    }
+
+    /// Returns the extra headers sent with every request
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    /// Sends a header with every request, such as an API token
+    ///
+    /// A header added earlier with the same name, ignoring case, is replaced.
+    pub fn add_header(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        let name = name.into();
+        self.headers.retain(|(existing, _)| !existing.eq_ignore_ascii_case(&name));
+        self.headers.push((name, value.into()));
+    }
+
+    /// Returns the user name sent with HTTP basic auth, if any
+    pub fn basic_auth_user(&self) -> Option<&str> {
+        self.basic_auth.as_ref().map(|(user, _)| user.as_str())
+    }
+
+    /// Authenticates every request with HTTP basic auth
+    ///
+    /// This sets the `Authorization` header, so it replaces a token added with
+    /// [`RemoteOptions::add_header`] and vice versa, whichever comes last. The credentials
+    /// aren't sent along when a server redirects to another host.
+    pub fn set_basic_auth(&mut self, user: impl Into<String>, password: impl Into<String>) {
+        self.headers.retain(|(name, _)| !name.eq_ignore_ascii_case("Authorization"));
+        self.basic_auth = Some((user.into(), password.into()));
+    }
+
+    /// Returns how requests reach the server
+    pub fn proxy(&self) -> &RemoteProxy {
+        &self.proxy
+    }
+
+    /// Sets how requests reach the server; [`RemoteProxy::Direct`] by default
+    pub fn set_proxy(&mut self, proxy: RemoteProxy) {
+        self.proxy = proxy;
+        self.agent = OnceLock::new();
+    }
+
+    /// Returns the directory downloads are cached in, if any
+    pub fn cache_dir(&self) -> Option<&Path> {
+        self.cache_dir.as_deref()
+    }
+
+    /// Caches downloads on disk in `dir`; `None`, the default, disables the cache
+    ///
+    /// [`crate::fetch_with_options`] and [`crate::fetch_plugin_json_with_options`] keep
+    /// what they download in the directory, keyed by URL and validator (`ETag` or
+    /// `Last-Modified`) as well as by the hash in the archive's header. Later calls,
+    /// also from other processes, send a conditional request and use the cached copy if
+    /// the server answers `304 Not Modified`. A manifest is also reused when the server
+    /// sends no validator but the archive's header hash is already known, so only the
+    /// header is downloaded. The directory is created when first written to.
+    pub fn set_cache_dir(&mut self, dir: Option<PathBuf>) {
+        self.cache_dir = dir;
+    }
+
+    /// Returns the agent requests are sent with, which keeps idle connections open for reuse
+    pub(crate) fn agent(&self) -> io::Result<&ureq::Agent> {
+        if let Some(agent) = self.agent.get() {
+            return Ok(agent);
+        }
+        let agent = match &self.proxy {
+            RemoteProxy::Direct => ureq::AgentBuilder::new(),
+            RemoteProxy::System => ureq::AgentBuilder::new().try_proxy_from_env(true),
+            RemoteProxy::Url(proxy) => {
+                let proxy = ureq::Proxy::new(proxy).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+                ureq::AgentBuilder::new().proxy(proxy)
+            }
+        }
+        .build();
+        // Another thread may have got there first; either agent will do
+        Ok(self.agent.get_or_init(|| agent))
+    }
+
+    /// Returns the headers to send, including the basic auth credentials
+    pub(crate) fn request_headers(&self) -> Vec<(String, String)> {
+        let mut headers = self.headers.clone();
+        if headers.iter().any(|(name, _)| name.eq_ignore_ascii_case("Authorization")) {
+            return headers;
+        }
+        if let Some((user, password)) = &self.basic_auth {
+            let credentials = base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", user, password));
+            headers.push(("Authorization".to_string(), format!("Basic {}", credentials)));
+        }
+        headers
+    }
+
+    /// Returns the delay before retry number `retry`, counting from 1
+    pub(crate) fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.initial_backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+}
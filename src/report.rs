@@ -0,0 +1,104 @@
+//! Inventory reports of an archive's contents
+
+use std::io::{self, Write};
+
+/// Inventory of an archive, returned by [`crate::ObbyArchive::manifest_report`]
+///
+/// With the `serde` feature enabled the report implements `Serialize`, so it can be
+/// written as JSON or any other serde format. [`ManifestReport::write_csv`] writes the
+/// entry list as CSV without extra dependencies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ManifestReport {
+    /// The Obsidian API version the plugin was built against
+    pub api_version: String,
+    /// The plugin's assembly name
+    pub plugin_assembly: String,
+    /// The plugin's version
+    pub plugin_version: String,
+    /// Whether the archive carries a signature
+    pub signed: bool,
+    /// SHA-384 hash of the data section stored in the header, as lowercase hex
+    pub hash: String,
+    /// Entries in archive order
+    pub entries: Vec<ReportEntry>,
+}
+
+/// One entry of a [`ManifestReport`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ReportEntry {
+    /// The entry's name
+    pub name: String,
+    /// Decompressed size in bytes
+    pub size: u64,
+    /// Size as stored in the archive
+    pub compressed_size: u64,
+    /// Whether the entry is stored compressed
+    pub compressed: bool,
+    /// SHA-256 of the decompressed contents, as lowercase hex
+    pub sha256: String,
+}
+
+impl ManifestReport {
+    /// Writes the entry list as CSV with a header row
+    ///
+    /// Columns are `name,size,compressed_size,compressed,sha256`. Fields are quoted
+    /// when they contain commas, quotes or line breaks.
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - Where to write the CSV.
+    pub fn write_csv<W: Write>(&self, mut out: W) -> io::Result<()> {
+        writeln!(out, "name,size,compressed_size,compressed,sha256")?;
+        for entry in &self.entries {
+            writeln!(
+                out,
+                "{},{},{},{},{}",
+                csv_field(&entry.name),
+                entry.size,
+                entry.compressed_size,
+                entry.compressed,
+                entry.sha256
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_csv() {
+        let report = ManifestReport {
+            api_version: "1.0.0".to_string(),
+            plugin_assembly: "Plugin".to_string(),
+            plugin_version: "1.0.0".to_string(),
+            signed: false,
+            hash: String::new(),
+            entries: vec![ReportEntry {
+                name: "odd, \"name\".txt".to_string(),
+                size: 10,
+                compressed_size: 8,
+                compressed: true,
+                sha256: "ab".to_string(),
+            }],
+        };
+        let mut csv = Vec::new();
+        report.write_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "name,size,compressed_size,compressed,sha256\n\"odd, \"\"name\"\".txt\",10,8,true,ab\n"
+        );
+    }
+}
@@ -0,0 +1,237 @@
+//! Mapping entry names to safe paths on disk
+//!
+//! Entry names come from the archive and can't be trusted: a hostile archive may use
+//! names like `../../etc/passwd`, `C:\Windows\system32\evil.dll` or names containing NUL
+//! bytes to write outside the extraction directory. A [`SanitizePolicy`] decides how
+//! names are turned into relative paths before anything is written, and
+//! [`validate_relative_path`] checks the result whatever the policy.
+
+use std::fmt;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use crate::tree::components;
+use crate::ObbyError;
+
+/// How entry names are turned into paths when extracting to disk
+///
+/// Every policy produces a path relative to the extraction directory that passes
+/// [`validate_relative_path`]; names that can't be made safe are rejected with
+/// [`ObbyError::UnsafePath`].
+///
+/// # Example
+///
+/// ```no_run
+/// use obsidian_lib::{ObbyArchive, ObbyReadOptions, SanitizePolicy};
+/// use std::fs::File;
+///
+/// let mut options = ObbyReadOptions::default();
+/// options.set_sanitize_policy(SanitizePolicy::AllowDotfiles);
+/// let mut archive = ObbyArchive::with_options(File::open("plugin.obby").unwrap(), options).unwrap();
+/// archive.extract_all("out").unwrap();
+/// ```
+#[derive(Clone, Copy, Default)]
+pub enum SanitizePolicy {
+    /// Rejects absolute paths, drive prefixes, `..`, control characters, `:`, Windows
+    /// device names and any component starting with a dot
+    #[default]
+    Strict,
+    /// Like `Strict`, but allows components starting with a dot, such as `.hotreload`
+    AllowDotfiles,
+    /// Delegates to a caller-provided function, which returns the relative path to write
+    /// to or `None` to reject the name
+    ///
+    /// The returned path is still checked with [`validate_relative_path`].
+    Custom(fn(&str) -> Option<PathBuf>),
+}
+
+impl SanitizePolicy {
+    /// Maps an entry name to a path relative to the extraction directory
+    ///
+    /// # Arguments
+    ///
+    /// * `entry_name` - The name of the entry as stored in the archive.
+    ///
+    /// # Returns
+    ///
+    /// The relative path, or an `io::Error` carrying [`ObbyError::UnsafePath`] if the
+    /// policy rejects the name.
+    pub fn sanitize(&self, entry_name: &str) -> io::Result<PathBuf> {
+        let path = match self {
+            SanitizePolicy::Strict => builtin(entry_name, false),
+            SanitizePolicy::AllowDotfiles => builtin(entry_name, true),
+            SanitizePolicy::Custom(f) => f(entry_name),
+        };
+        path.filter(|path| validate_relative_path(path).is_ok()).ok_or_else(|| {
+            ObbyError::UnsafePath {
+                entry: entry_name.to_string(),
+            }
+            .into_io()
+        })
+    }
+}
+
+impl fmt::Debug for SanitizePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SanitizePolicy::Strict => f.write_str("Strict"),
+            SanitizePolicy::AllowDotfiles => f.write_str("AllowDotfiles"),
+            SanitizePolicy::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+fn builtin(entry_name: &str, allow_dotfiles: bool) -> Option<PathBuf> {
+    if entry_name.starts_with(['/', '\\']) {
+        return None;
+    }
+
+    let mut path = PathBuf::new();
+    for part in components(entry_name) {
+        if part == "." {
+            continue;
+        }
+        let hidden = part.starts_with('.') && !allow_dotfiles;
+        if part == ".." || hidden || !is_safe_component(part) {
+            return None;
+        }
+        path.push(part);
+    }
+    Some(path)
+}
+
+/// Checks that a relative path can be written below an extraction directory on any platform
+///
+/// This is the check every [`SanitizePolicy`] applies to the paths it produces, for
+/// embedders that map entry names to paths themselves. A path passes if it names at
+/// least one file or directory, so `.` and `./.` are rejected, and all of its
+/// components besides `.` are plain names that:
+///
+/// - are valid UTF-8 and contain no control characters, `:` or `\`, so they can't
+///   carry drive letters, alternate data streams or hidden separators;
+/// - don't end with `.` or a space, which Windows silently strips;
+/// - aren't Windows device names such as `CON`, `NUL` or `COM1`, with or without an
+///   extension, which open a device instead of a file in any directory.
+///
+/// Absolute paths, drive prefixes and `..` components are rejected, so the path can't
+/// escape the directory it is joined onto. Symlinks are a property of the destination,
+/// not the path; [`crate::ObbyArchive::extract_all`] refuses to write through them.
+///
+/// # Arguments
+///
+/// * `path` - The path relative to the extraction directory.
+///
+/// # Returns
+///
+/// `Ok(())` if the path is safe, or an `io::Error` carrying [`ObbyError::UnsafePath`].
+///
+/// # Example
+///
+/// ```
+/// use obsidian_lib::validate_relative_path;
+/// use std::path::Path;
+///
+/// assert!(validate_relative_path(Path::new("assets/logo.png")).is_ok());
+/// assert!(validate_relative_path(Path::new("../outside")).is_err());
+/// assert!(validate_relative_path(Path::new("aux.js")).is_err());
+/// ```
+pub fn validate_relative_path(path: &Path) -> io::Result<()> {
+    // A path of only `.` components is the extraction directory itself
+    let safe = path.components().any(|component| matches!(component, Component::Normal(_)))
+        && path.components().all(|component| match component {
+            Component::Normal(part) => part.to_str().is_some_and(is_safe_component),
+            Component::CurDir => true,
+            _ => false,
+        });
+    if safe {
+        return Ok(());
+    }
+    Err(ObbyError::UnsafePath {
+        entry: path.to_string_lossy().into_owned(),
+    }
+    .into_io())
+}
+
+/// Windows device names, reserved in every directory and with any extension
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9", "LPT1", "LPT2",
+    "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Whether a single path component is safe on every platform
+fn is_safe_component(part: &str) -> bool {
+    let stem = part.split('.').next().unwrap_or(part).trim_end_matches(' ');
+    !part.contains([':', '\\'])
+        && !part.chars().any(char::is_control)
+        && !part.ends_with(['.', ' '])
+        && !RESERVED_NAMES.iter().any(|reserved| reserved.eq_ignore_ascii_case(stem))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strict() {
+        let policy = SanitizePolicy::Strict;
+        assert_eq!(policy.sanitize("assets/./logo.png").unwrap(), PathBuf::from("assets").join("logo.png"));
+        assert_eq!(policy.sanitize("lib\\native.dll").unwrap(), PathBuf::from("lib").join("native.dll"));
+
+        for hostile in [
+            "../../etc/passwd",
+            "assets/../../x",
+            "/etc/passwd",
+            "\\\\server\\share",
+            "C:\\windows\\system32\\evil.dll",
+            "file.txt:stream",
+            "nul\0byte",
+            ".hotreload",
+            "",
+            "./",
+            "CON",
+            "lib/nul.txt",
+            "Aux.json",
+            "com1 .log",
+            "trailing.",
+            "trailing ",
+        ] {
+            let err = policy.sanitize(hostile).unwrap_err();
+            assert!(
+                matches!(ObbyError::from_io(&err), Some(ObbyError::UnsafePath { .. })),
+                "{:?} was accepted",
+                hostile
+            );
+        }
+    }
+
+    #[test]
+    fn test_allow_dotfiles() {
+        let policy = SanitizePolicy::AllowDotfiles;
+        assert_eq!(policy.sanitize(".hotreload").unwrap(), PathBuf::from(".hotreload"));
+        assert!(policy.sanitize("../x").is_err());
+    }
+
+    #[test]
+    fn test_custom() {
+        let flatten = SanitizePolicy::Custom(|name| name.rsplit('/').next().map(PathBuf::from));
+        assert_eq!(flatten.sanitize("assets/logo.png").unwrap(), PathBuf::from("logo.png"));
+
+        // The custom function's output is still checked
+        let escape = SanitizePolicy::Custom(|_| Some(PathBuf::from("../outside")));
+        assert!(escape.sanitize("a").is_err());
+        let device = SanitizePolicy::Custom(|_| Some(PathBuf::from("assets/CON")));
+        assert!(device.sanitize("a").is_err());
+        let root = SanitizePolicy::Custom(|_| Some(PathBuf::from("./.")));
+        assert!(root.sanitize("a").is_err());
+    }
+
+    #[test]
+    fn test_validate_relative_path() {
+        for safe in ["plugin.json", "assets/console.png", "lib/com10.dll", "./a/nul-byte.txt"] {
+            assert!(validate_relative_path(Path::new(safe)).is_ok(), "{:?} was rejected", safe);
+        }
+        for unsafe_path in ["", ".", "./.", "/etc/passwd", "a/../../b", "a/b:c", "LPT9", "a\\b"] {
+            assert!(validate_relative_path(Path::new(unsafe_path)).is_err(), "{:?} was accepted", unsafe_path);
+        }
+    }
+}
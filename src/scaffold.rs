@@ -0,0 +1,82 @@
+//! `obby new`: generating the skeleton of a new plugin
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use obsidian_lib::manifest::PluginManifest;
+
+use crate::cli::Template;
+
+/// Creates a plugin skeleton for `id` in `dir` and prints how to package it
+///
+/// `dir` must not exist or be empty, so nothing is ever overwritten.
+pub fn new_plugin(id: &str, dir: &Path, template: Template, name: Option<&str>, authors: Vec<String>) -> io::Result<()> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Plugin ID {:?} may only contain letters, digits, '-', '_' and '.'", id),
+        ));
+    }
+    if fs::read_dir(dir).is_ok_and(|mut entries| entries.next().is_some()) {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists and isn't empty", dir.display()),
+        ));
+    }
+
+    let name = name.map_or_else(|| display_name(id), str::to_string);
+    let manifest = PluginManifest {
+        id: Some(id.to_string()),
+        name: Some(name.clone()),
+        version: Some("1.0.0".to_string()),
+        authors,
+        description: Some("An Obsidian plugin.".to_string()),
+        project_url: None,
+        extra: Default::default(),
+    };
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join("plugin.json"), manifest.to_json() + "\n")?;
+    if template == Template::Basic {
+        fs::write(dir.join("README.md"), readme(&name))?;
+    }
+
+    let assembly = assembly_name(id);
+    println!("Created {} in {}", id, dir.display());
+    println!("Copy the build output of {} into it, then package it with:", assembly);
+    println!("  obby create {}.obby --dir {} --assembly {} --version 1.0.0", id, dir.display(), assembly);
+    Ok(())
+}
+
+/// Turns `my-plugin` into `My Plugin`
+fn display_name(id: &str) -> String {
+    words(id)
+        .map(capitalize)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Turns `my-plugin` into `MyPlugin`, the conventional assembly name
+fn assembly_name(id: &str) -> String {
+    words(id).map(capitalize).collect()
+}
+
+fn words(id: &str) -> impl Iterator<Item = &str> {
+    id.split(['-', '_', '.']).filter(|word| !word.is_empty())
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars.next().map_or_else(String::new, |first| first.to_ascii_uppercase().to_string() + chars.as_str())
+}
+
+fn readme(name: &str) -> String {
+    format!(
+        "# {name}\n\
+         \n\
+         An Obsidian plugin.\n\
+         \n\
+         Everything in this directory is packaged, so keep only `plugin.json`, this file\n\
+         and the plugin's build output (its `.dll` and dependencies) here.\n"
+    )
+}
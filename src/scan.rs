@@ -0,0 +1,128 @@
+//! Finding archives in a directory tree
+
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+
+use crate::{read_header, ObbyMetadata};
+
+/// Walks `path` recursively and yields the header metadata of every `.obby` file found
+///
+/// Only the header of each archive is read, so scanning a large plugins directory is
+/// cheap. Files are visited in name order, each directory's files before its
+/// subdirectories. Symlinked directories are not followed.
+///
+/// # Arguments
+///
+/// * `path` - The directory to scan.
+///
+/// # Returns
+///
+/// An iterator of `(path, metadata)` pairs. A file that can't be parsed, or a directory
+/// that can't be read, yields an `io::Error` naming the path, and the scan carries on.
+///
+/// # Example
+///
+/// ```no_run
+/// for result in obsidian_lib::scan_dir("plugins") {
+///     match result {
+///         Ok((path, metadata)) => println!("{} {} ({})", metadata.plugin_assembly, metadata.plugin_version, path.display()),
+///         Err(e) => eprintln!("{}", e),
+///     }
+/// }
+/// ```
+pub fn scan_dir<P: AsRef<Path>>(path: P) -> impl Iterator<Item = io::Result<(PathBuf, ObbyMetadata)>> {
+    walk(path.as_ref()).map(|(path, result)| match result {
+        Ok(metadata) => Ok((path, metadata)),
+        Err(e) => Err(io::Error::new(e.kind(), format!("{}: {}", path.display(), e))),
+    })
+}
+
+/// Like [`scan_dir`], but yields the path alongside each result instead of in error messages
+///
+/// A directory that can't be read is reported with its own path.
+pub(crate) fn walk(root: &Path) -> impl Iterator<Item = (PathBuf, io::Result<ObbyMetadata>)> {
+    let mut dirs = vec![root.to_path_buf()];
+    let mut files: Vec<PathBuf> = Vec::new();
+    std::iter::from_fn(move || loop {
+        if let Some(file) = files.pop() {
+            let metadata = read_metadata(&file);
+            return Some((file, metadata));
+        }
+        let dir = dirs.pop()?;
+        match list_dir(&dir) {
+            Ok((mut subdirs, mut archives)) => {
+                // Both stacks pop from the back, so store them in reverse name order
+                subdirs.reverse();
+                archives.reverse();
+                dirs.extend(subdirs);
+                files = archives;
+            }
+            Err(e) => return Some((dir, Err(e))),
+        }
+    })
+}
+
+/// Returns the subdirectories and `.obby` files directly inside `dir`, sorted by name
+fn list_dir(dir: &Path) -> io::Result<(Vec<PathBuf>, Vec<PathBuf>)> {
+    let mut subdirs = Vec::new();
+    let mut archives = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            subdirs.push(path);
+        } else if is_archive_path(&path) {
+            archives.push(path);
+        }
+    }
+    subdirs.sort();
+    archives.sort();
+    Ok((subdirs, archives))
+}
+
+/// Whether `path` has an `.obby` extension, ignoring case
+pub(crate) fn is_archive_path(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("obby"))
+}
+
+fn read_metadata(path: &Path) -> io::Result<ObbyMetadata> {
+    let file = File::open(path)?;
+    read_header(BufReader::new(file)).map(|header| header.metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+
+    #[test]
+    fn test_scan_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = |name: &str| ObbyTestBuilder::new().plugin(name, "1.0.0").entry("plugin.json", b"{}").build();
+        fs::create_dir_all(dir.path().join("nested/deeper")).unwrap();
+        fs::write(dir.path().join("b.obby"), archive("B")).unwrap();
+        fs::write(dir.path().join("a.OBBY"), archive("A")).unwrap();
+        fs::write(dir.path().join("notes.txt"), b"not an archive").unwrap();
+        fs::write(dir.path().join("nested/deeper/c.obby"), archive("C")).unwrap();
+        fs::write(dir.path().join("nested/broken.obby"), b"OBBY").unwrap();
+
+        let results: Vec<_> = scan_dir(dir.path()).collect();
+        assert_eq!(results.len(), 4);
+        let names: Vec<_> = results
+            .iter()
+            .filter_map(|result| result.as_ref().ok())
+            .map(|(_, metadata)| metadata.plugin_assembly.as_str())
+            .collect();
+        assert_eq!(names, vec!["A", "B", "C"]);
+        let error = results[2].as_ref().unwrap_err();
+        assert!(error.to_string().contains("broken.obby"));
+    }
+
+    #[test]
+    fn test_scan_missing_dir() {
+        let results: Vec<_> = scan_dir("/nonexistent/obby/plugins").collect();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].as_ref().unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+}
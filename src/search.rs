@@ -0,0 +1,115 @@
+//! Searching entry contents for byte patterns
+
+use std::io::{self, Read, Seek};
+
+use crate::ObbyArchive;
+
+/// Size of the chunks entries are scanned in
+const CHUNK_LEN: usize = 64 * 1024;
+
+impl<R: Read + Seek> ObbyArchive<R> {
+    /// Finds every occurrence of a byte pattern in the decompressed entries
+    ///
+    /// Entries are decompressed and scanned in chunks, so memory use doesn't depend on
+    /// entry size. Overlapping occurrences are all reported.
+    ///
+    /// # Arguments
+    ///
+    /// * `needle` - The bytes to look for. Must not be empty.
+    ///
+    /// # Returns
+    ///
+    /// The entries containing `needle`, in archive order, each with the offsets of its
+    /// occurrences within the decompressed entry.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut archive = obsidian_lib::open("plugin.obby")?;
+    /// for (entry, offsets) in archive.search(b"System.Diagnostics.Process")? {
+    ///     println!("{}: {} matches", entry, offsets.len());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn search(&mut self, needle: &[u8]) -> io::Result<Vec<(String, Vec<u64>)>> {
+        if needle.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Search pattern is empty"));
+        }
+
+        let mut results = Vec::new();
+        for name in self.order.clone() {
+            let offsets = self.entry_reader(&name).and_then(|reader| find_all(reader, needle));
+            let offsets = self.decompression_error(&name, offsets)?;
+            if !offsets.is_empty() {
+                results.push((name, offsets));
+            }
+        }
+        Ok(results)
+    }
+}
+
+/// Returns the offsets of every occurrence of `needle` in `reader`'s data
+fn find_all(mut reader: impl Read, needle: &[u8]) -> io::Result<Vec<u64>> {
+    let mut offsets = Vec::new();
+    // The tail of the previous chunk is kept so matches spanning chunks are found
+    let mut window = Vec::with_capacity(CHUNK_LEN + needle.len());
+    let mut window_start = 0u64;
+    let mut chunk = vec![0u8; CHUNK_LEN];
+
+    loop {
+        let read = reader.read(&mut chunk)?;
+        if read == 0 {
+            return Ok(offsets);
+        }
+        window.extend_from_slice(&chunk[..read]);
+
+        offsets.extend(
+            window
+                .windows(needle.len())
+                .enumerate()
+                .filter(|(_, candidate)| *candidate == needle)
+                .map(|(i, _)| window_start + i as u64),
+        );
+
+        let keep = window.len().min(needle.len() - 1);
+        let drop = window.len() - keep;
+        window.drain(..drop);
+        window_start += drop as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+
+    #[test]
+    fn test_search() {
+        let mut large = vec![b'.'; 200_000];
+        large[CHUNK_LEN - 2..CHUNK_LEN + 2].copy_from_slice(b"evil");
+        large[150_000..150_004].copy_from_slice(b"evil");
+        let buffer = ObbyTestBuilder::new()
+            .entry("plugin.json", b"{}")
+            .entry("Plugin.dll", &large)
+            .stored_entry("notes.txt", b"evilevil")
+            .build();
+        let mut archive = ObbyArchive::from_bytes(buffer).unwrap();
+
+        assert_eq!(
+            archive.search(b"evil").unwrap(),
+            vec![
+                ("Plugin.dll".to_string(), vec![CHUNK_LEN as u64 - 2, 150_000]),
+                ("notes.txt".to_string(), vec![0, 4]),
+            ]
+        );
+        assert!(archive.search(b"missing").unwrap().is_empty());
+        assert_eq!(archive.search(b"").unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_overlapping_matches() {
+        assert_eq!(find_all(&b"aaaa"[..], b"aa").unwrap(), vec![0, 1, 2]);
+    }
+}
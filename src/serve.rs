@@ -0,0 +1,296 @@
+//! Serving archive entries as HTTP responses
+//!
+//! Enabled with the `http-serve` feature. The functions here work on the request and
+//! response types of the [`http`] crate, which axum, hyper, warp and most other Rust
+//! web frameworks use, so they plug into any of them without an adapter.
+
+use std::io::{self, Read, Seek};
+
+use http::header::{self, HeaderMap, HeaderValue};
+use http::{Method, Request, Response, StatusCode};
+
+use crate::{mime, HashAlgo, ObbyArchive};
+
+impl<R: Read + Seek> ObbyArchive<R> {
+    /// Answers an HTTP request for one entry
+    ///
+    /// `GET` and `HEAD` are served; other methods get `405 Method Not Allowed`. The
+    /// response carries a `Content-Type` sniffed from the entry's data and a strong
+    /// `ETag` derived from its SHA-256, so `If-None-Match` is answered with
+    /// `304 Not Modified`. A single byte range in `Range` is answered with
+    /// `206 Partial Content` (honouring `If-Range`), an unsatisfiable one with
+    /// `416 Range Not Satisfiable`; requests for several ranges get the whole entry.
+    ///
+    /// The entry is decompressed in full to hash it, so this suits the modest entries of
+    /// plugin archives rather than huge media files. Add caching headers such as
+    /// `Cache-Control` to the response as your application needs.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry` - The entry to serve.
+    /// * `request` - The request; only its method and headers are used.
+    ///
+    /// # Returns
+    ///
+    /// The response, which is `404 Not Found` if the entry doesn't exist, or an
+    /// `io::Error` if the entry can't be read.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut archive = obsidian_lib::open("plugin.obby")?;
+    /// let request = http::Request::get("/files/README.md").header("Range", "bytes=0-99").body(()).unwrap();
+    /// let response = archive.serve_entry("README.md", &request)?;
+    /// assert_eq!(response.status(), http::StatusCode::PARTIAL_CONTENT);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn serve_entry<B>(&mut self, entry: &str, request: &Request<B>) -> io::Result<Response<Vec<u8>>> {
+        if request.method() != Method::GET && request.method() != Method::HEAD {
+            return Ok(status_response(StatusCode::METHOD_NOT_ALLOWED, &[(header::ALLOW, "GET, HEAD")]));
+        }
+        let (data, digest) = match self.extract_entry_hashed(entry, HashAlgo::Sha256) {
+            Ok(extracted) => extracted,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(status_response(StatusCode::NOT_FOUND, &[])),
+            Err(e) => return Err(e),
+        };
+        let etag = format!("\"{}\"", digest.to_hex());
+        let headers = request.headers();
+
+        let mut response = Response::builder()
+            .header(header::ETAG, &etag)
+            .header(header::ACCEPT_RANGES, "bytes");
+        if let Some(if_none_match) = header_str(headers, header::IF_NONE_MATCH) {
+            if etag_matches(if_none_match, &etag) {
+                return Ok(response.status(StatusCode::NOT_MODIFIED).body(Vec::new()).unwrap());
+            }
+        }
+
+        let mut content_type = mime::sniff(&data[..data.len().min(mime::MIME_SNIFF_LEN)], entry).to_string();
+        if content_type.starts_with("text/") || content_type == "application/json" {
+            content_type.push_str("; charset=utf-8");
+        }
+        response = response.header(header::CONTENT_TYPE, content_type);
+
+        let len = data.len() as u64;
+        let range = header_str(headers, header::RANGE).filter(|_| {
+            // A stale `If-Range` means the client's partial copy is outdated
+            header_str(headers, header::IF_RANGE).is_none_or(|if_range| if_range == etag)
+        });
+        let (status, body) = match range.map(|range| parse_range(range, len)) {
+            Some(RangeRequest::Single(start, end)) => {
+                response = response.header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, len));
+                (StatusCode::PARTIAL_CONTENT, data[start as usize..=end as usize].to_vec())
+            }
+            Some(RangeRequest::Unsatisfiable) => {
+                return Ok(status_response(StatusCode::RANGE_NOT_SATISFIABLE, &[(header::CONTENT_RANGE, &format!("bytes */{}", len))]));
+            }
+            Some(RangeRequest::Ignored) | None => (StatusCode::OK, data),
+        };
+        response = response.header(header::CONTENT_LENGTH, body.len());
+        let body = if request.method() == Method::HEAD { Vec::new() } else { body };
+        Ok(response.status(status).body(body).unwrap())
+    }
+}
+
+/// Answers an HTTP request for the entry named by the request path
+///
+/// The path below `prefix` is percent-decoded and served with
+/// [`ObbyArchive::serve_entry`], so with the prefix `/files/` a request for
+/// `/files/assets/my%20icon.png` serves the entry `assets/my icon.png`. Paths outside
+/// the prefix get `404 Not Found`, and paths that don't decode to UTF-8 get
+/// `400 Bad Request`.
+///
+/// # Arguments
+///
+/// * `archive` - The archive to serve from.
+/// * `request` - The request.
+/// * `prefix` - The part of the path the entries are mounted under, such as `/files/`.
+///
+/// # Returns
+///
+/// The response, or an `io::Error` if the entry can't be read.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// let mut archive = obsidian_lib::open("plugin.obby")?;
+/// let request = http::Request::get("/files/plugin.json").body(()).unwrap();
+/// let response = obsidian_lib::serve_request(&mut archive, &request, "/files/")?;
+/// println!("{} {:?}", response.status(), response.headers().get("content-type"));
+/// # Ok(())
+/// # }
+/// ```
+pub fn serve_request<R: Read + Seek, B>(
+    archive: &mut ObbyArchive<R>,
+    request: &Request<B>,
+    prefix: &str,
+) -> io::Result<Response<Vec<u8>>> {
+    let Some(path) = request.uri().path().strip_prefix(prefix) else {
+        return Ok(status_response(StatusCode::NOT_FOUND, &[]));
+    };
+    match percent_decode(path) {
+        Some(entry) => archive.serve_entry(&entry, request),
+        None => Ok(status_response(StatusCode::BAD_REQUEST, &[])),
+    }
+}
+
+/// A `Range` header, resolved against the entry length
+#[derive(Debug, PartialEq, Eq)]
+enum RangeRequest {
+    /// Inclusive first and last byte
+    Single(u64, u64),
+    Unsatisfiable,
+    /// Not a single byte range, so the whole entry is served
+    Ignored,
+}
+
+fn parse_range(header: &str, len: u64) -> RangeRequest {
+    let Some(spec) = header.trim().strip_prefix("bytes=") else {
+        return RangeRequest::Ignored;
+    };
+    let Some((start, end)) = spec.trim().split_once('-').filter(|_| !spec.contains(',')) else {
+        return RangeRequest::Ignored;
+    };
+    let (start, end) = (start.trim(), end.trim());
+    let range = match (start.parse::<u64>(), end.parse::<u64>()) {
+        // `bytes=-N`: the last N bytes
+        _ if start.is_empty() => match end.parse::<u64>() {
+            Ok(0) => return RangeRequest::Unsatisfiable,
+            Ok(suffix) => (len.saturating_sub(suffix), len.wrapping_sub(1)),
+            Err(_) => return RangeRequest::Ignored,
+        },
+        (Ok(start), _) if end.is_empty() => (start, len.wrapping_sub(1)),
+        (Ok(start), Ok(end)) if start <= end => (start, end.min(len.wrapping_sub(1))),
+        _ => return RangeRequest::Ignored,
+    };
+    if len == 0 || range.0 >= len {
+        RangeRequest::Unsatisfiable
+    } else {
+        RangeRequest::Single(range.0, range.1)
+    }
+}
+
+/// Whether an `If-None-Match` list names `etag`, comparing weakly as the header requires
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match
+        .split(',')
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+}
+
+fn header_str(headers: &HeaderMap, name: header::HeaderName) -> Option<&str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+/// An empty response with a status and extra headers
+fn status_response(status: StatusCode, headers: &[(header::HeaderName, &str)]) -> Response<Vec<u8>> {
+    let mut response = Response::new(Vec::new());
+    *response.status_mut() = status;
+    for (name, value) in headers {
+        response.headers_mut().insert(name.clone(), HeaderValue::from_str(value).unwrap());
+    }
+    response
+}
+
+/// Decodes `%XX` escapes, failing on malformed escapes or invalid UTF-8
+fn percent_decode(path: &str) -> Option<String> {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+
+    fn archive() -> ObbyArchive<io::Cursor<Vec<u8>>> {
+        let buffer = ObbyTestBuilder::new()
+            .entry("README.md", b"# Plugin\n\nHello")
+            .stored_entry("assets/my icon.png", b"\x89PNG\r\n\x1a\n....")
+            .build();
+        ObbyArchive::from_bytes(buffer).unwrap()
+    }
+
+    #[test]
+    fn test_serve_entry() {
+        let mut archive = archive();
+        let response = archive.serve_entry("README.md", &Request::get("/").body(()).unwrap()).unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/markdown; charset=utf-8");
+        assert_eq!(response.headers()[header::CONTENT_LENGTH], "15");
+        assert_eq!(response.body(), b"# Plugin\n\nHello");
+        let etag = response.headers()[header::ETAG].to_str().unwrap().to_string();
+
+        let request = Request::get("/").header(header::IF_NONE_MATCH, format!("W/{}", etag)).body(()).unwrap();
+        assert_eq!(archive.serve_entry("README.md", &request).unwrap().status(), StatusCode::NOT_MODIFIED);
+
+        let request = Request::get("/").header(header::RANGE, "bytes=2-7").body(()).unwrap();
+        let response = archive.serve_entry("README.md", &request).unwrap();
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes 2-7/15");
+        assert_eq!(response.body(), b"Plugin");
+
+        // An outdated `If-Range` gets the whole entry
+        let request = Request::get("/")
+            .header(header::RANGE, "bytes=2-7")
+            .header(header::IF_RANGE, "\"stale\"")
+            .body(())
+            .unwrap();
+        assert_eq!(archive.serve_entry("README.md", &request).unwrap().status(), StatusCode::OK);
+
+        let request = Request::head("/").header(header::RANGE, "bytes=100-").body(()).unwrap();
+        let response = archive.serve_entry("README.md", &request).unwrap();
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes */15");
+
+        let response = archive.serve_entry("README.md", &Request::head("/").body(()).unwrap()).unwrap();
+        assert_eq!(response.headers()[header::CONTENT_LENGTH], "15");
+        assert!(response.body().is_empty());
+
+        let response = archive.serve_entry("README.md", &Request::post("/").body(()).unwrap()).unwrap();
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        let response = archive.serve_entry("missing", &Request::get("/").body(()).unwrap()).unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_serve_request() {
+        let mut archive = archive();
+        let serve = |archive: &mut ObbyArchive<_>, uri: &str| {
+            serve_request(archive, &Request::get(uri).body(()).unwrap(), "/files/").unwrap()
+        };
+        let response = serve(&mut archive, "/files/assets/my%20icon.png");
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "image/png");
+        assert_eq!(serve(&mut archive, "/other/README.md").status(), StatusCode::NOT_FOUND);
+        assert_eq!(serve(&mut archive, "/files/%ff").status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("bytes=0-", 10), RangeRequest::Single(0, 9));
+        assert_eq!(parse_range("bytes=-3", 10), RangeRequest::Single(7, 9));
+        assert_eq!(parse_range("bytes=-30", 10), RangeRequest::Single(0, 9));
+        assert_eq!(parse_range("bytes=5-100", 10), RangeRequest::Single(5, 9));
+        assert_eq!(parse_range("bytes=10-", 10), RangeRequest::Unsatisfiable);
+        assert_eq!(parse_range("bytes=0-", 0), RangeRequest::Unsatisfiable);
+        assert_eq!(parse_range("bytes=0-1,3-4", 10), RangeRequest::Ignored);
+        assert_eq!(parse_range("items=0-1", 10), RangeRequest::Ignored);
+        assert_eq!(parse_range("bytes=5-2", 10), RangeRequest::Ignored);
+    }
+}
@@ -0,0 +1,175 @@
+//! RSA signing support for `.obby` archives
+//!
+//! Obsidian signs the SHA-384 hash of an archive's data section with an RSA-3072 key
+//! using PKCS#1 v1.5 padding, which yields the fixed 384-byte signature stored in the header.
+
+use std::io::{self, Read, Seek};
+use std::path::Path;
+
+use rsa::pkcs1::{DecodeRsaPrivateKey, DecodeRsaPublicKey};
+use rsa::pkcs8::{DecodePrivateKey, DecodePublicKey, EncodePublicKey};
+use rsa::traits::PublicKeyParts;
+use rsa::{Pkcs1v15Sign, RsaPrivateKey, RsaPublicKey};
+use sha2::{Digest, Sha256, Sha384};
+
+use crate::{ObbyArchive, ObbyError, SIGNATURE_LEN};
+
+/// Loads an RSA private key from PEM text
+///
+/// Both PKCS#8 (`BEGIN PRIVATE KEY`) and PKCS#1 (`BEGIN RSA PRIVATE KEY`) encodings are accepted.
+/// The key must be 3072 bits so that its signatures fit the archive's signature field.
+///
+/// # Arguments
+///
+/// * `pem` - The PEM-encoded private key.
+pub fn load_private_key_pem(pem: &str) -> io::Result<RsaPrivateKey> {
+    let key = RsaPrivateKey::from_pkcs8_pem(pem)
+        .or_else(|_| RsaPrivateKey::from_pkcs1_pem(pem))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid private key: {}", e)))?;
+    check_key_size(&key)?;
+    Ok(key)
+}
+
+/// Loads an RSA private key from a PEM file
+///
+/// # Arguments
+///
+/// * `path` - Path to the PEM file.
+pub fn load_private_key<P: AsRef<Path>>(path: P) -> io::Result<RsaPrivateKey> {
+    load_private_key_pem(&std::fs::read_to_string(path)?)
+}
+
+/// Loads an RSA public key from PEM text
+///
+/// Both SubjectPublicKeyInfo (`BEGIN PUBLIC KEY`) and PKCS#1 (`BEGIN RSA PUBLIC KEY`)
+/// encodings are accepted.
+///
+/// # Arguments
+///
+/// * `pem` - The PEM-encoded public key.
+pub fn load_public_key_pem(pem: &str) -> io::Result<RsaPublicKey> {
+    RsaPublicKey::from_public_key_pem(pem)
+        .or_else(|_| RsaPublicKey::from_pkcs1_pem(pem))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid public key: {}", e)))
+}
+
+/// Loads an RSA public key from DER bytes
+///
+/// Both SubjectPublicKeyInfo and PKCS#1 encodings are accepted.
+///
+/// # Arguments
+///
+/// * `der` - The DER-encoded public key.
+pub fn load_public_key_der(der: &[u8]) -> io::Result<RsaPublicKey> {
+    RsaPublicKey::from_public_key_der(der)
+        .or_else(|_| RsaPublicKey::from_pkcs1_der(der))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid public key: {}", e)))
+}
+
+/// Loads an RSA public key from a PEM file
+///
+/// # Arguments
+///
+/// * `path` - Path to the PEM file.
+pub fn load_public_key<P: AsRef<Path>>(path: P) -> io::Result<RsaPublicKey> {
+    load_public_key_pem(&std::fs::read_to_string(path)?)
+}
+
+/// Returns the SHA-256 fingerprint of a public key, as lowercase hex
+///
+/// The fingerprint is taken over the key's DER-encoded SubjectPublicKeyInfo, which
+/// matches `openssl pkey -pubin -outform DER | sha256sum`.
+pub fn key_fingerprint(key: &RsaPublicKey) -> io::Result<String> {
+    let der = key
+        .to_public_key_der()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("Invalid public key: {}", e)))?;
+    Ok(crate::to_hex(&Sha256::digest(der.as_bytes())))
+}
+
+/// Verifies an archive's hash and signature against a set of trusted keys
+///
+/// The data section is first checked against the hash in the header (see
+/// [`ObbyArchive::verify_hash`]); the signature is then checked against each key in turn.
+///
+/// # Arguments
+///
+/// * `archive` - The archive to verify.
+/// * `keys` - The trusted public keys.
+///
+/// # Returns
+///
+/// The index in `keys` of the key that made the signature. Unsigned archives fail with
+/// [`ObbyError::MissingSignature`], and signatures made by none of the keys with
+/// [`ObbyError::InvalidSignature`].
+pub fn verify_signature<R: Read + Seek>(archive: &mut ObbyArchive<R>, keys: &[RsaPublicKey]) -> io::Result<usize> {
+    archive.verify_hash()?;
+    let metadata = archive.metadata();
+    let signature = metadata
+        .signature
+        .as_deref()
+        .ok_or_else(|| ObbyError::MissingSignature.into_io())?;
+
+    keys.iter()
+        .position(|key| key.verify(Pkcs1v15Sign::new::<Sha384>(), &metadata.hash, signature).is_ok())
+        .ok_or_else(|| ObbyError::InvalidSignature.into_io())
+}
+
+/// Ensures signatures produced with `key` are exactly [`SIGNATURE_LEN`] bytes long
+pub(crate) fn check_key_size(key: &RsaPrivateKey) -> io::Result<()> {
+    if key.size() != SIGNATURE_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Signing key must be {} bits, got {}", SIGNATURE_LEN * 8, key.size() * 8),
+        ));
+    }
+    Ok(())
+}
+
+/// Signs a SHA-384 hash of the data section
+pub(crate) fn sign_hash(key: &RsaPrivateKey, hash: &[u8]) -> io::Result<Vec<u8>> {
+    key.sign(Pkcs1v15Sign::new::<Sha384>(), hash)
+        .map_err(|e| io::Error::other(format!("Signing failed: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ObbyWriter;
+    use std::io::Cursor;
+
+    fn signed_archive(key: &RsaPrivateKey) -> Vec<u8> {
+        let mut writer = ObbyWriter::new(Vec::new(), "Plugin", "1.0.0");
+        writer.set_signing_key(key.clone()).unwrap();
+        writer.add_entry("plugin.json", b"{}").unwrap();
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let key = load_private_key("test_dir/signing_key.pem").unwrap();
+        let other = load_private_key("test_dir/other_key.pem").unwrap();
+        let trusted = [other.to_public_key(), key.to_public_key()];
+
+        let bytes = signed_archive(&key);
+        let mut archive = ObbyArchive::new(Cursor::new(&bytes[..])).unwrap();
+        assert_eq!(verify_signature(&mut archive, &trusted).unwrap(), 1);
+
+        let err = verify_signature(&mut archive, &trusted[..1]).unwrap_err();
+        assert!(matches!(ObbyError::from_io(&err), Some(ObbyError::InvalidSignature)));
+
+        let pem = key.to_public_key().to_public_key_pem(Default::default()).unwrap();
+        let loaded = load_public_key_pem(&pem).unwrap();
+        assert_eq!(key_fingerprint(&loaded).unwrap(), key_fingerprint(&trusted[1]).unwrap());
+        let der = key.to_public_key().to_public_key_der().unwrap();
+        assert_eq!(load_public_key_der(der.as_bytes()).unwrap(), trusted[1]);
+        assert!(load_public_key_der(b"not a key").is_err());
+    }
+
+    #[test]
+    fn test_unsigned_archive() {
+        let bytes = ObbyWriter::new(Vec::new(), "Plugin", "1.0.0").finish().unwrap();
+        let mut archive = ObbyArchive::new(Cursor::new(bytes)).unwrap();
+        let err = verify_signature(&mut archive, &[]).unwrap_err();
+        assert!(matches!(ObbyError::from_io(&err), Some(ObbyError::MissingSignature)));
+    }
+}
@@ -0,0 +1,282 @@
+//! In-memory backend that parses straight from a byte slice
+//!
+//! The parser itself lives in [`parse`], which doesn't depend on `std`. This module
+//! adapts it to [`ObbyArchive`].
+
+use std::borrow::Cow;
+use std::io::{self, BufRead, Read, Seek, SeekFrom};
+
+use crate::{entry_bytes_in, EntryInfo, ObbyArchive, ObbyError, ObbyMetadata, ObbyReadOptions, ParseWarning, ParsedHeader};
+
+mod parse;
+
+pub use parse::{SliceEntry, SliceError, SliceErrorKind, SliceReader};
+
+/// A `Read + Seek` view of a byte slice, the source of an archive opened with [`ObbyArchive::from_slice_reader`]
+///
+/// Unlike `Cursor<&[u8]>`, such an archive is parsed with [`SliceReader`] by offset
+/// arithmetic rather than through `Read`, and [`ObbyArchive::entry_slice`] hands out
+/// stored entries borrowed for the lifetime of the slice rather than of the archive.
+///
+/// # Example
+///
+/// ```no_run
+/// use obsidian_lib::ObbyArchive;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let bytes = std::fs::read("plugin.obby")?;
+/// let manifest = {
+///     let archive = ObbyArchive::from_slice_reader(&bytes)?;
+///     archive.entry_slice("plugin.json")?
+/// };
+/// println!("{}", String::from_utf8_lossy(&manifest));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct SliceCursor<'a> {
+    data: &'a [u8],
+    pos: u64,
+}
+
+impl<'a> SliceCursor<'a> {
+    /// Creates a cursor positioned at the start of `data`
+    pub fn new(data: &'a [u8]) -> Self {
+        SliceCursor { data, pos: 0 }
+    }
+
+    /// Returns the whole underlying slice
+    pub fn get_ref(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Returns the current position
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// The unread part of the slice, empty once the position is past the end
+    fn remaining(&self) -> &'a [u8] {
+        let start = self.pos.min(self.data.len() as u64) as usize;
+        &self.data[start..]
+    }
+}
+
+impl Read for SliceCursor<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.remaining();
+        let n = remaining.len().min(buf.len());
+        buf[..n].copy_from_slice(&remaining[..n]);
+        self.pos += n as u64;
+        Ok(n)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        let remaining = self.remaining();
+        if remaining.len() < buf.len() {
+            self.pos += remaining.len() as u64;
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "failed to fill whole buffer"));
+        }
+        buf.copy_from_slice(&remaining[..buf.len()]);
+        self.pos += buf.len() as u64;
+        Ok(())
+    }
+}
+
+impl BufRead for SliceCursor<'_> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        Ok(self.remaining())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos += amt as u64;
+    }
+}
+
+impl Seek for SliceCursor<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(n) => {
+                self.pos = n;
+                return Ok(n);
+            }
+            SeekFrom::End(n) => (self.data.len() as u64, n),
+            SeekFrom::Current(n) => (self.pos, n),
+        };
+        self.pos = base.checked_add_signed(offset).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "invalid seek to a negative or overflowing position")
+        })?;
+        Ok(self.pos)
+    }
+
+    fn stream_position(&mut self) -> io::Result<u64> {
+        Ok(self.pos)
+    }
+}
+
+impl From<SliceError> for io::Error {
+    fn from(error: SliceError) -> Self {
+        let kind = match error.kind() {
+            SliceErrorKind::UnexpectedEof => io::ErrorKind::UnexpectedEof,
+            _ => io::ErrorKind::InvalidData,
+        };
+        ObbyError::Parse {
+            field: error.field().to_string(),
+            offset: error.offset() as u64,
+            source: io::Error::new(kind, error.kind().to_string()),
+        }
+        .into_io()
+    }
+}
+
+impl<'a> ObbyArchive<SliceCursor<'a>> {
+    /// Creates an `ObbyArchive` that parses an in-memory buffer in place
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The complete contents of an `.obby` file.
+    pub fn from_slice_reader(bytes: &'a [u8]) -> io::Result<Self> {
+        Self::from_slice_reader_with_options(bytes, ObbyReadOptions::default())
+    }
+
+    /// Creates an `ObbyArchive` over an in-memory buffer with custom read options
+    ///
+    /// The `buffer_size` option is ignored, since the data is already in memory.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The complete contents of an `.obby` file.
+    /// * `options` - The `ObbyReadOptions` to use for this archive.
+    pub fn from_slice_reader_with_options(bytes: &'a [u8], options: ObbyReadOptions) -> io::Result<Self> {
+        let parsed = SliceReader::parse(bytes)?;
+        let table = parsed
+            .entries()
+            .iter()
+            .filter(|entry| options.entry_filter().is_none_or(|filter| filter.matches(entry.name())))
+            .map(|entry| {
+                let info = EntryInfo {
+                    offset: entry.offset(),
+                    length: entry.length(),
+                    compressed_length: entry.compressed_length(),
+                };
+                (entry.name().to_string(), info)
+            })
+            .collect();
+        let mut header = ParsedHeader {
+            metadata: ObbyMetadata {
+                api_version: parsed.api_version().to_string(),
+                hash: parsed.hash().to_vec(),
+                signature: parsed.signature().map(<[u8]>::to_vec),
+                data_length: parsed.data_length(),
+                plugin_assembly: parsed.plugin_assembly().to_string(),
+                plugin_version: parsed.plugin_version().to_string(),
+            },
+            data_section_offset: parsed.data_section_pos() as u64,
+            table,
+            entry_data_len: parsed.entries().iter().map(|entry| entry.compressed_length() as u64).sum(),
+            extras: Vec::new(),
+            extras_len: 0,
+            trailing_len: 0,
+            warnings: parsed
+                .invalid_utf8()
+                .iter()
+                .map(|(field, value)| ParseWarning::InvalidUtf8 { field, value: value.clone() })
+                .collect(),
+        };
+
+        let mut reader = SliceCursor::new(bytes);
+        let data_start_pos = parsed.data_start_pos() as u64;
+        header.check_layout(&mut reader, header.data_section_offset, data_start_pos, bytes.len() as u64, &options)?;
+        Ok(Self::from_parts(reader, options, header, 0, data_start_pos))
+    }
+
+    /// Returns an entry's data, borrowed from the input slice for stored entries
+    ///
+    /// Works like [`ObbyArchive::entry_bytes`], except that borrowed data outlives the
+    /// archive itself.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry_name` - The name of the entry to read.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the entry's data, or an `io::Error` if the entry doesn't exist or is truncated.
+    pub fn entry_slice(&self, entry_name: &str) -> io::Result<Cow<'a, [u8]>> {
+        entry_bytes_in(&self.options, &self.entries, self.reader.get_ref(), self.data_start_pos, entry_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+
+    #[test]
+    fn test_slice_reader_archive() {
+        let buffer = ObbyTestBuilder::new()
+            .stored_entry("plugin.json", br#"{"id": "x"}"#)
+            .entry("main.js", &[b'a'; 500])
+            .build();
+
+        let mut archive = ObbyArchive::from_slice_reader(&buffer).unwrap();
+        assert_eq!(archive.list_entries(), vec!["plugin.json", "main.js"]);
+        assert!(matches!(archive.entry_slice("plugin.json").unwrap(), Cow::Borrowed(_)));
+        assert_eq!(&*archive.entry_slice("main.js").unwrap(), &[b'a'; 500]);
+        assert_eq!(archive.extract_entry("main.js").unwrap(), vec![b'a'; 500]);
+        archive.verify_hash().unwrap();
+
+        assert!(ObbyArchive::from_slice_reader(&buffer[..buffer.len() / 2]).is_err());
+    }
+
+    #[test]
+    fn test_slice_reader_parse() {
+        let buffer = ObbyTestBuilder::new()
+            .stored_entry("plugin.json", br#"{"id": "x"}"#)
+            .entry("main.js", &[b'a'; 500])
+            .build();
+        let header = crate::parse_header(&buffer[..]).unwrap();
+        let borrowed = |bytes: &[u8]| buffer.as_ptr_range().contains(&bytes.as_ptr());
+
+        let parsed = SliceReader::parse(&buffer).unwrap();
+        assert_eq!(parsed.plugin_assembly(), header.metadata.plugin_assembly);
+        assert_eq!(parsed.hash(), &header.metadata.hash[..]);
+        assert_eq!(parsed.data_start_pos() as u64, header.data_offset);
+        let manifest = parsed.entry("plugin.json").unwrap();
+        assert!(borrowed(manifest.name().as_bytes()));
+        assert!(borrowed(manifest.stored().unwrap()));
+        assert_eq!(manifest.stored().unwrap(), br#"{"id": "x"}"#);
+        assert!(parsed.entry("main.js").unwrap().is_compressed());
+        assert!(parsed.invalid_utf8().is_empty());
+
+        let truncated = SliceReader::parse(&buffer[..buffer.len() - 1]).unwrap();
+        assert_eq!(truncated.entry("main.js").unwrap().stored(), None);
+
+        let err = SliceReader::parse(&buffer[..10]).unwrap_err();
+        assert_eq!((err.kind(), err.field()), (SliceErrorKind::UnexpectedEof, "hash"));
+        let err = io::Error::from(err);
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert!(err.to_string().starts_with("Failed to read hash at offset 0x"));
+
+        let mut negative = buffer.clone();
+        let field_pos = negative.windows(7).position(|w| w == b"main.js").unwrap() + 7 + 4;
+        negative[field_pos..field_pos + 4].copy_from_slice(&(-1i32).to_le_bytes());
+        let err = SliceReader::parse(&negative).unwrap_err();
+        assert_eq!(err.kind(), SliceErrorKind::NegativeLength);
+        assert_eq!((err.field(), err.offset()), ("entry[1].compressed_length", field_pos));
+        let err = ObbyArchive::from_slice_reader(&negative).unwrap_err();
+        assert!(matches!(ObbyError::from_io(&err), Some(ObbyError::Parse { .. })));
+    }
+
+    #[test]
+    fn test_seek() {
+        let mut reader = SliceCursor::new(b"abcdef");
+        assert_eq!(reader.seek(SeekFrom::End(-2)).unwrap(), 4);
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"ef");
+        assert!(reader.seek(SeekFrom::Current(-10)).is_err());
+        reader.seek(SeekFrom::Start(100)).unwrap();
+        assert_eq!(reader.read(&mut [0u8; 4]).unwrap(), 0);
+    }
+}
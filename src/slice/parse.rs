@@ -0,0 +1,364 @@
+//! Archive parsing over a byte slice with offset arithmetic
+//!
+//! The parser itself is written against `core` and `alloc` only, though the crate as a
+//! whole still needs `std`. Strings and entry data are borrowed from the slice; only
+//! strings that aren't valid UTF-8 are copied, with invalid sequences replaced by U+FFFD.
+
+use alloc::borrow::Cow;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{HASH_LEN, MAGIC, SIGNATURE_LEN};
+
+/// Smallest possible entry table row: an empty name and two lengths
+const MIN_ROW_LEN: usize = 1 + 4 + 4;
+
+/// Why parsing a slice failed, returned by [`SliceError::kind`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SliceErrorKind {
+    /// The slice ends inside the field
+    UnexpectedEof,
+    /// The slice doesn't start with the `OBBY` magic bytes
+    InvalidMagic,
+    /// A 7-bit encoded integer is longer than five bytes or overflows 32 bits
+    InvalidVarInt,
+    /// A length or count is negative
+    NegativeLength,
+}
+
+impl fmt::Display for SliceErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SliceErrorKind::UnexpectedEof => "failed to fill whole buffer",
+            SliceErrorKind::InvalidMagic => "Invalid plugin header",
+            SliceErrorKind::InvalidVarInt => "Invalid 7-bit encoded integer",
+            SliceErrorKind::NegativeLength => "Negative length in archive",
+        })
+    }
+}
+
+/// Error returned by [`SliceReader::parse`]
+///
+/// Converts into an `io::Error` carrying [`crate::ObbyError::Parse`], like errors from
+/// the other parsers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SliceError {
+    kind: SliceErrorKind,
+    field: String,
+    offset: usize,
+}
+
+impl SliceError {
+    /// Returns why parsing failed
+    pub fn kind(&self) -> SliceErrorKind {
+        self.kind
+    }
+
+    /// Returns the field being read, such as `entry[12].compressed_length`
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// Returns the position of the field in the slice
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl fmt::Display for SliceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed to read {} at offset {:#x}: {}", self.field, self.offset, self.kind)
+    }
+}
+
+impl core::error::Error for SliceError {}
+
+/// An entry table row, borrowed from the slice
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SliceEntry<'a> {
+    name: Cow<'a, str>,
+    /// Position of the entry's data relative to the first entry's data
+    offset: u64,
+    length: i32,
+    compressed_length: i32,
+    /// The stored bytes, cut short if the slice ends first
+    stored: &'a [u8],
+}
+
+impl<'a> SliceEntry<'a> {
+    /// Returns the entry's name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the position of the entry's data, relative to the first entry's data
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns the uncompressed size
+    pub fn length(&self) -> i32 {
+        self.length
+    }
+
+    /// Returns the stored size
+    pub fn compressed_length(&self) -> i32 {
+        self.compressed_length
+    }
+
+    /// Returns whether the entry is compressed, which the format marks by a stored size that differs from the real size
+    pub fn is_compressed(&self) -> bool {
+        self.compressed_length != self.length
+    }
+
+    /// Returns the entry's stored bytes, or `None` if the slice ends before them
+    pub fn stored(&self) -> Option<&'a [u8]> {
+        (self.stored.len() == self.compressed_length as usize).then_some(self.stored)
+    }
+}
+
+/// An `.obby` archive parsed in place from a byte slice
+///
+/// The header and entry table are decoded by indexing into the slice rather than
+/// through `Read`, and nothing is copied. To decompress entries, open the slice with
+/// [`crate::ObbyArchive::from_slice_reader`].
+///
+/// # Example
+///
+/// ```no_run
+/// use obsidian_lib::SliceReader;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let bytes = std::fs::read("plugin.obby")?;
+/// let archive = SliceReader::parse(&bytes)?;
+/// for entry in archive.entries() {
+///     println!("{} ({} bytes)", entry.name(), entry.length());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SliceReader<'a> {
+    data: &'a [u8],
+    api_version: Cow<'a, str>,
+    hash: &'a [u8],
+    signature: Option<&'a [u8]>,
+    data_length: i32,
+    plugin_assembly: Cow<'a, str>,
+    plugin_version: Cow<'a, str>,
+    data_section_pos: usize,
+    data_start_pos: usize,
+    entries: Vec<SliceEntry<'a>>,
+    invalid_utf8: Vec<(&'static str, String)>,
+}
+
+impl<'a> SliceReader<'a> {
+    /// Parses the header and entry table at the start of `data`
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The archive, from its magic bytes on.
+    ///
+    /// # Returns
+    ///
+    /// The parsed archive, or a [`SliceError`] naming the field that is malformed or cut off.
+    pub fn parse(data: &'a [u8]) -> Result<Self, SliceError> {
+        let mut fields = Fields { data, pos: 0, invalid_utf8: Vec::new() };
+
+        fields.read(|| "magic".to_string(), |fields| match fields.take(MAGIC.len())? == MAGIC {
+            true => Ok(()),
+            false => Err(SliceErrorKind::InvalidMagic),
+        })?;
+        let api_version = fields.read(|| "api_version".to_string(), |fields| fields.string("API version"))?;
+        let hash = fields.read(|| "hash".to_string(), |fields| fields.take(HASH_LEN))?;
+        let signed = fields.read(|| "signed".to_string(), |fields| fields.take(1))?[0] != 0;
+        let signature = match signed {
+            true => Some(fields.read(|| "signature".to_string(), |fields| fields.take(SIGNATURE_LEN))?),
+            false => None,
+        };
+        let data_length = fields.read(|| "data_length".to_string(), Fields::i32)?;
+        let data_section_pos = fields.pos;
+        let plugin_assembly = fields.read(|| "plugin_assembly".to_string(), |fields| fields.string("assembly name"))?;
+        let plugin_version = fields.read(|| "plugin_version".to_string(), |fields| fields.string("plugin version"))?;
+
+        let entry_count = fields.read(|| "entry_count".to_string(), Fields::length)?;
+        // Counts come from untrusted input, so only trust them as far as the data goes
+        let mut entries = Vec::with_capacity((entry_count as usize).min(fields.remaining() / MIN_ROW_LEN));
+        let mut offset = 0u64;
+        for index in 0..entry_count {
+            let name = fields.read(|| format!("entry[{}].name", index), |fields| fields.string("entry name"))?;
+            let length = fields.read(|| format!("entry[{}].length", index), Fields::length)?;
+            let compressed_length = fields.read(|| format!("entry[{}].compressed_length", index), Fields::length)?;
+            entries.push((name, offset, length, compressed_length));
+            offset += compressed_length as u64;
+        }
+
+        let data_start_pos = fields.pos;
+        let entries = entries
+            .into_iter()
+            .map(|(name, offset, length, compressed_length)| {
+                let start = (data_start_pos as u64).saturating_add(offset).min(data.len() as u64) as usize;
+                let end = start.saturating_add(compressed_length as usize).min(data.len());
+                SliceEntry { name, offset, length, compressed_length, stored: &data[start..end] }
+            })
+            .collect();
+
+        Ok(SliceReader {
+            data,
+            api_version,
+            hash,
+            signature,
+            data_length,
+            plugin_assembly,
+            plugin_version,
+            data_section_pos,
+            data_start_pos,
+            entries,
+            invalid_utf8: fields.invalid_utf8,
+        })
+    }
+
+    /// Returns the whole slice the archive was parsed from
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Returns the Obsidian API version the plugin was built against
+    pub fn api_version(&self) -> &str {
+        &self.api_version
+    }
+
+    /// Returns the SHA-384 hash of the data section
+    pub fn hash(&self) -> &'a [u8] {
+        self.hash
+    }
+
+    /// Returns the RSA signature of the hash, if the archive is signed
+    pub fn signature(&self) -> Option<&'a [u8]> {
+        self.signature
+    }
+
+    /// Returns the declared length of the data section in bytes
+    pub fn data_length(&self) -> i32 {
+        self.data_length
+    }
+
+    /// Returns the plugin's assembly name
+    pub fn plugin_assembly(&self) -> &str {
+        &self.plugin_assembly
+    }
+
+    /// Returns the plugin's version
+    pub fn plugin_version(&self) -> &str {
+        &self.plugin_version
+    }
+
+    /// Returns the position of the hashed data section in the slice
+    pub fn data_section_pos(&self) -> usize {
+        self.data_section_pos
+    }
+
+    /// Returns the position of the first entry's data in the slice
+    pub fn data_start_pos(&self) -> usize {
+        self.data_start_pos
+    }
+
+    /// Returns the entry table, in table order and including any duplicate names
+    pub fn entries(&self) -> &[SliceEntry<'a>] {
+        &self.entries
+    }
+
+    /// Finds an entry by name
+    ///
+    /// When a name appears more than once, the last occurrence wins, as in [`crate::ObbyArchive`].
+    pub fn entry(&self, name: &str) -> Option<&SliceEntry<'a>> {
+        self.entries.iter().rev().find(|entry| entry.name() == name)
+    }
+
+    /// Returns the strings that weren't valid UTF-8, as the kind of field (such as
+    /// `"entry name"`) and the string as decoded, in the order they were read
+    pub fn invalid_utf8(&self) -> &[(&'static str, String)] {
+        &self.invalid_utf8
+    }
+}
+
+/// Position in the slice being parsed
+struct Fields<'a> {
+    data: &'a [u8],
+    pos: usize,
+    invalid_utf8: Vec<(&'static str, String)>,
+}
+
+impl<'a> Fields<'a> {
+    /// Reads a field with `read`, attaching its name and position to any error
+    fn read<T>(
+        &mut self,
+        field: impl FnOnce() -> String,
+        read: impl FnOnce(&mut Self) -> Result<T, SliceErrorKind>,
+    ) -> Result<T, SliceError> {
+        let offset = self.pos;
+        read(self).map_err(|kind| SliceError { kind, field: field(), offset })
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SliceErrorKind> {
+        if len > self.remaining() {
+            return Err(SliceErrorKind::UnexpectedEof);
+        }
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn i32(&mut self) -> Result<i32, SliceErrorKind> {
+        let bytes = self.take(4)?;
+        Ok(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Reads a length or count, rejecting negative values
+    fn length(&mut self) -> Result<i32, SliceErrorKind> {
+        match self.i32()? {
+            value if value < 0 => Err(SliceErrorKind::NegativeLength),
+            value => Ok(value),
+        }
+    }
+
+    /// Reads a 7-bit encoded integer, following the same rules as [`crate::format::wire::BinaryReader`]
+    fn var_int(&mut self) -> Result<i32, SliceErrorKind> {
+        let mut value = 0u32;
+        for step in 0..5 {
+            let byte = self.take(1)?[0];
+            if step == 4 && byte > 0x0F {
+                break;
+            }
+            value |= ((byte & 0x7F) as u32) << (step * 7);
+            if byte & 0x80 == 0 {
+                return Ok(value as i32);
+            }
+        }
+        Err(SliceErrorKind::InvalidVarInt)
+    }
+
+    /// Reads a length-prefixed string, recording it as `kind` if it isn't valid UTF-8
+    fn string(&mut self, kind: &'static str) -> Result<Cow<'a, str>, SliceErrorKind> {
+        let length = self.var_int()?;
+        if length < 0 {
+            return Err(SliceErrorKind::NegativeLength);
+        }
+        let bytes = self.take(length as usize)?;
+        Ok(match core::str::from_utf8(bytes) {
+            Ok(value) => Cow::Borrowed(value),
+            Err(_) => {
+                let value = String::from_utf8_lossy(bytes).into_owned();
+                self.invalid_utf8.push((kind, value.clone()));
+                Cow::Owned(value)
+            }
+        })
+    }
+}
@@ -0,0 +1,356 @@
+//! Random-access backends for archives
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::Arc;
+
+use crate::{ObbyArchive, ObbyReadOptions};
+
+/// Random-access storage an archive can be read from
+///
+/// Implement this for storage that is naturally addressed by offset, such as memory
+/// maps, HTTP range requests or object stores, then open it with
+/// [`ObbyArchive::from_source`]. [`SourceReader`] adapts any source to `Read + Seek`, so
+/// the archive works exactly as with a file. Reads take `&self`, so one source can be
+/// shared between archives and threads.
+///
+/// Implementations are provided for `File`, byte slices, `Vec<u8>`, and for references
+/// and `Arc`s of other sources.
+///
+/// # Example
+///
+/// ```
+/// use obsidian_lib::ObbySource;
+/// use std::io;
+///
+/// /// A source that serves a fixed buffer, as a remote backend would serve ranges
+/// struct Remote(Vec<u8>);
+///
+/// impl ObbySource for Remote {
+///     fn size(&self) -> io::Result<u64> {
+///         Ok(self.0.len() as u64)
+///     }
+///
+///     fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+///         self.0.as_slice().read_at(offset, buf)
+///     }
+/// }
+/// ```
+pub trait ObbySource {
+    /// Returns the total size of the source in bytes
+    fn size(&self) -> io::Result<u64>;
+
+    /// Reads bytes starting at `offset` into `buf`
+    ///
+    /// Like `Read::read`, this may read fewer bytes than requested, and returns `0` only
+    /// when `offset` is at or past the end of the source or `buf` is empty.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - Position of the first byte to read.
+    /// * `buf` - Where to store the bytes.
+    ///
+    /// # Returns
+    ///
+    /// The number of bytes read.
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize>;
+}
+
+impl ObbySource for [u8] {
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.len() as u64)
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        let start = offset.min(self.len() as u64) as usize;
+        let n = (self.len() - start).min(buf.len());
+        buf[..n].copy_from_slice(&self[start..start + n]);
+        Ok(n)
+    }
+}
+
+impl ObbySource for Vec<u8> {
+    fn size(&self) -> io::Result<u64> {
+        self.as_slice().size()
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        self.as_slice().read_at(offset, buf)
+    }
+}
+
+impl ObbySource for File {
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.metadata()?.len())
+    }
+
+    #[cfg(unix)]
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        std::os::unix::fs::FileExt::read_at(self, buf, offset)
+    }
+
+    #[cfg(windows)]
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        std::os::windows::fs::FileExt::seek_read(self, buf, offset)
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        let mut file = self;
+        file.seek(SeekFrom::Start(offset))?;
+        file.read(buf)
+    }
+}
+
+impl<S: ObbySource + ?Sized> ObbySource for &S {
+    fn size(&self) -> io::Result<u64> {
+        (**self).size()
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        (**self).read_at(offset, buf)
+    }
+}
+
+impl<S: ObbySource + ?Sized> ObbySource for Arc<S> {
+    fn size(&self) -> io::Result<u64> {
+        (**self).size()
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        (**self).read_at(offset, buf)
+    }
+}
+
+impl<S: ObbySource + ?Sized> ObbySource for Box<S> {
+    fn size(&self) -> io::Result<u64> {
+        (**self).size()
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        (**self).read_at(offset, buf)
+    }
+}
+
+/// Number of bytes [`SourceReader`] requests from its source at a time unless overridden
+pub const DEFAULT_BLOCK_SIZE: usize = 64 * 1024;
+
+/// A `Read + Seek` cursor over an [`ObbySource`]
+///
+/// Small reads are served from a read-ahead buffer: when a read misses it, one block
+/// starting at the current position is requested from the source, so parsing the
+/// entry table or streaming an entry in small pieces costs one
+/// [`ObbySource::read_at`] call per block rather than per read. Seeking keeps the
+/// buffer, so reads that land in it again cost nothing. Reads of at least a block go
+/// to the source directly. The source's size is only requested when seeking relative
+/// to the end, and then cached.
+#[derive(Debug)]
+pub struct SourceReader<S: ObbySource> {
+    source: S,
+    pos: u64,
+    size: Option<u64>,
+    block_size: usize,
+    /// Bytes read ahead, starting at `buffer_start`
+    buffer: Vec<u8>,
+    buffer_start: u64,
+}
+
+impl<S: ObbySource> SourceReader<S> {
+    /// Creates a reader positioned at the start of `source`, reading [`DEFAULT_BLOCK_SIZE`] bytes at a time
+    pub fn new(source: S) -> Self {
+        Self::with_block_size(source, DEFAULT_BLOCK_SIZE)
+    }
+
+    /// Creates a reader positioned at the start of `source`, reading `block_size` bytes at a time
+    ///
+    /// Sources with a high cost per request, such as object stores, benefit from larger
+    /// blocks. A block size of `0` disables read-ahead, so every `read` becomes one
+    /// `read_at` call.
+    pub fn with_block_size(source: S, block_size: usize) -> Self {
+        SourceReader { source, pos: 0, size: None, block_size, buffer: Vec::new(), buffer_start: 0 }
+    }
+
+    /// Returns how many bytes are requested from the source at a time
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// Returns the underlying source
+    pub fn get_ref(&self) -> &S {
+        &self.source
+    }
+
+    /// Consumes the reader, returning the underlying source
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+}
+
+impl<S: ObbySource> SourceReader<S> {
+    /// Returns the buffered bytes from the current position on, if any
+    fn buffered(&self) -> &[u8] {
+        match self.pos.checked_sub(self.buffer_start) {
+            Some(skip) if skip < self.buffer.len() as u64 => &self.buffer[skip as usize..],
+            _ => &[],
+        }
+    }
+}
+
+impl<S: ObbySource> Read for SourceReader<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.buffered().is_empty() {
+            if buf.len() >= self.block_size {
+                let n = self.source.read_at(self.pos, buf)?;
+                self.pos += n as u64;
+                return Ok(n);
+            }
+            self.buffer.resize(self.block_size, 0);
+            let filled = self.source.read_at(self.pos, &mut self.buffer);
+            // Keep the buffer consistent even if the source failed
+            self.buffer.truncate(*filled.as_ref().unwrap_or(&0));
+            self.buffer_start = self.pos;
+            filled?;
+        }
+        let buffered = self.buffered();
+        let n = buffered.len().min(buf.len());
+        buf[..n].copy_from_slice(&buffered[..n]);
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<S: ObbySource> Seek for SourceReader<S> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(n) => {
+                self.pos = n;
+                return Ok(n);
+            }
+            SeekFrom::End(n) => {
+                let size = match self.size {
+                    Some(size) => size,
+                    None => *self.size.insert(self.source.size()?),
+                };
+                (size, n)
+            }
+            SeekFrom::Current(n) => (self.pos, n),
+        };
+        self.pos = base.checked_add_signed(offset).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "invalid seek to a negative or overflowing position")
+        })?;
+        Ok(self.pos)
+    }
+
+    fn stream_position(&mut self) -> io::Result<u64> {
+        Ok(self.pos)
+    }
+}
+
+impl<S: ObbySource> ObbyArchive<SourceReader<S>> {
+    /// Creates an `ObbyArchive` over a random-access [`ObbySource`]
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The storage holding the archive, starting at offset 0.
+    pub fn from_source(source: S) -> io::Result<Self> {
+        Self::from_source_with_options(source, ObbyReadOptions::default())
+    }
+
+    /// Creates an `ObbyArchive` over a random-access [`ObbySource`] with custom read options
+    ///
+    /// The source is read in blocks of [`DEFAULT_BLOCK_SIZE`] bytes, or of the
+    /// `buffer_size` option if that is larger, which matters for sources with a high cost
+    /// per request. For a different block size, open a [`SourceReader::with_block_size`]
+    /// with [`ObbyArchive::with_options`].
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The storage holding the archive, starting at offset 0.
+    /// * `options` - The `ObbyReadOptions` to use for this archive.
+    pub fn from_source_with_options(source: S, options: ObbyReadOptions) -> io::Result<Self> {
+        let block_size = options.buffer_size().max(DEFAULT_BLOCK_SIZE);
+        Self::with_options(SourceReader::with_block_size(source, block_size), options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+    use std::cell::Cell;
+    use std::io::Write;
+
+    /// Serves a buffer while counting requests
+    struct Counting {
+        data: Vec<u8>,
+        requests: Cell<usize>,
+    }
+
+    impl ObbySource for Counting {
+        fn size(&self) -> io::Result<u64> {
+            Ok(self.data.len() as u64)
+        }
+
+        fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+            self.requests.set(self.requests.get() + 1);
+            self.data.read_at(offset, buf)
+        }
+    }
+
+    #[test]
+    fn test_custom_source() {
+        let buffer = ObbyTestBuilder::new()
+            .entry("plugin.json", br#"{"id": "x"}"#)
+            .entry("main.js", &[b'a'; 5000])
+            .build();
+        let source = Counting { data: buffer, requests: Cell::new(0) };
+        let mut archive = ObbyArchive::from_source(&source).unwrap();
+        assert_eq!(archive.extract_entry("main.js").unwrap(), vec![b'a'; 5000]);
+        archive.verify_hash().unwrap();
+        assert!(source.requests.get() > 0);
+
+        assert!(ObbyArchive::from_source(&source.data[..20]).is_err());
+    }
+
+    #[test]
+    fn test_read_ahead() {
+        let buffer = ObbyTestBuilder::new()
+            .entry("plugin.json", br#"{"id": "x"}"#)
+            .entry("notes.txt", "lorem ipsum ".repeat(500).as_bytes())
+            .build();
+        let requests = |block_size| {
+            let source = Counting { data: buffer.clone(), requests: Cell::new(0) };
+            let mut archive = ObbyArchive::with_options(SourceReader::with_block_size(&source, block_size), ObbyReadOptions::default()).unwrap();
+            assert_eq!(archive.extract_entry("plugin.json").unwrap(), br#"{"id": "x"}"#);
+            assert_eq!(archive.extract_entry("notes.txt").unwrap().len(), 6000);
+            source.requests.get()
+        };
+        // The whole archive fits in one block
+        assert_eq!(requests(DEFAULT_BLOCK_SIZE), 1);
+        assert!(requests(0) > 1);
+
+        // Reads straddling blocks and seeks back into the buffer return the right bytes
+        let mut reader = SourceReader::with_block_size(&buffer[..], 7);
+        let mut all = Vec::new();
+        reader.read_to_end(&mut all).unwrap();
+        assert_eq!(all, buffer);
+        let mut middle = [0; 5];
+        reader.seek(SeekFrom::Start(10)).unwrap();
+        reader.read_exact(&mut middle).unwrap();
+        assert_eq!(middle, buffer[10..15]);
+    }
+
+    #[test]
+    fn test_file_source() {
+        let buffer = ObbyTestBuilder::new().stored_entry("plugin.json", b"{}").build();
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(&buffer).unwrap();
+        let file = Arc::new(file);
+
+        // Positional reads don't move a shared cursor, so archives can share the file
+        let mut first = ObbyArchive::from_source(Arc::clone(&file)).unwrap();
+        let mut second = ObbyArchive::from_source(Arc::clone(&file)).unwrap();
+        assert_eq!(first.extract_entry("plugin.json").unwrap(), b"{}");
+        assert_eq!(second.extract_entry("plugin.json").unwrap(), b"{}");
+    }
+}
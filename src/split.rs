@@ -0,0 +1,115 @@
+//! Partitioning an archive into several by entry prefix
+
+use std::io::{self, Read, Seek, Write};
+
+use crate::{ObbyArchive, ObbyWriter};
+
+/// One part of an [`ObbyArchive::split`]: the entries under a prefix and where they go
+#[derive(Debug)]
+pub struct SplitRule<W: Write> {
+    prefix: String,
+    out: W,
+}
+
+impl<W: Write> SplitRule<W> {
+    /// Sends entries whose names start with `prefix` to `out`
+    ///
+    /// An empty prefix matches every entry, which makes a catch-all for the last rule.
+    pub fn new(prefix: &str, out: W) -> Self {
+        SplitRule { prefix: prefix.to_string(), out }
+    }
+
+    /// Returns the prefix this rule matches
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+}
+
+impl<R: Read + Seek> ObbyArchive<R> {
+    /// Writes the entries into several new archives, partitioned by prefix
+    ///
+    /// Each entry goes to the first rule whose prefix its name starts with; entries that
+    /// no rule matches are left out. Every part gets this archive's API version,
+    /// assembly name and version, and entries are copied without recompression. The
+    /// plugin manifest is an ordinary entry, so give it a rule of its own if several
+    /// parts need it. The parts are unsigned.
+    ///
+    /// # Arguments
+    ///
+    /// * `rules` - The parts to write, in order of precedence.
+    ///
+    /// # Returns
+    ///
+    /// The rules' sinks in the same order, each holding a complete archive, which may
+    /// have no entries.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use obsidian_lib::SplitRule;
+    /// use std::fs::File;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut archive = obsidian_lib::open("plugin.obby")?;
+    /// archive.split(vec![
+    ///     SplitRule::new("assets/", File::create("plugin-assets.obby")?),
+    ///     SplitRule::new("", File::create("plugin-core.obby")?),
+    /// ])?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn split<W: Write>(&mut self, rules: Vec<SplitRule<W>>) -> io::Result<Vec<W>> {
+        let metadata = self.metadata().clone();
+        let prefixes: Vec<String> = rules.iter().map(|rule| rule.prefix.clone()).collect();
+        let mut writers: Vec<ObbyWriter<W>> = rules
+            .into_iter()
+            .map(|rule| {
+                let mut writer = ObbyWriter::new(rule.out, &metadata.plugin_assembly, &metadata.plugin_version);
+                writer.set_api_version(&metadata.api_version);
+                writer
+            })
+            .collect();
+        for name in self.list_entries() {
+            if let Some(index) = prefixes.iter().position(|prefix| name.starts_with(prefix.as_str())) {
+                writers[index].copy_entry_from(self, &name)?;
+            }
+        }
+        writers.into_iter().map(ObbyWriter::finish).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+
+    #[test]
+    fn test_split() {
+        let buffer = ObbyTestBuilder::new()
+            .plugin("Plugin", "2.0.0")
+            .entry("plugin.json", b"{}")
+            .entry("assets/a.png", b"a")
+            .entry("main.js", b"js")
+            .entry("assets/b.png", b"b")
+            .entry("docs/README.md", b"readme")
+            .build();
+        let mut archive = ObbyArchive::from_bytes(buffer).unwrap();
+        let parts = archive
+            .split(vec![
+                SplitRule::new("assets/", Vec::new()),
+                SplitRule::new("plugin.json", Vec::new()),
+                SplitRule::new("main", Vec::new()),
+                SplitRule::new("empty/", Vec::new()),
+            ])
+            .unwrap();
+        let names: Vec<Vec<String>> = parts
+            .into_iter()
+            .map(|part| {
+                let part = ObbyArchive::from_bytes(part).unwrap();
+                assert_eq!(part.metadata().plugin_version, "2.0.0");
+                part.list_entries()
+            })
+            .collect();
+        assert_eq!(names, vec![vec!["assets/a.png", "assets/b.png"], vec!["plugin.json"], vec!["main.js"], vec![]]);
+    }
+}
@@ -0,0 +1,92 @@
+//! Temporary storage for entry payloads that are too large to keep in memory
+
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::PathBuf;
+
+/// Size of the chunks copied between the spool and other streams
+const CHUNK_LEN: usize = 64 * 1024;
+
+/// An append-only temporary file, removed when dropped
+///
+/// Used by [`crate::ObbyWriter::add_entry_from_reader`] to hold compressed payloads
+/// until the archive is finished.
+pub(crate) struct Spool {
+    file: File,
+    len: u64,
+    _path: RemoveOnDrop,
+}
+
+/// Removes the spool file once the file itself is closed, which Windows requires
+struct RemoveOnDrop(PathBuf);
+
+impl Drop for RemoveOnDrop {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+impl Spool {
+    /// Creates an empty spool in the system's temporary directory
+    pub(crate) fn new() -> io::Result<Spool> {
+        let (file, path) = crate::output::create_temp(&std::env::temp_dir().join("obby-spool"))?;
+        Ok(Spool { file, len: 0, _path: RemoveOnDrop(path) })
+    }
+
+    /// Returns the number of bytes appended so far
+    pub(crate) fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Drops everything appended after `len`, undoing a failed append
+    pub(crate) fn truncate(&mut self, len: u64) -> io::Result<()> {
+        self.file.set_len(len)?;
+        self.len = len;
+        Ok(())
+    }
+
+    /// Copies the bytes in `range` to `out`
+    pub(crate) fn copy_range<W: Write + ?Sized>(&mut self, range: Range<u64>, out: &mut W) -> io::Result<()> {
+        self.file.seek(SeekFrom::Start(range.start))?;
+        let copied = io::copy(&mut (&mut self.file).take(range.end - range.start), out)?;
+        if copied != range.end - range.start {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Spool file is shorter than expected"));
+        }
+        Ok(())
+    }
+
+    /// Appends the inflated contents of the deflate stream stored in `range`
+    pub(crate) fn append_inflated(&mut self, range: Range<u64>) -> io::Result<()> {
+        let mut decoder = flate2::write::DeflateDecoder::new(Vec::new());
+        let mut buf = vec![0u8; CHUNK_LEN];
+        let mut pos = range.start;
+        while pos < range.end {
+            self.file.seek(SeekFrom::Start(pos))?;
+            let max = (range.end - pos).min(CHUNK_LEN as u64) as usize;
+            let read = self.file.read(&mut buf[..max])?;
+            if read == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Spool file is shorter than expected"));
+            }
+            pos += read as u64;
+            decoder.write_all(&buf[..read])?;
+            let inflated = std::mem::take(decoder.get_mut());
+            self.write_all(&inflated)?;
+        }
+        let inflated = decoder.finish()?;
+        self.write_all(&inflated)
+    }
+}
+
+impl Write for Spool {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.seek(SeekFrom::Start(self.len))?;
+        let written = self.file.write(buf)?;
+        self.len += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
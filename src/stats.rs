@@ -0,0 +1,103 @@
+//! Size statistics computed from the entry table
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::EntryInfo;
+
+/// Summary of an archive's contents, returned by [`crate::ObbyArchive::stats`]
+///
+/// Everything is computed from the entry table, so no entry data is read.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ArchiveStats {
+    /// Number of entries
+    pub entry_count: usize,
+    /// Sum of the entries' sizes as stored in the archive
+    pub compressed_size: u64,
+    /// Sum of the entries' decompressed sizes
+    pub uncompressed_size: u64,
+    /// Name and decompressed size of the largest entry, if there are any entries
+    pub largest_entry: Option<(String, u64)>,
+    /// Number of entries per lowercased file extension; entries without one are counted under `""`
+    pub extensions: BTreeMap<String, usize>,
+}
+
+impl ArchiveStats {
+    pub(crate) fn from_entries<'a>(entries: impl IntoIterator<Item = (&'a str, &'a EntryInfo)>) -> Self {
+        let mut stats = ArchiveStats::default();
+        for (name, info) in entries {
+            let length = info.length as u64;
+            stats.entry_count += 1;
+            stats.compressed_size += info.compressed_length as u64;
+            stats.uncompressed_size += length;
+            if stats.largest_entry.as_ref().is_none_or(|(_, largest)| length > *largest) {
+                stats.largest_entry = Some((name.to_string(), length));
+            }
+            *stats.extensions.entry(extension(name)).or_insert(0) += 1;
+        }
+        stats
+    }
+
+    /// Returns the compressed size as a fraction of the uncompressed size
+    ///
+    /// Smaller is better; `1.0` means nothing was saved. Archives without any data
+    /// report `1.0`.
+    pub fn compression_ratio(&self) -> f64 {
+        if self.uncompressed_size == 0 {
+            1.0
+        } else {
+            self.compressed_size as f64 / self.uncompressed_size as f64
+        }
+    }
+}
+
+/// Details of a single extraction, returned by [`crate::ObbyArchive::extract_entry_info`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtractStats {
+    /// Size of the entry as stored in the archive
+    pub compressed_size: u64,
+    /// Size of the extracted data
+    pub decompressed_size: u64,
+    /// Whether the entry had to be decompressed
+    pub decompressed: bool,
+    /// Time spent reading and decompressing the entry
+    ///
+    /// Always zero on `wasm32-unknown-unknown`, which has no clock.
+    pub elapsed: Duration,
+}
+
+/// Returns the lowercased extension of the last path component of `name`
+fn extension(name: &str) -> String {
+    let file_name = crate::tree::components(name).last().unwrap_or("");
+    match file_name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => ext.to_ascii_lowercase(),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats() {
+        let table = [
+            ("plugin.json", EntryInfo { offset: 0, length: 100, compressed_length: 60 }),
+            ("Plugin.DLL", EntryInfo { offset: 60, length: 4000, compressed_length: 1500 }),
+            ("lib.v2/.hidden", EntryInfo { offset: 1560, length: 10, compressed_length: 10 }),
+            ("assets/a.dll", EntryInfo { offset: 1570, length: 40, compressed_length: 30 }),
+        ];
+        let stats = ArchiveStats::from_entries(table.iter().map(|(name, info)| (*name, info)));
+
+        assert_eq!(stats.entry_count, 4);
+        assert_eq!(stats.compressed_size, 1600);
+        assert_eq!(stats.uncompressed_size, 4150);
+        assert_eq!(stats.largest_entry, Some(("Plugin.DLL".to_string(), 4000)));
+        assert_eq!(stats.extensions.get("dll"), Some(&2));
+        assert_eq!(stats.extensions.get("json"), Some(&1));
+        assert_eq!(stats.extensions.get(""), Some(&1));
+        assert!((stats.compression_ratio() - 1600.0 / 4150.0).abs() < 1e-9);
+
+        assert_eq!(ArchiveStats::from_entries([]).compression_ratio(), 1.0);
+    }
+}
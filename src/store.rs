@@ -0,0 +1,169 @@
+//! Reading archives straight from object stores (S3, GCS, Azure)
+//!
+//! Enabled with the `object_store` feature.
+
+use std::future::Future;
+use std::io;
+use std::sync::Arc;
+
+use object_store::path::Path;
+use object_store::ObjectStore;
+use tokio::runtime::{Builder, Handle, Runtime, RuntimeFlavor};
+
+use crate::ObbySource;
+
+/// An [`ObbySource`] reading an object through the `object_store` crate
+///
+/// Each block a [`crate::SourceReader`] reads is a ranged `GET`, so only the header, the
+/// entry table and the entries actually extracted are downloaded. Raise the block size
+/// with [`crate::ObbyReadOptions::set_buffer_size`] to download in fewer requests.
+///
+/// Reads block on the store's futures. They run on the Tokio runtime that was current
+/// when the source was created if it is a multi-thread runtime, and on a private runtime
+/// otherwise: blocking on a current-thread runtime from outside its own `block_on` would
+/// leave nothing driving its IO. Inside an async task, create and use the source from
+/// `tokio::task::spawn_blocking`.
+///
+/// # Example
+///
+/// ```no_run
+/// use obsidian_lib::{ObbyArchive, ObjectStoreSource};
+/// use object_store::memory::InMemory;
+/// use std::sync::Arc;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let store = Arc::new(InMemory::new()); // or an AmazonS3, GoogleCloudStorage, ...
+/// let source = ObjectStoreSource::new(store, "plugins/my-plugin.obby".into())?;
+/// let mut archive = ObbyArchive::from_source(source)?;
+/// let manifest = archive.extract_entry("plugin.json")?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ObjectStoreSource {
+    store: Arc<dyn ObjectStore>,
+    location: Path,
+    size: u64,
+    runtime: RuntimeRef,
+}
+
+#[derive(Debug, Clone)]
+enum RuntimeRef {
+    Handle(Handle),
+    Owned(Arc<Runtime>),
+}
+
+impl ObjectStoreSource {
+    /// Creates a source for the object at `location`
+    ///
+    /// The object's size is requested once, here.
+    ///
+    /// # Arguments
+    ///
+    /// * `store` - The object store holding the archive.
+    /// * `location` - Path of the archive within the store.
+    ///
+    /// # Returns
+    ///
+    /// The source, or an `io::Error` of kind `NotFound` if the object doesn't exist.
+    pub fn new(store: Arc<dyn ObjectStore>, location: Path) -> io::Result<Self> {
+        let runtime = match Handle::try_current() {
+            Ok(handle) if handle.runtime_flavor() != RuntimeFlavor::CurrentThread => RuntimeRef::Handle(handle),
+            _ => RuntimeRef::Owned(Arc::new(Builder::new_current_thread().enable_all().build()?)),
+        };
+        let mut source = ObjectStoreSource { store, location, size: 0, runtime };
+        source.size = source.block_on(source.store.head(&source.location)).map_err(into_io)?.size;
+        Ok(source)
+    }
+
+    /// Returns the archive's location within the store
+    pub fn location(&self) -> &Path {
+        &self.location
+    }
+
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        match &self.runtime {
+            RuntimeRef::Handle(handle) => handle.block_on(future),
+            RuntimeRef::Owned(runtime) => runtime.block_on(future),
+        }
+    }
+}
+
+impl ObbySource for ObjectStoreSource {
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.size)
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        let end = offset.saturating_add(buf.len() as u64).min(self.size);
+        if offset >= end {
+            return Ok(0);
+        }
+        let bytes = self
+            .block_on(self.store.get_range(&self.location, offset..end))
+            .map_err(into_io)?;
+        let n = bytes.len().min(buf.len());
+        buf[..n].copy_from_slice(&bytes[..n]);
+        Ok(n)
+    }
+}
+
+fn into_io(e: object_store::Error) -> io::Error {
+    match e {
+        object_store::Error::NotFound { .. } => io::Error::new(io::ErrorKind::NotFound, e),
+        e => io::Error::other(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+    use crate::ObbyArchive;
+    use object_store::memory::InMemory;
+
+    #[test]
+    fn test_object_store_source() {
+        let buffer = ObbyTestBuilder::new()
+            .entry("plugin.json", br#"{"id": "remote"}"#)
+            .entry("main.js", &[b'a'; 5000])
+            .build();
+        let store = Arc::new(InMemory::new());
+        let location = Path::from("plugins/remote.obby");
+        Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(store.put(&location, buffer.into()))
+            .unwrap();
+
+        let source = ObjectStoreSource::new(store.clone(), location).unwrap();
+        let mut archive = ObbyArchive::from_source(source).unwrap();
+        assert_eq!(archive.extract_entry("plugin.json").unwrap(), br#"{"id": "remote"}"#);
+        assert_eq!(archive.extract_entry("main.js").unwrap(), vec![b'a'; 5000]);
+        archive.verify_hash().unwrap();
+
+        let err = ObjectStoreSource::new(store, Path::from("missing.obby")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_current_thread_runtime() {
+        let buffer = ObbyTestBuilder::new().entry("main.js", &[b'a'; 5000]).build();
+        let store = Arc::new(InMemory::new());
+        let location = Path::from("plugins/remote.obby");
+        let runtime = Builder::new_current_thread().enable_all().build().unwrap();
+        runtime.block_on(store.put(&location, buffer.into())).unwrap();
+
+        // As from `#[tokio::main(flavor = "current_thread")]`
+        let data = runtime.block_on(async move {
+            tokio::task::spawn_blocking(move || {
+                let source = ObjectStoreSource::new(store, location).unwrap();
+                assert!(matches!(source.runtime, RuntimeRef::Owned(_)));
+                ObbyArchive::from_source(source).unwrap().extract_entry("main.js").unwrap()
+            })
+            .await
+            .unwrap()
+        });
+        assert_eq!(data, vec![b'a'; 5000]);
+    }
+}
@@ -0,0 +1,310 @@
+//! Forward-only reading of `.obby` archives from non-seekable sources
+//!
+//! Entry data is stored in the same order as the entry table, so an archive can be
+//! processed in a single pass: parse the header and table, then read each entry's
+//! body as it comes. This makes it possible to pull `plugin.json` out of an HTTP
+//! response body without buffering the whole download.
+
+use std::io::{self, Read};
+
+use crate::{decoding_reader, find_manifest_in, read_header, EntryInfo, ObbyError, ObbyMetadata, ObbyReadOptions};
+
+/// Reader that processes an archive from a plain `Read` in one forward pass
+///
+/// Entries must be visited in archive order with [`ObbyStreamReader::next_entry`];
+/// whatever part of an entry isn't read is skipped when moving to the next one.
+/// Extra fields, which follow the last entry, aren't read; see [`crate::EntryExtra`].
+///
+/// # Type Parameters
+///
+/// * `R`: Any type that implements `Read`, such as an HTTP response body or `std::io::Stdin`.
+///
+/// # Example
+///
+/// ```no_run
+/// use obsidian_lib::ObbyStreamReader;
+/// use std::io::{self, Read};
+///
+/// # fn main() -> io::Result<()> {
+/// let mut stream = ObbyStreamReader::new(io::stdin().lock())?;
+/// while let Some(mut entry) = stream.next_entry()? {
+///     if entry.name() == "plugin.json" {
+///         let mut json = String::new();
+///         entry.read_to_string(&mut json)?;
+///         println!("{}", json);
+///         break;
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct ObbyStreamReader<R: Read> {
+    reader: R,
+    metadata: ObbyMetadata,
+    table: Vec<(String, EntryInfo)>,
+    next: usize,
+    /// Raw bytes of the previous entry's body that haven't been consumed yet
+    remaining: u64,
+    options: ObbyReadOptions,
+}
+
+impl<R: Read> ObbyStreamReader<R> {
+    /// Parses the header and entry table from `reader`
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The source, positioned at the start of the archive. Wrapping unbuffered
+    ///   sources in a `BufReader` is recommended, as the table is read field by field.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the stream reader, positioned before the first entry's data.
+    pub fn new(reader: R) -> io::Result<Self> {
+        Self::with_options(reader, ObbyReadOptions::default())
+    }
+
+    /// Parses the header and entry table from `reader` using custom read options
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The source, positioned at the start of the archive.
+    /// * `options` - The `ObbyReadOptions` to use, e.g. for additional codecs.
+    pub fn with_options(mut reader: R, options: ObbyReadOptions) -> io::Result<Self> {
+        let header = read_header(&mut reader)?;
+
+        Ok(ObbyStreamReader {
+            reader,
+            metadata: header.metadata,
+            table: header.table,
+            next: 0,
+            remaining: 0,
+            options,
+        })
+    }
+
+    /// Returns the archive's header metadata
+    pub fn metadata(&self) -> &ObbyMetadata {
+        &self.metadata
+    }
+
+    /// Returns the names of all entries, in the order they will be yielded
+    pub fn entry_names(&self) -> Vec<String> {
+        self.table.iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    /// Finds the entry holding the plugin manifest
+    ///
+    /// Works like [`crate::ObbyArchive::find_manifest`]; the name can then be passed to
+    /// [`ObbyStreamReader::extract_entry`].
+    pub fn find_manifest(&self) -> io::Result<String> {
+        let names = self.entry_names();
+        find_manifest_in(&names).map(str::to_string)
+    }
+
+    /// Advances to the next entry
+    ///
+    /// Any unread data of the previous entry is skipped first.
+    ///
+    /// # Returns
+    ///
+    /// The next entry, or `None` once every entry has been visited.
+    pub fn next_entry(&mut self) -> io::Result<Option<ObbyStreamEntry<'_>>> {
+        self.skip_remaining()?;
+
+        let Some((name, info)) = self.table.get(self.next) else {
+            return Ok(None);
+        };
+        self.next += 1;
+        self.remaining = info.compressed_length as u64;
+
+        let body = EntryBody {
+            reader: &mut self.reader,
+            remaining: &mut self.remaining,
+        };
+        let reader: Box<dyn Read + '_> = if info.is_compressed() {
+            decoding_reader(&self.options, body, info.length)?
+        } else {
+            Box::new(body)
+        };
+
+        Ok(Some(ObbyStreamEntry {
+            name: name.clone(),
+            length: info.length,
+            compressed_length: info.compressed_length,
+            reader,
+        }))
+    }
+
+    /// Skips forward to the named entry and returns its decompressed contents
+    ///
+    /// Entries before it are skipped without being decompressed. Because the stream
+    /// only moves forward, entries that were already passed can't be extracted.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry_name` - The name of the entry to extract.
+    ///
+    /// # Returns
+    ///
+    /// The entry's data, or an `io::Error` of kind `NotFound` if no such entry is ahead.
+    pub fn extract_entry(&mut self, entry_name: &str) -> io::Result<Vec<u8>> {
+        while let Some(mut entry) = self.next_entry()? {
+            if entry.name() == entry_name {
+                let is_compressed = entry.is_compressed();
+                let mut data = Vec::new();
+                return match entry.read_to_end(&mut data) {
+                    Ok(_) => Ok(data),
+                    Err(source) if is_compressed => Err(ObbyError::decompression(entry_name, source)),
+                    Err(e) => Err(e),
+                };
+            }
+        }
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Entry '{}' not found in the rest of the stream", entry_name),
+        ))
+    }
+
+    /// Consumes the stream reader, returning the underlying reader
+    ///
+    /// The reader is positioned wherever the last entry's reading stopped.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn skip_remaining(&mut self) -> io::Result<()> {
+        if self.remaining > 0 {
+            let skipped = io::copy(&mut (&mut self.reader).take(self.remaining), &mut io::sink())?;
+            if skipped < self.remaining {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Archive data ends inside an entry"));
+            }
+            self.remaining = 0;
+        }
+        Ok(())
+    }
+}
+
+/// An entry yielded by [`ObbyStreamReader::next_entry`]
+///
+/// Reading from it yields the entry's decompressed contents.
+pub struct ObbyStreamEntry<'a> {
+    name: String,
+    length: i32,
+    compressed_length: i32,
+    reader: Box<dyn Read + 'a>,
+}
+
+impl ObbyStreamEntry<'_> {
+    /// The entry's name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The entry's decompressed size in bytes
+    pub fn length(&self) -> u64 {
+        self.length as u64
+    }
+
+    /// The size of the entry's data as stored in the archive
+    pub fn compressed_length(&self) -> u64 {
+        self.compressed_length as u64
+    }
+
+    /// Whether the entry's data is compressed
+    pub fn is_compressed(&self) -> bool {
+        self.compressed_length != self.length
+    }
+}
+
+impl Read for ObbyStreamEntry<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+/// The raw bytes of one entry, tracking how much of it is left in the parent reader
+struct EntryBody<'a, R: Read> {
+    reader: &'a mut R,
+    remaining: &'a mut u64,
+}
+
+impl<R: Read> Read for EntryBody<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if *self.remaining == 0 {
+            return Ok(0);
+        }
+        let max = buf.len().min(usize::try_from(*self.remaining).unwrap_or(usize::MAX));
+        let read = self.reader.read(&mut buf[..max])?;
+        if read == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Archive data ends inside an entry"));
+        }
+        *self.remaining -= read as u64;
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+
+    fn sample_archive() -> Vec<u8> {
+        ObbyTestBuilder::new()
+            .entry("ObsidianPlugin.dll", &vec![0x4D; 4096])
+            .stored_entry("icon.png", b"\x89PNG")
+            .entry("plugin.json", br#"{"id": "test-plugin", "version": "1.0.0"}"#)
+            .build()
+    }
+
+    #[test]
+    fn test_entries_in_order_with_partial_reads() {
+        let bytes = sample_archive();
+        let mut stream = ObbyStreamReader::new(&bytes[..]).unwrap();
+        assert_eq!(stream.metadata().plugin_assembly, "TestPlugin");
+        assert_eq!(stream.entry_names(), vec!["ObsidianPlugin.dll", "icon.png", "plugin.json"]);
+
+        // Read only part of the first entry; the rest must be skipped
+        {
+            let mut first = stream.next_entry().unwrap().unwrap();
+            assert!(first.is_compressed());
+            let mut head = [0u8; 10];
+            first.read_exact(&mut head).unwrap();
+            assert_eq!(head, [0x4D; 10]);
+        }
+
+        // Don't read the second entry at all
+        {
+            let second = stream.next_entry().unwrap().unwrap();
+            assert_eq!(second.name(), "icon.png");
+            assert!(!second.is_compressed());
+        }
+
+        {
+            let mut third = stream.next_entry().unwrap().unwrap();
+            let mut json = String::new();
+            third.read_to_string(&mut json).unwrap();
+            assert!(json.contains("test-plugin"));
+        }
+
+        assert!(stream.next_entry().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_extract_entry_skips_forward_only() {
+        let bytes = sample_archive();
+        let mut stream = ObbyStreamReader::new(&bytes[..]).unwrap();
+        assert_eq!(stream.extract_entry("icon.png").unwrap(), b"\x89PNG");
+
+        let err = stream.extract_entry("ObsidianPlugin.dll").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_truncated_stream() {
+        let mut bytes = sample_archive();
+        bytes.truncate(bytes.len() - 5);
+        let mut stream = ObbyStreamReader::new(&bytes[..]).unwrap();
+        let err = stream.extract_entry("plugin.json").unwrap_err();
+        assert!(matches!(err.kind(), io::ErrorKind::UnexpectedEof | io::ErrorKind::InvalidData));
+    }
+}
@@ -0,0 +1,246 @@
+//! Helpers for building `.obby` archives in tests
+//!
+//! [`ObbyTestBuilder`] is a small reference encoder for the format. It is deliberately
+//! independent of [`crate::ObbyWriter`], so tests can use it to check the reader and the
+//! writer against each other rather than against a checked-in binary fixture.
+//!
+//! This module is available with the `testing` feature.
+
+use std::io::Write;
+
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use sha2::{Digest, Sha384};
+
+use crate::{HASH_LEN, MAGIC, SIGNATURE_LEN};
+
+/// Builds valid `.obby` archives in memory
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "testing")] {
+/// use obsidian_lib::testing::ObbyTestBuilder;
+/// use obsidian_lib::ObbyArchive;
+/// use std::io::Cursor;
+///
+/// let bytes = ObbyTestBuilder::new()
+///     .entry("plugin.json", br#"{"id": "test"}"#)
+///     .stored_entry("icon.png", b"\x89PNG")
+///     .build();
+///
+/// let mut archive = ObbyArchive::new(Cursor::new(bytes)).unwrap();
+/// assert_eq!(archive.extract_entry("icon.png").unwrap(), b"\x89PNG");
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ObbyTestBuilder {
+    api_version: String,
+    plugin_assembly: String,
+    plugin_version: String,
+    signature: Option<Vec<u8>>,
+    entries: Vec<TestEntry>,
+}
+
+#[derive(Debug, Clone)]
+struct TestEntry {
+    name: String,
+    data: Vec<u8>,
+    compress: bool,
+}
+
+impl ObbyTestBuilder {
+    /// Creates a builder for an unsigned archive of `TestPlugin` version `1.0.0.0` with no entries
+    pub fn new() -> Self {
+        ObbyTestBuilder {
+            api_version: "1.0.0".to_string(),
+            plugin_assembly: "TestPlugin".to_string(),
+            plugin_version: "1.0.0.0".to_string(),
+            signature: None,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Sets the API version recorded in the header
+    pub fn api_version(mut self, api_version: &str) -> Self {
+        self.api_version = api_version.to_string();
+        self
+    }
+
+    /// Sets the plugin assembly name and version
+    pub fn plugin(mut self, assembly: &str, version: &str) -> Self {
+        self.plugin_assembly = assembly.to_string();
+        self.plugin_version = version.to_string();
+        self
+    }
+
+    /// Marks the archive as signed, storing `signature` verbatim
+    ///
+    /// The signature is padded or truncated to the format's 384 bytes. It is not
+    /// a valid signature of the archive unless the caller computed one.
+    pub fn signature(mut self, signature: &[u8]) -> Self {
+        let mut signature = signature.to_vec();
+        signature.resize(SIGNATURE_LEN, 0);
+        self.signature = Some(signature);
+        self
+    }
+
+    /// Adds a deflate-compressed entry
+    ///
+    /// Unlike [`crate::ObbyWriter`], the compressed payload is kept even when it is larger
+    /// than the data. Only if both happen to be the same length is the entry stored instead,
+    /// because the format tells compressed and stored entries apart by their lengths.
+    pub fn entry(mut self, name: &str, data: &[u8]) -> Self {
+        self.entries.push(TestEntry {
+            name: name.to_string(),
+            data: data.to_vec(),
+            compress: true,
+        });
+        self
+    }
+
+    /// Adds an uncompressed entry
+    pub fn stored_entry(mut self, name: &str, data: &[u8]) -> Self {
+        self.entries.push(TestEntry {
+            name: name.to_string(),
+            data: data.to_vec(),
+            compress: false,
+        });
+        self
+    }
+
+    /// Encodes the archive
+    pub fn build(&self) -> Vec<u8> {
+        let payloads: Vec<Vec<u8>> = self.entries.iter().map(TestEntry::payload).collect();
+
+        let mut data = Vec::new();
+        push_string(&mut data, &self.plugin_assembly);
+        push_string(&mut data, &self.plugin_version);
+        data.extend_from_slice(&(self.entries.len() as i32).to_le_bytes());
+        for (entry, payload) in self.entries.iter().zip(&payloads) {
+            push_string(&mut data, &entry.name);
+            data.extend_from_slice(&(entry.data.len() as i32).to_le_bytes());
+            data.extend_from_slice(&(payload.len() as i32).to_le_bytes());
+        }
+        for payload in &payloads {
+            data.extend_from_slice(payload);
+        }
+
+        let hash = Sha384::digest(&data);
+        debug_assert_eq!(hash.len(), HASH_LEN);
+
+        let mut out = MAGIC.to_vec();
+        push_string(&mut out, &self.api_version);
+        out.extend_from_slice(&hash);
+        match &self.signature {
+            Some(signature) => {
+                out.push(1);
+                out.extend_from_slice(signature);
+            }
+            None => out.push(0),
+        }
+        out.extend_from_slice(&(data.len() as i32).to_le_bytes());
+        out.extend_from_slice(&data);
+        out
+    }
+}
+
+impl Default for ObbyTestBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TestEntry {
+    fn payload(&self) -> Vec<u8> {
+        if !self.compress {
+            return self.data.clone();
+        }
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&self.data).unwrap();
+        let compressed = encoder.finish().unwrap();
+        if compressed.len() != self.data.len() {
+            compressed
+        } else {
+            self.data.clone()
+        }
+    }
+}
+
+/// Writes a length-prefixed string the way .NET's `BinaryWriter.Write(string)` does
+fn push_string(out: &mut Vec<u8>, value: &str) {
+    let mut len = value.len();
+    while len >= 0x80 {
+        out.push((len as u8) | 0x80);
+        len >>= 7;
+    }
+    out.push(len as u8);
+    out.extend_from_slice(value.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ObbyArchive, ObbyWriter};
+    use proptest::collection::{btree_map, vec};
+    use proptest::prelude::*;
+    use std::io::Cursor;
+
+    fn entry_name() -> impl Strategy<Value = String> {
+        "[a-zA-Z0-9_.-]{1,24}(/[a-zA-Z0-9_.-]{1,24}){0,3}"
+    }
+
+    fn entry_data() -> impl Strategy<Value = Vec<u8>> {
+        prop_oneof![
+            vec(any::<u8>(), 0..2048),
+            // Highly compressible data, so both stored and deflated entries show up
+            (any::<u8>(), 0usize..4096).prop_map(|(byte, len)| vec![byte; len]),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn builder_round_trip(
+            entries in btree_map(entry_name(), (entry_data(), any::<bool>()), 0..12),
+            assembly in "[A-Za-z.]{1,40}",
+            version in "[0-9]{1,3}(\\.[0-9]{1,3}){0,3}",
+            signed in any::<bool>(),
+        ) {
+            let mut builder = ObbyTestBuilder::new().plugin(&assembly, &version);
+            if signed {
+                builder = builder.signature(&[0xAB; 16]);
+            }
+            for (name, (data, compress)) in &entries {
+                builder = if *compress { builder.entry(name, data) } else { builder.stored_entry(name, data) };
+            }
+
+            let mut archive = ObbyArchive::new(Cursor::new(builder.build())).unwrap();
+            prop_assert_eq!(&archive.metadata().plugin_assembly, &assembly);
+            prop_assert_eq!(&archive.metadata().plugin_version, &version);
+            prop_assert_eq!(archive.metadata().signature.is_some(), signed);
+            prop_assert_eq!(archive.list_entries(), entries.keys().cloned().collect::<Vec<_>>());
+            for (name, (data, _)) in &entries {
+                prop_assert_eq!(&archive.extract_entry(name).unwrap(), data);
+            }
+        }
+
+        #[test]
+        fn writer_matches_builder(entries in btree_map(entry_name(), entry_data(), 0..12)) {
+            let mut writer = ObbyWriter::new(Vec::new(), "TestPlugin", "1.0.0.0");
+            let mut builder = ObbyTestBuilder::new();
+            for (name, data) in &entries {
+                writer.add_entry(name, data).unwrap();
+                builder = builder.entry(name, data);
+            }
+            let written = writer.finish().unwrap();
+            let built = builder.build();
+
+            let mut from_writer = ObbyArchive::new(Cursor::new(written)).unwrap();
+            let mut from_builder = ObbyArchive::new(Cursor::new(built)).unwrap();
+            prop_assert_eq!(from_writer.list_entries(), from_builder.list_entries());
+            for name in entries.keys() {
+                prop_assert_eq!(from_writer.extract_entry(name).unwrap(), from_builder.extract_entry(name).unwrap());
+            }
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! Previewing entries as text
+
+use std::io::{self, Read, Seek};
+
+use crate::{mime, ObbyArchive};
+
+/// The encoding a [`TextPreview`] was decoded from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum TextEncoding {
+    /// UTF-8, with or without a byte order mark
+    Utf8,
+    /// UTF-16, little-endian, detected by its byte order mark
+    Utf16Le,
+    /// UTF-16, big-endian, detected by its byte order mark
+    Utf16Be,
+}
+
+impl TextEncoding {
+    /// Returns the encoding's name as used in HTTP and HTML, such as `"utf-16le"`
+    pub fn name(&self) -> &'static str {
+        match self {
+            TextEncoding::Utf8 => "utf-8",
+            TextEncoding::Utf16Le => "utf-16le",
+            TextEncoding::Utf16Be => "utf-16be",
+        }
+    }
+}
+
+/// The start of an entry decoded as text; returned by [`ObbyArchive::read_text_entry`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum TextPreview {
+    /// The entry is text
+    Text {
+        /// The decoded text, without a byte order mark
+        text: String,
+        /// The encoding it was decoded from
+        encoding: TextEncoding,
+        /// Whether the entry continues past `text`
+        truncated: bool,
+    },
+    /// The entry isn't text in any supported encoding
+    Binary,
+}
+
+impl<R: Read + Seek> ObbyArchive<R> {
+    /// Decodes the start of an entry as text, for previewing configs and readmes
+    ///
+    /// UTF-16 is recognised by its byte order mark; everything else must be UTF-8. Text
+    /// is cut at a character boundary, so a preview never ends in a broken character.
+    /// Content that contains NUL or other control bytes, or isn't valid in its encoding,
+    /// is reported as [`TextPreview::Binary`].
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The entry to preview.
+    /// * `max_bytes` - The most bytes of the decompressed entry to decode, including
+    ///   any byte order mark.
+    ///
+    /// # Returns
+    ///
+    /// The [`TextPreview`], or an `io::Error` of kind `NotFound` if the entry doesn't exist.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use obsidian_lib::TextPreview;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut archive = obsidian_lib::open("plugin.obby")?;
+    /// match archive.read_text_entry("README.md", 4096)? {
+    ///     TextPreview::Text { text, truncated, .. } => {
+    ///         println!("{}{}", text, if truncated { "…" } else { "" })
+    ///     }
+    ///     TextPreview::Binary => println!("(binary)"),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_text_entry(&mut self, name: &str, max_bytes: usize) -> io::Result<TextPreview> {
+        // One byte more tells whether the entry was truncated
+        let limit = (max_bytes as u64).saturating_add(1);
+        let data = self.entry_reader(name).and_then(|reader| {
+            let mut data = Vec::new();
+            reader.take(limit).read_to_end(&mut data)?;
+            Ok(data)
+        });
+        let mut data = self.decompression_error(name, data)?;
+        let truncated = data.len() > max_bytes;
+        data.truncate(max_bytes);
+        Ok(decode_preview(&data, truncated))
+    }
+}
+
+/// Decodes a prefix of an entry, `truncated` saying whether more of it follows
+fn decode_preview(data: &[u8], truncated: bool) -> TextPreview {
+    let (encoding, body) = match data {
+        [0xEF, 0xBB, 0xBF, rest @ ..] => (TextEncoding::Utf8, rest),
+        [0xFF, 0xFE, rest @ ..] => (TextEncoding::Utf16Le, rest),
+        [0xFE, 0xFF, rest @ ..] => (TextEncoding::Utf16Be, rest),
+        _ => (TextEncoding::Utf8, data),
+    };
+    let text = match encoding {
+        TextEncoding::Utf8 => decode_utf8(body, truncated),
+        TextEncoding::Utf16Le => decode_utf16(body, truncated, u16::from_le_bytes),
+        TextEncoding::Utf16Be => decode_utf16(body, truncated, u16::from_be_bytes),
+    };
+    match text {
+        Some(text) if text.is_empty() || mime::is_text(text.as_bytes()) => TextPreview::Text { text, encoding, truncated },
+        _ => TextPreview::Binary,
+    }
+}
+
+/// Decodes UTF-8, dropping a character cut off by truncation
+fn decode_utf8(data: &[u8], truncated: bool) -> Option<String> {
+    match std::str::from_utf8(data) {
+        Ok(text) => Some(text.to_string()),
+        Err(e) if truncated && e.error_len().is_none() => {
+            Some(std::str::from_utf8(&data[..e.valid_up_to()]).ok()?.to_string())
+        }
+        Err(_) => None,
+    }
+}
+
+/// Decodes UTF-16, dropping a code unit or surrogate pair cut off by truncation
+fn decode_utf16(data: &[u8], truncated: bool, unit: fn([u8; 2]) -> u16) -> Option<String> {
+    if !data.len().is_multiple_of(2) && !truncated {
+        return None;
+    }
+    let mut units: Vec<u16> = data.chunks_exact(2).map(|pair| unit([pair[0], pair[1]])).collect();
+    if truncated && units.last().is_some_and(|&last| (0xD800..0xDC00).contains(&last)) {
+        units.pop();
+    }
+    char::decode_utf16(units).collect::<Result<String, _>>().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+
+    fn text(preview: TextPreview) -> (String, TextEncoding, bool) {
+        match preview {
+            TextPreview::Text { text, encoding, truncated } => (text, encoding, truncated),
+            TextPreview::Binary => panic!("unexpected binary"),
+        }
+    }
+
+    #[test]
+    fn test_read_text_entry() {
+        let utf16: Vec<u8> = [0xFF, 0xFE].into_iter().chain("h\u{e9}\u{1F600}!".encode_utf16().flat_map(u16::to_le_bytes)).collect();
+        let buffer = ObbyTestBuilder::new()
+            .entry("README.md", "caf\u{e9} au lait".as_bytes())
+            .entry("config.txt", &utf16)
+            .entry("bom.txt", b"\xEF\xBB\xBFhi")
+            .entry("empty.txt", b"")
+            .entry("icon.png", b"\x89PNG\r\n\x1a\n\0\0")
+            .stored_entry("latin1.txt", b"caf\xE9")
+            .build();
+        let mut archive = ObbyArchive::from_bytes(buffer).unwrap();
+
+        assert_eq!(text(archive.read_text_entry("README.md", 100).unwrap()), ("caf\u{e9} au lait".to_string(), TextEncoding::Utf8, false));
+        // "é" is two bytes; cutting after its first byte drops it
+        assert_eq!(text(archive.read_text_entry("README.md", 4).unwrap()), ("caf".to_string(), TextEncoding::Utf8, true));
+        assert_eq!(text(archive.read_text_entry("config.txt", 100).unwrap()), ("h\u{e9}\u{1F600}!".to_string(), TextEncoding::Utf16Le, false));
+        // BOM, two units, then half of the surrogate pair
+        assert_eq!(text(archive.read_text_entry("config.txt", 8).unwrap()), ("h\u{e9}".to_string(), TextEncoding::Utf16Le, true));
+        assert_eq!(text(archive.read_text_entry("bom.txt", 100).unwrap()).0, "hi");
+        assert_eq!(text(archive.read_text_entry("empty.txt", 100).unwrap()), (String::new(), TextEncoding::Utf8, false));
+        assert!(!text(archive.read_text_entry("README.md", usize::MAX).unwrap()).2);
+        assert_eq!(archive.read_text_entry("icon.png", 100).unwrap(), TextPreview::Binary);
+        assert_eq!(archive.read_text_entry("latin1.txt", 100).unwrap(), TextPreview::Binary);
+        assert_eq!(archive.read_text_entry("missing", 100).unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+}
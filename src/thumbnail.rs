@@ -0,0 +1,93 @@
+//! Generating thumbnails of image entries
+
+use std::io::{self, Cursor, Read, Seek};
+
+use image::{ImageFormat, ImageReader};
+
+use crate::ObbyArchive;
+
+impl<R: Read + Seek> ObbyArchive<R> {
+    /// Decodes a PNG or JPEG entry and returns it scaled down to fit in a square, as PNG
+    ///
+    /// The aspect ratio is kept, and images that already fit are re-encoded at their
+    /// original size rather than enlarged. The format is detected from the data, not the
+    /// entry name. Decoding is subject to the `image` crate's default memory limits, so a
+    /// crafted image can't exhaust memory.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry` - The image entry.
+    /// * `max_dim` - The largest width or height of the thumbnail, in pixels.
+    ///
+    /// # Returns
+    ///
+    /// The thumbnail as PNG, or an `io::Error` of kind `NotFound` if the entry doesn't
+    /// exist, of kind `InvalidInput` if `max_dim` is zero, or of kind `InvalidData` if
+    /// the entry isn't a PNG or JPEG image that can be decoded.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut archive = obsidian_lib::open("plugin.obby")?;
+    /// let icon = archive.extract_icon()?;
+    /// std::fs::write("gallery/icon.png", archive.thumbnail(&icon.entry, 128)?)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn thumbnail(&mut self, entry: &str, max_dim: u32) -> io::Result<Vec<u8>> {
+        if max_dim == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Thumbnail size must be at least 1 pixel"));
+        }
+        let data = self.extract_entry(entry)?;
+        let invalid = |e: image::ImageError| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("Entry '{}' isn't a supported image: {}", entry, e))
+        };
+        let image = ImageReader::new(Cursor::new(data))
+            .with_guessed_format()?
+            .decode()
+            .map_err(invalid)?;
+        let image = if image.width() > max_dim || image.height() > max_dim {
+            image.thumbnail(max_dim, max_dim)
+        } else {
+            image
+        };
+
+        let mut png = Vec::new();
+        image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png).map_err(invalid)?;
+        Ok(png)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+    use image::{DynamicImage, GenericImageView, RgbImage};
+
+    fn encode(width: u32, height: u32, format: ImageFormat) -> Vec<u8> {
+        let image = DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| image::Rgb([x as u8, y as u8, 128])));
+        let mut data = Vec::new();
+        image.write_to(&mut Cursor::new(&mut data), format).unwrap();
+        data
+    }
+
+    #[test]
+    fn test_thumbnail() {
+        let buffer = ObbyTestBuilder::new()
+            .entry("gallery/wide.png", &encode(200, 100, ImageFormat::Png))
+            .entry("gallery/photo.dat", &encode(60, 120, ImageFormat::Jpeg))
+            .entry("icon.png", &encode(16, 16, ImageFormat::Png))
+            .entry("README.md", b"# Plugin")
+            .build();
+        let mut archive = ObbyArchive::from_bytes(buffer).unwrap();
+        let dimensions = |png: Vec<u8>| image::load_from_memory_with_format(&png, ImageFormat::Png).unwrap().dimensions();
+
+        assert_eq!(dimensions(archive.thumbnail("gallery/wide.png", 50).unwrap()), (50, 25));
+        assert_eq!(dimensions(archive.thumbnail("gallery/photo.dat", 40).unwrap()), (20, 40));
+        assert_eq!(dimensions(archive.thumbnail("icon.png", 64).unwrap()), (16, 16));
+        assert_eq!(archive.thumbnail("README.md", 64).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(archive.thumbnail("icon.png", 0).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(archive.thumbnail("missing.png", 64).unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,172 @@
+//! Hierarchical views of entry names
+//!
+//! Entry names are flat strings, but archives usually contain nested paths such as
+//! `assets/icons/logo.png`. Both `/` and `\` are treated as separators, since archives
+//! built on Windows may use either.
+
+/// Splits an entry name into its non-empty path components
+pub(crate) fn components(name: &str) -> impl Iterator<Item = &str> {
+    name.split(['/', '\\']).filter(|part| !part.is_empty())
+}
+
+/// Whether `name` lies inside the directory `prefix`
+///
+/// Matching is done on whole components, so `assets` contains `assets/a.png` but not
+/// `assets2/a.png`. An empty prefix contains every entry.
+pub(crate) fn is_under(name: &str, prefix: &str) -> bool {
+    let mut name_parts = components(name);
+    for part in components(prefix) {
+        if name_parts.next() != Some(part) {
+            return false;
+        }
+    }
+    name_parts.next().is_some()
+}
+
+/// Returns every directory implied by `names`, sorted, as `/`-joined paths
+pub(crate) fn directories<'a>(names: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut dirs = std::collections::BTreeSet::new();
+    for name in names {
+        let parts: Vec<&str> = components(name).collect();
+        for depth in 1..parts.len() {
+            dirs.insert(parts[..depth].join("/"));
+        }
+    }
+    dirs.into_iter().collect()
+}
+
+/// A node in the file tree of an archive
+///
+/// Built with [`crate::ObbyArchive::tree`]. The root is an unnamed directory; children
+/// keep the order in which they first appear in the entry table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryTree {
+    /// A directory implied by the entries below it
+    Dir {
+        /// The last path component, or an empty string for the root
+        name: String,
+        /// Files and subdirectories
+        children: Vec<EntryTree>,
+    },
+    /// An entry in the archive
+    File {
+        /// The last path component
+        name: String,
+        /// The full entry name, as accepted by `extract_entry`
+        path: String,
+    },
+}
+
+impl EntryTree {
+    /// Builds a tree from entry names
+    ///
+    /// # Arguments
+    ///
+    /// * `names` - Entry names in archive order.
+    pub fn from_names<'a>(names: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut root = EntryTree::Dir {
+            name: String::new(),
+            children: Vec::new(),
+        };
+        for name in names {
+            let parts: Vec<&str> = components(name).collect();
+            if let Some((file, dirs)) = parts.split_last() {
+                let mut node = &mut root;
+                for dir in dirs {
+                    node = node.child_dir(dir);
+                }
+                if let EntryTree::Dir { children, .. } = node {
+                    children.push(EntryTree::File {
+                        name: file.to_string(),
+                        path: name.to_string(),
+                    });
+                }
+            }
+        }
+        root
+    }
+
+    /// The node's name: the last component of its path
+    pub fn name(&self) -> &str {
+        match self {
+            EntryTree::Dir { name, .. } | EntryTree::File { name, .. } => name,
+        }
+    }
+
+    /// Whether this node is a directory
+    pub fn is_dir(&self) -> bool {
+        matches!(self, EntryTree::Dir { .. })
+    }
+
+    /// The node's children; empty for files
+    pub fn children(&self) -> &[EntryTree] {
+        match self {
+            EntryTree::Dir { children, .. } => children,
+            EntryTree::File { .. } => &[],
+        }
+    }
+
+    /// Returns the subdirectory `name` of this directory node, creating it if needed
+    fn child_dir(&mut self, dir: &str) -> &mut EntryTree {
+        let EntryTree::Dir { children, .. } = self else {
+            unreachable!("files have no children");
+        };
+        let index = match children.iter().position(|c| c.is_dir() && c.name() == dir) {
+            Some(index) => index,
+            None => {
+                children.push(EntryTree::Dir {
+                    name: dir.to_string(),
+                    children: Vec::new(),
+                });
+                children.len() - 1
+            }
+        };
+        &mut children[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAMES: [&str; 5] = [
+        "plugin.json",
+        "assets/icons/logo.png",
+        "assets/style.css",
+        "assets2/readme.md",
+        "lib\\native.dll",
+    ];
+
+    #[test]
+    fn test_is_under() {
+        assert!(is_under("assets/style.css", "assets/"));
+        assert!(is_under("assets/icons/logo.png", "assets"));
+        assert!(!is_under("assets2/readme.md", "assets"));
+        assert!(!is_under("assets", "assets"));
+        assert!(is_under("lib\\native.dll", "lib/"));
+        assert!(is_under("plugin.json", ""));
+    }
+
+    #[test]
+    fn test_directories() {
+        assert_eq!(directories(NAMES), vec!["assets", "assets/icons", "assets2", "lib"]);
+    }
+
+    #[test]
+    fn test_tree() {
+        let tree = EntryTree::from_names(NAMES);
+        let top: Vec<&str> = tree.children().iter().map(EntryTree::name).collect();
+        assert_eq!(top, vec!["plugin.json", "assets", "assets2", "lib"]);
+
+        let assets = &tree.children()[1];
+        assert!(assets.is_dir());
+        assert_eq!(assets.children()[0].name(), "icons");
+        assert_eq!(
+            assets.children()[1],
+            EntryTree::File {
+                name: "style.css".to_string(),
+                path: "assets/style.css".to_string()
+            }
+        );
+    }
+}
@@ -0,0 +1,65 @@
+//! Structural oddities found while parsing an archive
+
+use std::fmt;
+
+/// Something unusual about an archive's structure, returned by [`crate::ObbyArchive::warnings`]
+///
+/// By default the archive opens anyway and the oddities are collected, so archives from
+/// buggy packers can still be read. With [`crate::ObbyReadOptions::set_strict`] each of
+/// these makes opening fail with an `io::Error` of kind `InvalidData`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub enum ParseWarning {
+    /// A string in the header isn't valid UTF-8; invalid sequences were replaced with U+FFFD
+    InvalidUtf8 {
+        /// Which field holds the string, such as `"entry name"`
+        field: &'static str,
+        /// The string as decoded
+        value: String,
+    },
+    /// The data length in the header doesn't match the size of the data section
+    DataLengthMismatch {
+        /// The length stored in the header
+        declared: i32,
+        /// The number of bytes from the start of the data section to the end of the source
+        actual: u64,
+    },
+    /// The source continues past the data of the last entry
+    TrailingData {
+        /// Number of extra bytes
+        length: u64,
+    },
+    /// An entry carries an extra field this version doesn't know; it is kept in [`crate::EntryExtra`]
+    UnknownExtraField {
+        /// The entry's name
+        entry: String,
+        /// The field's key
+        key: String,
+    },
+    /// The extras block after the last entry is malformed, so it was left as trailing data
+    InvalidExtraFields {
+        /// What is wrong with it
+        message: String,
+    },
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseWarning::InvalidUtf8 { field, value } => write!(f, "Invalid UTF-8 in {} {:?}", field, value),
+            ParseWarning::DataLengthMismatch { declared, actual } => write!(
+                f,
+                "Header declares {} bytes of data but the archive holds {}",
+                declared, actual
+            ),
+            ParseWarning::TrailingData { length } => {
+                write!(f, "{} unexpected bytes after the last entry", length)
+            }
+            ParseWarning::UnknownExtraField { entry, key } => {
+                write!(f, "Unknown extra field '{}' on entry '{}'", key, entry)
+            }
+            ParseWarning::InvalidExtraFields { message } => write!(f, "Invalid extra fields: {}", message),
+        }
+    }
+}
@@ -0,0 +1,662 @@
+//! WebAssembly bindings for working with `.obby` archives from JavaScript
+//!
+//! With the `nodejs` feature (built with `wasm-pack build --target nodejs --features nodejs`),
+//! entry data is returned as Node.js `Buffer`s instead of plain `Uint8Array`s. Buffers are
+//! `Uint8Array`s, so code written for either build works with both.
+
+use std::io::{self, Cursor, Read};
+
+use js_sys::{Function, Map, Object, Promise, Reflect, Uint8Array};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::{future_to_promise, JsFuture};
+
+use crate::manifest::PluginManifest;
+use crate::{decoding_reader, find_manifest_in, read_header, read_raw, EntryInfo, ObbyArchive, ObbyError, ObbyMetadata, ObbyReadOptions, TextPreview};
+
+/// Bytes decompressed by `extract_entry_async` between yields to the event loop
+const ASYNC_CHUNK_LEN: usize = 1024 * 1024;
+
+/// Size of the chunks passed to the callback of `extract_to_stream`
+const STREAM_CHUNK_LEN: usize = 64 * 1024;
+
+#[cfg(feature = "nodejs")]
+#[wasm_bindgen]
+extern "C" {
+    /// Node.js `Buffer`
+    #[wasm_bindgen(extends = Uint8Array)]
+    type Buffer;
+
+    /// `Buffer.from(arrayBuffer, byteOffset, length)`, which shares the memory instead of copying it
+    #[wasm_bindgen(static_method_of = Buffer, js_name = from)]
+    fn from_array_buffer(buffer: &JsValue, byte_offset: u32, length: u32) -> Buffer;
+}
+
+/// A wrapper struct for the WebAssembly environment to interact with `.obby` files
+///
+/// This struct provides a WASM-compatible interface for working with `.obby` archives.
+#[wasm_bindgen]
+pub struct WasmObbyArchive {
+    inner: ObbyArchive<Cursor<Vec<u8>>>
+}
+
+/// The class of failure behind a `WasmObbyError`
+///
+/// Exported to JavaScript as an enum object, so callers can branch with
+/// `error.kind === WasmObbyErrorKind.NotFound` instead of matching on messages.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WasmObbyErrorKind {
+    /// The requested entry doesn't exist in the archive
+    NotFound,
+    /// The buffer is not a valid `.obby` archive, or an entry's contents are malformed
+    InvalidFormat,
+    /// A compressed entry could not be decoded
+    Decompression,
+    /// The archive's hash doesn't match its contents, or its signature is missing or invalid
+    Verification,
+    /// Any other failure
+    Io,
+}
+
+/// Error thrown by `WasmObbyArchive` methods
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct WasmObbyError {
+    kind: WasmObbyErrorKind,
+    message: String,
+}
+
+#[wasm_bindgen]
+impl WasmObbyError {
+    #[wasm_bindgen(getter)]
+    /// The class of failure
+    pub fn kind(&self) -> WasmObbyErrorKind {
+        self.kind
+    }
+
+    #[wasm_bindgen(getter)]
+    /// A human-readable description of the failure
+    pub fn message(&self) -> String {
+        self.message.clone()
+    }
+
+    #[wasm_bindgen(js_name = toString)]
+    /// Formats the error like a JavaScript `Error`
+    pub fn to_js_string(&self) -> String {
+        format!("WasmObbyError ({:?}): {}", self.kind, self.message)
+    }
+}
+
+impl WasmObbyError {
+    fn new(kind: WasmObbyErrorKind, message: String) -> Self {
+        WasmObbyError { kind, message }
+    }
+}
+
+impl From<io::Error> for WasmObbyError {
+    fn from(error: io::Error) -> Self {
+        let kind = match ObbyError::from_io(&error) {
+            Some(ObbyError::Decompression { .. }) => WasmObbyErrorKind::Decompression,
+            Some(
+                ObbyError::HashMismatch { .. }
+                | ObbyError::ChecksumMismatch { .. }
+                | ObbyError::MissingSignature
+                | ObbyError::InvalidSignature,
+            ) => WasmObbyErrorKind::Verification,
+            _ => match error.kind() {
+                io::ErrorKind::NotFound => WasmObbyErrorKind::NotFound,
+                io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof => WasmObbyErrorKind::InvalidFormat,
+                _ => WasmObbyErrorKind::Io,
+            },
+        };
+        WasmObbyError::new(kind, error.to_string())
+    }
+}
+
+#[wasm_bindgen]
+impl WasmObbyArchive {
+    #[wasm_bindgen(constructor)]
+    /// Creates a new `WasmObbyArchive` instance from a byte buffer
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer` - A byte slice representing the `.obby` file contents.
+    ///
+    /// # Returns
+    ///
+    /// A `WasmObbyArchive` instance.
+    pub fn new(buffer: &[u8]) -> Result<WasmObbyArchive, WasmObbyError> {
+        let inner = ObbyArchive::from_bytes(buffer.to_vec())?;
+
+        Ok(WasmObbyArchive { inner })
+    }
+
+    #[wasm_bindgen]
+    /// Lists all entries in the `.obby` archive
+    ///
+    /// # Returns
+    ///
+    /// A JavaScript array of strings representing the names of all entries.
+    pub fn list_entries(&self) -> Box<[JsValue]> {
+        self.inner
+            .entry_names()
+            .map(JsValue::from)
+            .collect::<Vec<_>>()
+            .into_boxed_slice()
+    }
+
+    #[wasm_bindgen(js_name = entriesWithInfo)]
+    /// Lists all entries with their sizes, in archive order
+    ///
+    /// Saves file-tree UIs a call per entry to show sizes.
+    ///
+    /// # Returns
+    ///
+    /// A JavaScript array of `{ name, length, compressedLength, isCompressed }` objects,
+    /// with sizes in bytes.
+    pub fn entries_with_info(&self) -> Box<[JsValue]> {
+        self.inner
+            .entry_names()
+            .map(|name| {
+                let info = &self.inner.entries[name];
+                js_object([
+                    ("name", JsValue::from(name)),
+                    ("length", JsValue::from(info.length)),
+                    ("compressedLength", JsValue::from(info.compressed_length)),
+                    ("isCompressed", JsValue::from(info.is_compressed())),
+                ])
+                .into()
+            })
+            .collect::<Vec<_>>()
+            .into_boxed_slice()
+    }
+
+    #[wasm_bindgen]
+    /// Extracts a specific entry by name
+    ///
+    /// # Arguments
+    ///
+    /// * `entry_name` - The name of the entry to extract.
+    ///
+    /// # Returns
+    ///
+    /// A `Uint8Array` (a `Buffer` with the `nodejs` feature) containing the entry's data.
+    pub fn extract_entry(&mut self, entry_name: &str) -> Result<Uint8Array, WasmObbyError> {
+        let data = self.inner.extract_entry(entry_name)?;
+
+        Ok(js_bytes(&data))
+    }
+
+    #[wasm_bindgen(js_name = extractEntryAsync)]
+    /// Extracts a specific entry without blocking the event loop
+    ///
+    /// The entry is decompressed in 1 MiB steps, handing control back to the event loop
+    /// (via `setTimeout`) after each one, so pages stay responsive while large entries
+    /// are unpacked. The archive can be used, or even freed, while the promise is pending.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry_name` - The name of the entry to extract.
+    ///
+    /// # Returns
+    ///
+    /// A `Promise` resolving to a `Uint8Array` of the entry's data, or rejecting with a
+    /// `WasmObbyError`.
+    pub fn extract_entry_async(&mut self, entry_name: &str) -> Promise {
+        let inner = &mut self.inner;
+        let name = entry_name.to_string();
+        let prepared = crate::lookup_entry(&inner.entries, entry_name).and_then(|entry| {
+            let raw = read_raw(&mut inner.reader, inner.data_start_pos, entry)?;
+            let length = entry.length;
+            if entry.is_compressed() {
+                decoding_reader(&inner.options, Cursor::new(raw), length)
+            } else {
+                Ok(Box::new(Cursor::new(raw)) as Box<dyn Read>)
+            }
+        });
+
+        future_to_promise(async move {
+            let mut reader = prepared.map_err(WasmObbyError::from)?;
+            let mut data = Vec::new();
+            loop {
+                let read = (&mut reader)
+                    .take(ASYNC_CHUNK_LEN as u64)
+                    .read_to_end(&mut data)
+                    .map_err(|source| WasmObbyError::from(ObbyError::decompression(&name, source)))?;
+                if read == 0 {
+                    break;
+                }
+                yield_to_event_loop().await?;
+            }
+            Ok(js_bytes(&data).into())
+        })
+    }
+
+    #[wasm_bindgen(js_name = extractToStream)]
+    /// Extracts an entry in chunks, passing each one to `callback` as it is decompressed
+    ///
+    /// Only one chunk of up to 64 KiB is held in memory at a time, so this suits piping
+    /// large entries into a Node.js stream: `archive.extractToStream(name, chunk => out.write(chunk))`.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry_name` - The name of the entry to extract.
+    /// * `callback` - A function called as `callback(chunk)` with consecutive chunks of the
+    ///   entry's data. If it throws, extraction stops and the exception is rethrown unchanged.
+    ///
+    /// # Returns
+    ///
+    /// The total number of bytes passed to `callback`.
+    pub fn extract_to_stream(&mut self, entry_name: &str, callback: &Function) -> Result<f64, JsValue> {
+        let mut reader = self.inner.entry_reader(entry_name).map_err(WasmObbyError::from)?;
+        let mut chunk = vec![0u8; STREAM_CHUNK_LEN];
+        let mut total = 0u64;
+        loop {
+            let read = match reader.read(&mut chunk) {
+                Ok(0) => return Ok(total as f64),
+                Ok(read) => read,
+                Err(e) => {
+                    drop(reader);
+                    let error = self.inner.decompression_error(entry_name, Err::<(), _>(e)).unwrap_err();
+                    return Err(WasmObbyError::from(error).into());
+                }
+            };
+            callback.call1(&JsValue::NULL, &js_bytes(&chunk[..read]))?;
+            total += read as u64;
+        }
+    }
+
+    #[wasm_bindgen]
+    /// Extracts every entry in one call
+    ///
+    /// # Returns
+    ///
+    /// A JavaScript `Map` from entry name to a `Uint8Array` of the entry's data,
+    /// in the order the entries appear in the archive.
+    pub fn extract_all(&mut self) -> Result<Map, WasmObbyError> {
+        let map = Map::new();
+        for name in self.inner.list_entries() {
+            let data = self.extract_entry(&name)?;
+            map.set(&JsValue::from_str(&name), &data);
+        }
+        Ok(map)
+    }
+
+    #[wasm_bindgen]
+    /// Extracts every entry, passing each one to `callback` as soon as it is decompressed
+    ///
+    /// Unlike `extract_all`, only one entry is held in memory at a time.
+    ///
+    /// # Arguments
+    ///
+    /// * `callback` - A function called as `callback(name, data)` with the entry name and a `Uint8Array`.
+    ///   If it throws, extraction stops and the exception is rethrown unchanged.
+    pub fn extract_each(&mut self, callback: &Function) -> Result<(), JsValue> {
+        for name in self.inner.list_entries() {
+            let data = self.extract_entry(&name)?;
+            callback.call2(&JsValue::NULL, &JsValue::from_str(&name), &data)?;
+        }
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = readTextEntry)]
+    /// Decodes the start of an entry as text, for previewing configs and readmes
+    ///
+    /// # Arguments
+    ///
+    /// * `entry_name` - The entry to preview.
+    /// * `max_bytes` - The most bytes of the entry to decode.
+    ///
+    /// # Returns
+    ///
+    /// An object `{ binary, text, encoding, truncated }`. For binary entries `binary` is
+    /// `true` and `text` and `encoding` are `null`; otherwise `encoding` is `"utf-8"`,
+    /// `"utf-16le"` or `"utf-16be"`, and `truncated` says whether the entry continues.
+    pub fn read_text_entry(&mut self, entry_name: &str, max_bytes: usize) -> Result<Object, WasmObbyError> {
+        let (text, encoding, truncated) = match self.inner.read_text_entry(entry_name, max_bytes)? {
+            TextPreview::Text { text, encoding, truncated } => (JsValue::from(text), JsValue::from(encoding.name()), truncated),
+            TextPreview::Binary => (JsValue::NULL, JsValue::NULL, false),
+        };
+        Ok(js_object([
+            ("binary", JsValue::from(text.is_null())),
+            ("text", text),
+            ("encoding", encoding),
+            ("truncated", JsValue::from(truncated)),
+        ]))
+    }
+
+    #[wasm_bindgen(js_name = verifyHash)]
+    /// Checks the archive's contents against the SHA-384 hash stored in its header
+    ///
+    /// # Returns
+    ///
+    /// Nothing if the hash matches; throws a `WasmObbyError` of kind `Verification` if it doesn't.
+    pub fn verify_hash(&mut self) -> Result<(), WasmObbyError> {
+        Ok(self.inner.verify_hash()?)
+    }
+
+    #[cfg(feature = "signing")]
+    #[wasm_bindgen(js_name = verifySignature)]
+    /// Checks the archive's hash and its signature against a trusted public key
+    ///
+    /// Lets upload forms reject tampered or unsigned plugins before sending them anywhere.
+    ///
+    /// # Arguments
+    ///
+    /// * `public_key_der` - The trusted RSA public key, DER-encoded as SubjectPublicKeyInfo or PKCS#1.
+    ///
+    /// # Returns
+    ///
+    /// Nothing if the archive was signed with the key; throws a `WasmObbyError` of kind
+    /// `Verification` if the hash doesn't match or the signature is missing or was made by
+    /// another key, and of kind `InvalidFormat` if the key can't be parsed.
+    pub fn verify_signature(&mut self, public_key_der: &[u8]) -> Result<(), WasmObbyError> {
+        let key = crate::signing::load_public_key_der(public_key_der)?;
+        crate::signing::verify_signature(&mut self.inner, &[key])?;
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    /// Extracts and returns the contents of the `plugin.json` file from the `.obby` archive
+    ///
+    /// # Returns
+    ///
+    /// A `Result<String, WasmObbyError>` containing the parsed JSON string if successful.
+    pub fn extract_plugin_json(&mut self) -> Result<String, WasmObbyError> {
+        let manifest = self.inner.find_manifest()?.to_string();
+        let data = self.inner.extract_entry(&manifest)?;
+        let text = String::from_utf8(data)
+            .map_err(|e| WasmObbyError::new(WasmObbyErrorKind::InvalidFormat, e.to_string()))?;
+        Ok(text)
+    }
+
+    #[wasm_bindgen(js_name = pluginManifest)]
+    /// Parses the plugin manifest into a plain JavaScript object
+    ///
+    /// Unlike `extract_plugin_json`, no `JSON.parse` is needed, and the shape is consistent:
+    /// well-known fields use camelCase (`projectUrl`), `authors` is always an array, and
+    /// missing fields are left out. Other fields are passed through as they were written.
+    ///
+    /// # Returns
+    ///
+    /// The manifest object; throws a `WasmObbyError` of kind `NotFound` if the archive has
+    /// no manifest, and of kind `InvalidFormat` if it isn't a valid JSON object.
+    pub fn plugin_manifest(&mut self) -> Result<JsValue, WasmObbyError> {
+        manifest_to_js(&self.inner.plugin_manifest()?)
+    }
+}
+
+/// Parses an archive from chunks as they arrive, such as the body of a `fetch`
+///
+/// Feed each chunk to `push`. As soon as the header and entry table are in, the entry
+/// names are known; each entry becomes available once all of its bytes have arrived, so
+/// the manifest can be shown long before a large plugin finishes downloading. Call
+/// `finish` at the end to get a `WasmObbyArchive` for the whole archive.
+///
+/// ```js
+/// const parser = new WasmObbyParser();
+/// for await (const chunk of response.body) {
+///     parser.push(chunk);
+///     const manifest = parser.pluginManifest();
+///     if (manifest) showManifest(manifest);
+/// }
+/// const archive = parser.finish();
+/// ```
+#[wasm_bindgen]
+pub struct WasmObbyParser {
+    buffer: Vec<u8>,
+    header: Option<StreamedHeader>,
+    /// Index in the table of the next entry `nextEntry` returns
+    next: usize,
+    options: ObbyReadOptions,
+}
+
+/// The parts of the header a `WasmObbyParser` needs once it has been parsed
+struct StreamedHeader {
+    metadata: ObbyMetadata,
+    table: Vec<(String, EntryInfo)>,
+    /// Position of the first entry's data in the buffer
+    data_start_pos: u64,
+}
+
+impl Default for WasmObbyParser {
+    fn default() -> Self {
+        WasmObbyParser::new()
+    }
+}
+
+#[wasm_bindgen]
+impl WasmObbyParser {
+    #[wasm_bindgen(constructor)]
+    /// Creates a parser that hasn't received any data yet
+    pub fn new() -> WasmObbyParser {
+        WasmObbyParser { buffer: Vec::new(), header: None, next: 0, options: ObbyReadOptions::default() }
+    }
+
+    #[wasm_bindgen]
+    /// Appends the next chunk of the archive
+    ///
+    /// # Returns
+    ///
+    /// Nothing; throws a `WasmObbyError` of kind `InvalidFormat` as soon as the received
+    /// data can't be the start of a valid archive.
+    pub fn push(&mut self, chunk: &[u8]) -> Result<(), WasmObbyError> {
+        self.buffer.extend_from_slice(chunk);
+        if self.header.is_some() {
+            return Ok(());
+        }
+        let mut cursor = Cursor::new(&self.buffer[..]);
+        match read_header(&mut cursor) {
+            Ok(header) => {
+                self.header = Some(StreamedHeader {
+                    metadata: header.metadata,
+                    table: header.table,
+                    data_start_pos: cursor.position(),
+                });
+                Ok(())
+            }
+            // The header isn't complete yet
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    #[wasm_bindgen(getter, js_name = bytesReceived)]
+    /// Returns how many bytes have been pushed so far
+    pub fn bytes_received(&self) -> f64 {
+        self.buffer.len() as f64
+    }
+
+    #[wasm_bindgen(getter, js_name = headerReady)]
+    /// Returns whether the header and entry table have been received
+    pub fn header_ready(&self) -> bool {
+        self.header.is_some()
+    }
+
+    #[wasm_bindgen]
+    /// Returns the header metadata, once it has been received
+    ///
+    /// # Returns
+    ///
+    /// An object `{ apiVersion, pluginAssembly, pluginVersion, signed, dataLength }`, or
+    /// `null` until the header has been received.
+    pub fn metadata(&self) -> JsValue {
+        let Some(header) = &self.header else {
+            return JsValue::NULL;
+        };
+        let metadata = &header.metadata;
+        js_object([
+            ("apiVersion", JsValue::from(&metadata.api_version)),
+            ("pluginAssembly", JsValue::from(&metadata.plugin_assembly)),
+            ("pluginVersion", JsValue::from(&metadata.plugin_version)),
+            ("signed", JsValue::from(metadata.signature.is_some())),
+            ("dataLength", JsValue::from(metadata.data_length)),
+        ])
+        .into()
+    }
+
+    #[wasm_bindgen(js_name = entryNames)]
+    /// Lists the entry names, in archive order
+    ///
+    /// # Returns
+    ///
+    /// A JavaScript array of strings, or `undefined` until the header has been received.
+    pub fn entry_names(&self) -> Option<Box<[JsValue]>> {
+        let header = self.header.as_ref()?;
+        Some(header.table.iter().map(|(name, _)| JsValue::from(name)).collect())
+    }
+
+    #[wasm_bindgen(js_name = pluginManifest)]
+    /// Parses the plugin manifest, once it has been received
+    ///
+    /// The result has the same shape as `WasmObbyArchive.pluginManifest`.
+    ///
+    /// # Returns
+    ///
+    /// The manifest object, or `null` while the header or the manifest entry is still
+    /// incomplete; throws a `WasmObbyError` of kind `NotFound` if the archive has no
+    /// manifest, and of kind `InvalidFormat` if it isn't a valid JSON object.
+    pub fn plugin_manifest(&self) -> Result<JsValue, WasmObbyError> {
+        let Some(header) = &self.header else {
+            return Ok(JsValue::NULL);
+        };
+        let names: Vec<String> = header.table.iter().map(|(name, _)| name.clone()).collect();
+        let name = find_manifest_in(&names)?;
+        // The last entry with a name wins, as in `WasmObbyArchive`
+        let index = names.iter().rposition(|other| other == name).expect("found above");
+        match self.received_entry(index)? {
+            Some(json) => manifest_to_js(&PluginManifest::from_json(&json)?),
+            None => Ok(JsValue::NULL),
+        }
+    }
+
+    #[wasm_bindgen(js_name = nextEntry)]
+    /// Returns the next entry, in archive order, once all of its bytes have been received
+    ///
+    /// Call it in a loop after each `push` to process entries while the download continues.
+    ///
+    /// # Returns
+    ///
+    /// An object `{ name, data }` with the decompressed data as a `Uint8Array`, or `null`
+    /// if the next entry is incomplete or every entry has been returned.
+    pub fn next_entry(&mut self) -> Result<JsValue, WasmObbyError> {
+        let Some(header) = &self.header else {
+            return Ok(JsValue::NULL);
+        };
+        let Some(data) = self.received_entry(self.next)? else {
+            return Ok(JsValue::NULL);
+        };
+        let name = header.table[self.next].0.clone();
+        self.next += 1;
+        Ok(js_object([("name", JsValue::from(name)), ("data", js_bytes(&data).into())]).into())
+    }
+
+    #[wasm_bindgen]
+    /// Checks that the whole archive has been received and opens it
+    ///
+    /// # Returns
+    ///
+    /// A `WasmObbyArchive` over everything pushed; throws a `WasmObbyError` of kind
+    /// `InvalidFormat` if the archive is incomplete or malformed.
+    pub fn finish(self) -> Result<WasmObbyArchive, WasmObbyError> {
+        let inner = ObbyArchive::from_bytes(self.buffer)?;
+        Ok(WasmObbyArchive { inner })
+    }
+}
+
+impl WasmObbyParser {
+    /// Returns the decompressed data of the entry at `index` in the table, if all of it has arrived
+    fn received_entry(&self, index: usize) -> io::Result<Option<Vec<u8>>> {
+        let Some(header) = &self.header else {
+            return Ok(None);
+        };
+        let Some((name, info)) = header.table.get(index) else {
+            return Ok(None);
+        };
+        let start = header.data_start_pos + info.offset;
+        let end = start + info.compressed_length as u64;
+        if end > self.buffer.len() as u64 {
+            return Ok(None);
+        }
+        let raw = &self.buffer[start as usize..end as usize];
+        if !info.is_compressed() {
+            return Ok(Some(raw.to_vec()));
+        }
+        let mut data = Vec::new();
+        decoding_reader(&self.options, raw, info.length)
+            .and_then(|mut reader| reader.read_to_end(&mut data))
+            .map_err(|source| ObbyError::decompression(name, source))?;
+        Ok(Some(data))
+    }
+}
+
+/// Converts a manifest into a plain JavaScript object
+fn manifest_to_js(manifest: &PluginManifest) -> Result<JsValue, WasmObbyError> {
+    serde::Serialize::serialize(manifest, &serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| WasmObbyError::new(WasmObbyErrorKind::InvalidFormat, e.to_string()))
+}
+
+/// Builds a plain JavaScript object from its properties
+fn js_object<const N: usize>(properties: [(&str, JsValue); N]) -> Object {
+    let object = Object::new();
+    for (key, value) in properties {
+        Reflect::set(&object, &JsValue::from_str(key), &value).expect("setting a property on a plain object");
+    }
+    object
+}
+
+/// Copies bytes out of wasm memory into a `Uint8Array`, or a `Buffer` with the `nodejs` feature
+fn js_bytes(data: &[u8]) -> Uint8Array {
+    let array = Uint8Array::from(data);
+    #[cfg(feature = "nodejs")]
+    let array = Buffer::from_array_buffer(&array.buffer(), 0, array.length()).unchecked_into();
+    array
+}
+
+/// Resolves on the next macrotask, giving the browser a chance to render and handle input
+fn yield_to_event_loop() -> JsFuture {
+    let promise = Promise::new(&mut |resolve, _reject| {
+        // Looked up on the global object so this works in windows, workers and Node.js alike
+        let set_timeout = Reflect::get(&js_sys::global(), &JsValue::from_str("setTimeout"))
+            .ok()
+            .and_then(|f| f.dyn_into::<Function>().ok());
+        let _ = match set_timeout {
+            Some(set_timeout) => set_timeout.call2(&JsValue::NULL, &resolve, &JsValue::from(0)),
+            None => resolve.call0(&JsValue::NULL),
+        };
+    });
+    JsFuture::from(promise)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+
+    #[test]
+    fn test_parser_yields_entries_as_they_arrive() {
+        let buffer = ObbyTestBuilder::new()
+            .entry("plugin.json", br#"{"id": "plugin"}"#)
+            .stored_entry("assets/big.bin", &[7; 1000])
+            .build();
+        let mut parser = WasmObbyParser::new();
+        let mut received = 0;
+        for chunk in buffer.chunks(97) {
+            parser.push(chunk).unwrap();
+            if parser.header_ready() && received == 0 {
+                // Entries only become available once complete
+                if let Some(data) = parser.received_entry(0).unwrap() {
+                    assert_eq!(data, br#"{"id": "plugin"}"#);
+                    assert!(parser.received_entry(1).unwrap().is_none());
+                    received += 1;
+                }
+            }
+        }
+        assert_eq!(received, 1);
+        assert_eq!(parser.received_entry(1).unwrap().unwrap(), vec![7; 1000]);
+        assert!(parser.finish().is_ok());
+
+        let mut parser = WasmObbyParser::new();
+        assert_eq!(parser.push(b"NOPE").unwrap_err().kind(), WasmObbyErrorKind::InvalidFormat);
+    }
+}
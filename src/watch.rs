@@ -0,0 +1,175 @@
+//! Keeping a [`Catalog`] up to date as its directory changes
+//!
+//! Enabled with the `notify` feature.
+
+use std::collections::BTreeSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::catalog::{Catalog, CatalogChange};
+
+/// How long to wait for more file system events before applying a batch
+///
+/// Copying an archive into place usually produces several events; batching them
+/// avoids reading the archive while it is half written.
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// A running watch started by [`Catalog::watch`]
+///
+/// Watching stops when this is dropped.
+#[derive(Debug)]
+pub struct CatalogWatcher {
+    catalog: Arc<Mutex<Catalog>>,
+    _watcher: RecommendedWatcher,
+}
+
+impl CatalogWatcher {
+    /// Returns a copy of the catalog as of the last applied change
+    pub fn catalog(&self) -> Catalog {
+        self.catalog.lock().unwrap().clone()
+    }
+}
+
+impl Catalog {
+    /// Builds a catalog of `root` and keeps it up to date as archives are added, removed or changed
+    ///
+    /// File system events are batched for a short while, then applied with
+    /// [`Catalog::refresh`] on a background thread, which calls `callback` with the
+    /// updated catalog and the list of changes. The callback is not called for
+    /// events that don't touch any `.obby` file.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The plugins directory.
+    /// * `callback` - Called after each batch of changes.
+    ///
+    /// # Returns
+    ///
+    /// A [`CatalogWatcher`] that keeps watching until dropped, or an `io::Error` if
+    /// `root` can't be read or watched.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> std::io::Result<()> {
+    /// let watcher = obsidian_lib::catalog::Catalog::watch("plugins", |catalog, changes| {
+    ///     println!("{} plugins installed, {} changed", catalog.plugins.len(), changes.len());
+    /// })?;
+    /// println!("{} plugins installed", watcher.catalog().plugins.len());
+    /// # std::thread::park();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn watch<P, F>(root: P, mut callback: F) -> io::Result<CatalogWatcher>
+    where
+        P: AsRef<Path>,
+        F: FnMut(&Catalog, &[CatalogChange]) + Send + 'static,
+    {
+        let root = root.as_ref().to_path_buf();
+        let catalog = Arc::new(Mutex::new(Catalog::build(&root)?));
+
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender).map_err(into_io)?;
+        watcher.watch(&root, RecursiveMode::Recursive).map_err(into_io)?;
+
+        let shared = Arc::clone(&catalog);
+        thread::spawn(move || {
+            // Ends once the watcher, and with it the sender, is dropped
+            while let Ok(first) = receiver.recv() {
+                let mut paths = BTreeSet::new();
+                let mut event = first;
+                loop {
+                    match event {
+                        // Reads, including our own, don't change the catalog
+                        Ok(event) if matches!(event.kind, EventKind::Access(_)) => {}
+                        Ok(event) if event.need_rescan() => {
+                            paths.insert(root.clone());
+                        }
+                        Ok(event) => paths.extend(event.paths),
+                        Err(_) => {
+                            paths.insert(root.clone());
+                        }
+                    }
+                    event = match receiver.recv_timeout(DEBOUNCE) {
+                        Ok(next) => next,
+                        Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => break,
+                    };
+                }
+
+                let mut catalog = shared.lock().unwrap();
+                let mut changes = Vec::new();
+                for path in outermost(paths) {
+                    changes.extend(catalog.refresh(&path));
+                }
+                if !changes.is_empty() {
+                    callback(&catalog, &changes);
+                }
+            }
+        });
+
+        Ok(CatalogWatcher { catalog, _watcher: watcher })
+    }
+}
+
+/// Drops paths that lie under another path in the set, since refreshing a directory covers them
+fn outermost(paths: BTreeSet<PathBuf>) -> Vec<PathBuf> {
+    let mut result: Vec<PathBuf> = Vec::new();
+    // Sorted order puts each directory before the paths under it
+    for path in paths {
+        if !result.iter().any(|kept| path.starts_with(kept)) {
+            result.push(path);
+        }
+    }
+    result
+}
+
+fn into_io(e: notify::Error) -> io::Error {
+    match e.kind {
+        notify::ErrorKind::Io(e) => e,
+        notify::ErrorKind::PathNotFound => io::Error::new(io::ErrorKind::NotFound, "Watched path not found"),
+        _ => io::Error::other(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ObbyTestBuilder;
+    use std::fs;
+
+    #[test]
+    fn test_watch() {
+        let dir = tempfile::tempdir().unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let watcher = Catalog::watch(dir.path(), move |catalog, changes| {
+            sender.send((catalog.plugins.len(), changes.to_vec())).unwrap();
+        })
+        .unwrap();
+        assert!(watcher.catalog().plugins.is_empty());
+
+        let path = dir.path().join("a.obby");
+        let archive = ObbyTestBuilder::new().entry("plugin.json", br#"{"id": "alpha"}"#).build();
+        fs::write(&path, archive).unwrap();
+        let (count, changes) = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(changes, vec![CatalogChange::Added(path.clone())]);
+        assert_eq!(watcher.catalog().plugins[0].id, "alpha");
+
+        fs::remove_file(&path).unwrap();
+        let (count, changes) = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(count, 0);
+        assert_eq!(changes, vec![CatalogChange::Removed(path)]);
+    }
+
+    #[test]
+    fn test_outermost() {
+        let paths = ["a/b.obby", "a", "c/d.obby", "ab.obby"].map(PathBuf::from).into_iter().collect();
+        assert_eq!(outermost(paths), ["a", "ab.obby", "c/d.obby"].map(PathBuf::from));
+    }
+}
@@ -0,0 +1,1010 @@
+//! Creation of `.obby` archives
+//!
+//! The [`ObbyWriter`] collects entries in memory, compresses them with deflate and
+//! writes a complete archive (header, hash, optional signature, entry table and data)
+//! to any `Write` sink when finished.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Read, Seek, Write};
+use std::ops::Range;
+use std::path::Path;
+
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use sha2::{Digest, Sha256, Sha384};
+
+#[cfg(feature = "signing")]
+use rsa::RsaPrivateKey;
+
+use crate::extra::write_extras;
+use crate::format::wire::BinaryWriter;
+use crate::spool::Spool;
+#[cfg(feature = "json")]
+use crate::checksums::{checksums_json, CHECKSUMS_NAME};
+#[cfg(feature = "encryption")]
+use crate::encryption::{EncryptionSecret, Encryptor, ENCRYPTION_NAME};
+use crate::{EntryExtra, HashAlgo, ObbyArchive, OutputFile, DEFAULT_API_VERSION, MAGIC};
+
+/// Writer for building `.obby` archives
+///
+/// Entries are compressed as they are added, using the level set with
+/// [`ObbyWriter::set_compression`]. Entries that don't shrink under deflate are stored
+/// as-is, which the format signals with `compressed_length == length`.
+///
+/// # Type Parameters
+///
+/// * `W`: The sink the finished archive is written to, such as `std::fs::File` or `Vec<u8>`.
+///
+/// # Example
+///
+/// ```no_run
+/// use obsidian_lib::ObbyWriter;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut writer = ObbyWriter::create("plugin.obby", "MyPlugin", "1.2.3");
+/// writer.add_entry("plugin.json", br#"{"id": "my-plugin"}"#)?;
+/// writer.add_dir("./bin/Release")?;
+/// writer.finish()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ObbyWriter<W: Write> {
+    sink: W,
+    api_version: String,
+    plugin_assembly: String,
+    plugin_version: String,
+    compression: Compression,
+    entries: Vec<PendingEntry>,
+    names: HashSet<String>,
+    /// Extra fields by entry name
+    extras: HashMap<String, EntryExtra>,
+    write_extras: bool,
+    name_mapper: Option<NameMapper>,
+    reproducible: bool,
+    embed_checksums: bool,
+    /// Payloads of entries added from readers, created on first use
+    spool: Option<Spool>,
+    #[cfg(feature = "signing")]
+    signing_key: Option<RsaPrivateKey>,
+    #[cfg(feature = "encryption")]
+    encryptor: Option<Encryptor>,
+}
+
+/// Rewrites entry names as they are added; see [`ObbyWriter::set_name_mapper`]
+pub(crate) type NameMapper = Box<dyn Fn(&str) -> String + Send + Sync>;
+
+struct PendingEntry {
+    name: String,
+    length: i32,
+    payload: Payload,
+    /// SHA-256 of the contents, computed while embedding checksums
+    #[cfg_attr(not(feature = "json"), allow(dead_code))]
+    sha256: Option<Vec<u8>>,
+}
+
+/// Where an entry's stored bytes are kept until the archive is written
+enum Payload {
+    Memory(Vec<u8>),
+    /// A range of the writer's [`Spool`]
+    Spooled(Range<u64>),
+}
+
+impl Payload {
+    fn len(&self) -> u64 {
+        match self {
+            Payload::Memory(data) => data.len() as u64,
+            Payload::Spooled(range) => range.end - range.start,
+        }
+    }
+}
+
+impl<W: Write> ObbyWriter<W> {
+    /// Creates a new writer that will write the archive to `sink`
+    ///
+    /// # Arguments
+    ///
+    /// * `sink` - Where the finished archive is written.
+    /// * `plugin_assembly` - The plugin's assembly name (e.g. `MyPlugin`).
+    /// * `plugin_version` - The plugin's version (e.g. `1.2.3`).
+    pub fn new(sink: W, plugin_assembly: &str, plugin_version: &str) -> Self {
+        ObbyWriter {
+            sink,
+            api_version: DEFAULT_API_VERSION.to_string(),
+            plugin_assembly: plugin_assembly.to_string(),
+            plugin_version: plugin_version.to_string(),
+            compression: Compression::default(),
+            entries: Vec::new(),
+            names: HashSet::new(),
+            extras: HashMap::new(),
+            write_extras: false,
+            name_mapper: None,
+            reproducible: false,
+            embed_checksums: false,
+            spool: None,
+            #[cfg(feature = "signing")]
+            signing_key: None,
+            #[cfg(feature = "encryption")]
+            encryptor: None,
+        }
+    }
+
+    /// Sets the Obsidian API version recorded in the header
+    ///
+    /// Defaults to `1.0.0`.
+    pub fn set_api_version(&mut self, api_version: &str) {
+        self.api_version = api_version.to_string();
+    }
+
+    /// Sets the deflate level used for entries added after this call
+    ///
+    /// Defaults to `Compression::default()`. `Compression::none()` stores entries uncompressed.
+    /// Individual entries can override this with [`ObbyWriter::add_entry_with_compression`].
+    pub fn set_compression(&mut self, compression: Compression) {
+        self.compression = compression;
+    }
+
+    /// Sets a function that rewrites entry names as they are added
+    ///
+    /// The mapped name is what gets validated, checked for duplicates and written.
+    /// [`normalize_entry_name`] is a ready-made mapper.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use obsidian_lib::{normalize_entry_name, ObbyWriter};
+    ///
+    /// let mut writer = ObbyWriter::new(Vec::new(), "MyPlugin", "1.2.3");
+    /// writer.set_name_mapper(normalize_entry_name);
+    /// writer.add_entry(".\\assets\\icon.png", b"png").unwrap();
+    /// ```
+    pub fn set_name_mapper<F: Fn(&str) -> String + Send + Sync + 'static>(&mut self, mapper: F) {
+        self.name_mapper = Some(Box::new(mapper));
+    }
+
+    /// Makes the output depend only on the entries' names and contents, not the order they were added in
+    ///
+    /// The writer never records timestamps, and deflate and PKCS#1 v1.5 signatures are
+    /// deterministic, so the remaining source of variation is entry order. With this
+    /// enabled, entries are written sorted by name, and the same set of entries with the
+    /// same compression levels always produces byte-identical archives. Defaults to `false`,
+    /// which keeps insertion order.
+    pub fn set_reproducible(&mut self, reproducible: bool) {
+        self.reproducible = reproducible;
+    }
+
+    /// Writes the entries' extra fields in a block after the entry data
+    ///
+    /// The block is an extension of this crate rather than part of the `.obby` format,
+    /// and is counted in the archive's hash; see [`EntryExtra`] for its layout. Extras
+    /// set with [`ObbyWriter::set_entry_extra`] or copied by
+    /// [`ObbyWriter::copy_entry_from`] are only written with this enabled. Defaults to
+    /// `false`.
+    pub fn set_write_extras(&mut self, write_extras: bool) {
+        self.write_extras = write_extras;
+    }
+
+    /// Adds a [`CHECKSUMS_NAME`] entry listing the SHA-256 of every other entry
+    ///
+    /// The entry is added by [`ObbyWriter::finish`] and can be checked with
+    /// [`ObbyArchive::verify_embedded_checksums`], which gives integrity at the entry
+    /// level even for unsigned archives. Checksums are computed as entries are added, so
+    /// enable this before adding any. Defaults to `false`.
+    #[cfg(feature = "json")]
+    pub fn set_embed_checksums(&mut self, embed_checksums: bool) {
+        self.embed_checksums = embed_checksums;
+    }
+
+    /// Signs the archive with the given RSA-3072 key when it is finished
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The private key; see [`crate::signing::load_private_key`].
+    #[cfg(feature = "signing")]
+    pub fn set_signing_key(&mut self, key: RsaPrivateKey) -> io::Result<()> {
+        crate::signing::check_key_size(&key)?;
+        self.signing_key = Some(key);
+        Ok(())
+    }
+
+    /// Sets the passphrase or key that [`ObbyWriter::add_encrypted_entry`] encrypts with
+    ///
+    /// For a passphrase, this derives the key with a fresh salt, which is deliberately
+    /// slow. An archive has a single secret, so it can only be replaced until the first
+    /// encrypted entry has been added.
+    ///
+    /// # Returns
+    ///
+    /// An `io::Error` of kind `InvalidInput` if entries were already encrypted with
+    /// another secret.
+    #[cfg(feature = "encryption")]
+    pub fn set_encryption(&mut self, secret: &EncryptionSecret) -> io::Result<()> {
+        if !self.encryptor.as_ref().is_none_or(Encryptor::is_empty) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The encryption secret can't change once entries have been encrypted",
+            ));
+        }
+        self.encryptor = Some(Encryptor::new(secret));
+        Ok(())
+    }
+
+    /// Adds an entry that is encrypted with the secret from [`ObbyWriter::set_encryption`]
+    ///
+    /// Encrypted entries are stored uncompressed, and the archive gets a
+    /// [`crate::ENCRYPTION_NAME`] entry flagging the extension. See
+    /// [`ObbyArchive::decryptor`] for reading them back.
+    ///
+    /// # Returns
+    ///
+    /// The same errors as [`ObbyWriter::add_entry`], or an `io::Error` of kind
+    /// `InvalidInput` if no secret was set.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use obsidian_lib::{EncryptionSecret, ObbyWriter};
+    ///
+    /// let mut writer = ObbyWriter::new(Vec::new(), "MyPlugin", "1.2.3");
+    /// writer.set_encryption(&EncryptionSecret::Key([0x42; 32])).unwrap();
+    /// writer.add_entry("plugin.json", br#"{"id": "my-plugin"}"#).unwrap();
+    /// writer.add_encrypted_entry("assets/licensed.bin", b"...").unwrap();
+    /// writer.finish().unwrap();
+    /// ```
+    #[cfg(feature = "encryption")]
+    pub fn add_encrypted_entry(&mut self, name: &str, data: &[u8]) -> io::Result<()> {
+        let name = self.entry_name(name)?;
+        let Some(encryptor) = &mut self.encryptor else {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "No encryption secret set"));
+        };
+        let data = encryptor.encrypt(&name, data)?;
+        let length = i32::try_from(data.len()).map_err(|_| too_large(&name))?;
+        // Checksums cover what other readers see, the ciphertext
+        let sha256 = self.embed_checksums.then(|| Sha256::digest(&data).to_vec());
+        self.names.insert(name.clone());
+        self.entries.push(PendingEntry {
+            name,
+            length,
+            payload: Payload::Memory(data),
+            sha256,
+        });
+        Ok(())
+    }
+
+    /// Adds an entry to the archive
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The entry name. Must be unique within the archive.
+    /// * `data` - The uncompressed entry contents.
+    ///
+    /// # Returns
+    ///
+    /// An `io::Error` of kind `AlreadyExists` if an entry with the same name was already added,
+    /// or of kind `InvalidInput` if the name can't be stored (see [`validate_entry_name`]).
+    pub fn add_entry(&mut self, name: &str, data: &[u8]) -> io::Result<()> {
+        self.add_entry_with_compression(name, data, self.compression)
+    }
+
+    /// Adds an entry to the archive using a specific compression level
+    ///
+    /// This is useful for content that is already compressed (PNG, JPEG, nested archives),
+    /// where deflating again only costs time: pass `Compression::none()` to store it as-is.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The entry name. Must be unique within the archive.
+    /// * `data` - The uncompressed entry contents.
+    /// * `compression` - The deflate level for this entry only.
+    pub fn add_entry_with_compression(&mut self, name: &str, data: &[u8], compression: Compression) -> io::Result<()> {
+        let name = self.entry_name(name)?;
+        let length = i32::try_from(data.len()).map_err(|_| too_large(&name))?;
+        let sha256 = self.embed_checksums.then(|| Sha256::digest(data).to_vec());
+
+        let data = compress(data, compression)?;
+
+        self.names.insert(name.clone());
+        self.entries.push(PendingEntry {
+            name,
+            length,
+            payload: Payload::Memory(data),
+            sha256,
+        });
+        Ok(())
+    }
+
+    /// Adds an entry whose contents are read from `reader`
+    ///
+    /// The contents are compressed as they are read and the result is kept in a
+    /// temporary file until [`ObbyWriter::finish`], so large files can be packaged
+    /// without holding them in memory. As with [`ObbyWriter::add_entry`], an entry that
+    /// doesn't shrink is stored as-is; it is then written to the temporary file a second
+    /// time, uncompressed.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The entry name. Must be unique within the archive.
+    /// * `reader` - The uncompressed entry contents, read to the end.
+    ///
+    /// # Returns
+    ///
+    /// The same errors as [`ObbyWriter::add_entry`], an `io::Error` of kind
+    /// `InvalidInput` if more than 2 GiB are read, or any error from `reader` or the
+    /// temporary file. A failed entry isn't added.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use obsidian_lib::ObbyWriter;
+    /// use std::fs::File;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut writer = ObbyWriter::create("plugin.obby", "MyPlugin", "1.2.3");
+    /// writer.add_entry_from_reader("assets/world.bin", File::open("world.bin")?)?;
+    /// writer.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn add_entry_from_reader<Rd: Read>(&mut self, name: &str, reader: Rd) -> io::Result<()> {
+        let name = self.entry_name(name)?;
+        let spool = match &mut self.spool {
+            Some(spool) => spool,
+            None => self.spool.insert(Spool::new()?),
+        };
+        let start = spool.len();
+        let mut hasher = self.embed_checksums.then(Sha256::new);
+        let reader = HashingReader { inner: reader, hasher: hasher.as_mut() };
+        match spool_entry(spool, &name, reader, self.compression) {
+            Ok((length, range)) => {
+                self.names.insert(name.clone());
+                self.entries.push(PendingEntry {
+                    name,
+                    length,
+                    payload: Payload::Spooled(range),
+                    sha256: hasher.map(|hasher| hasher.finalize().to_vec()),
+                });
+                Ok(())
+            }
+            Err(e) => {
+                let _ = spool.truncate(start);
+                Err(e)
+            }
+        }
+    }
+
+    /// Copies an entry from an existing archive without decompressing it
+    ///
+    /// The entry's stored bytes and declared size are transferred as they are, so
+    /// repacking or merging archives doesn't pay for recompressing entries that didn't
+    /// change. The copy keeps whatever compression the entry had, regardless of
+    /// [`ObbyWriter::set_compression`]. The name mapper applies to the copied name.
+    /// Only when embedding checksums is the entry decompressed, to compute its checksum.
+    ///
+    /// # Arguments
+    ///
+    /// * `archive` - The archive to copy from.
+    /// * `name` - The name of the entry in `archive`, which is also its name in this archive.
+    ///
+    /// # Returns
+    ///
+    /// An `io::Error` of kind `NotFound` if `archive` has no such entry, or the same
+    /// errors as [`ObbyWriter::add_entry`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use obsidian_lib::ObbyWriter;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut old = obsidian_lib::open("old.obby")?;
+    /// let mut writer = ObbyWriter::create("new.obby", "MyPlugin", "1.2.4");
+    /// writer.add_entry("plugin.json", br#"{"id": "my-plugin", "version": "1.2.4"}"#)?;
+    /// for name in old.list_entries() {
+    ///     if name != "plugin.json" {
+    ///         writer.copy_entry_from(&mut old, &name)?;
+    ///     }
+    /// }
+    /// writer.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn copy_entry_from<R: Read + Seek>(&mut self, archive: &mut ObbyArchive<R>, name: &str) -> io::Result<()> {
+        let (length, data) = archive.raw_entry(name)?;
+        let sha256 = match self.embed_checksums {
+            true => Some(archive.extract_entry_hashed(name, HashAlgo::Sha256)?.1.bytes),
+            false => None,
+        };
+        let extra = archive.entry_extra(name).cloned();
+        let name = self.entry_name(name)?;
+        self.names.insert(name.clone());
+        if let Some(extra) = extra {
+            self.extras.insert(name.clone(), extra);
+        }
+        self.entries.push(PendingEntry {
+            name,
+            length,
+            payload: Payload::Memory(data),
+            sha256,
+        });
+        Ok(())
+    }
+
+    /// Stores extra fields, such as a modification time, for an entry already added
+    ///
+    /// The fields are written after the last entry's data if
+    /// [`ObbyWriter::set_write_extras`] is enabled; see [`EntryExtra`]. Setting them
+    /// again replaces the previous fields.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The entry's name as stored in the archive, after any name mapping.
+    /// * `extra` - The fields.
+    ///
+    /// # Returns
+    ///
+    /// An `io::Error` of kind `NotFound` if no entry named `name` was added.
+    pub fn set_entry_extra(&mut self, name: &str, extra: EntryExtra) -> io::Result<()> {
+        if !self.names.contains(name) {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Entry '{}' not found in archive", name),
+            ));
+        }
+        self.extras.insert(name.to_string(), extra);
+        Ok(())
+    }
+
+    /// Applies the name mapper and checks that the resulting name can be added
+    fn entry_name(&self, name: &str) -> io::Result<String> {
+        let name = match &self.name_mapper {
+            Some(mapper) => mapper(name),
+            None => name.to_string(),
+        };
+        validate_entry_name(&name)?;
+        if self.names.contains(&name) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("Entry '{}' already exists in archive", name),
+            ));
+        }
+        Ok(name)
+    }
+
+    /// Adds every file below `dir`, recursively
+    ///
+    /// Entry names are the paths relative to `dir`, using `/` as the separator.
+    /// Files are added in sorted order so the same directory always produces the same archive.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The directory to package.
+    pub fn add_dir<P: AsRef<Path>>(&mut self, dir: P) -> io::Result<()> {
+        let mut files = Vec::new();
+        collect_files(dir.as_ref(), "", &mut files)?;
+        files.sort();
+
+        for (name, path) in files {
+            let data = fs::read(&path)?;
+            self.add_entry(&name, &data)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the archive to the sink and returns it
+    ///
+    /// This computes the SHA-384 hash of the data section and, if a signing key was set,
+    /// its signature.
+    ///
+    /// # Returns
+    ///
+    /// The sink, or an `io::Error` of kind `AlreadyExists` if an entry was added under
+    /// the name of one the writer generates, such as [`CHECKSUMS_NAME`], or of kind
+    /// `InvalidInput` if checksums were enabled after entries were added.
+    pub fn finish(mut self) -> io::Result<W> {
+        #[cfg(feature = "encryption")]
+        if let Some(encryptor) = self.encryptor.take().filter(|encryptor| !encryptor.is_empty()) {
+            self.add_generated_entry(ENCRYPTION_NAME, &encryptor.metadata_json())?;
+        }
+        #[cfg(feature = "json")]
+        if self.embed_checksums {
+            self.add_checksums()?;
+        }
+        if self.reproducible {
+            self.entries.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+        let table = self.entry_table()?;
+        let extras = match self.write_extras {
+            true => write_extras(
+                self.entries
+                    .iter()
+                    .filter_map(|entry| Some((entry.name.as_str(), self.extras.get(&entry.name)?))),
+            )?,
+            false => Vec::new(),
+        };
+        let data_length = self.entries.iter().map(|entry| entry.payload.len()).sum::<u64>()
+            + table.len() as u64
+            + extras.len() as u64;
+        let data_length = i32::try_from(data_length).map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidInput, "Archive data exceeds the format's size limit")
+        })?;
+        let mut hasher = Sha384::new();
+        hasher.update(&table);
+        write_payloads(&self.entries, self.spool.as_mut(), &mut hasher)?;
+        hasher.update(&extras);
+        let hash = hasher.finalize();
+
+        let mut header = BinaryWriter::new(MAGIC.to_vec());
+        header.write_string(&self.api_version)?;
+        header.write_bytes(&hash)?;
+        match self.signature(&hash)? {
+            Some(signature) => {
+                header.write_u8(1)?;
+                header.write_bytes(&signature)?;
+            }
+            None => header.write_u8(0)?,
+        }
+        header.write_i32(data_length)?;
+
+        self.sink.write_all(&header.into_inner())?;
+        self.sink.write_all(&table)?;
+        write_payloads(&self.entries, self.spool.as_mut(), &mut self.sink)?;
+        self.sink.write_all(&extras)?;
+        self.sink.flush()?;
+        Ok(self.sink)
+    }
+
+    /// Adds the checksums entry for the entries added so far
+    #[cfg(feature = "json")]
+    fn add_checksums(&mut self) -> io::Result<()> {
+        let checksums = self
+            .entries
+            .iter()
+            .map(|entry| match &entry.sha256 {
+                Some(digest) => Ok((entry.name.as_str(), digest.as_slice())),
+                None => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("No checksum for entry '{}'; embed checksums before adding entries", entry.name),
+                )),
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        let json = checksums_json(checksums.into_iter());
+        self.add_generated_entry(CHECKSUMS_NAME, &json)
+    }
+
+    /// Adds an entry the writer generates itself, bypassing the name mapper
+    #[cfg(feature = "json")]
+    fn add_generated_entry(&mut self, name: &str, data: &[u8]) -> io::Result<()> {
+        if self.names.contains(name) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("Entry '{}' already exists in archive", name),
+            ));
+        }
+        let length = i32::try_from(data.len()).map_err(|_| too_large(name))?;
+        let sha256 = self.embed_checksums.then(|| Sha256::digest(data).to_vec());
+        self.names.insert(name.to_string());
+        self.entries.push(PendingEntry {
+            name: name.to_string(),
+            length,
+            payload: Payload::Memory(compress(data, self.compression)?),
+            sha256,
+        });
+        Ok(())
+    }
+
+    /// Serializes the plugin info and entry table, which start the data section
+    fn entry_table(&self) -> io::Result<Vec<u8>> {
+        let mut data = BinaryWriter::new(Vec::new());
+        data.write_string(&self.plugin_assembly)?;
+        data.write_string(&self.plugin_version)?;
+        let count = i32::try_from(self.entries.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Archive has too many entries"))?;
+        data.write_i32(count)?;
+
+        for entry in &self.entries {
+            let compressed_length = i32::try_from(entry.payload.len()).map_err(|_| too_large(&entry.name))?;
+            data.write_string(&entry.name)?;
+            data.write_i32(entry.length)?;
+            data.write_i32(compressed_length)?;
+        }
+        Ok(data.into_inner())
+    }
+
+    #[cfg(feature = "signing")]
+    fn signature(&self, hash: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        self.signing_key
+            .as_ref()
+            .map(|key| crate::signing::sign_hash(key, hash))
+            .transpose()
+    }
+
+    #[cfg(not(feature = "signing"))]
+    fn signature(&self, _hash: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        Ok(None)
+    }
+}
+
+impl ObbyWriter<OutputFile> {
+    /// Creates a new writer that will write the archive to the file at `path`
+    ///
+    /// The file is written atomically: the archive goes to a temporary file next to
+    /// `path`, which only replaces `path` once [`ObbyWriter::finish`] has written all of
+    /// it. A failed or abandoned write leaves any existing file untouched. See
+    /// [`OutputFile`] for details.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Where the finished archive is written.
+    /// * `plugin_assembly` - The plugin's assembly name (e.g. `MyPlugin`).
+    /// * `plugin_version` - The plugin's version (e.g. `1.2.3`).
+    pub fn create<P: AsRef<Path>>(path: P, plugin_assembly: &str, plugin_version: &str) -> Self {
+        ObbyWriter::new(OutputFile::new(path), plugin_assembly, plugin_version)
+    }
+
+    /// Writes straight to the file instead of through a temporary file
+    ///
+    /// Useful for targets that can't be replaced by a rename, although named pipes and
+    /// devices are already detected. Defaults to `true`.
+    pub fn set_atomic(&mut self, atomic: bool) {
+        self.sink.set_atomic(atomic);
+    }
+}
+
+/// Normalizes an entry name to the form the format's own tooling writes
+///
+/// Backslashes become forward slashes, and leading `/` as well as empty and `.` path
+/// components are removed, so `./assets\\icon.png` becomes `assets/icon.png`. Pass it to [`ObbyWriter::set_name_mapper`] to apply it
+/// to every added entry.
+///
+/// # Example
+///
+/// ```
+/// use obsidian_lib::normalize_entry_name;
+///
+/// assert_eq!(normalize_entry_name(".\\assets//icon.png"), "assets/icon.png");
+/// ```
+pub fn normalize_entry_name(name: &str) -> String {
+    name.split(['/', '\\'])
+        .filter(|component| !component.is_empty() && *component != ".")
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Checks that an entry name can be written to an archive
+///
+/// Names must be non-empty, fit the format's 7-bit encoded length prefix (at most
+/// `i32::MAX` bytes), and contain no NUL characters, which .NET readers truncate at.
+///
+/// # Returns
+///
+/// An `io::Error` of kind `InvalidInput` describing the problem.
+pub fn validate_entry_name(name: &str) -> io::Result<()> {
+    let problem = if name.is_empty() {
+        "is empty"
+    } else if i32::try_from(name.len()).is_err() {
+        "is too long"
+    } else if name.contains('\0') {
+        "contains a NUL character"
+    } else {
+        return Ok(());
+    };
+    Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("Entry name {:?} {}", name, problem),
+    ))
+}
+
+/// Writes the entries' stored bytes, in table order
+fn write_payloads<W: Write + ?Sized>(entries: &[PendingEntry], mut spool: Option<&mut Spool>, out: &mut W) -> io::Result<()> {
+    for entry in entries {
+        match &entry.payload {
+            Payload::Memory(data) => out.write_all(data)?,
+            Payload::Spooled(range) => spool
+                .as_deref_mut()
+                .expect("spooled entries have a spool")
+                .copy_range(range.clone(), out)?,
+        }
+    }
+    Ok(())
+}
+
+/// Feeds everything read through it to a hasher, if there is one
+struct HashingReader<'a, Rd> {
+    inner: Rd,
+    hasher: Option<&'a mut Sha256>,
+}
+
+impl<Rd: Read> Read for HashingReader<'_, Rd> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        if let Some(hasher) = &mut self.hasher {
+            hasher.update(&buf[..read]);
+        }
+        Ok(read)
+    }
+}
+
+/// Compresses everything `reader` yields onto the end of `spool`
+///
+/// # Returns
+///
+/// The entry's length and the range of `spool` holding its stored bytes, which are
+/// the raw bytes if deflate didn't make them smaller.
+fn spool_entry<Rd: Read>(spool: &mut Spool, name: &str, reader: Rd, compression: Compression) -> io::Result<(i32, Range<u64>)> {
+    let start = spool.len();
+    // One byte past the limit is enough to tell that an entry is too large
+    let mut reader = reader.take(i32::MAX as u64 + 1);
+    let length = if compression == Compression::none() {
+        io::copy(&mut reader, spool)?
+    } else {
+        let mut encoder = DeflateEncoder::new(&mut *spool, compression);
+        let length = io::copy(&mut reader, &mut encoder)?;
+        encoder.finish()?;
+        length
+    };
+    let length = i32::try_from(length).map_err(|_| too_large(name))?;
+    let mut range = start..spool.len();
+    if compression != Compression::none() && range.end - range.start >= length as u64 {
+        spool.append_inflated(range.clone())?;
+        range = range.end..spool.len();
+    }
+    Ok((length, range))
+}
+
+fn too_large(name: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, format!("Entry '{}' is too large", name))
+}
+
+/// Deflates `data`, falling back to the raw bytes when that doesn't make it smaller
+fn compress(data: &[u8], compression: Compression) -> io::Result<Vec<u8>> {
+    if compression == Compression::none() {
+        return Ok(data.to_vec());
+    }
+
+    let mut encoder = DeflateEncoder::new(Vec::new(), compression);
+    encoder.write_all(data)?;
+    let compressed = encoder.finish()?;
+
+    // A compressed payload that isn't smaller would be indistinguishable from a stored one
+    if compressed.len() < data.len() {
+        Ok(compressed)
+    } else {
+        Ok(data.to_vec())
+    }
+}
+
+/// Recursively collects `(entry name, path)` pairs for all files below `dir`
+fn collect_files(dir: &Path, prefix: &str, files: &mut Vec<(String, std::path::PathBuf)>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        let file_name = file_name.to_str().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("File name {:?} is not valid UTF-8", entry.file_name()),
+            )
+        })?;
+        let name = format!("{}{}", prefix, file_name);
+
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            collect_files(&entry.path(), &format!("{}/", name), files)?;
+        } else if file_type.is_file() {
+            files.push((name, entry.path()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_round_trip() {
+        let json = br#"{"id": "test-plugin", "version": "1.2.3"}"#;
+        let dll = vec![0x4Du8; 4096];
+
+        let mut writer = ObbyWriter::new(Vec::new(), "TestPlugin", "1.2.3");
+        writer.add_entry("plugin.json", json).unwrap();
+        writer.add_entry("TestPlugin.dll", &dll).unwrap();
+        let bytes = writer.finish().unwrap();
+
+        let mut archive = ObbyArchive::new(Cursor::new(bytes)).unwrap();
+        let mut entries = archive.list_entries();
+        entries.sort();
+        assert_eq!(entries, vec!["TestPlugin.dll", "plugin.json"]);
+        assert_eq!(archive.extract_entry("plugin.json").unwrap(), json);
+        assert_eq!(archive.extract_entry("TestPlugin.dll").unwrap(), dll);
+    }
+
+    #[test]
+    fn test_uncompressed_entries_are_stored() {
+        let text = vec![b'a'; 2048];
+
+        let mut writer = ObbyWriter::new(Vec::new(), "TestPlugin", "1.0.0");
+        writer.set_compression(Compression::none());
+        writer.add_entry("stored.txt", &text).unwrap();
+        let bytes = writer.finish().unwrap();
+
+        // The raw entry bytes end the archive when nothing is compressed
+        assert!(bytes.ends_with(&text));
+        let mut archive = ObbyArchive::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(archive.extract_entry("stored.txt").unwrap(), text);
+    }
+
+    #[test]
+    fn test_per_entry_compression_override() {
+        let text = vec![b'a'; 2048];
+
+        let mut writer = ObbyWriter::new(Vec::new(), "TestPlugin", "1.0.0");
+        writer.set_compression(Compression::best());
+        writer.add_entry("compressed.txt", &text).unwrap();
+        writer.add_entry_with_compression("icon.png", &text, Compression::none()).unwrap();
+        let bytes = writer.finish().unwrap();
+
+        // Stored data goes last, so the archive ends with the raw bytes of the override
+        assert!(bytes.ends_with(&text));
+        assert!(bytes.len() < 2 * text.len());
+        let mut archive = ObbyArchive::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(archive.extract_entry("compressed.txt").unwrap(), text);
+        assert_eq!(archive.extract_entry("icon.png").unwrap(), text);
+    }
+
+    #[test]
+    fn test_add_entry_from_reader() {
+        let text = b"lorem ipsum ".repeat(10_000);
+        // Doesn't shrink under deflate, so it is stored
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let noise: Vec<u8> = (0..50_000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+        let build = |streamed: bool| {
+            let mut writer = ObbyWriter::new(Vec::new(), "TestPlugin", "1.0.0");
+            writer.add_entry("plugin.json", b"{}").unwrap();
+            for (name, data) in [("text.txt", &text), ("noise.bin", &noise)] {
+                if streamed {
+                    writer.add_entry_from_reader(name, &data[..]).unwrap();
+                } else {
+                    writer.add_entry(name, data).unwrap();
+                }
+            }
+            writer.finish().unwrap()
+        };
+
+        let bytes = build(true);
+        assert_eq!(bytes, build(false));
+        let mut archive = ObbyArchive::from_bytes(bytes).unwrap();
+        archive.verify_hash().unwrap();
+        assert_eq!(archive.extract_entry("text.txt").unwrap(), text);
+        assert_eq!(archive.extract_entry("noise.bin").unwrap(), noise);
+        assert_eq!(archive.entry_byte_range("noise.bin").unwrap().count(), noise.len());
+    }
+
+    #[test]
+    fn test_failed_reader_adds_nothing() {
+        struct Failing(usize);
+        impl Read for Failing {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                if self.0 == 0 {
+                    return Err(io::Error::other("disconnected"));
+                }
+                self.0 -= 1;
+                buf.fill(b'x');
+                Ok(buf.len())
+            }
+        }
+
+        let mut writer = ObbyWriter::new(Vec::new(), "TestPlugin", "1.0.0");
+        assert!(writer.add_entry_from_reader("broken.bin", Failing(3)).is_err());
+        writer.add_entry_from_reader("ok.txt", &b"ok"[..]).unwrap();
+        let archive = ObbyArchive::from_bytes(writer.finish().unwrap()).unwrap();
+        assert_eq!(archive.list_entries(), vec!["ok.txt"]);
+        assert_eq!(archive.entry_byte_range("ok.txt").unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_copy_entry_from() {
+        let text = b"lorem ipsum ".repeat(100);
+        let buffer = crate::testing::ObbyTestBuilder::new()
+            .entry("notes.txt", &text)
+            .stored_entry("plugin.json", b"{}")
+            .build();
+        let mut source = ObbyArchive::from_bytes(buffer).unwrap();
+
+        let mut writer = ObbyWriter::new(Vec::new(), "TestPlugin", "1.0.0");
+        writer.set_compression(Compression::none());
+        writer.copy_entry_from(&mut source, "notes.txt").unwrap();
+        writer.copy_entry_from(&mut source, "plugin.json").unwrap();
+        assert_eq!(writer.copy_entry_from(&mut source, "missing").unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert_eq!(writer.copy_entry_from(&mut source, "notes.txt").unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+
+        let mut copy = ObbyArchive::from_bytes(writer.finish().unwrap()).unwrap();
+        for name in ["notes.txt", "plugin.json"] {
+            assert_eq!(copy.entry_byte_range(name).unwrap().count(), source.entry_byte_range(name).unwrap().count());
+            assert_eq!(copy.extract_entry(name).unwrap(), source.extract_entry(name).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_duplicate_entry() {
+        let mut writer = ObbyWriter::new(Vec::new(), "TestPlugin", "1.0.0");
+        writer.add_entry("plugin.json", b"{}").unwrap();
+        let err = writer.add_entry("plugin.json", b"{}").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+    }
+
+    #[test]
+    fn test_name_mapper() {
+        let mut writer = ObbyWriter::new(Vec::new(), "TestPlugin", "1.0.0");
+        writer.set_name_mapper(normalize_entry_name);
+        // A writer with a mapper can still be handed to another thread
+        let mut writer = std::thread::spawn(move || writer).join().unwrap();
+        writer.add_entry("./assets\\icon.png", b"png").unwrap();
+        let err = writer.add_entry("assets//icon.png", b"png").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(writer.add_entry("./", b"").unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        let bytes = writer.finish().unwrap();
+
+        let archive = ObbyArchive::from_bytes(bytes).unwrap();
+        assert_eq!(archive.list_entries(), vec!["assets/icon.png"]);
+    }
+
+    #[test]
+    fn test_normalize_and_validate_names() {
+        assert_eq!(normalize_entry_name("/a/./b/"), "a/b");
+        assert_eq!(normalize_entry_name("plugin.json"), "plugin.json");
+        assert!(validate_entry_name("a\0b").is_err());
+        assert!(validate_entry_name("").is_err());
+        assert!(validate_entry_name("assets/icon.png").is_ok());
+    }
+
+    #[test]
+    fn test_reproducible_output() {
+        let build = |names: &[&str]| {
+            let mut writer = ObbyWriter::new(Vec::new(), "TestPlugin", "1.0.0");
+            writer.set_reproducible(true);
+            for name in names {
+                writer.add_entry(name, name.repeat(100).as_bytes()).unwrap();
+            }
+            writer.finish().unwrap()
+        };
+
+        let bytes = build(&["plugin.json", "b.js", "a.js"]);
+        assert_eq!(bytes, build(&["a.js", "plugin.json", "b.js"]));
+        let archive = ObbyArchive::from_bytes(bytes).unwrap();
+        assert_eq!(archive.list_entries(), vec!["a.js", "b.js", "plugin.json"]);
+    }
+
+    #[test]
+    fn test_hash_covers_data_section() {
+        let mut writer = ObbyWriter::new(Vec::new(), "TestPlugin", "1.0.0");
+        writer.add_entry("plugin.json", b"{}").unwrap();
+        let bytes = writer.finish().unwrap();
+
+        // "OBBY" + "\x051.0.0" + hash (48) + unsigned flag + data length
+        let data_start = 4 + 6 + 48 + 1 + 4;
+        assert_eq!(&bytes[10..58], Sha384::digest(&bytes[data_start..]).as_slice());
+    }
+
+    #[test]
+    fn test_add_dir_uses_relative_names() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("assets")).unwrap();
+        fs::write(dir.path().join("plugin.json"), b"{}").unwrap();
+        fs::write(dir.path().join("assets").join("icon.png"), b"png").unwrap();
+
+        let mut writer = ObbyWriter::new(Vec::new(), "TestPlugin", "1.0.0");
+        writer.add_dir(dir.path()).unwrap();
+        let bytes = writer.finish().unwrap();
+
+        let mut archive = ObbyArchive::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(archive.extract_entry("assets/icon.png").unwrap(), b"png");
+    }
+}